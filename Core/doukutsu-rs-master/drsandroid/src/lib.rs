@@ -5,7 +5,7 @@ pub fn android_main() {
 
     std::env::set_current_dir(&resource_dir).unwrap();
     
-    let options = doukutsu_rs::game::LaunchOptions { server_mode: false, editor: false, return_types: false, external_timer: false, resource_dir: None, usr_dir: None};
+    let options = doukutsu_rs::game::LaunchOptions { server_mode: false, editor: false, return_types: false, external_timer: false, resource_dir: None, usr_dir: None, audio_capture: None};
 
     doukutsu_rs::game::init(options).unwrap();
 }
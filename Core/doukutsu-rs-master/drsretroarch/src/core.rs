@@ -15,6 +15,7 @@ use std::ffi::c_void;
 use doukutsu_rs::framework::backend_libretro::{LibretroEventLoop, LibretroBackend, RenderMode};
 use doukutsu_rs::framework::backend::{BackendEventLoop, Backend};
 use doukutsu_rs::framework::keyboard::ScanCode;
+use doukutsu_rs::framework::post_process::PostProcessPass;
 use doukutsu_rs::framework::gamepad::{Button, Axis};
 use doukutsu_rs::framework::context::{self, Context};
 use doukutsu_rs::game::Game;
@@ -98,6 +99,10 @@ fn parse_ratio(opt: &str) -> Result<(u32,u32), ()> {
 }
 
 
+fn parse_string(opt: &str) -> Result<String, ()> {
+    Ok(opt.to_owned())
+}
+
 fn parse_bool(opt: &str) -> Result<bool, ()> {
     match opt {
         "true" | "enabled" | "on" => Ok(true),
@@ -115,6 +120,9 @@ libretro_variables!(
         screen_ratio: (u32, u32), parse_ratio
             => "Screen Ratio; \
                 4:3 (original)|16:9|21:9",
+        post_process: String, parse_string
+            => "Screen filter (leave disabled when using frontend shaders); \
+                disabled|crt|scanlines|lcd_grid|protanopia|deuteranopia|tritanopia",
         draw_debug_outlines: bool, parse_bool
             => "Debug Outlines; disabled|enabled",       
         show_fps: bool, parse_bool
@@ -150,6 +158,7 @@ struct Core<'a>  {
     async_audio_enabled: bool, //true if async audio has been enabled
     delta_time: i64, //time since last frame
     audio_runner: Runner, //object that containst the audio context
+    post_process: Option<String>, //last applied value of the post-process core option
 }
 
 impl<'a>  Core<'a>  {
@@ -296,6 +305,7 @@ impl<'a>  Core<'a>  {
             async_audio_enabled,
             delta_time: 0,
             audio_runner: audio_runner.unwrap(),
+            post_process: None,
 
             ////data_path: data.clone().to_path_buf(), 
         };
@@ -499,6 +509,15 @@ impl<'a>  libretro::Context  for Core<'a>  {
         self.state_ref.debugger = CoreVariables::show_debug_window();
         self.state_ref.more_rust = CoreVariables::more_rust();
 
+        //filters are left to the frontend's own shaders unless one is picked here,
+        //only applied when the option changes so a chain picked in the in-game menu isn't reset by other options
+        let post_process = CoreVariables::post_process();
+        if self.post_process.as_deref() != Some(post_process.as_str()) {
+            self.state_ref.settings.post_process = PostProcessPass::preset(&post_process).into_iter().collect();
+            self.state_ref.apply_post_process(&mut self.context);
+            self.post_process = Some(post_process);
+        }
+
 
 
 
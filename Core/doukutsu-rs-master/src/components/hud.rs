@@ -8,6 +8,7 @@ use crate::game::frame::Frame;
 use crate::game::inventory::Inventory;
 use crate::game::shared_game_state::SharedGameState;
 use crate::game::player::Player;
+use crate::game::weapon::custom::weapon_icon_rect;
 use crate::game::weapon::WeaponType;
 
 pub struct HUD {
@@ -85,7 +86,7 @@ impl GameEntity<(&Player, &mut Inventory)> for HUD {
         self.current_level = inventory.get_current_level() as usize;
 
         for (a, slot) in self.weapon_types.iter_mut().enumerate() {
-            *slot = if let Some(weapon) = inventory.get_weapon(a) { weapon.wtype as u8 } else { 0 };
+            *slot = if let Some(weapon) = inventory.get_weapon(a) { weapon.wtype.id() } else { 0 };
         }
 
         // update health bar
@@ -258,8 +259,6 @@ impl GameEntity<(&Player, &mut Inventory)> for HUD {
         let batch = state.texture_set.get_or_load_batch(ctx, &state.constants, "ArmsImage")?;
 
         if self.weapon_count != 0 {
-            let mut rect = Rect::new(0, 0, 0, 16);
-
             // First frame of animation is off by one weapon
             // There's probably a more elegant solution than this
             let first_frame_offset = if self.weapon_x_pos == 32 {
@@ -291,8 +290,7 @@ impl GameEntity<(&Player, &mut Inventory)> for HUD {
 
                 let wtype = self.weapon_types[a];
                 if wtype != 0 {
-                    rect.left = wtype as u16 * 16;
-                    rect.right = rect.left + 16;
+                    let rect = weapon_icon_rect(&state.constants, wtype);
                     batch.add_rect(pos_x + weapon_offset, 16.0 + top, &rect);
                 }
             }
@@ -10,6 +10,7 @@ use crate::game::shared_game_state::SharedGameState;
 use crate::input::touch_controls::TouchControlType;
 use crate::game::player::Player;
 use crate::game::scripting::tsc::text_script::{ScriptMode, TextScriptExecutionState};
+use crate::game::weapon::custom::weapon_icon_rect;
 use crate::game::weapon::{WeaponLevel, WeaponType};
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -64,7 +65,7 @@ impl InventoryUI {
     }
 
     fn get_weapon_event_number(&self, inventory: &Inventory) -> u16 {
-        inventory.get_current_weapon().map(|w| w.wtype as u16 + 1000).unwrap_or(1000)
+        inventory.get_current_weapon().map(|w| w.wtype.id() as u16 + 1000).unwrap_or(1000)
     }
 
     fn exit(&mut self, state: &mut SharedGameState, _player: &mut Player, inventory: &mut Inventory, hud: &mut HUD) {
@@ -363,10 +364,7 @@ impl GameEntity<(&mut Context, &mut Player, &mut Inventory, &mut HUD)> for Inven
                 break;
             }
 
-            tmp_rect.left = (weapon.wtype as u16 % 16) * 16;
-            tmp_rect.top = (weapon.wtype as u16 / 16) * 16;
-            tmp_rect.right = tmp_rect.left + 16;
-            tmp_rect.bottom = tmp_rect.top + 16;
+            tmp_rect = weapon_icon_rect(&state.constants, weapon.wtype.id());
 
             batch.add_rect(x + 12.0 + idx as f32 * 40.0, y + 16.0, &tmp_rect);
         }
@@ -43,8 +43,35 @@ impl Tilemap {
         textures: &StageTexturePaths,
         stage: &Stage,
     ) -> GameResult {
+        self.draw_layer(state, ctx, frame, layer, textures, stage, false).map(|_| ())
+    }
+
+    /// Draws the normal map layer of the tileset, returns false if it doesn't have one.
+    pub fn draw_normal_map(
+        &self,
+        state: &mut SharedGameState,
+        ctx: &mut Context,
+        frame: &Frame,
+        layer: TileLayer,
+        textures: &StageTexturePaths,
+        stage: &Stage,
+    ) -> GameResult<bool> {
+        self.draw_layer(state, ctx, frame, layer, textures, stage, true)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_layer(
+        &self,
+        state: &mut SharedGameState,
+        ctx: &mut Context,
+        frame: &Frame,
+        layer: TileLayer,
+        textures: &StageTexturePaths,
+        stage: &Stage,
+        normal_map: bool,
+    ) -> GameResult<bool> {
         if stage.map.tile_size == TileSize::Tile8x8 && layer == TileLayer::Snack {
-            return Ok(());
+            return Ok(false);
         }
 
         let tex = match layer {
@@ -70,7 +97,7 @@ impl Tilemap {
         };
 
         if !uses_layers && layer == TileLayer::Middleground {
-            return Ok(());
+            return Ok(false);
         }
 
         let tile_size = state.tile_size.as_int();
@@ -79,6 +106,14 @@ impl Tilemap {
         let halftf = tile_sizef / 2.0;
 
         let batch = state.texture_set.get_or_load_batch(ctx, &state.constants, tex)?;
+        let batch = if normal_map {
+            match batch.normal() {
+                Some(batch) => batch,
+                None => return Ok(false),
+            }
+        } else {
+            batch.as_mut()
+        };
         let mut rect = Rect::new(0, 0, tile_size as u16, tile_size as u16);
         let (mut frame_x, mut frame_y) = frame.xy_interpolated(state.frame_time);
 
@@ -161,6 +196,10 @@ impl Tilemap {
 
         batch.draw(ctx)?;
 
+        if normal_map {
+            return Ok(true);
+        }
+
         if !self.no_water && layer == TileLayer::Foreground && stage.data.background_type == BackgroundType::Water {
             let batch = state.texture_set.get_or_load_batch(ctx, &state.constants, &textures.background)?;
             let rect_top = Rect { left: 0, top: 0, right: 32, bottom: 16 };
@@ -229,6 +268,6 @@ impl Tilemap {
             batch.draw(ctx)?;
         }
 
-        Ok(())
+        Ok(true)
     }
 }
@@ -111,6 +111,10 @@ impl BuiltinFS {
                     FSNode::Directory(
                         "builtin_data",
                         vec![
+                            FSNode::File(
+                                "audio_effects.json",
+                                include_bytes!("builtin/builtin_data/audio_effects.json"),
+                            ),
                             FSNode::File("buttons.png", include_bytes!("builtin/builtin_data/buttons.png")),
                             FSNode::File("triangles.png", include_bytes!("builtin/builtin_data/triangles.png")),
                             FSNode::Directory(
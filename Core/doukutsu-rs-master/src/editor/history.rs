@@ -0,0 +1,102 @@
+use crate::game::map::{Map, NPCData};
+use crate::game::stage::PxPackStageData;
+
+/// Maximum number of commands kept in the undo stack.
+const MAX_HISTORY_SIZE: usize = 256;
+
+#[derive(Clone)]
+pub struct TileChange {
+    /// Index into `Map::tiles`.
+    pub index: usize,
+    pub old_tile: u8,
+    pub new_tile: u8,
+}
+
+/// Copy of everything a resize operation touches, restored as a whole on undo/redo.
+#[derive(Clone)]
+pub struct StageSnapshot {
+    pub map: Map,
+    pub pxpack_data: Option<PxPackStageData>,
+    pub entities: Vec<NPCData>,
+}
+
+#[derive(Clone)]
+pub enum EditorCommand {
+    ChangeTiles(Vec<TileChange>),
+    AddEntity(usize, NPCData),
+    RemoveEntity(usize, NPCData),
+    /// Entity index, state before and after the change.
+    ModifyEntity(usize, NPCData, NPCData),
+    /// State before and after the resize.
+    Resize(Box<StageSnapshot>, Box<StageSnapshot>),
+}
+
+pub struct History {
+    undo_stack: Vec<EditorCommand>,
+    redo_stack: Vec<EditorCommand>,
+    /// Whether the last command can still absorb modifications made by `push_merged_modify`.
+    merge_open: bool,
+}
+
+impl History {
+    pub fn new() -> History {
+        History { undo_stack: Vec::new(), redo_stack: Vec::new(), merge_open: false }
+    }
+
+    /// Records an already applied command.
+    pub fn push(&mut self, command: EditorCommand) {
+        self.redo_stack.clear();
+        self.undo_stack.push(command);
+        self.merge_open = false;
+
+        if self.undo_stack.len() > MAX_HISTORY_SIZE {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    /// Records an entity modification, merging it with the previous one if it touched the same entity
+    /// and no `break_merge` happened in between. Used by the property editor so typing a number doesn't
+    /// create an undo step per keystroke.
+    pub fn push_merged_modify(&mut self, index: usize, old: NPCData, new: NPCData) {
+        if self.merge_open && self.redo_stack.is_empty() {
+            if let Some(EditorCommand::ModifyEntity(last_index, _, last_new)) = self.undo_stack.last_mut() {
+                if *last_index == index {
+                    *last_new = new;
+                    return;
+                }
+            }
+        }
+
+        self.push(EditorCommand::ModifyEntity(index, old, new));
+        self.merge_open = true;
+    }
+
+    /// Makes the next `push_merged_modify` start a new undo step.
+    pub fn break_merge(&mut self) {
+        self.merge_open = false;
+    }
+
+    pub fn pop_undo(&mut self) -> Option<EditorCommand> {
+        let command = self.undo_stack.pop()?;
+        self.merge_open = false;
+        self.redo_stack.push(command.clone());
+
+        Some(command)
+    }
+
+    pub fn pop_redo(&mut self) -> Option<EditorCommand> {
+        let command = self.redo_stack.pop()?;
+        self.merge_open = false;
+        self.undo_stack.push(command.clone());
+
+        Some(command)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+}
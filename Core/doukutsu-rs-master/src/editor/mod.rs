@@ -1,19 +1,33 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ops::Deref;
 use std::rc::Rc;
 
-use imgui::{Image, MouseButton, Window};
+use imgui::{Condition, Image, Key, MouseButton, Window};
 
 use crate::common::{Color, Rect};
 use crate::components::background::Background;
+use crate::components::flash::Flash;
 use crate::components::tilemap::{TileLayer, Tilemap};
+use crate::editor::history::{EditorCommand, History, StageSnapshot, TileChange};
+use crate::entity::GameEntity;
 use crate::framework::context::Context;
 use crate::framework::error::GameResult;
 use crate::framework::graphics;
 use crate::game::shared_game_state::SharedGameState;
 use crate::game::frame::Frame;
+use crate::game::map::NPCData;
+use crate::game::npc::boss::BossNPC;
+use crate::game::npc::list::NPCList;
+use crate::game::npc::NPC;
+use crate::game::player::Player;
 use crate::game::stage::{Stage, StageTexturePaths};
+use crate::game::weapon::bullet::BulletManager;
+use crate::graphics::font::Font;
 use crate::graphics::texture_set::I_MAG;
+use crate::sound::backend_null::SoundManagerNull;
+
+pub mod history;
 
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum CurrentTool {
@@ -21,11 +35,41 @@ pub enum CurrentTool {
     Brush,
     Fill,
     Rectangle,
+    Entity,
 }
 
+static NPC_FLAG_NAMES: [&str; 16] = [
+    "Solid (soft)",
+    "Ignore tile 0x44",
+    "Invulnerable",
+    "Ignore solidity",
+    "Bouncy",
+    "Shootable",
+    "Solid (hard)",
+    "Rear and top don't hurt",
+    "Event when touched",
+    "Event when killed",
+    "Unknown (0x400)",
+    "Appear when flag set",
+    "Spawn facing right",
+    "Interactable",
+    "Hide unless flag set",
+    "Show damage",
+];
+
 pub struct EditorInstance {
     pub stage: Stage,
     pub stage_id: usize,
+    pub entities: Vec<NPCData>,
+    pub selected_entity: Option<usize>,
+    pub dragged_entity: Option<usize>,
+    pub history: History,
+    /// Tiles changed by the current brush stroke, committed to history when the mouse is released.
+    pending_tiles: Vec<TileChange>,
+    /// State of the dragged entity before the drag started.
+    drag_origin: Option<NPCData>,
+    /// NPCs ticked once to set up the sprite shown for entities, keyed by NPC type and flags.
+    entity_previews: HashMap<(u16, u16), NPC>,
     pub frame: Frame,
     pub background: Background,
     pub stage_textures: Rc<RefCell<StageTexturePaths>>,
@@ -37,7 +81,7 @@ pub struct EditorInstance {
 }
 
 impl EditorInstance {
-    pub fn new(stage_id: usize, stage: Stage) -> EditorInstance {
+    pub fn new(stage_id: usize, stage: Stage, entities: Vec<NPCData>) -> EditorInstance {
         let stage_textures = {
             let mut textures = StageTexturePaths::new();
             textures.update(&stage);
@@ -50,6 +94,13 @@ impl EditorInstance {
         EditorInstance {
             stage,
             stage_id,
+            entities,
+            selected_entity: None,
+            dragged_entity: None,
+            history: History::new(),
+            pending_tiles: Vec::new(),
+            drag_origin: None,
+            entity_previews: HashMap::new(),
             frame,
             background: Background::new(),
             stage_textures,
@@ -61,12 +112,35 @@ impl EditorInstance {
         }
     }
 
+    /// Saves the stage into the editor data directory, where it shadows the stage of the game or the active mod.
+    pub fn save(&self, state: &SharedGameState, ctx: &mut Context) -> GameResult {
+        let mut create = |path: &str| state.create_data_file(ctx, path);
+
+        self.stage.save(&mut create)?;
+        self.stage.save_npcs(&self.entities, &mut create)?;
+
+        log::info!("Saved stage {} to editor data directory", self.stage.data.map);
+
+        Ok(())
+    }
+
     pub fn process(&mut self, state: &mut SharedGameState, ctx: &mut Context, ui: &mut imgui::Ui, tool: CurrentTool) {
         self.frame.prev_x = self.frame.x;
         self.frame.prev_y = self.frame.y;
         self.mouse_pos = (ui.io().mouse_pos[0], ui.io().mouse_pos[1]);
         self.want_capture_mouse = ui.io().want_capture_mouse;
 
+        if !ui.is_mouse_down(MouseButton::Left) {
+            self.finish_mouse_action();
+        }
+
+        if ui.is_mouse_released(MouseButton::Left) {
+            // every click or slider drag in the property editor is a separate undo step
+            self.history.break_merge();
+        }
+
+        self.update_entity_previews(state, ctx);
+
         let mut drag = false;
 
         match tool {
@@ -87,19 +161,14 @@ impl EditorInstance {
                 drag |= ui.is_mouse_down(MouseButton::Right);
 
                 if !drag && ui.is_mouse_down(MouseButton::Left) {
-                    let tile_size = self.stage.map.tile_size.as_int();
-                    let halft = tile_size / 2;
-                    let stage_mouse_x = (self.frame.x / 0x200) + halft + (self.mouse_pos.0 / self.zoom) as i32;
-                    let stage_mouse_y = (self.frame.y / 0x200) + halft + (self.mouse_pos.1 / self.zoom) as i32;
-                    let tile_x = stage_mouse_x / tile_size;
-                    let tile_y = stage_mouse_y / tile_size;
+                    let (tile_x, tile_y) = self.cursor_tile_pos();
 
                     if tile_x >= 0
                         && tile_y >= 0
                         && tile_x < self.stage.map.width as i32
                         && tile_y < self.stage.map.height as i32
                     {
-                        self.stage.change_tile(tile_x as usize, tile_y as usize, self.current_tile);
+                        self.paint_tile(tile_x as usize, tile_y as usize, self.current_tile);
                     }
                 }
             }
@@ -111,6 +180,35 @@ impl EditorInstance {
                 self.palette_window(state, ctx, ui);
                 drag |= ui.is_mouse_down(MouseButton::Right);
             }
+            CurrentTool::Entity => {
+                self.entity_window(state, ui);
+
+                if ui.io().want_capture_mouse {
+                    return;
+                }
+
+                drag |= ui.is_mouse_down(MouseButton::Right);
+
+                if ui.is_mouse_clicked(MouseButton::Left) {
+                    let (x, y) = self.cursor_stage_pos();
+                    self.selected_entity = self.entity_at(state, x, y);
+                    self.dragged_entity = self.selected_entity;
+                    self.drag_origin = self.selected_entity.and_then(|idx| self.entities.get(idx).cloned());
+                }
+
+                if let Some(idx) = self.dragged_entity {
+                    let (tile_x, tile_y) = self.cursor_tile_pos();
+
+                    if let Some(entity) = self.entities.get_mut(idx) {
+                        entity.x = tile_x.clamp(0, self.stage.map.width as i32 - 1) as i16;
+                        entity.y = tile_y.clamp(0, self.stage.map.height as i32 - 1) as i16;
+                    }
+                }
+
+                if ui.is_key_pressed(Key::Delete) {
+                    self.delete_selected_entity();
+                }
+            }
         }
 
         if drag {
@@ -121,6 +219,301 @@ impl EditorInstance {
         }
     }
 
+    fn paint_tile(&mut self, x: usize, y: usize, tile: u8) {
+        let old_tile = self.stage.tile_at(x, y);
+
+        if self.stage.change_tile(x, y, tile) {
+            let index = y * self.stage.map.width as usize + x;
+            self.pending_tiles.push(TileChange { index, old_tile, new_tile: tile });
+        }
+    }
+
+    /// Commits brush strokes and entity drags into the history.
+    fn finish_mouse_action(&mut self) {
+        if !self.pending_tiles.is_empty() {
+            let changes = std::mem::take(&mut self.pending_tiles);
+            self.history.push(EditorCommand::ChangeTiles(changes));
+        }
+
+        if let (Some(idx), Some(origin)) = (self.dragged_entity.take(), self.drag_origin.take()) {
+            if let Some(entity) = self.entities.get(idx) {
+                if *entity != origin {
+                    self.history.push(EditorCommand::ModifyEntity(idx, origin, entity.clone()));
+                }
+            }
+        }
+    }
+
+    pub fn undo(&mut self) {
+        self.finish_mouse_action();
+
+        if let Some(command) = self.history.pop_undo() {
+            self.apply_command(&command, true);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        self.finish_mouse_action();
+
+        if let Some(command) = self.history.pop_redo() {
+            self.apply_command(&command, false);
+        }
+    }
+
+    fn apply_command(&mut self, command: &EditorCommand, revert: bool) {
+        match command {
+            EditorCommand::ChangeTiles(changes) => {
+                let mut apply = |change: &TileChange| {
+                    if let Some(tile) = self.stage.map.tiles.get_mut(change.index) {
+                        *tile = if revert { change.old_tile } else { change.new_tile };
+                    }
+                };
+
+                if revert {
+                    changes.iter().rev().for_each(&mut apply);
+                } else {
+                    changes.iter().for_each(&mut apply);
+                }
+            }
+            EditorCommand::AddEntity(idx, data) | EditorCommand::RemoveEntity(idx, data) => {
+                let add = matches!(command, EditorCommand::AddEntity(..)) != revert;
+
+                if add {
+                    self.entities.insert((*idx).min(self.entities.len()), data.clone());
+                } else if *idx < self.entities.len() {
+                    self.entities.remove(*idx);
+                }
+            }
+            EditorCommand::ModifyEntity(idx, old, new) => {
+                if let Some(entity) = self.entities.get_mut(*idx) {
+                    *entity = if revert { old.clone() } else { new.clone() };
+                }
+            }
+            EditorCommand::Resize(before, after) => {
+                let snapshot = if revert { before } else { after };
+
+                self.stage.map = snapshot.map.clone();
+                self.stage.data.pxpack_data = snapshot.pxpack_data.clone();
+                self.entities = snapshot.entities.clone();
+            }
+        }
+
+        self.renumber_entities();
+        if self.selected_entity.map_or(false, |idx| idx >= self.entities.len()) {
+            self.selected_entity = None;
+        }
+    }
+
+    fn snapshot(&self) -> StageSnapshot {
+        StageSnapshot {
+            map: self.stage.map.clone(),
+            pxpack_data: self.stage.data.pxpack_data.clone(),
+            entities: self.entities.clone(),
+        }
+    }
+
+    /// Resizes the map, moving tiles and entities by given offset (in tiles).
+    /// Entities that end up outside of the new bounds are removed.
+    pub fn resize(&mut self, width: u16, height: u16, offset_x: i32, offset_y: i32) {
+        self.finish_mouse_action();
+
+        let before = self.snapshot();
+
+        self.stage.resize(width, height, offset_x, offset_y);
+
+        let mut kept = Vec::with_capacity(self.entities.len());
+        for (idx, mut entity) in std::mem::take(&mut self.entities).into_iter().enumerate() {
+            let x = entity.x as i32 + offset_x;
+            let y = entity.y as i32 + offset_y;
+
+            if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+                if self.selected_entity == Some(idx) {
+                    self.selected_entity = None;
+                }
+                continue;
+            }
+
+            if self.selected_entity == Some(idx) {
+                self.selected_entity = Some(kept.len());
+            }
+
+            entity.x = x as i16;
+            entity.y = y as i16;
+            kept.push(entity);
+        }
+        self.entities = kept;
+        self.renumber_entities();
+
+        let after = self.snapshot();
+        self.history.push(EditorCommand::Resize(Box::new(before), Box::new(after)));
+    }
+
+    fn renumber_entities(&mut self) {
+        // NPC slots are assigned in .pxe order
+        for (i, entity) in self.entities.iter_mut().enumerate() {
+            entity.id = 170 + i as u16;
+        }
+    }
+
+    /// Returns the position of the mouse cursor in stage coordinates (pixels).
+    fn cursor_stage_pos(&self) -> (i32, i32) {
+        (
+            (self.frame.x / 0x200) + (self.mouse_pos.0 / self.zoom) as i32,
+            (self.frame.y / 0x200) + (self.mouse_pos.1 / self.zoom) as i32,
+        )
+    }
+
+    /// Returns the tile under the mouse cursor, might be out of map bounds.
+    fn cursor_tile_pos(&self) -> (i32, i32) {
+        let tile_size = self.stage.map.tile_size.as_int();
+        let halft = tile_size / 2;
+        let (stage_mouse_x, stage_mouse_y) = self.cursor_stage_pos();
+
+        ((stage_mouse_x + halft).div_euclid(tile_size), (stage_mouse_y + halft).div_euclid(tile_size))
+    }
+
+    /// Returns the bounds of an entity in stage coordinates (pixels).
+    fn entity_bounds(&self, state: &SharedGameState, entity: &NPCData) -> Rect<i32> {
+        let tile_size = self.stage.map.tile_size.as_int();
+        let halft = tile_size / 2;
+        let x = entity.x as i32 * tile_size;
+        let y = entity.y as i32 * tile_size;
+        let bounds = state.npc_table.get_display_bounds(entity.npc_type);
+
+        if bounds.left == 0 && bounds.top == 0 && bounds.right == 0 && bounds.bottom == 0 {
+            return Rect::new(x - halft, y - halft, x + halft, y + halft);
+        }
+
+        Rect::new(
+            x - (bounds.left / 0x200) as i32,
+            y - (bounds.top / 0x200) as i32,
+            x + (bounds.right / 0x200) as i32,
+            y + (bounds.bottom / 0x200) as i32,
+        )
+    }
+
+    /// Ticks entities of types without a preview once in an empty world so their AI sets up the frame they're drawn in.
+    ///
+    /// The AI runs against the live game state, so sounds are muted and everything else it can touch is restored
+    /// afterwards.
+    fn update_entity_previews(&mut self, state: &mut SharedGameState, ctx: &mut Context) {
+        let missing: Vec<NPCData> = self
+            .entities
+            .iter()
+            .filter(|entity| !self.entity_previews.contains_key(&(entity.npc_type, entity.flags)))
+            .cloned()
+            .collect();
+
+        if missing.is_empty() {
+            return;
+        }
+
+        let sound_manager = match SoundManagerNull::new(ctx) {
+            Ok(null_manager) => std::mem::replace(&mut state.sound_manager, null_manager),
+            Err(e) => {
+                log::warn!("Failed to create NPC previews: {}", e);
+                return;
+            }
+        };
+        let effect_rng = state.effect_rng.dump_state();
+        let quake = (
+            state.quake_counter,
+            state.quake_rumble_counter,
+            state.super_quake_counter,
+            state.super_quake_rumble_counter,
+        );
+        let carets_len = state.carets.len();
+        let npc_super_pos = state.npc_super_pos;
+        let npc_curly = (state.npc_curly_target, state.npc_curly_counter);
+        let water_level = state.water_level;
+
+        let mut player1 = Player::new(state, ctx);
+        let mut player2 = Player::new(state, ctx);
+        let npc_list = NPCList::new();
+        let mut stage = self.stage.clone();
+        let mut bullet_manager = BulletManager::new();
+        let mut flash = Flash::new();
+        let mut boss = BossNPC::new();
+
+        for entity in missing {
+            let key = (entity.npc_type, entity.flags);
+            if self.entity_previews.contains_key(&key) {
+                continue;
+            }
+
+            let mut npc = NPC::create_from_data(&entity, &state.npc_table, self.stage.map.tile_size);
+            npc.cond.set_alive(true);
+
+            // dying NPCs set their flag
+            let flag = state.get_flag(entity.flag_num as usize);
+            let res = npc.tick(
+                state,
+                ([&mut player1, &mut player2], &npc_list, &mut stage, &mut bullet_manager, &mut flash, &mut boss),
+            );
+            if let Err(e) = res {
+                log::warn!("Failed to create preview of NPC type {}: {}", entity.npc_type, e);
+            }
+            state.set_flag(entity.flag_num as usize, flag);
+
+            self.entity_previews.insert(key, npc);
+        }
+
+        state.sound_manager = sound_manager;
+        state.effect_rng.load_state(effect_rng);
+        (
+            state.quake_counter,
+            state.quake_rumble_counter,
+            state.super_quake_counter,
+            state.super_quake_rumble_counter,
+        ) = quake;
+        state.carets.truncate(carets_len);
+        state.npc_super_pos = npc_super_pos;
+        (state.npc_curly_target, state.npc_curly_counter) = npc_curly;
+        state.water_level = water_level;
+    }
+
+    fn entity_at(&self, state: &SharedGameState, x: i32, y: i32) -> Option<usize> {
+        // iterate backwards so the entity drawn on top is picked first
+        self.entities.iter().rposition(|entity| {
+            let bounds = self.entity_bounds(state, entity);
+            x >= bounds.left && x < bounds.right && y >= bounds.top && y < bounds.bottom
+        })
+    }
+
+    fn create_entity(&mut self, state: &SharedGameState) {
+        let tile_size = self.stage.map.tile_size.as_int();
+        let x = (self.frame.x / 0x200 + (state.canvas_size.0 / 2.0) as i32) / tile_size;
+        let y = (self.frame.y / 0x200 + (state.canvas_size.1 / 2.0) as i32) / tile_size;
+
+        let entity = NPCData {
+            id: 170 + self.entities.len() as u16,
+            x: x.clamp(0, self.stage.map.width as i32 - 1) as i16,
+            y: y.clamp(0, self.stage.map.height as i32 - 1) as i16,
+            flag_num: 0,
+            event_num: 0,
+            npc_type: 0,
+            flags: 0,
+            layer: 0,
+        };
+
+        self.entities.push(entity.clone());
+        self.selected_entity = Some(self.entities.len() - 1);
+        self.history.push(EditorCommand::AddEntity(self.entities.len() - 1, entity));
+    }
+
+    fn delete_selected_entity(&mut self) {
+        self.finish_mouse_action();
+
+        if let Some(idx) = self.selected_entity.take() {
+            if idx < self.entities.len() {
+                let entity = self.entities.remove(idx);
+                self.history.push(EditorCommand::RemoveEntity(idx, entity));
+            }
+
+            self.renumber_entities();
+        }
+    }
+
     fn tile_cursor(&self, state: &mut SharedGameState, ctx: &mut Context) -> GameResult {
         if self.want_capture_mouse {
             return Ok(());
@@ -128,10 +521,7 @@ impl EditorInstance {
 
         let tile_size = self.stage.map.tile_size.as_int();
         let halft = tile_size / 2;
-        let stage_mouse_x = (self.frame.x / 0x200) + halft + (self.mouse_pos.0 / self.zoom) as i32;
-        let stage_mouse_y = (self.frame.y / 0x200) + halft + (self.mouse_pos.1 / self.zoom) as i32;
-        let tile_x = stage_mouse_x / tile_size;
-        let tile_y = stage_mouse_y / tile_size;
+        let (tile_x, tile_y) = self.cursor_tile_pos();
         let frame_x = self.frame.x as f32 / 512.0;
         let frame_y = self.frame.y as f32 / 512.0;
 
@@ -211,6 +601,141 @@ impl EditorInstance {
             });
     }
 
+    fn entity_window(&mut self, state: &mut SharedGameState, ui: &imgui::Ui) {
+        Window::new("Entities")
+            .size([300.0, 460.0], Condition::FirstUseEver)
+            .position(ui.io().display_size, Condition::FirstUseEver)
+            .position_pivot([1.0, 1.0])
+            .build(ui, || {
+                if ui.button("New") {
+                    self.create_entity(state);
+                }
+
+                ui.same_line();
+                ui.disabled(self.selected_entity.is_none(), || {
+                    if ui.button("Delete") {
+                        self.delete_selected_entity();
+                    }
+                });
+
+                let labels: Vec<String> = self
+                    .entities
+                    .iter()
+                    .map(|e| format!("#{} type={} event={} ({}, {})", e.id, e.npc_type, e.event_num, e.x, e.y))
+                    .collect();
+                let labels: Vec<&str> = labels.iter().map(|l| l.as_str()).collect();
+                let mut selected = self.selected_entity.map_or(-1, |idx| idx as i32);
+
+                if ui.list_box("##entities", &mut selected, &labels, 8) {
+                    self.selected_entity = if selected >= 0 { Some(selected as usize) } else { None };
+                }
+
+                let idx = match self.selected_entity {
+                    Some(idx) if idx < self.entities.len() => idx,
+                    _ => return,
+                };
+                let old_entity = self.entities[idx].clone();
+                let entity = &mut self.entities[idx];
+
+                ui.separator();
+
+                let mut position = [entity.x as i32, entity.y as i32];
+                if ui.input_int2("Position", &mut position).build() {
+                    entity.x = position[0].clamp(0, self.stage.map.width as i32 - 1) as i16;
+                    entity.y = position[1].clamp(0, self.stage.map.height as i32 - 1) as i16;
+                }
+
+                let mut npc_type = entity.npc_type as i32;
+                if ui.input_int("Type", &mut npc_type).build() {
+                    entity.npc_type = npc_type.clamp(0, u16::MAX as i32) as u16;
+                }
+
+                let mut event_num = entity.event_num as i32;
+                if ui.input_int("Event", &mut event_num).build() {
+                    entity.event_num = event_num.clamp(0, 9999) as u16;
+                }
+
+                let mut flag_num = entity.flag_num as i32;
+                if ui.input_int("Flag", &mut flag_num).build() {
+                    entity.flag_num = flag_num.clamp(0, 7999) as u16;
+                }
+
+                if let Some(table_entry) = state.npc_table.get_entry(entity.npc_type) {
+                    let texture = state.npc_table.get_texture_ref(table_entry.spritesheet_id as u16);
+                    ui.text(format!("Spritesheet: {}", &*texture));
+                    ui.text(format!("Life: {}, Damage: {}", table_entry.life, table_entry.damage));
+                } else {
+                    ui.text_colored([1.0, 0.4, 0.4, 1.0], "Unknown NPC type!");
+                }
+
+                ui.separator();
+
+                for (bit, name) in NPC_FLAG_NAMES.iter().enumerate() {
+                    ui.checkbox_flags(name, &mut entity.flags, 1 << bit);
+                }
+
+                if *entity != old_entity {
+                    let new_entity = entity.clone();
+                    self.history.push_merged_modify(idx, old_entity, new_entity);
+                }
+            });
+    }
+
+    fn draw_entities(&self, state: &mut SharedGameState, ctx: &mut Context, tool: CurrentTool) -> GameResult {
+        // NPCs look up their spritesheets in the textures of the stage being played
+        let stage_textures = std::mem::replace(&mut state.npc_table.stage_textures, self.stage_textures.clone());
+        let result = self.draw_entity_list(state, ctx, tool);
+        state.npc_table.stage_textures = stage_textures;
+
+        result
+    }
+
+    fn draw_entity_list(&self, state: &mut SharedGameState, ctx: &mut Context, tool: CurrentTool) -> GameResult {
+        let (frame_x, frame_y) = self.frame.xy_interpolated(state.frame_time);
+        let alpha = if tool == CurrentTool::Entity { 160 } else { 64 };
+        let tile_size = self.stage.map.tile_size.as_int() * 0x200;
+
+        for (idx, entity) in self.entities.iter().enumerate() {
+            if let Some(preview) = self.entity_previews.get(&(entity.npc_type, entity.flags)) {
+                let mut npc = preview.clone();
+                npc.x = entity.x as i32 * tile_size;
+                npc.y = entity.y as i32 * tile_size;
+                npc.prev_x = npc.x;
+                npc.prev_y = npc.y;
+                npc.shock = 0;
+                npc.cond.set_alive(true);
+                npc.cond.set_hidden(false);
+
+                npc.draw(state, ctx, &self.frame)?;
+            }
+
+            let bounds = self.entity_bounds(state, entity);
+            let rect = Rect::new(
+                ((bounds.left as f32 - frame_x) * state.scale) as isize,
+                ((bounds.top as f32 - frame_y) * state.scale) as isize,
+                ((bounds.right as f32 - frame_x) * state.scale) as isize,
+                ((bounds.bottom as f32 - frame_y) * state.scale) as isize,
+            );
+
+            let selected = self.selected_entity == Some(idx);
+            let color =
+                if selected { Color::from_rgba(255, 64, 64, alpha) } else { Color::from_rgba(64, 255, 64, alpha) };
+
+            graphics::draw_outline_rect(ctx, rect, if selected { 2 } else { 1 }, color)?;
+
+            if tool == CurrentTool::Entity {
+                state.font.builder().position(bounds.left as f32 - frame_x, bounds.top as f32 - frame_y).draw(
+                    &entity.npc_type.to_string(),
+                    ctx,
+                    &state.constants,
+                    &mut state.texture_set,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn draw(&self, state: &mut SharedGameState, ctx: &mut Context, tool: CurrentTool) -> GameResult {
         let old_scale = state.scale;
         set_scale(state, self.zoom);
@@ -224,9 +749,10 @@ impl EditorInstance {
         self.tilemap.draw(state, ctx, &self.frame, TileLayer::Snack, &*paths, &self.stage)?;
 
         self.draw_black_bars(state, ctx)?;
+        self.draw_entities(state, ctx, tool)?;
 
         match tool {
-            CurrentTool::Move => (),
+            CurrentTool::Move | CurrentTool::Entity => (),
             CurrentTool::Brush | CurrentTool::Fill | CurrentTool::Rectangle => {
                 self.tile_cursor(state, ctx)?;
             }
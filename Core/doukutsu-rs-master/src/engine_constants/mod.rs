@@ -8,21 +8,24 @@ use xmltree::Element;
 use crate::case_insensitive_hashmap;
 use crate::common::{BulletFlag, Color, Rect};
 use crate::engine_constants::npcs::NPCConsts;
+use crate::engine_constants::overrides::override_section;
 use crate::framework::context::Context;
-use crate::framework::error::GameResult;
+use crate::framework::error::{GameError, GameResult};
 use crate::framework::filesystem;
 use crate::framework::gamepad::{Axis, Button};
 use crate::game::player::ControlMode;
 use crate::game::scripting::tsc::text_script::TextScriptEncoding;
 use crate::game::settings::Settings;
 use crate::game::shared_game_state::{FontData, Season};
+use crate::game::weapon::custom::CustomWeapon;
 use crate::i18n::Locale;
 use crate::sound::pixtone::{Channel, Envelope, PixToneParameters, Waveform};
 use crate::sound::backend::SoundManager;
 
 mod npcs;
+mod overrides;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PhysicsConsts {
     pub max_dash: i32,
     pub max_move: i32,
@@ -34,7 +37,7 @@ pub struct PhysicsConsts {
     pub jump: i32,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
 pub struct BoosterConsts {
     pub fuel: u32,
     pub b2_0_up: i32,
@@ -44,7 +47,7 @@ pub struct BoosterConsts {
     pub b2_0_right: i32,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PlayerConsts {
     pub life: u16,
     pub max_life: u16,
@@ -56,7 +59,7 @@ pub struct PlayerConsts {
     pub frames_bubble: [Rect<u16>; 2],
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
 pub struct GameConsts {
     pub intro_stage: u16,
     pub intro_event: u16,
@@ -67,7 +70,7 @@ pub struct GameConsts {
     pub tile_offset_x: i32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct CaretConsts {
     pub offsets: [(i32, i32); 18],
     pub bubble_left_rects: Vec<Rect<u16>>,
@@ -125,7 +128,7 @@ impl Clone for CaretConsts {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
 pub struct BulletData {
     pub damage: u8,
     pub life: u8,
@@ -138,7 +141,7 @@ pub struct BulletData {
     pub display_bounds: Rect<u8>,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
 pub struct BulletRects {
     pub b001_snake_l1: [Rect<u16>; 8],
     pub b002_003_snake_l2_3: [Rect<u16>; 3],
@@ -175,7 +178,7 @@ pub struct BulletRects {
     pub b042_spur_trail_l3: [Rect<u16>; 6],
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct WeaponConsts {
     pub bullet_table: Vec<BulletData>,
     pub bullet_rects: BulletRects,
@@ -192,7 +195,7 @@ impl Clone for WeaponConsts {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
 pub struct WorldConsts {
     pub snack_rect: Rect<u16>,
     pub water_push_rect: Rect<u16>,
@@ -212,7 +215,7 @@ pub struct ExtraSoundtrack {
     pub available: bool,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
 pub struct TextScriptConsts {
     pub encoding: TextScriptEncoding,
     pub encrypted: bool,
@@ -245,7 +248,7 @@ pub struct TextScriptConsts {
     pub fade_ticks: i8,
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct TitleConsts {
     pub intro_text: String,
     pub logo_rect: Rect<u16>,
@@ -316,6 +319,8 @@ impl GamepadConsts {
 #[derive(Debug)]
 pub struct EngineConstants {
     pub base_paths: Vec<String>,
+    /// Paths searched for TSC scripts, `base_paths` with the per-locale overlays in front of each.
+    pub text_script_paths: Vec<String>,
     pub is_cs_plus: bool,
     pub is_switch: bool,
     pub is_demo: bool,
@@ -345,12 +350,17 @@ pub struct EngineConstants {
     pub missile_flags: Vec<u16>,
     pub locales: Vec<Locale>,
     pub gamepad: GamepadConsts,
+    /// Weapons defined by `weapons.json` files, keyed by weapon id.
+    pub custom_weapons: HashMap<u8, CustomWeapon>,
+    /// Patches reverting the changes made by `constants.json` files, in the order they were applied.
+    constant_overrides: Vec<(String, serde_json::Value)>,
 }
 
 impl Clone for EngineConstants {
     fn clone(&self) -> EngineConstants {
         EngineConstants {
             base_paths: self.base_paths.clone(),
+            text_script_paths: self.text_script_paths.clone(),
             is_cs_plus: self.is_cs_plus,
             is_switch: self.is_switch,
             is_demo: self.is_demo,
@@ -380,6 +390,8 @@ impl Clone for EngineConstants {
             missile_flags: self.missile_flags.clone(),
             locales: self.locales.clone(),
             gamepad: self.gamepad.clone(),
+            custom_weapons: self.custom_weapons.clone(),
+            constant_overrides: self.constant_overrides.clone(),
         }
     }
 }
@@ -388,6 +400,7 @@ impl EngineConstants {
     pub fn defaults() -> Self {
         EngineConstants {
             base_paths: Vec::new(),
+            text_script_paths: Vec::new(),
             is_cs_plus: false,
             is_switch: false,
             is_demo: false,
@@ -1715,7 +1728,9 @@ impl EngineConstants {
                 holder.button_rects.get_mut(&Button::East).unwrap()[3] = button;
 
                 holder
-            }
+            },
+            custom_weapons: HashMap::new(),
+            constant_overrides: Vec::new(),
         }
     }
 
@@ -1848,6 +1863,22 @@ impl EngineConstants {
                 self.base_paths.retain(|path| !path.contains("ogph/"));
             }
         }
+
+        self.rebuild_text_script_paths(settings);
+    }
+
+    /// Rebuilds the list of directories scripts are looked up in, which depends on the fallback chain of the locale.
+    pub fn rebuild_text_script_paths(&mut self, settings: &Settings) {
+        // translated scripts in locale/<code>/ take precedence over the ones in the same directory,
+        // English ones are the untranslated originals
+        let chain = Locale::fallback_chain(&self.locales, &settings.locale);
+        self.text_script_paths.clear();
+        for path in self.base_paths.iter() {
+            for code in chain.iter().filter(|code| *code != "en") {
+                self.text_script_paths.push(format!("{}locale/{}/", path, code));
+            }
+            self.text_script_paths.push(path.clone());
+        }
     }
 
     pub fn special_treatment_for_csplus_mods(&mut self, mod_path: Option<&String>) {
@@ -1925,7 +1956,104 @@ impl EngineConstants {
         Ok(())
     }
 
-    pub fn apply_constant_json_files(&mut self) {}
+    /// Applies `constants.json` files found in data directories over the current constants, starting with
+    /// the lowest priority directory so mods can override the base game. Values changed by previously
+    /// applied files are reverted first.
+    pub fn apply_constant_json_files(&mut self, ctx: &mut Context) -> GameResult {
+        self.revert_constant_json_files();
+
+        for root in self.base_paths.clone().iter().rev() {
+            let path = [root, "constants.json"].join("");
+            if !filesystem::exists(ctx, &path) {
+                continue;
+            }
+
+            let patch: serde_json::Value = serde_json::from_reader(filesystem::open(ctx, &path)?)
+                .map_err(|e| GameError::ParseError(format!("{}: {}", path, e)))?;
+            let sections = match patch {
+                serde_json::Value::Object(sections) => sections,
+                _ => return Err(GameError::ParseError(format!("{}: expected an object", path))),
+            };
+
+            log::info!("Applying constant overrides from {}", path);
+            for (key, value) in sections.iter() {
+                let undo = self.override_constant(key, value).map_err(|e| match e {
+                    GameError::InvalidValue(msg) => GameError::InvalidValue(format!("{}: {}", path, msg)),
+                    e => e,
+                })?;
+
+                self.constant_overrides.push((key.clone(), undo));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads weapon definitions from `weapons.json` files in data directories, definitions in mod
+    /// directories replace the ones with the same id.
+    pub fn load_custom_weapons(&mut self, ctx: &mut Context) -> GameResult {
+        self.custom_weapons.clear();
+
+        for root in self.base_paths.clone().iter().rev() {
+            let path = [root, "weapons.json"].join("");
+            if !filesystem::exists(ctx, &path) {
+                continue;
+            }
+
+            let weapons: HashMap<String, CustomWeapon> = serde_json::from_reader(filesystem::open(ctx, &path)?)
+                .map_err(|e| GameError::ParseError(format!("{}: {}", path, e)))?;
+
+            for (key, weapon) in weapons {
+                let id = match key.parse::<u8>() {
+                    Ok(id) if id >= 14 => id,
+                    _ => {
+                        return Err(GameError::InvalidValue(format!(
+                            "{}: weapon id must be a number between 14 and 255, found {}",
+                            path, key
+                        )))
+                    }
+                };
+
+                if let Some(&btype) = weapon.bullets.iter().find(|&&b| b as usize >= self.weapon.bullet_table.len()) {
+                    return Err(GameError::InvalidValue(format!("{}: weapon {}: unknown bullet type {}", path, id, btype)));
+                }
+
+                log::info!("Loaded custom weapon {} ({}) from {}", id, weapon.name, path);
+                self.custom_weapons.insert(id, weapon);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restores the values changed by `apply_constant_json_files`.
+    pub fn revert_constant_json_files(&mut self) {
+        let overrides = std::mem::take(&mut self.constant_overrides);
+
+        for (key, undo) in overrides.iter().rev() {
+            if let Err(e) = self.override_constant(key, undo) {
+                log::warn!("Failed to revert constant override: {}", e);
+            }
+        }
+    }
+
+    fn override_constant(&mut self, key: &str, patch: &serde_json::Value) -> GameResult<serde_json::Value> {
+        match key {
+            "game" => override_section(&mut self.game, patch, key),
+            "player" => override_section(&mut self.player, patch, key),
+            "booster" => override_section(&mut self.booster, patch, key),
+            "caret" => override_section(&mut self.caret, patch, key),
+            "world" => override_section(&mut self.world, patch, key),
+            "npc" => override_section(&mut self.npc, patch, key),
+            "weapon" => override_section(&mut self.weapon, patch, key),
+            "textscript" => override_section(&mut self.textscript, patch, key),
+            "title" => override_section(&mut self.title, patch, key),
+            "inventory_dim_color" => override_section(&mut self.inventory_dim_color, patch, key),
+            "font_space_offset" => override_section(&mut self.font_space_offset, patch, key),
+            "missile_flags" => override_section(&mut self.missile_flags, patch, key),
+            _ => Err(GameError::InvalidValue(format!("{}: unknown constant", key))),
+        }
+    }
 
     pub fn load_texture_size_hints(&mut self, ctx: &mut Context) -> GameResult {
         if let Ok(file) = filesystem::open_find(ctx, &self.base_paths, "texture_sizes.json") {
@@ -0,0 +1,114 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::framework::error::{GameError, GameResult};
+
+fn kind_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(n) if n.is_f64() => "a floating point number",
+        Value::Number(_) => "an integer",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+fn same_kind(target: &Value, patch: &Value) -> bool {
+    match (target, patch) {
+        // integers are accepted in place of floats, but not the other way around
+        (Value::Number(target), Value::Number(patch)) => target.is_f64() || !patch.is_f64(),
+        _ => std::mem::discriminant(target) == std::mem::discriminant(patch),
+    }
+}
+
+/// Merges `patch` into `target` and returns a patch that restores the replaced values.
+///
+/// Objects are merged key by key. Arrays can either be replaced as a whole or patched per element
+/// by passing an object with index keys, eg. `{"5": {"damage": 10}}`. Unknown keys and values of
+/// a different type are rejected with an error naming the offending key.
+pub fn merge_json(target: &mut Value, patch: &Value, path: &str) -> GameResult<Value> {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            let mut undo = Map::new();
+
+            for (key, value) in patch {
+                let key_path = format!("{}.{}", path, key);
+                let entry = target
+                    .get_mut(key)
+                    .ok_or_else(|| GameError::InvalidValue(format!("{}: unknown constant", key_path)))?;
+
+                undo.insert(key.clone(), merge_json(entry, value, &key_path)?);
+            }
+
+            Ok(Value::Object(undo))
+        }
+        (Value::Array(target), Value::Object(patch)) => {
+            let mut undo = Map::new();
+
+            for (key, value) in patch {
+                let key_path = format!("{}[{}]", path, key);
+                let len = target.len();
+                let entry = key.parse::<usize>().ok().and_then(|idx| target.get_mut(idx)).ok_or_else(|| {
+                    GameError::InvalidValue(format!("{}: expected an index lower than {}", key_path, len))
+                })?;
+
+                undo.insert(key.clone(), merge_json(entry, value, &key_path)?);
+            }
+
+            Ok(Value::Object(undo))
+        }
+        (target, patch) => {
+            if !same_kind(target, patch) {
+                return Err(GameError::InvalidValue(format!(
+                    "{}: expected {}, found {}",
+                    path,
+                    kind_name(target),
+                    kind_name(patch)
+                )));
+            }
+
+            Ok(std::mem::replace(target, patch.clone()))
+        }
+    }
+}
+
+/// Applies a JSON patch to a constants section, returns a patch that reverts the change.
+pub fn override_section<T: Serialize + DeserializeOwned>(
+    section: &mut T,
+    patch: &Value,
+    path: &str,
+) -> GameResult<Value> {
+    let mut value = serde_json::to_value(&*section)?;
+    let undo = merge_json(&mut value, patch, path)?;
+
+    *section = serde_json::from_value(value).map_err(|e| GameError::InvalidValue(format!("{}: {}", path, e)))?;
+
+    Ok(undo)
+}
+
+#[test]
+fn merge_json_test() {
+    let mut target = serde_json::json!({ "life": 3, "speed": 1.5, "rects": [[0, 0, 16, 16], [16, 0, 32, 16]] });
+    let original = target.clone();
+
+    let patch = serde_json::json!({ "life": 5, "speed": 2.5, "rects": { "1": [0, 16, 16, 32] } });
+    let undo = merge_json(&mut target, &patch, "test").unwrap();
+
+    assert_eq!(target["life"], 5);
+    assert_eq!(target["rects"][1], serde_json::json!([0, 16, 16, 32]));
+
+    merge_json(&mut target, &undo, "test").unwrap();
+    assert_eq!(target, original);
+
+    let err = merge_json(&mut target, &serde_json::json!({ "lief": 5 }), "test").unwrap_err();
+    assert!(err.to_string().contains("test.lief"));
+
+    let err = merge_json(&mut target, &serde_json::json!({ "life": 1.5 }), "test").unwrap_err();
+    assert!(err.to_string().contains("test.life"));
+
+    let err = merge_json(&mut target, &serde_json::json!({ "rects": { "2": [] } }), "test").unwrap_err();
+    assert!(err.to_string().contains("test.rects[2]"));
+}
@@ -4,7 +4,7 @@ use imgui::DrawData;
 
 use crate::common::{Color, Rect};
 use crate::framework::context::Context;
-use crate::framework::error::GameResult;
+use crate::framework::error::{GameError, GameResult};
 use crate::framework::graphics::{BlendMode, VSyncMode};
 use crate::game::Game;
 use crate::game::shared_game_state::SharedGameState;
@@ -23,6 +23,15 @@ pub enum BackendShader {
     WaterFill(f32, f32, (f32, f32)),
     Fill,
     Texture,
+    /// (width, height) of the normal map texture, which has to match the render target
+    NormalLight(f32, f32),
+}
+
+/// Fragment shader of a post-process pass, along with values for its `Params` uniform.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostProcessShader {
+    pub source: String,
+    pub params: [f32; 4],
 }
 
 pub trait Backend {
@@ -54,6 +63,17 @@ pub trait BackendRenderer {
         Ok(())
     }
 
+    /// Sets the shaders the final frame is rendered through before presenting, `scale` is the size of a game pixel.
+    fn set_post_process(&mut self, _passes: &[PostProcessShader], _scale: f32) -> GameResult {
+        Ok(())
+    }
+
+    /// Reads back the frame drawn so far as `(width, height, RGBA pixels)`, top row first. Post-processing isn't
+    /// applied to it, since that only happens when presenting.
+    fn capture_frame(&mut self) -> GameResult<(u16, u16, Vec<u8>)> {
+        Err(GameError::RenderError("Frame capture is not supported by this renderer.".to_owned()))
+    }
+
     fn create_texture_mutable(&mut self, width: u16, height: u16) -> GameResult<Box<dyn BackendTexture>>;
 
     fn create_texture(&mut self, width: u16, height: u16, data: &[u8]) -> GameResult<Box<dyn BackendTexture>>;
@@ -80,6 +100,11 @@ pub trait BackendRenderer {
         false
     }
 
+    /// Whether `BackendShader::NormalLight` can be drawn with `draw_triangle_list`.
+    fn supports_normal_lighting(&self) -> bool {
+        false
+    }
+
     fn draw_triangle_list(
         &mut self,
         vertices: &[VertexData],
@@ -99,6 +124,11 @@ pub trait BackendTexture {
 
     fn draw(&mut self) -> GameResult;
 
+    /// Replaces the pixels of given region of the texture with RGBA data.
+    fn update(&mut self, _x: u16, _y: u16, _width: u16, _height: u16, _data: &[u8]) -> GameResult {
+        Err(GameError::RenderError("Updating textures is not supported by this backend.".to_owned()))
+    }
+
     fn as_any(&self) -> &dyn Any;
 }
 
@@ -1319,7 +1319,7 @@ impl BackendRenderer for Deko3DRenderer {
         cmdbuf.bind_vtx_buffer(0, self.vbo.buffer.get_gpu_addr(), self.vbo.buffer.get_size());
 
         match shader {
-            BackendShader::Fill | BackendShader::WaterFill(_, _, _) => {
+            BackendShader::Fill | BackendShader::WaterFill(_, _, _) | BackendShader::NormalLight(_, _) => {
                 self.color_shader.update_uniforms(VertUBO { proj_mtx: self.curr_mtx });
                 self.color_shader.bind(cmdbuf);
             }
@@ -161,7 +161,17 @@ struct SDL2Context {
 impl SDL2EventLoop {
     pub fn new(sdl: &Sdl, size_hint: (u16, u16), ctx: &Context) -> GameResult<Box<dyn BackendEventLoop>> {
         let event_pump = sdl.event_pump().map_err(GameError::WindowError)?;
-        let video = sdl.video().map_err(GameError::WindowError)?;
+        let video = match sdl.video() {
+            Ok(video) => video,
+            // there's no display to show the hidden window on (eg. when rendering replays on a server),
+            // SDL's offscreen driver still provides an OpenGL context through EGL
+            Err(err) if ctx.hidden_window => {
+                log::warn!("Failed to initialize video ({}), falling back to the offscreen driver.", err);
+                std::env::set_var("SDL_VIDEODRIVER", "offscreen");
+                sdl.video().map_err(GameError::WindowError)?
+            }
+            Err(err) => return Err(GameError::WindowError(err)),
+        };
 
         let game_controller = sdl.game_controller().map_err(GameError::GamepadError)?;
         let mut controller_mappings = filesystem::open(ctx, "/builtin/gamecontrollerdb.txt")?;
@@ -175,6 +185,9 @@ impl SDL2EventLoop {
         let mut win_builder = video.window("Cave Story (doukutsu-rs)", size_hint.0 as _, size_hint.1 as _);
         win_builder.position_centered();
         win_builder.resizable();
+        if ctx.hidden_window {
+            win_builder.hidden();
+        }
 
         #[cfg(feature = "render-opengl")]
         win_builder.opengl();
@@ -1103,6 +1116,19 @@ impl BackendTexture for SDL2Texture {
         }
     }
 
+    fn update(&mut self, x: u16, y: u16, width: u16, height: u16, data: &[u8]) -> GameResult {
+        match &mut self.texture {
+            None => Ok(()),
+            Some(texture) => texture
+                .update(
+                    Some(sdl2::rect::Rect::new(x as i32, y as i32, width as u32, height as u32)),
+                    data,
+                    width as usize * 4,
+                )
+                .map_err(|e| GameError::RenderError(e.to_string())),
+        }
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
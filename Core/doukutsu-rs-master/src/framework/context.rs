@@ -15,6 +15,8 @@ use crate::framework::backend_libretro::{LibretroBackend, LibretroEventLoop, Ren
 
 pub struct Context {
     pub headless: bool,
+    /// Keeps the window from being shown, for rendering without user interaction.
+    pub hidden_window: bool,
     pub size_hint: (u16, u16),
     pub(crate) filesystem: Filesystem,
     pub(crate) renderer: Option<Box<dyn BackendRenderer>>,
@@ -30,6 +32,7 @@ impl Context {
     pub fn new() -> Context {
         Context {
             headless: false,
+            hidden_window: false,
             size_hint: (640, 480),
             filesystem: Filesystem::new(),
             renderer: None,
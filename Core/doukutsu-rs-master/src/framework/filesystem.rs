@@ -218,6 +218,11 @@ impl Filesystem {
         self.vfs.push_back(vfs);
     }
 
+    /// Mounts a VFS which takes precedence over all the ones mounted before.
+    pub fn mount_vfs_front(&mut self, vfs: Box<dyn vfs::VFS>) {
+        self.vfs.push_front(vfs);
+    }
+
     pub fn mount_user_vfs(&mut self, vfs: Box<dyn vfs::VFS>) {
         self.user_vfs.push_back(vfs);
     }
@@ -387,6 +392,11 @@ pub fn mount_vfs(ctx: &mut Context, vfs: Box<dyn vfs::VFS>) {
     ctx.filesystem.mount_vfs(vfs)
 }
 
+/// Adds a VFS in front of the list of resource search locations.
+pub fn mount_vfs_front(ctx: &mut Context, vfs: Box<dyn vfs::VFS>) {
+    ctx.filesystem.mount_vfs_front(vfs)
+}
+
 /// Adds a VFS to the list of user data search locations.
 pub fn mount_user_vfs(ctx: &mut Context, vfs: Box<dyn vfs::VFS>) {
     ctx.filesystem.mount_user_vfs(vfs)
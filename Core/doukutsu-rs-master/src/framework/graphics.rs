@@ -1,5 +1,5 @@
 use crate::common::{Color, Rect};
-use crate::framework::backend::{BackendShader, BackendTexture, VertexData};
+use crate::framework::backend::{BackendShader, BackendTexture, PostProcessShader, VertexData};
 use crate::framework::context::Context;
 use crate::framework::error::{GameError, GameResult};
 
@@ -60,6 +60,22 @@ pub fn set_vsync_mode(ctx: &mut Context, mode: VSyncMode) -> GameResult {
     Ok(())
 }
 
+pub fn set_post_process(ctx: &mut Context, passes: &[PostProcessShader], scale: f32) -> GameResult {
+    if let Some(renderer) = &mut ctx.renderer {
+        renderer.set_post_process(passes, scale)?;
+    }
+
+    Ok(())
+}
+
+pub fn capture_frame(ctx: &mut Context) -> GameResult<(u16, u16, Vec<u8>)> {
+    if let Some(renderer) = &mut ctx.renderer {
+        return renderer.capture_frame();
+    }
+
+    Err(GameError::RenderError("Rendering backend hasn't been initialized yet.".to_string()))
+}
+
 #[allow(unused)]
 pub fn renderer_initialized(ctx: &mut Context) -> bool {
     ctx.renderer.is_some()
@@ -184,6 +200,10 @@ pub fn supports_vertex_draw(ctx: &Context) -> GameResult<bool> {
     Err(GameError::RenderError("Rendering backend hasn't been initialized yet.".to_string()))
 }
 
+pub fn supports_normal_lighting(ctx: &Context) -> bool {
+    ctx.renderer.as_ref().map_or(false, |renderer| renderer.supports_normal_lighting())
+}
+
 pub fn draw_triangle_list(
     ctx: &mut Context,
     vertices: &[VertexData],
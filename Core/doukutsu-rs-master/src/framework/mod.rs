@@ -18,6 +18,7 @@ pub mod gamepad;
 mod gl;
 pub mod graphics;
 pub mod keyboard;
+pub mod post_process;
 #[cfg(feature = "render-opengl")]
 pub mod render_opengl;
 pub mod ui;
@@ -0,0 +1,104 @@
+use std::io::Read;
+
+use crate::framework::backend::PostProcessShader;
+use crate::framework::context::Context;
+use crate::framework::error::{GameError, GameResult};
+use crate::framework::filesystem;
+
+const SHADER_CRT: &str = include_str!("shaders/post_process/crt.glsl");
+const SHADER_SCANLINES: &str = include_str!("shaders/post_process/scanlines.glsl");
+const SHADER_LCD_GRID: &str = include_str!("shaders/post_process/lcd_grid.glsl");
+const SHADER_COLORBLIND: &str = include_str!("shaders/post_process/colorblind.glsl");
+
+/// Built-in post-process shaders, `(name, source, default parameters)`.
+pub const PRESETS: [(&str, &str, [f32; 4]); 6] = [
+    ("crt", SHADER_CRT, [0.08, 0.3, 0.2, 0.35]),
+    ("scanlines", SHADER_SCANLINES, [0.4, 0.0, 0.0, 0.0]),
+    ("lcd_grid", SHADER_LCD_GRID, [0.35, 0.0, 0.0, 0.0]),
+    ("protanopia", SHADER_COLORBLIND, [0.0, 1.0, 0.0, 0.0]),
+    ("deuteranopia", SHADER_COLORBLIND, [1.0, 1.0, 0.0, 0.0]),
+    ("tritanopia", SHADER_COLORBLIND, [2.0, 1.0, 0.0, 0.0]),
+];
+
+/// A single pass of the post-process chain the final frame is rendered through.
+///
+/// Shaders are written in GLSL 1.10 without a version header and get `Texture`, `Params` (vec4),
+/// `OutputSize` (vec2, in pixels), `Scale` (size of a game pixel) and `Time` (frame counter) uniforms.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PostProcessPass {
+    /// Name of a built-in preset, or path of a GLSL fragment shader relative to the data directories.
+    pub shader: String,
+    /// Values of the `Params` uniform, their meaning depends on the shader.
+    #[serde(default)]
+    pub params: [f32; 4],
+}
+
+impl PostProcessPass {
+    pub fn preset(name: &str) -> Option<PostProcessPass> {
+        PRESETS
+            .iter()
+            .find(|(preset, _, _)| *preset == name)
+            .map(|(name, _, params)| PostProcessPass { shader: name.to_string(), params: *params })
+    }
+}
+
+/// Returns the name of the preset the chain consists of, `None` if it's empty or user-defined.
+pub fn chain_preset(passes: &[PostProcessPass]) -> Option<&str> {
+    match passes {
+        [pass] if PRESETS.iter().any(|(name, _, _)| *name == pass.shader) => Some(pass.shader.as_str()),
+        _ => None,
+    }
+}
+
+/// Looks up the fragment shader sources of the chain, user shaders are searched for in given roots.
+/// Passes whose shader can't be loaded are logged and left out, so the rest of the chain still applies.
+pub fn load_chain(ctx: &Context, roots: &Vec<String>, passes: &[PostProcessPass]) -> Vec<PostProcessShader> {
+    let mut shaders = Vec::with_capacity(passes.len());
+
+    for pass in passes {
+        match load_source(ctx, roots, &pass.shader) {
+            Ok(source) => shaders.push(PostProcessShader { source, params: pass.params }),
+            Err(err) => log::error!("Failed to load post-process shader {}: {}", pass.shader, err),
+        }
+    }
+
+    shaders
+}
+
+fn load_source(ctx: &Context, roots: &Vec<String>, shader: &str) -> GameResult<String> {
+    if let Some((_, source, _)) = PRESETS.iter().find(|(name, _, _)| *name == shader) {
+        return Ok(source.to_string());
+    }
+
+    let mut source = String::new();
+    filesystem::open_find(ctx, roots, shader)?
+        .read_to_string(&mut source)
+        .map_err(|e| GameError::ResourceLoadError(format!("Failed to read shader {}: {}", shader, e)))?;
+
+    Ok(source)
+}
+
+#[test]
+fn test_chain_preset() {
+    assert_eq!(chain_preset(&[]), None);
+    assert_eq!(chain_preset(&[PostProcessPass::preset("crt").unwrap()]), Some("crt"));
+    assert_eq!(chain_preset(&[PostProcessPass { shader: "shaders/custom.glsl".to_owned(), params: [0.0; 4] }]), None);
+    assert_eq!(
+        chain_preset(&[PostProcessPass::preset("crt").unwrap(), PostProcessPass::preset("tritanopia").unwrap()]),
+        None
+    );
+    assert!(PostProcessPass::preset("sepia").is_none());
+}
+
+#[test]
+fn test_load_chain_skips_missing_shaders() {
+    let ctx = Context::new();
+    let passes = [
+        PostProcessPass { shader: "shaders/missing.glsl".to_owned(), params: [0.0; 4] },
+        PostProcessPass::preset("scanlines").unwrap(),
+    ];
+
+    let shaders = load_chain(&ctx, &vec!["/".to_owned()], &passes);
+    assert_eq!(shaders.len(), 1);
+    assert_eq!(shaders[0].source, SHADER_SCANLINES);
+}
@@ -10,7 +10,9 @@ use std::sync::Arc;
 use imgui::{DrawCmd, DrawCmdParams, DrawData, DrawIdx, DrawVert, TextureId, Ui};
 
 use crate::common::{Color, Rect};
-use crate::framework::backend::{BackendRenderer, BackendShader, BackendTexture, SpriteBatchCommand, VertexData};
+use crate::framework::backend::{
+    BackendRenderer, BackendShader, BackendTexture, PostProcessShader, SpriteBatchCommand, VertexData,
+};
 use crate::framework::context::Context;
 use crate::framework::error::GameError;
 use crate::framework::error::GameError::RenderError;
@@ -396,6 +398,37 @@ impl BackendTexture for OpenGLTexture {
         }
     }
 
+    fn update(&mut self, x: u16, y: u16, width: u16, height: u16, data: &[u8]) -> GameResult {
+        unsafe {
+            if let Some(gl) = &GL_PROC {
+                handle_err(gl, 0);
+                if self.texture_id == 0 {
+                    return Ok(());
+                }
+
+                let current_texture_id = return_param(|x| gl.gl.GetIntegerv(gl::TEXTURE_BINDING_2D, x)) as u32;
+                gl.gl.BindTexture(gl::TEXTURE_2D, self.texture_id);
+                gl.gl.TexSubImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    x as _,
+                    y as _,
+                    width as _,
+                    height as _,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    data.as_ptr() as _,
+                );
+                gl.gl.BindTexture(gl::TEXTURE_2D, current_texture_id);
+
+                handle_err(gl, 0);
+                Ok(())
+            } else {
+                Err(RenderError("No OpenGL context available!".to_string()))
+            }
+        }
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -458,23 +491,39 @@ const VERTEX_SHADER_BASIC: &str = include_str!("shaders/opengl/vertex_basic_110.
 const FRAGMENT_SHADER_TEXTURED: &str = include_str!("shaders/opengl/fragment_textured_110.glsl");
 const FRAGMENT_SHADER_COLOR: &str = include_str!("shaders/opengl/fragment_color_110.glsl");
 const FRAGMENT_SHADER_WATER: &str = include_str!("shaders/opengl/fragment_water_110.glsl");
+const FRAGMENT_SHADER_NORMAL_LIGHT: &str = include_str!("shaders/opengl/fragment_normal_light_110.glsl");
 
 // openglES shaders
 const VERTEX_SHADER_BASIC_GLES: &str = include_str!("shaders/opengles/vertex_basic_100.glsl");
 const FRAGMENT_SHADER_TEXTURED_GLES: &str = include_str!("shaders/opengles/fragment_textured_100.glsl");
 const FRAGMENT_SHADER_COLOR_GLES: &str = include_str!("shaders/opengles/fragment_color_100.glsl");
+const FRAGMENT_SHADER_NORMAL_LIGHT_GLES: &str = include_str!("shaders/opengles/fragment_normal_light_100.glsl");
 
 // opengl 3.3 shaders with header "#version 330 core" (mainly for retroarch macOS)
 const VERTEX_SHADER3_BASIC: &str = include_str!("shaders/opengl3/vertex_basic_330.glsl");
 const FRAGMENT_SHADER3_TEXTURED: &str = include_str!("shaders/opengl3/fragment_textured_330.glsl");
 const FRAGMENT_SHADER3_COLOR: &str = include_str!("shaders/opengl3/fragment_color_330.glsl");
 const FRAGMENT_SHADER3_WATER: &str = include_str!("shaders/opengl3/fragment_water_330.glsl");
+const FRAGMENT_SHADER3_NORMAL_LIGHT: &str = include_str!("shaders/opengl3/fragment_normal_light_330.glsl");
 
 // same as stock 2.1 shaders but without version headers (mainly for retroarch, but also works on desktop)
 const VERTEX_SHADERM_BASIC: &str = include_str!("shaders/openglm/vertex_basic_m.glsl");
 const FRAGMENT_SHADERM_TEXTURED: &str = include_str!("shaders/openglm/fragment_textured_m.glsl");
 const FRAGMENT_SHADERM_COLOR: &str = include_str!("shaders/openglm/fragment_color_m.glsl");
 const FRAGMENT_SHADERM_WATER: &str = include_str!("shaders/openglm/fragment_water_m.glsl");
+const FRAGMENT_SHADERM_NORMAL_LIGHT: &str = include_str!("shaders/openglm/fragment_normal_light_m.glsl");
+
+// headers prepended to post-process shaders, which are written for GLSL 1.10 without a version header
+const POST_PROCESS_HEADER: &str = "#version 110\n";
+const POST_PROCESS_HEADER_GLES: &str = "precision mediump float;\n";
+const POST_PROCESS_HEADER3: &str = concat!(
+    "#version 330 core\n",
+    "#define varying in\n",
+    "#define texture2D texture\n",
+    "out vec4 outColor;\n",
+    "#define gl_FragColor outColor\n",
+);
+const POST_PROCESS_HEADERM: &str = "";
 
 #[derive(Copy, Clone)]
 struct RenderShader {
@@ -484,6 +533,8 @@ struct RenderShader {
     scale: GLint,
     time: GLint,
     frame_offset: GLint,
+    params: GLint,
+    output_size: GLint,
     position: GLuint,
     uv: GLuint,
     color: GLuint,
@@ -498,6 +549,8 @@ impl Default for RenderShader {
             scale: 0,
             time: 0,
             frame_offset: 0,
+            params: 0,
+            output_size: 0,
             position: 0,
             uv: 0,
             color: 0,
@@ -562,6 +615,8 @@ impl RenderShader {
             shader.scale = gl.gl.GetUniformLocation(shader.program_id, b"Scale\0".as_ptr() as _) as _;
             shader.time = gl.gl.GetUniformLocation(shader.program_id, b"Time\0".as_ptr() as _) as _;
             shader.frame_offset = gl.gl.GetUniformLocation(shader.program_id, b"FrameOffset\0".as_ptr() as _) as _;
+            shader.params = gl.gl.GetUniformLocation(shader.program_id, b"Params\0".as_ptr() as _) as _;
+            shader.output_size = gl.gl.GetUniformLocation(shader.program_id, b"OutputSize\0".as_ptr() as _) as _;
             shader.position = gl.gl.GetAttribLocation(shader.program_id, b"Position\0".as_ptr() as _) as _;
             shader.uv = gl.gl.GetAttribLocation(shader.program_id, b"UV\0".as_ptr() as _) as _;
             shader.color = gl.gl.GetAttribLocation(shader.program_id, b"Color\0".as_ptr() as _) as _;
@@ -623,11 +678,20 @@ impl RenderShader {
     }
 }
 
+struct PostProcessPass {
+    source: String,
+    shader: RenderShader,
+    params: [f32; 4],
+}
+
 struct RenderData {
     initialized: bool,
+    vertex_shader: &'static str,
+    post_process_header: &'static str,
     tex_shader: RenderShader,
     fill_shader: RenderShader,
     fill_water_shader: RenderShader,
+    normal_light_shader: RenderShader,
     vao: GLuint,
     vbo: GLuint,
     ebo: GLuint,
@@ -636,15 +700,25 @@ struct RenderData {
     surf_framebuffer: GLuint,
     surf_texture: GLuint,
     last_size: (u32, u32),
+    post_process: Vec<PostProcessPass>,
+    post_process_scale: f32,
+    post_process_frame: u32,
+    /// Targets passes render to when they aren't the last one in the chain.
+    post_process_framebuffers: [GLuint; 2],
+    post_process_textures: [GLuint; 2],
+    post_process_size: (u32, u32),
 }
 
 impl RenderData {
     fn new() -> Self {
         RenderData {
             initialized: false,
+            vertex_shader: VERTEX_SHADER_BASIC,
+            post_process_header: POST_PROCESS_HEADER,
             tex_shader: RenderShader::default(),
             fill_shader: RenderShader::default(),
             fill_water_shader: RenderShader::default(),
+            normal_light_shader: RenderShader::default(),
             vao: 0,
             vbo: 0,
             ebo: 0,
@@ -653,6 +727,12 @@ impl RenderData {
             surf_framebuffer: 0,
             surf_texture: 0,
             last_size: (640, 480),
+            post_process: Vec::new(),
+            post_process_scale: 1.0,
+            post_process_frame: 0,
+            post_process_framebuffers: [0; 2],
+            post_process_textures: [0; 2],
+            post_process_size: (0, 0),
         }
     }
 
@@ -667,6 +747,8 @@ impl RenderData {
             fshdr_tex,
             fshdr_fill,
             fshdr_fill_water,
+            fshdr_normal_light,
+            post_process_header,
         ) = match gl_version {
             GlVersionInfo::OpenGL(maj, min) => {
                 if maj == 3 {
@@ -676,7 +758,9 @@ impl RenderData {
                             VERTEX_SHADER_BASIC,
                             FRAGMENT_SHADER_TEXTURED,
                             FRAGMENT_SHADER_COLOR,
-                            FRAGMENT_SHADER_WATER
+                            FRAGMENT_SHADER_WATER,
+                            FRAGMENT_SHADER_NORMAL_LIGHT,
+                            POST_PROCESS_HEADER,
                         )
                     } else {
                         // (retroarch mac requests strict 3.3)
@@ -684,7 +768,9 @@ impl RenderData {
                             VERTEX_SHADER3_BASIC,
                             FRAGMENT_SHADER3_TEXTURED,
                             FRAGMENT_SHADER3_COLOR,
-                            FRAGMENT_SHADER3_WATER
+                            FRAGMENT_SHADER3_WATER,
+                            FRAGMENT_SHADER3_NORMAL_LIGHT,
+                            POST_PROCESS_HEADER3,
                         )
                     }
                 } else {
@@ -693,7 +779,9 @@ impl RenderData {
                         VERTEX_SHADERM_BASIC,
                         FRAGMENT_SHADERM_TEXTURED,
                         FRAGMENT_SHADERM_COLOR,
-                        FRAGMENT_SHADERM_WATER
+                        FRAGMENT_SHADERM_WATER,
+                        FRAGMENT_SHADERM_NORMAL_LIGHT,
+                        POST_PROCESS_HEADERM,
                     )
                 }
 
@@ -704,11 +792,16 @@ impl RenderData {
                     VERTEX_SHADER_BASIC_GLES,
                     FRAGMENT_SHADER_TEXTURED_GLES,
                     FRAGMENT_SHADER_COLOR_GLES,
-                    FRAGMENT_SHADER_COLOR_GLES
+                    FRAGMENT_SHADER_COLOR_GLES,
+                    FRAGMENT_SHADER_NORMAL_LIGHT_GLES,
+                    POST_PROCESS_HEADER_GLES,
                 )
             },
         };
 
+        self.vertex_shader = vshdr_basic;
+        self.post_process_header = post_process_header;
+
         unsafe {
             handle_err(gl, 0);
             self.tex_shader =
@@ -717,6 +810,13 @@ impl RenderData {
                 RenderShader::compile(gl, vshdr_basic, fshdr_fill).unwrap_or_else(|_| RenderShader::default());
             self.fill_water_shader =
                 RenderShader::compile(gl, vshdr_basic, fshdr_fill_water).unwrap_or_else(|_| RenderShader::default());
+            self.normal_light_shader = match RenderShader::compile(gl, vshdr_basic, fshdr_normal_light) {
+                Ok(shader) => shader,
+                Err(e) => {
+                    log::warn!("Failed to compile normal lighting shader, normal maps are disabled: {}", e);
+                    RenderShader::default()
+                }
+            };
 
             self.vao = return_param(|x| gl.gl.GenVertexArrays(1, x));
             self.vbo = return_param(|x| gl.gl.GenBuffers(1, x));
@@ -783,6 +883,80 @@ impl RenderData {
             handle_err(gl, 0);
         }
     }
+
+    unsafe fn set_post_process(&mut self, gl: &Gl, passes: &[PostProcessShader]) -> GameResult {
+        for pass in self.post_process.drain(..) {
+            gl.gl.DeleteProgram(pass.shader.program_id);
+        }
+
+        // a pass that fails to compile is left out, so the rest of the chain still applies
+        let mut result = Ok(());
+        for pass in passes {
+            // RenderShader::compile leaves out the last character, which is expected to be a line break
+            let source = format!("{}{}\n", self.post_process_header, pass.source);
+
+            match RenderShader::compile(gl, self.vertex_shader, &source) {
+                Ok(shader) => {
+                    self.post_process.push(PostProcessPass { source: pass.source.clone(), shader, params: pass.params })
+                }
+                Err(err) => {
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    unsafe fn update_post_process_targets(&mut self, gl: &Gl) {
+        if self.post_process_textures[0] != 0 && self.post_process_size == self.last_size {
+            return;
+        }
+
+        for idx in 0..2 {
+            if self.post_process_textures[idx] == 0 {
+                let texture_id = return_param(|x| gl.gl.GenTextures(1, x));
+                gl.gl.BindTexture(gl::TEXTURE_2D, texture_id);
+                gl.gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+                gl.gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+                gl.gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+                gl.gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+                self.post_process_textures[idx] = texture_id;
+            }
+
+            gl.gl.BindTexture(gl::TEXTURE_2D, self.post_process_textures[idx]);
+            gl.gl.TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as _,
+                self.last_size.0 as _,
+                self.last_size.1 as _,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                null() as _,
+            );
+            gl.gl.BindTexture(gl::TEXTURE_2D, 0 as _);
+
+            if self.post_process_framebuffers[idx] == 0 {
+                let framebuffer_id = return_param(|x| gl.gl.GenFramebuffers(1, x));
+                gl.gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer_id);
+                gl.gl.FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    gl::TEXTURE_2D,
+                    self.post_process_textures[idx],
+                    0,
+                );
+                self.post_process_framebuffers[idx] = framebuffer_id;
+            }
+        }
+
+        self.post_process_size = self.last_size;
+        handle_err(gl, 0);
+    }
 }
 
 pub struct Gl {
@@ -939,9 +1113,6 @@ impl BackendRenderer for OpenGLRenderer {
                 let matrix =
                     [[2.0f32, 0.0, 0.0, 0.0], [0.0, -2.0, 0.0, 0.0], [0.0, 0.0, -1.0, 0.0], [-1.0, 1.0, 0.0, 1.0]];
 
-                self.render_data.tex_shader.bind_attrib_pointer(gl, self.render_data.vbo, self.render_data.vao);
-                gl.gl.UniformMatrix4fv(self.render_data.tex_shader.proj_mtx, 1, gl::FALSE, matrix.as_ptr() as _);
-
                 let color = (255, 255, 255, 255);
                 let vertices = [
                     VertexData { position: (0.0, 1.0), uv: (0.0, 0.0), color },
@@ -953,13 +1124,20 @@ impl BackendRenderer for OpenGLRenderer {
                 ];
                 handle_err(gl, 0);
 
-                //todo: re-enable this later (cannot draw to framebuffer 0)
-                self.draw_arrays_tex_id(
-                    gl::TRIANGLES,
-                    &vertices,
-                    self.render_data.surf_texture,
-                    BackendShader::Texture,
-                )?;
+                if self.render_data.post_process.is_empty() {
+                    self.render_data.tex_shader.bind_attrib_pointer(gl, self.render_data.vbo, self.render_data.vao);
+                    gl.gl.UniformMatrix4fv(self.render_data.tex_shader.proj_mtx, 1, gl::FALSE, matrix.as_ptr() as _);
+
+                    //todo: re-enable this later (cannot draw to framebuffer 0)
+                    self.draw_arrays_tex_id(
+                        gl::TRIANGLES,
+                        &vertices,
+                        self.render_data.surf_texture,
+                        BackendShader::Texture,
+                    )?;
+                } else {
+                    self.draw_post_process(gl, fbo, &matrix, &vertices)?;
+                }
                 handle_err(gl, 0);
 
                 gl.gl.Finish();
@@ -1002,6 +1180,28 @@ impl BackendRenderer for OpenGLRenderer {
         Ok(())
     }
 
+    fn set_post_process(&mut self, passes: &[PostProcessShader], scale: f32) -> GameResult {
+        if let Some((_, gl)) = self.get_context() {
+            self.render_data.post_process_scale = scale;
+
+            // called on every resize, so only recompile when the shaders themselves have changed
+            let unchanged = self.render_data.post_process.len() == passes.len()
+                && self.render_data.post_process.iter().zip(passes).all(|(pass, shader)| pass.source == shader.source);
+
+            if unchanged {
+                for (pass, shader) in self.render_data.post_process.iter_mut().zip(passes) {
+                    pass.params = shader.params;
+                }
+
+                return Ok(());
+            }
+
+            unsafe { self.render_data.set_post_process(gl, passes) }
+        } else {
+            Err(RenderError("No OpenGL context available!".to_string()))
+        }
+    }
+
     fn prepare_draw(&mut self, width: f32, height: f32) -> GameResult {
         if let Some((_, gl)) = self.get_context() {
             unsafe {
@@ -1517,6 +1717,35 @@ impl BackendRenderer for OpenGLRenderer {
         true
     }
 
+    fn supports_normal_lighting(&self) -> bool {
+        self.render_data.normal_light_shader.program_id != 0
+    }
+
+    fn capture_frame(&mut self) -> GameResult<(u16, u16, Vec<u8>)> {
+        unsafe {
+            if let Some(gl) = &GL_PROC {
+                let (width, height) = self.render_data.last_size;
+                let row_len = width as usize * 4;
+                let mut pixels = vec![0u8; row_len * height as usize];
+
+                gl.gl.BindFramebuffer(gl::FRAMEBUFFER, self.render_data.surf_framebuffer);
+                gl.gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+                gl.gl.ReadPixels(0, 0, width as _, height as _, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as _);
+                handle_err(gl, 0);
+
+                // OpenGL returns the bottom row first
+                let mut flipped = Vec::with_capacity(pixels.len());
+                for row in pixels.chunks_exact(row_len).rev() {
+                    flipped.extend_from_slice(row);
+                }
+
+                Ok((width as u16, height as u16, flipped))
+            } else {
+                Err(RenderError("No OpenGL context available!".to_string()))
+            }
+        }
+    }
+
     fn draw_triangle_list(
         &mut self,
         vertices: &[VertexData],
@@ -1557,6 +1786,65 @@ impl OpenGLRenderer {
         unsafe { self.draw_arrays_tex_id(vert_type, vertices, texture_id, shader) }
     }
 
+    /// Renders the canvas through the post-process chain, the last pass draws to `fbo`.
+    unsafe fn draw_post_process(
+        &mut self,
+        gl: &Gl,
+        fbo: GLuint,
+        matrix: &[[f32; 4]; 4],
+        vertices: &[VertexData],
+    ) -> GameResult {
+        let render_data = &mut self.render_data;
+        let (width, height) = render_data.last_size;
+        let count = render_data.post_process.len();
+
+        if count > 1 {
+            render_data.update_post_process_targets(gl);
+        }
+
+        render_data.post_process_frame = render_data.post_process_frame.wrapping_add(1);
+
+        let mut texture = render_data.surf_texture;
+        for (idx, pass) in render_data.post_process.iter().enumerate() {
+            let last = idx + 1 == count;
+
+            if last {
+                gl.gl.BindFramebuffer(gl::FRAMEBUFFER, fbo);
+                gl.gl.Enable(gl::BLEND);
+            } else {
+                gl.gl.BindFramebuffer(gl::FRAMEBUFFER, render_data.post_process_framebuffers[idx % 2]);
+                gl.gl.Disable(gl::BLEND);
+            }
+            gl.gl.Viewport(0, 0, width as GLsizei, height as GLsizei);
+            handle_err(gl, 0);
+
+            pass.shader.bind_attrib_pointer(gl, render_data.vbo, render_data.vao)?;
+            gl.gl.Uniform1i(pass.shader.texture, 0);
+            gl.gl.UniformMatrix4fv(pass.shader.proj_mtx, 1, gl::FALSE, matrix.as_ptr() as _);
+            gl.gl.Uniform4f(pass.shader.params, pass.params[0], pass.params[1], pass.params[2], pass.params[3]);
+            gl.gl.Uniform2f(pass.shader.output_size, width as f32, height as f32);
+            gl.gl.Uniform1f(pass.shader.scale, render_data.post_process_scale);
+            gl.gl.Uniform1f(pass.shader.time, render_data.post_process_frame as f32);
+            handle_err(gl, 0);
+
+            gl.gl.BindTexture(gl::TEXTURE_2D, texture);
+            gl.gl.BufferData(
+                gl::ARRAY_BUFFER,
+                (vertices.len() * mem::size_of::<VertexData>()) as _,
+                vertices.as_ptr() as _,
+                gl::STREAM_DRAW,
+            );
+            gl.gl.DrawArrays(gl::TRIANGLES, 0, vertices.len() as _);
+            gl.gl.BindTexture(gl::TEXTURE_2D, 0);
+            gl.gl.BindBuffer(gl::ARRAY_BUFFER, 0);
+            handle_err(gl, 0);
+
+            texture = render_data.post_process_textures[idx % 2];
+        }
+
+        Ok(())
+    }
+
     unsafe fn draw_arrays_tex_id(
         &mut self,
         vert_type: GLenum,
@@ -1580,6 +1868,13 @@ impl OpenGLRenderer {
                     gl.gl.Uniform2f(self.render_data.fill_water_shader.frame_offset, frame_pos.0, frame_pos.1);
                     texture = self.render_data.surf_texture;
                 }
+                BackendShader::NormalLight(width, height) => {
+                    let shader = &self.render_data.normal_light_shader;
+                    shader.bind_attrib_pointer(gl, self.render_data.vbo, self.render_data.vao)?;
+                    gl.gl.Uniform1i(shader.texture, 0);
+                    gl.gl.UniformMatrix4fv(shader.proj_mtx, 1, gl::FALSE, self.curr_matrix.as_ptr() as _);
+                    gl.gl.Uniform2f(shader.output_size, width, height);
+                }
             }
             handle_err(gl, 0);
 
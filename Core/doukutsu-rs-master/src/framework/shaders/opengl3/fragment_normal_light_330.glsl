@@ -0,0 +1,24 @@
+#version 330 core
+
+uniform sampler2D Texture;
+uniform vec2 OutputSize;
+in vec2 Frag_UV;
+in vec4 Frag_Color;
+
+out vec4 outColor;
+
+void main()
+{
+    // position relative to the center of the light, in light radii
+    vec2 offset = Frag_UV * 2.0 - 1.0;
+    // same falloff as the builtin/lightmap/spot texture used without normal maps
+    float falloff = clamp(1.0 - length(offset) / 0.86, 0.0, 1.0);
+
+    vec3 normal = normalize(texture(Texture, gl_FragCoord.xy / OutputSize).rgb * 2.0 - 1.0);
+    vec3 light = normalize(vec3(-offset.x, offset.y, 0.5));
+
+    // divided so flat surfaces are lit the same as without a normal map
+    float shade = clamp(dot(normal, light) / light.z, 0.0, 2.0);
+
+    outColor = vec4(Frag_Color.rgb * falloff * shade, 1.0);
+}
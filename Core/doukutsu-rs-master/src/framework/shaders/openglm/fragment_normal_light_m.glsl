@@ -0,0 +1,22 @@
+//#version 110
+
+uniform sampler2D Texture;
+uniform vec2 OutputSize;
+varying vec2 Frag_UV;
+varying vec4 Frag_Color;
+
+void main()
+{
+    // position relative to the center of the light, in light radii
+    vec2 offset = Frag_UV * 2.0 - 1.0;
+    // same falloff as the builtin/lightmap/spot texture used without normal maps
+    float falloff = clamp(1.0 - length(offset) / 0.86, 0.0, 1.0);
+
+    vec3 normal = normalize(texture2D(Texture, gl_FragCoord.xy / OutputSize).rgb * 2.0 - 1.0);
+    vec3 light = normalize(vec3(-offset.x, offset.y, 0.5));
+
+    // divided so flat surfaces are lit the same as without a normal map
+    float shade = clamp(dot(normal, light) / light.z, 0.0, 2.0);
+
+    gl_FragColor = vec4(Frag_Color.rgb * falloff * shade, 1.0);
+}
@@ -0,0 +1,39 @@
+// Params.x - color vision deficiency to compensate for, 0 - protanopia, 1 - deuteranopia, 2 - tritanopia
+// Params.y - strength of the correction
+
+uniform sampler2D Texture;
+uniform vec4 Params;
+varying vec2 Frag_UV;
+varying vec4 Frag_Color;
+
+void main()
+{
+    vec4 color = texture2D(Texture, Frag_UV.st);
+    vec3 rgb = color.rgb;
+
+    float l = dot(rgb, vec3(17.8824, 43.5161, 4.11935));
+    float m = dot(rgb, vec3(3.45565, 27.1554, 3.86714));
+    float s = dot(rgb, vec3(0.0299566, 0.184309, 1.46709));
+
+    // simulate how the colors are seen
+    if (Params.x < 0.5) {
+        l = 2.02344 * m - 2.52581 * s;
+    } else if (Params.x < 1.5) {
+        m = 0.494207 * l + 1.24827 * s;
+    } else {
+        s = -0.395913 * l + 0.801109 * m;
+    }
+
+    vec3 lms = vec3(l, m, s);
+    vec3 simulated = vec3(
+        dot(lms, vec3(0.0809444479, -0.130504409, 0.116721066)),
+        dot(lms, vec3(-0.0102485335, 0.0540193266, -0.113614708)),
+        dot(lms, vec3(-0.000365296938, -0.00412161469, 0.693511405))
+    );
+
+    // shift the colors that can't be told apart towards ones that can
+    vec3 error = rgb - simulated;
+    vec3 shift = vec3(0.0, 0.7 * error.r + error.g, 0.7 * error.r + error.b);
+
+    gl_FragColor = vec4(clamp(rgb + shift * Params.y, 0.0, 1.0), color.a);
+}
@@ -0,0 +1,53 @@
+// Params.x - screen curvature
+// Params.y - darkness of the scanlines
+// Params.z - strength of the aperture grille
+// Params.w - strength of the vignette
+
+uniform sampler2D Texture;
+uniform vec2 OutputSize;
+uniform vec4 Params;
+uniform float Scale;
+varying vec2 Frag_UV;
+varying vec4 Frag_Color;
+
+vec2 warp(vec2 uv)
+{
+    vec2 pos = uv * 2.0 - 1.0;
+    pos *= vec2(1.0 + pos.y * pos.y * Params.x, 1.0 + pos.x * pos.x * Params.x);
+    return pos * 0.5 + 0.5;
+}
+
+void main()
+{
+    vec2 uv = warp(Frag_UV.st);
+    if (uv.s < 0.0 || uv.s > 1.0 || uv.t < 0.0 || uv.t > 1.0) {
+        gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec3 color = texture2D(Texture, uv).rgb;
+
+    // scanlines follow the warped game pixel rows
+    float period = max(floor(Scale + 0.5), 2.0);
+    float row = fract((1.0 - uv.t) * OutputSize.y / period);
+    color *= 1.0 - Params.y * (1.0 - sin(row * 3.14159265));
+
+    float column = mod(floor(gl_FragCoord.x), 3.0);
+    vec3 mask = vec3(1.0 - Params.z);
+    if (column < 1.0) {
+        mask.r = 1.0;
+    } else if (column < 2.0) {
+        mask.g = 1.0;
+    } else {
+        mask.b = 1.0;
+    }
+    color *= mask;
+
+    vec2 edge = uv * (1.0 - uv);
+    float vignette = pow(clamp(edge.x * edge.y * 16.0, 0.0, 1.0), Params.w);
+
+    // compensate for the light lost to scanlines and mask
+    color *= vignette * (1.0 + Params.y * 0.5 + Params.z * 0.5);
+
+    gl_FragColor = vec4(color, 1.0);
+}
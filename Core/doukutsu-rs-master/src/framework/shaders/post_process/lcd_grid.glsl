@@ -0,0 +1,21 @@
+// Params.x - darkness of the gaps between pixels
+
+uniform sampler2D Texture;
+uniform vec2 OutputSize;
+uniform vec4 Params;
+uniform float Scale;
+varying vec2 Frag_UV;
+varying vec4 Frag_Color;
+
+void main()
+{
+    vec4 color = texture2D(Texture, Frag_UV.st);
+
+    // the grid is only visible when there's more than one output pixel per game pixel
+    float period = floor(Scale + 0.5);
+    vec2 pos = vec2(Frag_UV.s, 1.0 - Frag_UV.t) * OutputSize;
+    vec2 gap = step(period - 1.0, mod(pos, period)) * step(2.0, period);
+    float grid = max(gap.x, gap.y);
+
+    gl_FragColor = vec4(color.rgb * (1.0 - Params.x * grid), color.a);
+}
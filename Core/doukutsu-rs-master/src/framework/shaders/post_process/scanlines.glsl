@@ -0,0 +1,20 @@
+// Params.x - darkness of the scanlines
+
+uniform sampler2D Texture;
+uniform vec2 OutputSize;
+uniform vec4 Params;
+uniform float Scale;
+varying vec2 Frag_UV;
+varying vec4 Frag_Color;
+
+void main()
+{
+    vec4 color = texture2D(Texture, Frag_UV.st);
+
+    // darken the bottom row of output pixels of every game pixel, every other row if not upscaled
+    float period = max(floor(Scale + 0.5), 2.0);
+    float row = (1.0 - Frag_UV.t) * OutputSize.y;
+    float line = step(period - 1.0, mod(row, period));
+
+    gl_FragColor = vec4(color.rgb * (1.0 - Params.x * line), color.a);
+}
@@ -9,11 +9,12 @@
 //! as a trait object, and its path abstraction is not the most
 //! convenient.
 
-use std::collections::VecDeque;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
 use std::ffi::OsStr;
 use std::fmt::{self, Debug};
 use std::fs;
-use std::io::{Read, Seek, Write};
+use std::io::{self, Read, Seek, Write};
 use std::path::{self, Component, Path, PathBuf};
 
 use crate::framework::error::{GameError, GameResult};
@@ -425,9 +426,6 @@ impl OverlayFS {
     }
 
     /// Adds a new VFS to the front of the list.
-    /// Currently unused, I suppose, but good to
-    /// have at least for tests.
-    #[allow(dead_code)]
     pub fn push_front(&mut self, fs: Box<dyn VFS>) {
         self.roots.push_front(fs);
     }
@@ -543,6 +541,196 @@ impl VFS for OverlayFS {
     }
 }
 
+/// A read-only VFS backed by a zip archive.
+///
+/// Lookups are case insensitive, like the ones of `PhysicalFS` on most platforms.
+pub struct ZipFS {
+    source: PathBuf,
+    archive: RefCell<zip::ZipArchive<Box<dyn VFile>>>,
+    /// Lowercase entry names mapped to the names stored in the archive.
+    index: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+/// Zip FS metadata
+pub struct ZipMetadata {
+    is_dir: bool,
+    len: u64,
+}
+
+impl VMetadata for ZipMetadata {
+    fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+    fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl ZipFS {
+    /// Creates a new ZipFS reading the archive from given file, `source` identifies it when unmounting.
+    pub fn new(source: &Path, file: Box<dyn VFile>) -> GameResult<ZipFS> {
+        let archive = zip::ZipArchive::new(file)
+            .map_err(|e| GameError::FilesystemError(format!("Cannot read zip archive {:?}: {}", source, e)))?;
+        let index = archive
+            .file_names()
+            .map(|name| (name.trim_end_matches('/').to_lowercase(), name.to_owned()))
+            .filter(|(key, _)| !key.is_empty())
+            .collect();
+
+        Ok(ZipFS { source: source.to_path_buf(), archive: RefCell::new(archive), index })
+    }
+
+    /// Reads every file in the archive, failing if any of them doesn't match its stored CRC32.
+    pub fn verify(&self) -> GameResult {
+        let mut archive = self.archive.borrow_mut();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).map_err(|e| GameError::FilesystemError(e.to_string()))?;
+            let name = file.name().to_owned();
+            io::copy(&mut file, &mut io::sink()).map_err(|e| {
+                GameError::FilesystemError(format!("Corrupted file {} in {:?}: {}", name, self.source, e))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn to_key(&self, path: &Path) -> GameResult<String> {
+        match sanitize_path(path) {
+            Some(p) => Ok(p.to_string_lossy().replace('\\', "/").to_lowercase()),
+            None => {
+                let msg = format!(
+                    "Path {:?} is not valid: must be an absolute path with no \
+                     references to parent directories",
+                    path
+                );
+                Err(GameError::FilesystemError(msg))
+            }
+        }
+    }
+
+    fn is_dir_key(&self, key: &str) -> bool {
+        if key.is_empty() {
+            return true;
+        }
+
+        // directories don't need to have their own entries, only the files inside
+        let prefix = format!("{}/", key);
+        self.index.get(key).map(|name| name.ends_with('/')).unwrap_or(false)
+            || self.index.keys().any(|k| k.starts_with(&prefix))
+    }
+
+    fn read_only_error(&self, path: &Path) -> GameError {
+        GameError::FilesystemError(format!("Cannot alter file {:?} in root {:?}, filesystem read-only", path, self))
+    }
+}
+
+impl Debug for ZipFS {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "<ZipFS source: {}>", self.source.display())
+    }
+}
+
+impl VFS for ZipFS {
+    /// Open the file at this path with the given options
+    fn open_options(&self, path: &Path, open_options: OpenOptions) -> GameResult<Box<dyn VFile>> {
+        if open_options.write || open_options.create || open_options.append || open_options.truncate {
+            return Err(self.read_only_error(path));
+        }
+
+        let key = self.to_key(path)?;
+        let name = match self.index.get(&key) {
+            Some(name) if !name.ends_with('/') => name,
+            _ => return Err(GameError::FilesystemError(format!("File {:?} not found in {:?}", path, self))),
+        };
+
+        // zip entries can't be seeked, so they're read into memory
+        let mut archive = self.archive.borrow_mut();
+        let mut file = archive.by_name(name).map_err(|e| GameError::FilesystemError(e.to_string()))?;
+        let mut buf = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut buf)?;
+
+        Ok(Box::new(io::Cursor::new(buf)))
+    }
+
+    /// Create a directory at the location by this path
+    fn mkdir(&self, path: &Path) -> GameResult {
+        Err(self.read_only_error(path))
+    }
+
+    /// Remove a file
+    fn rm(&self, path: &Path) -> GameResult {
+        Err(self.read_only_error(path))
+    }
+
+    /// Remove a file or directory and all its contents
+    fn rmrf(&self, path: &Path) -> GameResult {
+        Err(self.read_only_error(path))
+    }
+
+    /// Check if the file exists
+    fn exists(&self, path: &Path) -> bool {
+        match self.to_key(path) {
+            Ok(key) => self.index.contains_key(&key) || self.is_dir_key(&key),
+            _ => false,
+        }
+    }
+
+    /// Get the file's metadata
+    fn metadata(&self, path: &Path) -> GameResult<Box<dyn VMetadata>> {
+        let key = self.to_key(path)?;
+        if self.is_dir_key(&key) {
+            return Ok(Box::new(ZipMetadata { is_dir: true, len: 0 }));
+        }
+
+        let name = match self.index.get(&key) {
+            Some(name) => name,
+            None => return Err(GameError::FilesystemError(format!("File {:?} not found in {:?}", path, self))),
+        };
+
+        let mut archive = self.archive.borrow_mut();
+        let file = archive.by_name(name).map_err(|e| GameError::FilesystemError(e.to_string()))?;
+
+        Ok(Box::new(ZipMetadata { is_dir: false, len: file.size() }))
+    }
+
+    /// Retrieve the path entries in this path
+    fn read_dir(&self, path: &Path) -> GameResult<Box<dyn Iterator<Item = GameResult<PathBuf>>>> {
+        let key = self.to_key(path)?;
+        if !self.is_dir_key(&key) {
+            return Err(GameError::FilesystemError(format!("Directory {:?} not found in {:?}", path, self)));
+        }
+
+        let prefix = if key.is_empty() { key } else { format!("{}/", key) };
+        let depth = prefix.matches('/').count();
+
+        let mut entries: Vec<PathBuf> = Vec::new();
+        for (k, name) in self.index.iter() {
+            if !k.starts_with(&prefix) {
+                continue;
+            }
+
+            if let Some(child) = name.split('/').nth(depth).filter(|c| !c.is_empty()) {
+                let mut pathbuf = PathBuf::from(path);
+                pathbuf.push(child);
+                if !entries.contains(&pathbuf) {
+                    entries.push(pathbuf);
+                }
+            }
+        }
+
+        Ok(Box::new(entries.into_iter().map(Ok)))
+    }
+
+    /// Retrieve the actual location of the VFS root, if available.
+    fn to_path_buf(&self) -> Option<PathBuf> {
+        Some(self.source.clone())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::{self, BufRead};
@@ -686,5 +874,35 @@ mod tests {
         assert!(!fs.exists(testdir));
     }
 
+    #[test]
+    fn headless_test_read_zip() {
+        let mut buf = io::Cursor::new(Vec::new());
+        {
+            let mut writer = zip::ZipWriter::new(&mut buf);
+            let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+            writer.start_file("mod.json", options).unwrap();
+            writer.write_all(b"{}").unwrap();
+            writer.start_file("Npc/NpcSym.pbm", options).unwrap();
+            writer.write_all(b"npc").unwrap();
+            writer.finish().unwrap();
+        }
+        buf.set_position(0);
+
+        let fs = ZipFS::new(Path::new("/test.zip"), Box::new(buf)).unwrap();
+        assert!(fs.verify().is_ok());
+        assert!(fs.exists(Path::new("/mod.json")));
+        assert!(fs.exists(Path::new("/npc/npcsym.pbm")));
+        assert!(fs.metadata(Path::new("/Npc")).unwrap().is_dir());
+        assert!(fs.create(Path::new("/mod.json")).is_err());
+
+        let mut s = String::new();
+        fs.open(Path::new("/NPC/NpcSym.pbm")).unwrap().read_to_string(&mut s).unwrap();
+        assert_eq!(s, "npc");
+
+        let mut entries: Vec<PathBuf> = fs.read_dir(Path::new("/")).unwrap().map(|p| p.unwrap()).collect();
+        entries.sort();
+        assert_eq!(entries, vec![PathBuf::from("/Npc"), PathBuf::from("/mod.json")]);
+    }
+
     // BUG: TODO: Make sure all functions are tested for OverlayFS and ZipFS!!
 }
@@ -5,13 +5,17 @@ use crate::{
     framework::{
         context::Context,
         error::GameResult,
-        filesystem::{mount_user_vfs, mount_vfs, unmount_user_vfs},
+        filesystem,
+        filesystem::{mount_user_vfs, mount_vfs, mount_vfs_front, unmount_user_vfs, unmount_vfs},
         vfs::PhysicalFS,
     },
 };
 
 use super::LaunchOptions;
 
+/// Directory in the user directory which holds data files saved by the editor and the debugger tools.
+pub const EDITOR_DATA_DIR: &str = "/editor";
+
 pub struct FilesystemContainer {
     pub user_path: PathBuf,
     pub game_path: PathBuf,
@@ -206,6 +210,18 @@ impl FilesystemContainer {
         Ok(())
     }
 
+    /// Mounts the editor data directory in front of all other data, so saved files take precedence over the ones
+    /// from the game and from mods. Has to be called again after mods are mounted.
+    pub fn mount_editor_data(&self, ctx: &mut Context) {
+        let path = self.editor_data_path();
+        unmount_vfs(ctx, &path);
+        mount_vfs_front(ctx, Box::new(PhysicalFS::new(&path, true)));
+    }
+
+    fn editor_data_path(&self) -> PathBuf {
+        self.user_path.join(EDITOR_DATA_DIR.trim_start_matches('/'))
+    }
+
     pub fn open_user_directory(&self) -> GameResult {
         self.open_directory(self.user_path.clone())
     }
@@ -237,12 +253,14 @@ impl FilesystemContainer {
 
         // unmount old user dir
         unmount_user_vfs(ctx, &self.user_path);
+        unmount_vfs(ctx, &self.editor_data_path());
 
         // mount new user dir
         mount_user_vfs(ctx, Box::new(PhysicalFS::new(&user_dir, false)));
 
         self.user_path = user_dir.clone();
         self.is_portable = true;
+        self.mount_editor_data(ctx);
 
         Ok(())
     }
@@ -275,3 +293,52 @@ impl FilesystemContainer {
         })
     }
 }
+
+/// Opens a data file (eg. `Stage/Cave.tsc`) for writing in the editor data directory. `mod_path` is the directory
+/// of the running challenge mod, if any, so the file shadows the one the game loads.
+pub fn create_data_file(ctx: &mut Context, mod_path: Option<&str>, path: &str) -> GameResult<filesystem::File> {
+    let mut full_path = EDITOR_DATA_DIR.to_owned();
+    if let Some(mod_path) = mod_path {
+        full_path.push_str(mod_path.trim_end_matches('/'));
+    }
+    full_path.push('/');
+    full_path.push_str(path.trim_start_matches('/'));
+
+    if let Some((dir, _)) = full_path.rsplit_once('/') {
+        filesystem::user_create_dir(ctx, dir)?;
+    }
+
+    filesystem::user_create(ctx, full_path)
+}
+
+#[test]
+fn test_create_data_file_round_trip() {
+    use std::io::{Read, Write};
+
+    let root = std::env::temp_dir().join(format!("drs-editor-data-test-{}", std::process::id()));
+    let user_path = root.join("user");
+    let mod_dir = root.join("mod");
+    std::fs::create_dir_all(mod_dir.join("Stage")).unwrap();
+    std::fs::write(mod_dir.join("Stage/Test.tsc"), b"old").unwrap();
+
+    let mut ctx = Context::new();
+    mount_user_vfs(&mut ctx, Box::new(PhysicalFS::new(&user_path, false)));
+
+    let mut fs_container = FilesystemContainer::new();
+    fs_container.user_path = user_path;
+    fs_container.mount_editor_data(&mut ctx);
+    // mods get mounted in front of everything, the editor data has to be mounted again afterwards
+    mount_vfs_front(&mut ctx, Box::new(PhysicalFS::new(&mod_dir, true)));
+    fs_container.mount_editor_data(&mut ctx);
+
+    for mod_path in [None, Some("/Mod1")] {
+        create_data_file(&mut ctx, mod_path, "Stage/Test.tsc").unwrap().write_all(b"saved").unwrap();
+
+        let path = format!("{}/Stage/Test.tsc", mod_path.unwrap_or(""));
+        let mut data = Vec::new();
+        filesystem::open(&ctx, path).unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"saved");
+    }
+
+    let _ = std::fs::remove_dir_all(root);
+}
@@ -195,7 +195,7 @@ impl Inventory {
         let mut result = TakeExperienceResult::None;
 
         if let Some(weapon) = self.get_current_weapon_mut() {
-            let lvl_table = state.constants.weapon.level_table[weapon.wtype as usize];
+            let lvl_table = weapon.level_table(&state.constants);
             let mut tmp_exp = weapon.experience as isize - exp as isize;
 
             if tmp_exp >= 0 {
@@ -3,7 +3,7 @@ use std::io;
 use std::io::{BufRead, BufReader, Cursor, Read};
 use std::sync::Arc;
 
-use byteorder::{LE, ReadBytesExt};
+use byteorder::{LE, ReadBytesExt, WriteBytesExt};
 
 use crate::common::{Color, Rect};
 use crate::framework::context::Context;
@@ -11,8 +11,8 @@ use crate::framework::error::{GameError, GameResult};
 use crate::framework::error::GameError::ResourceLoadError;
 use crate::framework::filesystem;
 use crate::game::shared_game_state::TileSize;
-use crate::game::stage::{PxPackScroll, PxPackStageData, StageData};
-use crate::util::encoding::read_cur_shift_jis;
+use crate::game::stage::{PxPackAttribFile, PxPackScroll, PxPackStageData, StageData};
+use crate::util::encoding::{encode_shift_jis, read_cur_shift_jis};
 
 static SUPPORTED_PXM_VERSIONS: [u8; 1] = [0x10];
 static SUPPORTED_PXE_VERSIONS: [u8; 2] = [0, 0x10];
@@ -67,6 +67,28 @@ impl Map {
         Ok(Map { width, height, tiles, attrib, tile_size: TileSize::Tile16x16 })
     }
 
+    pub fn write_pxm<W: io::Write>(&self, mut map_data: W) -> GameResult {
+        let size = self.width as usize * self.height as usize;
+
+        if self.tiles.len() < size {
+            return Err(GameError::InvalidValue("Map tile data is smaller than map dimensions.".to_owned()));
+        }
+
+        map_data.write_all(b"PXM")?;
+        map_data.write_u8(SUPPORTED_PXM_VERSIONS[0])?;
+        map_data.write_u16::<LE>(self.width)?;
+        map_data.write_u16::<LE>(self.height)?;
+        map_data.write_all(&self.tiles[0..size])?;
+
+        Ok(())
+    }
+
+    pub fn write_pxa<W: io::Write>(&self, mut attrib_data: W) -> GameResult {
+        attrib_data.write_all(&self.attrib)?;
+
+        Ok(())
+    }
+
     pub fn load_pxpack<R: io::Read>(
         mut map_data: R,
         roots: &Vec<String>,
@@ -102,40 +124,35 @@ impl Map {
             Ok(chars.iter().collect())
         }
 
-        fn skip_string<R: io::Read>(map_data: &mut R) -> GameResult {
-            let bytes = map_data.read_u8()? as u32;
-            for _ in 0..bytes {
-                map_data.read_u8()?;
-            }
-
-            Ok(())
-        }
-
         let map_name = read_string(&mut map_data)?;
-        skip_string(&mut map_data)?; // left, right, up, down
-        skip_string(&mut map_data)?;
-        skip_string(&mut map_data)?;
-        skip_string(&mut map_data)?;
-        skip_string(&mut map_data)?; // spritesheet
-
-        map_data.read_u16::<LE>()?;
-        map_data.read_u16::<LE>()?;
-        map_data.read_u8()?;
+        let neighbour_maps = [
+            read_string(&mut map_data)?, // left, right, up, down
+            read_string(&mut map_data)?,
+            read_string(&mut map_data)?,
+            read_string(&mut map_data)?,
+        ];
+        let spritesheet = read_string(&mut map_data)?;
+
+        let mut header_unknown = [0u8; 5];
+        map_data.read_exact(&mut header_unknown)?;
 
         let bg_color = Color::from_rgb(map_data.read_u8()?, map_data.read_u8()?, map_data.read_u8()?);
 
+        let mut visibility = [0u8; 3];
+
         let mut tileset_fg = read_string(&mut map_data)?;
-        map_data.read_u8()?; // ignored
+        visibility[0] = map_data.read_u8()?;
         let scroll_fg = PxPackScroll::from(map_data.read_u8()?);
 
         let mut tileset_mg = read_string(&mut map_data)?;
-        map_data.read_u8()?; // ignored
+        visibility[1] = map_data.read_u8()?;
         let scroll_mg = PxPackScroll::from(map_data.read_u8()?);
 
         let mut tileset_bg = read_string(&mut map_data)?;
-        map_data.read_u8()?; // ignored
+        visibility[2] = map_data.read_u8()?;
         let scroll_bg = PxPackScroll::from(map_data.read_u8()?);
 
+        let empty_tilesets = [tileset_fg.is_empty(), tileset_mg.is_empty(), tileset_bg.is_empty()];
         if tileset_fg.is_empty() {
             tileset_fg = data.tileset.filename()
         }
@@ -206,13 +223,20 @@ impl Map {
             )?;
         }
 
+        let attrib_file;
         if let Ok(mut attrib_data) = filesystem::open_find(ctx, roots, ["Stage/", &tileset_fg, ".pxa"].join("")) {
             if attrib_data.read_exact(&mut attrib).is_err() {
                 log::warn!("Map attribute data is shorter than 256 bytes!");
             }
-        } else if let Ok(mut attrib_data) =
+
+            attrib_file = PxPackAttribFile::Pxa;
+        } else if let Ok(mut attrib_file_data) =
         filesystem::open_find(ctx, roots, ["Stage/", &tileset_fg, ".pxattr"].join(""))
         {
+            let mut raw_data = Vec::new();
+            attrib_file_data.read_to_end(&mut raw_data)?;
+            let mut attrib_data = Cursor::new(raw_data);
+
             attrib_data.read_exact(&mut magic)?;
 
             if &magic != b"pxMAP01\0" {
@@ -265,13 +289,18 @@ impl Map {
                     _ => 0,
                 };
             }
+
+            attrib_file = PxPackAttribFile::PxAttr(attrib_data.into_inner());
         } else {
             log::warn!(
                 "No tile attribute data found for foreground tileset {}, collision might be broken.",
                 tileset_fg
             );
+
+            attrib_file = PxPackAttribFile::None;
         }
 
+        let empty_name = map_name.is_empty();
         if !map_name.is_empty() {
             data.name = map_name;
         }
@@ -289,11 +318,136 @@ impl Map {
             size_bg: (width_bg, height_bg),
             offset_mg: size_fg,
             offset_bg: size_fg + size_mg,
+            neighbour_maps,
+            spritesheet,
+            header_unknown,
+            visibility,
+            empty_name,
+            empty_tilesets,
+            attrib_file,
         });
 
         Ok(Map { width: width_fg, height: height_fg, tiles, attrib, tile_size: TileSize::Tile8x8 })
     }
 
+    pub fn write_pxpack<W: io::Write>(&self, mut map_data: W, data: &StageData) -> GameResult {
+        let pxpack_data = match &data.pxpack_data {
+            Some(pxpack_data) => pxpack_data,
+            None => return Err(GameError::InvalidValue("Stage is not a PxPack map.".to_owned())),
+        };
+
+        fn write_string<W: io::Write>(map_data: &mut W, string: &str) -> GameResult {
+            let mut raw_chars = encode_shift_jis(string);
+            raw_chars.truncate(0xff);
+
+            map_data.write_u8(raw_chars.len() as u8)?;
+            map_data.write_all(&raw_chars)?;
+
+            Ok(())
+        }
+
+        fn write_layer<W: io::Write>(map_data: &mut W, (width, height): (u16, u16), tiles: &[u8]) -> GameResult {
+            map_data.write_all(b"pxMAP01\0")?;
+            map_data.write_u16::<LE>(width)?;
+            map_data.write_u16::<LE>(height)?;
+
+            if !tiles.is_empty() {
+                map_data.write_u8(0)?;
+                map_data.write_all(tiles)?;
+            }
+
+            Ok(())
+        }
+
+        map_data.write_all(b"PXPACK121127a**\0")?;
+
+        write_string(&mut map_data, if pxpack_data.empty_name { "" } else { &data.name })?;
+        for neighbour in pxpack_data.neighbour_maps.iter() {
+            write_string(&mut map_data, neighbour)?;
+        }
+        write_string(&mut map_data, &pxpack_data.spritesheet)?;
+
+        map_data.write_all(&pxpack_data.header_unknown)?;
+
+        let (r, g, b) = data.background_color.to_rgb();
+        map_data.write_u8(r)?;
+        map_data.write_u8(g)?;
+        map_data.write_u8(b)?;
+
+        let layers = [
+            (&pxpack_data.tileset_fg, pxpack_data.scroll_fg),
+            (&pxpack_data.tileset_mg, pxpack_data.scroll_mg),
+            (&pxpack_data.tileset_bg, pxpack_data.scroll_bg),
+        ];
+        let default_tileset = data.tileset.filename();
+
+        for (i, (tileset, scroll)) in layers.iter().enumerate() {
+            // keep names that were filled in from the stage table empty
+            if pxpack_data.empty_tilesets[i] && **tileset == default_tileset {
+                write_string(&mut map_data, "")?;
+            } else {
+                write_string(&mut map_data, tileset)?;
+            }
+            map_data.write_u8(pxpack_data.visibility[i])?;
+            map_data.write_u8(u8::from(*scroll))?;
+        }
+
+        let size_fg = pxpack_data.size_fg.0 as usize * pxpack_data.size_fg.1 as usize;
+        let size_mg = pxpack_data.size_mg.0 as usize * pxpack_data.size_mg.1 as usize;
+        let size_bg = pxpack_data.size_bg.0 as usize * pxpack_data.size_bg.1 as usize;
+        let offset_mg = pxpack_data.offset_mg as usize;
+        let offset_bg = pxpack_data.offset_bg as usize;
+
+        if self.tiles.len() < offset_bg + size_bg || offset_mg + size_mg > offset_bg || size_fg > offset_mg {
+            return Err(GameError::InvalidValue("Map tile data doesn't match PxPack layer sizes.".to_owned()));
+        }
+
+        // the foreground layer always has the extra byte, even if it's empty
+        map_data.write_all(b"pxMAP01\0")?;
+        map_data.write_u16::<LE>(pxpack_data.size_fg.0)?;
+        map_data.write_u16::<LE>(pxpack_data.size_fg.1)?;
+        map_data.write_u8(0)?;
+        map_data.write_all(&self.tiles[0..size_fg])?;
+
+        write_layer(&mut map_data, pxpack_data.size_mg, &self.tiles[offset_mg..offset_mg + size_mg])?;
+        write_layer(&mut map_data, pxpack_data.size_bg, &self.tiles[offset_bg..offset_bg + size_bg])?;
+
+        Ok(())
+    }
+
+    /// Resizes the map, moving existing tiles by given offset. Tiles that end up outside are discarded
+    /// and new ones are set to 0. Anything stored after the main layer (PxPack layers) is kept as-is.
+    pub fn resize(&mut self, width: u16, height: u16, offset_x: i32, offset_y: i32) {
+        let old_width = self.width as usize;
+        let old_height = self.height as usize;
+        let old_size = (old_width * old_height).min(self.tiles.len());
+        let mut tiles = vec![0u8; width as usize * height as usize];
+
+        for y in 0..old_height {
+            let new_y = y as i32 + offset_y;
+            if new_y < 0 || new_y >= height as i32 {
+                continue;
+            }
+
+            for x in 0..old_width {
+                let new_x = x as i32 + offset_x;
+                if new_x < 0 || new_x >= width as i32 {
+                    continue;
+                }
+
+                if let Some(&tile) = self.tiles.get(y * old_width + x) {
+                    tiles[new_y as usize * width as usize + new_x as usize] = tile;
+                }
+            }
+        }
+
+        tiles.extend_from_slice(&self.tiles[old_size..]);
+
+        self.tiles = tiles;
+        self.width = width;
+        self.height = height;
+    }
+
     pub fn get_attribute(&self, x: usize, y: usize) -> u8 {
         if x >= self.width as usize || y >= self.height as usize {
             return 0;
@@ -474,7 +628,7 @@ impl Map {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct NPCData {
     pub id: u16,
     pub x: i16,
@@ -520,6 +674,30 @@ impl NPCData {
 
         Ok(npcs)
     }
+
+    pub fn write_to<W: io::Write>(npcs: &[NPCData], mut data: W) -> GameResult {
+        // only use booster's lab format if any of the entries actually needs the layer field
+        let version = if npcs.iter().any(|npc| npc.layer != 0) { 0x10 } else { 0 };
+
+        data.write_all(b"PXE")?;
+        data.write_u8(version)?;
+        data.write_u32::<LE>(npcs.len() as u32)?;
+
+        for npc in npcs.iter() {
+            data.write_i16::<LE>(npc.x)?;
+            data.write_i16::<LE>(npc.y)?;
+            data.write_u16::<LE>(npc.flag_num)?;
+            data.write_u16::<LE>(npc.event_num)?;
+            data.write_u16::<LE>(npc.npc_type)?;
+            data.write_u16::<LE>(npc.flags)?;
+
+            if version == 0x10 {
+                data.write_u8(npc.layer)?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -625,3 +803,103 @@ impl WaterParams {
         self.entries.get(&tile).unwrap_or(&DEFAULT_ENTRY)
     }
 }
+
+#[test]
+fn pxm_roundtrip_test() {
+    let mut attrib = [0u8; 0x100];
+    attrib[0x41] = 0x41;
+
+    let map = Map { width: 3, height: 2, tiles: vec![0, 1, 2, 0x41, 4, 5], attrib, tile_size: TileSize::Tile16x16 };
+
+    let mut map_data = Vec::new();
+    let mut attrib_data = Vec::new();
+    map.write_pxm(&mut map_data).unwrap();
+    map.write_pxa(&mut attrib_data).unwrap();
+
+    let loaded = Map::load_pxm(Cursor::new(map_data), Cursor::new(attrib_data)).unwrap();
+    assert_eq!(loaded.width, 3);
+    assert_eq!(loaded.height, 2);
+    assert_eq!(loaded.tiles, map.tiles);
+    assert_eq!(loaded.get_attribute(0, 1), 0x41);
+}
+
+#[test]
+fn pxe_roundtrip_test() {
+    let npcs = vec![
+        NPCData { id: 170, x: 4, y: 7, flag_num: 10, event_num: 200, npc_type: 46, flags: 0x2100, layer: 0 },
+        NPCData { id: 171, x: -1, y: 0, flag_num: 0, event_num: 0, npc_type: 1, flags: 0, layer: 0 },
+    ];
+
+    let mut data = Vec::new();
+    NPCData::write_to(&npcs, &mut data).unwrap();
+
+    let loaded = NPCData::load_from(Cursor::new(data)).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[0].event_num, 200);
+    assert_eq!(loaded[0].flags, 0x2100);
+    assert_eq!(loaded[1].x, -1);
+    assert_eq!(loaded[1].id, 171);
+}
+
+#[test]
+fn map_resize_test() {
+    let mut map =
+        Map { width: 2, height: 2, tiles: vec![1, 2, 3, 4, 9, 9], attrib: [0u8; 0x100], tile_size: TileSize::Tile8x8 };
+
+    map.resize(3, 2, 1, -1);
+    assert_eq!((map.width, map.height), (3, 2));
+    assert_eq!(map.tiles, vec![0, 3, 4, 0, 0, 0, 9, 9]);
+}
+
+#[test]
+fn pxpack_write_keeps_empty_names_test() {
+    use crate::game::stage::{Background, BackgroundType, NpcType, Tileset};
+
+    let pxpack_data = PxPackStageData {
+        tileset_fg: "PrtFoo".to_owned(),
+        tileset_mg: "MgBar".to_owned(),
+        tileset_bg: "PrtFoo".to_owned(),
+        scroll_fg: PxPackScroll::Normal,
+        scroll_mg: PxPackScroll::Normal,
+        scroll_bg: PxPackScroll::Normal,
+        size_fg: (1, 1),
+        size_mg: (0, 0),
+        size_bg: (0, 0),
+        offset_mg: 1,
+        offset_bg: 1,
+        neighbour_maps: Default::default(),
+        spritesheet: String::new(),
+        header_unknown: [0; 5],
+        visibility: [0; 3],
+        empty_name: true,
+        empty_tilesets: [true, false, false],
+        attrib_file: PxPackAttribFile::None,
+    };
+    let data = StageData {
+        name: "Stage Table Name".to_owned(),
+        name_jp: String::new(),
+        map: "foo".to_owned(),
+        boss_no: 0,
+        tileset: Tileset::new("Foo"),
+        pxpack_data: Some(pxpack_data),
+        background: Background::new("bk0"),
+        background_type: BackgroundType::Black,
+        background_color: Color::from_rgb(0, 0, 0),
+        npc1: NpcType::new("0"),
+        npc2: NpcType::new("0"),
+    };
+    let map = Map { width: 1, height: 1, tiles: vec![7], attrib: [0u8; 0x100], tile_size: TileSize::Tile8x8 };
+
+    let mut map_data = Vec::new();
+    map.write_pxpack(&mut map_data, &data).unwrap();
+
+    // empty map name, 4 empty neighbour names, empty spritesheet, 5 unknown bytes and background color
+    let header_end = 16 + 6 + 5 + 3;
+    assert_eq!(&map_data[16..header_end], &[0u8; 14]);
+    // the foreground tileset was filled in from the stage table, the background one was named explicitly
+    assert_eq!(&map_data[header_end..header_end + 3], &[0, 0, 0]);
+    assert_eq!(map_data[header_end + 3], 5);
+    assert_eq!(&map_data[header_end + 4..header_end + 9], b"MgBar");
+    assert_eq!(map_data[header_end + 11], 6);
+    assert_eq!(&map_data[header_end + 12..header_end + 18], b"PrtFoo");
+}
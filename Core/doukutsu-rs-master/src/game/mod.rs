@@ -1,26 +1,37 @@
 use std::cell::UnsafeCell;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
 use std::pin::Pin;
 
 use lazy_static::lazy_static;
 
-use scripting::tsc::text_script::ScriptMode;
+use scripting::tsc::text_script::{ScriptMode, TextScriptEncoding};
 
 use crate::framework::context::Context;
-use crate::framework::error::GameResult;
+use crate::framework::error::{GameError, GameResult};
 use crate::framework::graphics;
 use crate::framework::graphics::VSyncMode;
 use crate::framework::ui::UI;
 use crate::game::filesystem_container::FilesystemContainer;
-use crate::game::shared_game_state::{Fps, SharedGameState, TimingMode};
+use crate::game::scripting::tsc::encoding::convert_script;
+use crate::game::scripting::tsc::lint::{lint_scripts, LintSeverity};
+use crate::game::shared_game_state::{Fps, ReplayState, SharedGameState, TimingMode};
+use crate::game::video_capture::VideoCapture;
 use crate::graphics::texture_set::{G_MAG, I_MAG};
+use crate::i18n::Locale;
 use crate::scene::loading_scene::LoadingScene;
 use crate::scene::Scene;
+use crate::sound::render;
+use crate::sound::render::RenderOptions;
 
 #[cfg(feature = "backend-libretro")]
 use crate::sound;
+#[cfg(not(feature = "backend-libretro"))]
+use crate::game::shared_game_state::{GameDifficulty, ReplayKind};
+#[cfg(not(feature = "backend-libretro"))]
+use crate::game::video_capture::AudioCapture;
 
 pub mod caret;
 pub mod filesystem_container;
@@ -35,6 +46,7 @@ pub mod scripting;
 pub mod settings;
 pub mod shared_game_state;
 pub mod stage;
+pub mod video_capture;
 pub mod weapon;
 
 #[cfg(not(feature = "backend-libretro"))]
@@ -45,6 +57,7 @@ pub struct LaunchOptions {
     pub external_timer: bool,
     pub usr_dir: Option<PathBuf>, //where the game should be loaded from
     pub resource_dir: Option<PathBuf>, //where the saves should be placed
+    pub audio_capture: Option<AudioCapture>, //mixes the audio for video capture instead of playing it
 }
 
 //todo: There HAS to be a better way to do this...
@@ -104,6 +117,8 @@ pub struct Game {
     next_tick_draw: u128,
     present: bool,
     fps: Fps,
+    /// Records every frame, the game runs one tick per frame as fast as it can while it's set.
+    pub(crate) capture: Option<VideoCapture>,
 }
 
 impl Game {
@@ -120,6 +135,7 @@ impl Game {
             next_tick_draw: 0,
             present: true,
             fps: Fps::new(),
+            capture: None,
         };
 
         Ok(s)
@@ -137,6 +153,24 @@ impl Game {
                     1.0 * state_ref.settings.speed
                 };
 
+            if let Some(capture) = &mut self.capture {
+                self.loops = capture.ticks_per_frame(speed);
+                if self.loops != 0 {
+                    scene.draw_tick(state_ref)?;
+                }
+
+                for _ in 0..self.loops {
+                    scene.tick(state_ref, ctx)?;
+                }
+
+                if state_ref.replay_state == ReplayState::None {
+                    log::info!("Replay playback has ended.");
+                    state_ref.shutdown();
+                }
+
+                return Ok(());
+            }
+
             match state_ref.settings.timing_mode {
                 TimingMode::_50Hz | TimingMode::_60Hz => {
                     let last_tick = self.next_tick;
@@ -180,6 +214,9 @@ impl Game {
         let state_ref = unsafe { &mut *self.state.get() };
 
         match ctx.vsync_mode {
+            _ if self.capture.is_some() => {
+                self.present = true;
+            }
             VSyncMode::Uncapped | VSyncMode::VSync => {
                 self.present = true;
             }
@@ -219,7 +256,9 @@ impl Game {
             return Ok(());
         }
 
-        if state_ref.settings.timing_mode != TimingMode::FrameSynchronized {
+        if self.capture.is_some() {
+            state_ref.frame_time = 1.0;
+        } else if state_ref.settings.timing_mode != TimingMode::FrameSynchronized {
             let mut elapsed = self.game_timer.elapsed().as_nanos();
 
             // Even with the non-monotonic Instant mitigation at the start of the event loop, there's still a chance of it not working.
@@ -238,6 +277,8 @@ impl Game {
         }
         self.loops = 0;
 
+        state_ref.poll_texture_pack();
+
         graphics::prepare_draw(ctx)?;
         graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
 
@@ -257,6 +298,10 @@ impl Game {
                 self.fps.act(state_ref, ctx, self.game_timer.elapsed().as_nanos())?;
             }
 
+            if let Some(capture) = &mut self.capture {
+                capture.capture_tick(ctx)?;
+            }
+
             self.ui.draw(state_ref, ctx, scene)?;
         }
 
@@ -368,6 +413,7 @@ pub fn init(options: LaunchOptions) -> GameResult<(Option<Pin<Box<Game>>>, Optio
     }
 
     game.state.get_mut().fs_container = Some(fs_container);
+    game.state.get_mut().mount_mods(&mut context);
 
     #[cfg(feature = "discord-rpc")]
     if game.state.get_mut().settings.discord_rpc {
@@ -390,6 +436,278 @@ pub fn init(options: LaunchOptions) -> GameResult<(Option<Pin<Box<Game>>>, Optio
     }
 }
 
+/// Checks every TSC script of the game (or of the mod with given id) and prints found problems to stdout,
+/// one JSON object per line. Returns the number of errors found.
+pub fn lint(options: LaunchOptions, mod_id: Option<String>) -> GameResult<usize> {
+    let mut options = options;
+
+    let _ = init_logger(options.usr_dir.clone());
+
+    let mut context = Box::pin(Context::new());
+    context.headless = true;
+
+    let mut fs_container = FilesystemContainer::new();
+    fs_container.mount_fs(&mut context, &mut options)?;
+
+    let mut state = SharedGameState::new(&mut context, &mut options)?;
+
+    if let Some(mod_id) = mod_id {
+        let mod_path = match state.mod_list.mods.iter().find(|m| m.id == mod_id) {
+            Some(mod_info) => mod_info.path.clone(),
+            None => return Err(GameError::InvalidValue(format!("Mod {} not found.", mod_id))),
+        };
+
+        state.constants.rebuild_path_list(Some(mod_path), state.season, &state.settings);
+    }
+
+    state.constants.apply_constant_json_files(&mut context)?;
+
+    #[allow(unused_mut)]
+    let mut custom_opcodes = HashSet::new();
+
+    #[cfg(feature = "scripting-lua")]
+    {
+        let state_ptr: *mut SharedGameState = &mut state;
+        state.lua.update_refs(state_ptr, &mut *context);
+        state.lua.reload_scripts(&mut context)?;
+        custom_opcodes.extend(state.lua.registered_tsc_opcodes());
+    }
+
+    let issues = lint_scripts(&mut context, &state.constants, &custom_opcodes)?;
+    for issue in issues.iter() {
+        println!("{}", serde_json::to_string(issue)?);
+    }
+
+    Ok(issues.iter().filter(|issue| issue.severity == LintSeverity::Error).count())
+}
+
+/// Prints the strings each locale (or the one with given code) is missing compared to English, and the ones English
+/// doesn't have, one JSON object per line. Strings provided by a fallback other than English count as translated.
+/// Returns the number of missing strings.
+pub fn locale_report(options: LaunchOptions, code: Option<String>) -> GameResult<usize> {
+    let mut options = options;
+
+    let _ = init_logger(options.usr_dir.clone());
+
+    let mut context = Box::pin(Context::new());
+    context.headless = true;
+
+    let mut fs_container = FilesystemContainer::new();
+    fs_container.mount_fs(&mut context, &mut options)?;
+
+    let state = SharedGameState::new(&mut context, &mut options)?;
+    let locales = &state.constants.locales;
+
+    let base = match locales.iter().find(|l| l.code == "en") {
+        Some(base) => base,
+        None => return Err(GameError::ResourceLoadError("English locale not found.".to_owned())),
+    };
+
+    if let Some(code) = &code {
+        if !locales.iter().any(|l| l.code == *code) {
+            return Err(GameError::InvalidValue(format!("Locale {} not found.", code)));
+        }
+    }
+
+    let mut missing_count = 0;
+    for locale in locales.iter().filter(|l| l.code != "en" && code.as_ref().map_or(true, |code| *code == l.code)) {
+        let mut missing = locale.missing_keys(base);
+        for fallback_code in Locale::fallback_chain(locales, &locale.code).iter().skip(1) {
+            if let Some(fallback) = locales.iter().find(|l| l.code != "en" && l.code == *fallback_code) {
+                let fallback_missing = fallback.missing_keys(base);
+                missing.retain(|key| fallback_missing.contains(key));
+            }
+        }
+
+        for key in missing.iter() {
+            println!("{}", serde_json::json!({ "locale": locale.code, "kind": "missing", "key": key }));
+        }
+
+        for key in locale.extra_keys(base).iter() {
+            println!("{}", serde_json::json!({ "locale": locale.code, "kind": "extra", "key": key }));
+        }
+
+        missing_count += missing.len();
+    }
+
+    Ok(missing_count)
+}
+
+/// Re-encodes given script files in place to the encoding named `encoding`, printing one JSON object per file.
+/// Scripts that can't be recognized are assumed to be Shift-JIS, they're treated as encrypted if the game's data uses
+/// encrypted scripts. Returns the number of files which contain characters that can't be represented in the target
+/// encoding.
+pub fn convert_tsc(options: LaunchOptions, encoding: String, paths: Vec<String>) -> GameResult<usize> {
+    let mut options = options;
+
+    let target = match TextScriptEncoding::from_name(&encoding) {
+        Some(target) => target,
+        None => return Err(GameError::InvalidValue(format!("Unsupported encoding: {}", encoding))),
+    };
+
+    if paths.is_empty() {
+        return Err(GameError::InvalidValue("No script files given.".to_owned()));
+    }
+
+    let _ = init_logger(options.usr_dir.clone());
+
+    let mut context = Box::pin(Context::new());
+    context.headless = true;
+
+    let mut fs_container = FilesystemContainer::new();
+    fs_container.mount_fs(&mut context, &mut options)?;
+
+    let state = SharedGameState::new(&mut context, &mut options)?;
+    let encrypted = state.constants.textscript.encrypted;
+
+    let mut lossy_count = 0;
+    for path in paths.iter() {
+        let data = std::fs::read(path)?;
+        let converted = convert_script(&data, encrypted, TextScriptEncoding::ShiftJIS, target);
+        std::fs::write(path, &converted.data)?;
+
+        println!(
+            "{}",
+            serde_json::json!({
+                "file": path,
+                "from": converted.source_encoding.name(),
+                "to": target.name(),
+                "lost_chars": converted.lost_chars,
+            })
+        );
+
+        if converted.lost_chars > 0 {
+            lossy_count += 1;
+        }
+    }
+
+    Ok(lossy_count)
+}
+
+/// Renders songs to WAV files in `output`, printing one JSON object per song. `songs` are names from the music table,
+/// looked up in given soundtrack the same way the game does, or paths to `.org` files. Every song of the music table
+/// is rendered if none are given. Returns the number of songs that failed to render.
+pub fn render_music(
+    options: LaunchOptions,
+    output: String,
+    songs: Vec<String>,
+    soundtrack: Option<String>,
+    render_options: RenderOptions,
+) -> GameResult<usize> {
+    let mut options = options;
+
+    let _ = init_logger(options.usr_dir.clone());
+
+    let mut context = Box::pin(Context::new());
+    context.headless = true;
+
+    let mut fs_container = FilesystemContainer::new();
+    fs_container.mount_fs(&mut context, &mut options)?;
+
+    let state = SharedGameState::new(&mut context, &mut options)?;
+    let soundtrack = soundtrack.unwrap_or_else(|| state.settings.soundtrack.clone());
+    let bank = render::load_wave_bank(&mut context)?;
+
+    let songs = if songs.is_empty() { state.constants.music_table.iter().skip(1).cloned().collect() } else { songs };
+
+    std::fs::create_dir_all(&output)?;
+
+    let mut failed_count = 0;
+    for song in songs.iter() {
+        let song_path = Path::new(song);
+        let is_file = song.to_ascii_lowercase().ends_with(".org") && song_path.is_file();
+        let name = if is_file {
+            song_path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+        } else {
+            song.clone()
+        };
+        let out_path = Path::new(&output).join(format!("{}.wav", name));
+
+        let result = if is_file {
+            std::fs::File::open(song_path)
+                .map_err(GameError::from)
+                .and_then(|file| render::render_organya_from(file, &bank, &render_options))
+        } else {
+            render::render_song(&mut context, &state.constants, &soundtrack, song, &bank, &render_options)
+        };
+
+        match result.and_then(|wav| render::write_wav(&wav, std::fs::File::create(&out_path)?)) {
+            Ok(()) => {
+                println!("{}", serde_json::json!({ "song": song, "file": out_path.to_string_lossy() }));
+            }
+            Err(e) => {
+                println!("{}", serde_json::json!({ "song": song, "error": e.to_string() }));
+                failed_count += 1;
+            }
+        }
+    }
+
+    Ok(failed_count)
+}
+
+/// Plays back a replay of the challenge mod with given id and records it to `<output>.y4m` and `<output>.wav` at the
+/// tick rate of the game, keeping the pace of live playback. The game runs in a hidden window (or offscreen if there's
+/// no display), as fast as it can and without playing any sound. Returns the number of recorded frames once the replay
+/// ends.
+#[cfg(not(feature = "backend-libretro"))]
+pub fn render_replay(
+    options: LaunchOptions,
+    output: String,
+    mod_id: String,
+    replay_kind: ReplayKind,
+    sample_rate: u32,
+) -> GameResult<u64> {
+    let mut options = options;
+
+    let _ = init_logger(options.usr_dir.clone());
+
+    let mut context = Box::pin(Context::new());
+    context.hidden_window = true;
+
+    let mut fs_container = FilesystemContainer::new();
+    fs_container.mount_fs(&mut context, &mut options)?;
+
+    options.audio_capture = Some(AudioCapture { sample_rate, runner: None });
+    let mut game = Box::pin(Game::new(&mut context, &mut options)?);
+    let runner = match options.audio_capture.take().and_then(|capture| capture.runner) {
+        Some(runner) => runner,
+        None => return Err(GameError::AudioError("Failed to set up audio capture.".to_owned())),
+    };
+
+    let state = game.state.get_mut();
+    state.fs_container = Some(fs_container);
+    state.mount_mods(&mut context);
+
+    state.mod_path = match state.mod_list.mods.iter().find(|m| m.id == mod_id) {
+        Some(mod_info) => Some(mod_info.path.clone()),
+        None => return Err(GameError::InvalidValue(format!("Mod {} not found.", mod_id))),
+    };
+    if !state.has_replay_data(&mut context, replay_kind) {
+        return Err(GameError::ResourceLoadError(format!("Mod {} has no replay to render.", mod_id)));
+    }
+
+    // none of these are saved, the settings are only written from the menus
+    state.settings.vsync_mode = VSyncMode::Uncapped;
+    state.settings.pause_on_focus_loss = false;
+    if state.settings.timing_mode == TimingMode::FrameSynchronized {
+        state.settings.timing_mode = TimingMode::_50Hz;
+    }
+
+    state.difficulty = GameDifficulty::Normal;
+    state.replay_state = ReplayState::Playback(replay_kind);
+    state.next_scene = Some(Box::new(LoadingScene::new()));
+
+    let tps = state.settings.timing_mode.get_tps();
+    game.capture = Some(VideoCapture::create(Path::new(&output), tps, sample_rate, runner)?);
+
+    context.run(game.as_mut().get_mut())?;
+
+    match game.capture.take() {
+        Some(capture) => capture.finish(),
+        None => Ok(0),
+    }
+}
+
 //new libretro stuff
 //this is like the function above, but returns the game and context it initialized instead of running it
 /*
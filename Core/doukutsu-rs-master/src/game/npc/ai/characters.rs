@@ -172,7 +172,7 @@ impl NPC {
                     self.vel_y = -0x400;
                     self.vel_x = 0x200;
 
-                    state.sound_manager.play_sfx(71);
+                    state.sound_manager.play_sfx_at(71, self.x, self.y);
                     npc_list.create_death_smoke(self.x, self.y, 0x800, 4, state, &self.rng);
                 }
             }
@@ -182,7 +182,7 @@ impl NPC {
                     self.action_counter = 0;
                     self.anim_num = 8;
 
-                    state.sound_manager.play_sfx(29);
+                    state.sound_manager.play_sfx_at(29, self.x, self.y);
                 }
 
                 self.anim_num += 1;
@@ -436,7 +436,7 @@ impl NPC {
                     self.action_num = 21;
                     self.action_counter = 0;
                     self.anim_num = 0;
-                    state.sound_manager.play_sfx(29);
+                    state.sound_manager.play_sfx_at(29, self.x, self.y);
                 }
 
                 self.anim_num += 1;
@@ -177,7 +177,7 @@ impl NPC {
                     self.npc_flags.set_shootable(true);
                     self.action_num = 20;
                     self.action_counter = 0;
-                    state.sound_manager.play_sfx(103);
+                    state.sound_manager.play_sfx_at(103, self.x, self.y);
                 }
             }
             20 => {
@@ -273,7 +273,7 @@ impl NPC {
         if self.action_num == 0 {
             self.action_num = 1;
             state.create_caret(self.x, self.y, CaretType::Shoot, Direction::Left);
-            state.sound_manager.play_sfx(32);
+            state.sound_manager.play_sfx_at(32, self.x, self.y);
 
             match self.direction {
                 Direction::Left => {
@@ -306,7 +306,7 @@ impl NPC {
             _ => false,
         } {
             state.create_caret(self.x, self.y, CaretType::ProjectileDissipation, Direction::Right);
-            state.sound_manager.play_sfx(28);
+            state.sound_manager.play_sfx_at(28, self.x, self.y);
             self.cond.set_alive(false);
 
             return Ok(());
@@ -501,7 +501,7 @@ impl NPC {
             self.anim_num = 1;
             self.action_counter3 = 0;
             self.vel_y = -0x600;
-            state.sound_manager.play_sfx(15);
+            state.sound_manager.play_sfx_at(15, self.x, self.y);
         }
 
         let mut delx = self.x - self.target_x;
@@ -529,7 +529,7 @@ impl NPC {
                     self.anim_num = 1;
                     self.action_counter3 = 0;
                     self.vel_y = -0x600;
-                    state.sound_manager.play_sfx(15);
+                    state.sound_manager.play_sfx_at(15, self.x, self.y);
                 }
             } else {
                 self.action_num = 100;
@@ -944,7 +944,7 @@ impl NPC {
             {
                 bullet_manager.create_bullet(npc.x, npc.y, 43, TargetPlayer::Player1, self.direction, &state.constants);
                 state.create_caret(npc.x, npc.y, CaretType::Shoot, Direction::Left);
-                state.sound_manager.play_sfx(117);
+                state.sound_manager.play_sfx_at(117, self.x, self.y);
             }
 
             let dir_offset = if player.direction == Direction::Right { 0 } else { 3 };
@@ -72,7 +72,7 @@ impl NPC {
                     self.vel_x = 0;
                     self.vel_y = 0;
                     self.action_counter = (self.anim_rect.bottom - self.anim_rect.top) * 2;
-                    state.sound_manager.play_sfx(0x1d);
+                    state.sound_manager.play_sfx_at(0x1d, self.x, self.y);
                 }
 
                 self.action_counter = self.action_counter.saturating_sub(1);
@@ -88,7 +88,7 @@ impl NPC {
                     self.action_counter = 0;
                     self.vel_x = 0;
                     self.vel_y = 0;
-                    state.sound_manager.play_sfx(0x1d);
+                    state.sound_manager.play_sfx_at(0x1d, self.x, self.y);
                 }
                 self.anim_num = 2;
                 self.action_counter += 1;
@@ -309,7 +309,7 @@ impl NPC {
 
                 if self.action_counter == 80 {
                     self.anim_num = 5;
-                    state.sound_manager.play_sfx(25);
+                    state.sound_manager.play_sfx_at(25, self.x, self.y);
 
                     let mut npc = NPC::create(264, &state.npc_table);
                     npc.cond.set_alive(true);
@@ -364,7 +364,7 @@ impl NPC {
                     self.action_counter = 0;
                     self.anim_num = 7;
 
-                    state.sound_manager.play_sfx(101);
+                    state.sound_manager.play_sfx_at(101, self.x, self.y);
 
                     let mut npc = NPC::create(266, &state.npc_table);
                     npc.cond.set_alive(true);
@@ -392,7 +392,7 @@ impl NPC {
                     self.damage = 0;
                     self.action_counter = 0;
 
-                    state.sound_manager.play_sfx(29);
+                    state.sound_manager.play_sfx_at(29, self.x, self.y);
                 }
 
                 self.action_counter += 2;
@@ -666,7 +666,7 @@ impl NPC {
                         self.anim_num = 6;
                         state.quake_counter = 10;
                         state.quake_rumble_counter = 10;
-                        state.sound_manager.play_sfx(26);
+                        state.sound_manager.play_sfx_at(26, self.x, self.y);
 
                         player.damage(5, state, npc_list);
                         player.vel_y = -0x400;
@@ -750,7 +750,7 @@ impl NPC {
 
                     state.quake_counter = 10;
                     state.quake_rumble_counter = 10;
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                 }
 
                 self.anim_num = 3;
@@ -781,7 +781,7 @@ impl NPC {
                     npc.direction = self.direction;
 
                     let _ = npc_list.spawn(0x100, npc);
-                    state.sound_manager.play_sfx(39);
+                    state.sound_manager.play_sfx_at(39, self.x, self.y);
                 }
 
                 if self.action_counter > 90 {
@@ -808,7 +808,7 @@ impl NPC {
                     self.damage = 10;
                     self.vel_x = self.direction.vector_x() * 0x5FF;
 
-                    state.sound_manager.play_sfx(25);
+                    state.sound_manager.play_sfx_at(25, self.x, self.y);
                 }
             }
             32 => {
@@ -873,7 +873,7 @@ impl NPC {
                     self.npc_flags.set_shootable(false);
                     self.npc_flags.set_invulnerable(false);
                     self.damage = 0;
-                    state.sound_manager.play_sfx(29);
+                    state.sound_manager.play_sfx_at(29, self.x, self.y);
                 }
 
                 self.action_counter += 2;
@@ -959,7 +959,7 @@ impl NPC {
                 state.quake_rumble_counter = 2;
                 self.action_counter += 1;
                 if self.action_counter % 6 == 3 {
-                    state.sound_manager.play_sfx(25);
+                    state.sound_manager.play_sfx_at(25, self.x, self.y);
                 }
 
                 self.x = if self.action_counter & 2 != 0 { self.target_x } else { self.target_x + 512 };
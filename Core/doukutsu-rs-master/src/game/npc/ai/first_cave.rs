@@ -132,7 +132,7 @@ impl NPC {
                     self.vel_x = self.direction.vector_x() * 0x100;
                     self.vel_y = -0x5ff;
 
-                    state.sound_manager.play_sfx(30);
+                    state.sound_manager.play_sfx_at(30, self.x, self.y);
                 }
             }
             3 => {
@@ -142,7 +142,7 @@ impl NPC {
                     self.action_counter = 0;
                     self.action_num = 1;
 
-                    state.sound_manager.play_sfx(23);
+                    state.sound_manager.play_sfx_at(23, self.x, self.y);
                 }
             }
             _ => (),
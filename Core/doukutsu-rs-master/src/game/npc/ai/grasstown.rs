@@ -73,7 +73,7 @@ impl NPC {
                     self.anim_num = 2;
                     self.vel_x = self.direction.vector_x() * 0x100;
                     self.vel_y = -0x5ff;
-                    state.sound_manager.play_sfx(108);
+                    state.sound_manager.play_sfx_at(108, self.x, self.y);
                 }
             }
             3 => {
@@ -102,7 +102,7 @@ impl NPC {
                     self.damage = 12;
                 } else {
                     if self.action_counter % 4 == 1 {
-                        state.sound_manager.play_sfx(110);
+                        state.sound_manager.play_sfx_at(110, self.x, self.y);
                     }
 
                     self.animate(0, 3, 5);
@@ -116,7 +116,7 @@ impl NPC {
                     self.anim_num = 0;
                     self.damage = 2;
 
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                     state.quake_counter = 30;
                     state.quake_rumble_counter = 30;
                 }
@@ -276,7 +276,7 @@ impl NPC {
                     self.anim_num = 2;
                     self.vel_x = self.direction.vector_x() * 0x100;
                     self.vel_y = -0x4cc;
-                    state.sound_manager.play_sfx(30);
+                    state.sound_manager.play_sfx_at(30, self.x, self.y);
                 }
             }
             3 => {
@@ -305,7 +305,7 @@ impl NPC {
                     self.damage = 3;
                 } else {
                     if self.action_counter % 4 == 1 {
-                        state.sound_manager.play_sfx(109);
+                        state.sound_manager.play_sfx_at(109, self.x, self.y);
                     }
 
                     if self.flags.hit_bottom_wall() {
@@ -323,7 +323,7 @@ impl NPC {
                     self.anim_num = 0;
                     self.damage = 2;
 
-                    state.sound_manager.play_sfx(23);
+                    state.sound_manager.play_sfx_at(23, self.x, self.y);
                 }
             }
             _ => (),
@@ -445,7 +445,7 @@ impl NPC {
             npc_list.create_death_smoke(self.x, self.y, self.display_bounds.right as usize, 8, state, &self.rng);
             self.create_xp_drop(state, npc_list);
 
-            state.sound_manager.play_sfx(71);
+            state.sound_manager.play_sfx_at(71, self.x, self.y);
         }
 
         if self.action_num == 2 {
@@ -736,7 +736,7 @@ impl NPC {
         }
 
         if self.action_counter2 % 4 == 1 {
-            state.sound_manager.play_sfx(46);
+            state.sound_manager.play_sfx_at(46, self.x, self.y);
         }
 
         let dir_offset = if self.direction == Direction::Left { 0 } else { 3 };
@@ -842,7 +842,7 @@ impl NPC {
             self.vel_y = -0x5ff;
 
             if !player.cond.hidden() {
-                state.sound_manager.play_sfx(30);
+                state.sound_manager.play_sfx_at(30, self.x, self.y);
             }
         }
 
@@ -886,7 +886,7 @@ impl NPC {
 
                 self.anim_counter += 1;
                 if self.anim_counter > 1 {
-                    state.sound_manager.play_sfx(43);
+                    state.sound_manager.play_sfx_at(43, self.x, self.y);
 
                     self.anim_counter = 0;
                     self.anim_num += 1;
@@ -920,7 +920,7 @@ impl NPC {
 
                 if self.action_counter & 0x02 != 0 {
                     self.x += 0x200;
-                    state.sound_manager.play_sfx(11);
+                    state.sound_manager.play_sfx_at(11, self.x, self.y);
                 } else {
                     self.x -= 0x200;
                 }
@@ -935,7 +935,7 @@ impl NPC {
                     self.action_num = 17;
                     self.action_counter = 0;
                     self.anim_num = 2;
-                    state.sound_manager.play_sfx(12);
+                    state.sound_manager.play_sfx_at(12, self.x, self.y);
 
                     let mut npc = NPC::create(4, &state.npc_table);
                     npc.cond.set_alive(true);
@@ -968,14 +968,14 @@ impl NPC {
                     self.anim_num += 1;
                     if self.anim_num > 4 {
                         self.anim_num = 3;
-                        state.sound_manager.play_sfx(11);
+                        state.sound_manager.play_sfx_at(11, self.x, self.y);
                     }
                 }
 
                 self.action_counter += 1;
                 if self.action_counter > 100 {
                     self.action_num = 20;
-                    state.sound_manager.play_sfx(12);
+                    state.sound_manager.play_sfx_at(12, self.x, self.y);
 
                     let mut npc = NPC::create(4, &state.npc_table);
                     npc.cond.set_alive(true);
@@ -996,7 +996,7 @@ impl NPC {
                 self.action_num = 22;
                 self.anim_num = 5;
 
-                state.sound_manager.play_sfx(51);
+                state.sound_manager.play_sfx_at(51, self.x, self.y);
             }
             100 | 101 => {
                 if self.action_num == 100 {
@@ -1058,7 +1058,7 @@ impl NPC {
             }
             10 => {
                 self.action_num = 0;
-                state.sound_manager.play_sfx(12);
+                state.sound_manager.play_sfx_at(12, self.x, self.y);
 
                 let mut npc = NPC::create(4, &state.npc_table);
                 npc.cond.set_alive(true);
@@ -1182,7 +1182,7 @@ impl NPC {
             self.vel_x = self.direction.vector_x() * 0x100;
             self.vel_y = -0x2ff;
 
-            state.sound_manager.play_sfx(6);
+            state.sound_manager.play_sfx_at(6, self.x, self.y);
         }
 
         self.vel_y = (self.vel_y + 0x80).min(0x5ff);
@@ -1258,9 +1258,9 @@ impl NPC {
                             self.vel_x *= 2;
                             self.damage = 5;
 
-                            state.sound_manager.play_sfx(102);
+                            state.sound_manager.play_sfx_at(102, self.x, self.y);
                         } else {
-                            state.sound_manager.play_sfx(30);
+                            state.sound_manager.play_sfx_at(30, self.x, self.y);
                         }
                     }
                 }
@@ -1271,7 +1271,7 @@ impl NPC {
                     self.anim_num = 1;
                     self.anim_counter = 0;
                     self.damage = 0;
-                    state.sound_manager.play_sfx(23);
+                    state.sound_manager.play_sfx_at(23, self.x, self.y);
 
                     let player = self.get_closest_player_mut(players);
                     if player.x > self.x + 0x12000
@@ -1307,14 +1307,14 @@ impl NPC {
                     self.npc_flags.set_shootable(false);
                     self.npc_flags.set_solid_soft(false);
 
-                    state.sound_manager.play_sfx(51);
+                    state.sound_manager.play_sfx_at(51, self.x, self.y);
                 }
 
                 if self.flags.hit_bottom_wall() {
                     self.action_num = 52;
                     self.anim_num = 5;
                     self.vel_x = 0;
-                    state.sound_manager.play_sfx(23);
+                    state.sound_manager.play_sfx_at(23, self.x, self.y);
                 }
             }
             _ => (),
@@ -1371,7 +1371,7 @@ impl NPC {
                     self.x = self.target_x;
                     self.y = self.target_y;
 
-                    state.sound_manager.play_sfx(44);
+                    state.sound_manager.play_sfx_at(44, self.x, self.y);
                 }
 
                 self.vel_x += 0x20;
@@ -1409,7 +1409,7 @@ impl NPC {
         }
 
         if self.action_counter % 4 == 0 && self.action_num >= 20 {
-            state.sound_manager.play_sfx(34);
+            state.sound_manager.play_sfx_at(34, self.x, self.y);
             state.create_caret(
                 self.x + self.direction.opposite().vector_x() * 0x1400,
                 self.y + 0x1400,
@@ -58,7 +58,7 @@ impl NPC {
                     self.action_num = 6;
                     self.anim_num = 7;
 
-                    state.sound_manager.play_sfx(70);
+                    state.sound_manager.play_sfx_at(70, self.x, self.y);
                 }
             }
             6 => {
@@ -170,7 +170,7 @@ impl NPC {
                     self.hit_bounds.left = 0x3000;
                     self.hit_bounds.top = 1;
 
-                    state.sound_manager.play_sfx(70);
+                    state.sound_manager.play_sfx_at(70, self.x, self.y);
                 }
             }
             6 => {
@@ -189,7 +189,7 @@ impl NPC {
                     self.anim_num = 9;
                     self.damage = 0;
 
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                     state.quake_counter = 30;
                     state.quake_rumble_counter = 30;
 
@@ -243,7 +243,7 @@ impl NPC {
                     npc.vel_y = vel_y;
 
                     let _ = npc_list.spawn(0x100, npc);
-                    state.sound_manager.play_sfx(12);
+                    state.sound_manager.play_sfx_at(12, self.x, self.y);
                 }
 
                 self.anim_num = if self.action_counter > 50 && (self.action_counter & 0x02) != 0 { 11 } else { 10 };
@@ -280,7 +280,7 @@ impl NPC {
             0 | 1 => {
                 if self.action_num == 0 {
                     self.action_num = 1;
-                    state.sound_manager.play_sfx(72);
+                    state.sound_manager.play_sfx_at(72, self.x, self.y);
 
                     let player = self.get_closest_player_mut(players);
                     self.direction = if self.x > player.x { Direction::Left } else { Direction::Right };
@@ -461,7 +461,7 @@ impl NPC {
                     self.vel_x = self.direction.vector_x() * 0x200;
                     self.vel_y = -0x5FF;
 
-                    state.sound_manager.play_sfx(108);
+                    state.sound_manager.play_sfx_at(108, self.x, self.y);
                 }
             }
             30 => {
@@ -471,7 +471,7 @@ impl NPC {
                     self.action_counter = 0;
                     state.quake_counter = 20;
                     state.quake_rumble_counter = 20;
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                 }
             }
             40 => {
@@ -509,7 +509,7 @@ impl NPC {
                     npc.vel_y = vel_y;
 
                     let _ = npc_list.spawn(0x100, npc);
-                    state.sound_manager.play_sfx(12);
+                    state.sound_manager.play_sfx_at(12, self.x, self.y);
                 }
 
                 if self.action_counter < 50 && self.action_counter & 2 != 0 {
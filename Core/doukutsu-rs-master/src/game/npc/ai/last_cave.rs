@@ -69,7 +69,7 @@ impl NPC {
                     self.anim_num = 2;
 
                     self.vel_y = -0x5ff;
-                    state.sound_manager.play_sfx(30);
+                    state.sound_manager.play_sfx_at(30, self.x, self.y);
 
                     if self.direction == Direction::Left {
                         self.vel_x = -0x200;
@@ -85,7 +85,7 @@ impl NPC {
                     self.action_num = 1;
                     self.anim_num = 0;
 
-                    state.sound_manager.play_sfx(23);
+                    state.sound_manager.play_sfx_at(23, self.x, self.y);
                 }
             }
             _ => (),
@@ -197,7 +197,7 @@ impl NPC {
                 && self.y > player.y - 0x14000
                 && self.y < player.y + 0x14000
             {
-                state.sound_manager.play_sfx(21);
+                state.sound_manager.play_sfx_at(21, self.x, self.y);
             }
 
             self.cond.set_alive(false);
@@ -316,7 +316,7 @@ impl NPC {
 
                         let _ = npc_list.spawn(0x100, npc);
 
-                        state.sound_manager.play_sfx(39);
+                        state.sound_manager.play_sfx_at(39, self.x, self.y);
                     }
                     34 | 44 | 54 => {
                         self.anim_num = 3;
@@ -361,7 +361,7 @@ impl NPC {
 
                         let _ = npc_list.spawn(0x100, npc);
 
-                        state.sound_manager.play_sfx(39);
+                        state.sound_manager.play_sfx_at(39, self.x, self.y);
                     }
                     34 | 44 => {
                         self.anim_num = 5;
@@ -379,7 +379,7 @@ impl NPC {
                     self.anim_num = 2;
                     state.quake_counter = 10;
                     state.quake_rumble_counter = 10;
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                 }
             }
             22 => {
@@ -400,7 +400,7 @@ impl NPC {
                     self.anim_num = 2;
                     state.quake_counter = 10;
                     state.quake_rumble_counter = 10;
-                    state.sound_manager.play_sfx(72);
+                    state.sound_manager.play_sfx_at(72, self.x, self.y);
                     self.create_xp_drop_custom(self.x, self.y, 19, state, npc_list);
 
                     npc_list.create_death_smoke(
@@ -469,7 +469,7 @@ impl NPC {
 
         self.action_counter += 1;
         if self.action_counter % 5 == 0 {
-            state.sound_manager.play_sfx(110);
+            state.sound_manager.play_sfx_at(110, self.x, self.y);
         }
 
         self.anim_num += 1;
@@ -275,7 +275,7 @@ impl NPC {
                     self.action_num = 3;
                     self.npc_flags.set_shootable(true);
 
-                    state.sound_manager.play_sfx(34);
+                    state.sound_manager.play_sfx_at(34, self.x, self.y);
                 }
 
                 self.face_player(player);
@@ -289,7 +289,7 @@ impl NPC {
                 if self.action_counter > 40 {
                     self.action_counter = 0;
                     self.action_num = 4;
-                    state.sound_manager.play_sfx(106);
+                    state.sound_manager.play_sfx_at(106, self.x, self.y);
                 }
             }
             4 => {
@@ -499,7 +499,7 @@ impl NPC {
                     self.anim_num = 3;
                     self.vel_x = 2 * self.rng.range(-512..512);
                     self.vel_y = -0x800;
-                    state.sound_manager.play_sfx(30);
+                    state.sound_manager.play_sfx_at(30, self.x, self.y);
                     self.action_counter2 += 1;
                 }
             }
@@ -553,7 +553,7 @@ impl NPC {
 
                     self.vel_x = 0x5FF * self.direction.vector_x();
 
-                    state.sound_manager.play_sfx(25);
+                    state.sound_manager.play_sfx_at(25, self.x, self.y);
                     self.npc_flags.set_shootable(false);
                     self.npc_flags.set_invulnerable(true);
                     self.damage = 10;
@@ -572,7 +572,7 @@ impl NPC {
                     self.action_counter = 0;
                     state.quake_counter = 16;
                     state.quake_rumble_counter = 16;
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                     self.damage = 4
                 }
 
@@ -622,7 +622,7 @@ impl NPC {
                     self.action_num = 320;
                     self.anim_num = 12;
                     self.vel_y = -0x800;
-                    state.sound_manager.play_sfx(25);
+                    state.sound_manager.play_sfx_at(25, self.x, self.y);
                     self.npc_flags.set_ignore_solidity(true);
                     self.npc_flags.set_shootable(false);
                     self.npc_flags.set_invulnerable(true);
@@ -643,7 +643,7 @@ impl NPC {
                     self.action_counter = 0;
                     state.quake_counter = 16;
                     state.quake_rumble_counter = 16;
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                 }
 
                 self.animate(1, 12, 13);
@@ -742,7 +742,7 @@ impl NPC {
                     self.vel_y = -0x200;
                     self.action_num = 110;
                     self.npc_flags.set_ignore_solidity(true);
-                    state.sound_manager.play_sfx(12);
+                    state.sound_manager.play_sfx_at(12, self.x, self.y);
                     state.quake_counter = 10;
                     state.quake_rumble_counter = 10;
 
@@ -824,7 +824,7 @@ impl NPC {
                     self.anim_num = 3;
                     self.vel_x = 2 * self.rng.range(-0x200..0x200);
                     self.vel_y = -0x800;
-                    state.sound_manager.play_sfx(30);
+                    state.sound_manager.play_sfx_at(30, self.x, self.y);
                 }
             }
             130 => {
@@ -42,7 +42,7 @@ impl NPC {
                 if (self.x - self.target_x).abs() < 0x600 && (self.y - self.target_y).abs() < 0x600 {
                     self.action_num = 2;
                     self.anim_num = 2;
-                    state.sound_manager.play_sfx(21);
+                    state.sound_manager.play_sfx_at(21, self.x, self.y);
 
                     if let Some(npc) = npc_list.get_npc(self.action_counter2 as usize) {
                         npc.cond.set_alive(false);
@@ -87,7 +87,7 @@ impl NPC {
                     self.target_x = self.x;
                     self.target_y = self.y;
 
-                    state.sound_manager.play_sfx(29);
+                    state.sound_manager.play_sfx_at(29, self.x, self.y);
                 }
 
                 self.x = self.target_x + self.rng.range(-1..1) as i32 * 0x200;
@@ -123,7 +123,7 @@ impl NPC {
                 self.clamp_fall_speed();
 
                 if self.flags.hit_bottom_wall() {
-                    state.sound_manager.play_sfx(23);
+                    state.sound_manager.play_sfx_at(23, self.x, self.y);
                     self.vel_y = 0;
                     self.action_num = 14;
                     self.npc_flags.set_ignore_solidity(true);
@@ -139,7 +139,7 @@ impl NPC {
 
                 self.action_counter += 1;
                 if self.action_counter == 30 {
-                    state.sound_manager.play_sfx(21);
+                    state.sound_manager.play_sfx_at(21, self.x, self.y);
                     let mut npc = NPC::create(66, &state.npc_table);
                     npc.cond.set_alive(true);
                     npc.x = self.x;
@@ -182,7 +182,7 @@ impl NPC {
 
                 self.action_counter += 1;
                 if self.action_counter == 30 {
-                    state.sound_manager.play_sfx(101);
+                    state.sound_manager.play_sfx_at(101, self.x, self.y);
                     flash.set_blink();
                     self.action_num = 27;
                     self.anim_num = 7;
@@ -266,7 +266,7 @@ impl NPC {
 
                 self.action_counter += 1;
                 if self.action_counter == 30 {
-                    state.sound_manager.play_sfx(21);
+                    state.sound_manager.play_sfx_at(21, self.x, self.y);
 
                     let mut npc = NPC::create(66, &state.npc_table);
                     npc.x = self.x;
@@ -313,7 +313,7 @@ impl NPC {
                     self.action_num = 27;
                     self.anim_num = 7;
 
-                    state.sound_manager.play_sfx(101);
+                    state.sound_manager.play_sfx_at(101, self.x, self.y);
                     flash.set_blink();
                 }
             }
@@ -355,7 +355,7 @@ impl NPC {
 
                 self.action_counter += 1;
                 if self.action_counter == 30 || self.action_counter == 40 || self.action_counter == 50 {
-                    state.sound_manager.play_sfx(33);
+                    state.sound_manager.play_sfx_at(33, self.x, self.y);
 
                     let mut npc = NPC::create(11, &state.npc_table);
                     npc.x = self.x + 0x1000;
@@ -525,7 +525,7 @@ impl NPC {
                     npc.vel_y = (angle.sin() * -2048.0) as i32;
 
                     let _ = npc_list.spawn(0x100, npc);
-                    state.sound_manager.play_sfx(34);
+                    state.sound_manager.play_sfx_at(34, self.x, self.y);
                 }
 
                 if self.action_counter > 30 {
@@ -569,7 +569,7 @@ impl NPC {
                     self.target_x = self.rng.range(9..31) * 0x2000;
                     self.target_y = self.rng.range(5..7) * 0x2000;
 
-                    state.sound_manager.play_sfx(29);
+                    state.sound_manager.play_sfx_at(29, self.x, self.y);
                 }
 
                 self.action_counter += 1;
@@ -642,7 +642,7 @@ impl NPC {
                     npc.y = self.y + 0x800;
                     let _ = npc_list.spawn(0x100, npc);
 
-                    state.sound_manager.play_sfx(34);
+                    state.sound_manager.play_sfx_at(34, self.x, self.y);
                 }
 
                 if self.action_counter > 72 {
@@ -788,7 +788,7 @@ impl NPC {
 
                     let _ = npc_list.spawn(0x100, npc);
 
-                    state.sound_manager.play_sfx(101);
+                    state.sound_manager.play_sfx_at(101, self.x, self.y);
                     self.cond.set_alive(false);
 
                     return Ok(());
@@ -929,7 +929,7 @@ impl NPC {
                 if self.action_num == 0 {
                     self.action_num = 1;
                     self.y -= 0x1000;
-                    state.sound_manager.play_sfx(29);
+                    state.sound_manager.play_sfx_at(29, self.x, self.y);
                 }
 
                 self.action_counter += 1;
@@ -1004,7 +1004,7 @@ impl NPC {
                     self.vel_x = 0;
                     self.vel_y = 0;
 
-                    state.sound_manager.play_sfx(103);
+                    state.sound_manager.play_sfx_at(103, self.x, self.y);
 
                     let player = self.get_closest_player_ref(&players);
                     self.direction = if self.x > player.x { Direction::Left } else { Direction::Right };
@@ -1015,7 +1015,7 @@ impl NPC {
                 self.anim_num = if self.action_counter & 2 != 0 { 4 } else { 5 };
 
                 if self.action_counter % 6 == 1 {
-                    state.sound_manager.play_sfx(39);
+                    state.sound_manager.play_sfx_at(39, self.x, self.y);
 
                     let mut npc = NPC::create(self.action_counter3, &state.npc_table);
                     npc.cond.set_alive(true);
@@ -1067,7 +1067,7 @@ impl NPC {
                     self.vel_y = 0;
                     self.direction = if self.x > player.x { Direction::Left } else { Direction::Right };
 
-                    state.sound_manager.play_sfx(103);
+                    state.sound_manager.play_sfx_at(103, self.x, self.y);
                 }
 
                 self.action_counter += 1;
@@ -1075,7 +1075,7 @@ impl NPC {
 
                 let period = if player.equip.has_booster_2_0() { 10 } else { 24 };
                 if self.action_counter % period == 1 {
-                    state.sound_manager.play_sfx(39);
+                    state.sound_manager.play_sfx_at(39, self.x, self.y);
 
                     let mut npc = NPC::create(301, &state.npc_table);
                     npc.cond.set_alive(true);
@@ -1190,7 +1190,7 @@ impl NPC {
                     self.action_counter2 += 1;
                     self.action_num = if self.action_counter2 > 4 { 12 } else { 10 };
 
-                    state.sound_manager.play_sfx(30);
+                    state.sound_manager.play_sfx_at(30, self.x, self.y);
                     self.vel_x = self.direction.vector_x() * 0x200;
                     self.vel_y = -0x600;
                 }
@@ -63,14 +63,14 @@ impl NPC {
             }
 
             if self.flags.hit_bottom_wall() {
-                state.sound_manager.play_sfx(45);
+                state.sound_manager.play_sfx_at(45, self.x, self.y);
 
                 self.vel_y = -0x280;
                 self.vel_x = 2 * self.vel_x / 3;
             }
 
             if self.flags.hit_left_wall() || self.flags.hit_right_wall() || self.flags.hit_bottom_wall() {
-                state.sound_manager.play_sfx(45);
+                state.sound_manager.play_sfx_at(45, self.x, self.y);
                 self.action_counter2 += 1;
 
                 if self.action_counter2 > 2 {
@@ -44,7 +44,7 @@ impl NPC {
                     self.action_counter = 64;
                     self.action_num = 4;
 
-                    state.sound_manager.play_sfx(29);
+                    state.sound_manager.play_sfx_at(29, self.x, self.y);
                 }
             }
             4 => {
@@ -93,7 +93,7 @@ impl NPC {
                 self.x += 0x2000;
                 self.y += 0x1000;
 
-                state.sound_manager.play_sfx(29);
+                state.sound_manager.play_sfx_at(29, self.x, self.y);
             }
             1 => {
                 self.action_counter += 1;
@@ -171,7 +171,7 @@ impl NPC {
                 self.action_num = 11;
                 self.anim_num = 2;
 
-                state.sound_manager.play_sfx(71);
+                state.sound_manager.play_sfx_at(71, self.x, self.y);
 
                 let mut npc = NPC::create(4, &state.npc_table);
                 npc.cond.set_alive(true);
@@ -193,7 +193,7 @@ impl NPC {
                 self.action_num = 21;
                 self.action_counter = 63;
 
-                state.sound_manager.play_sfx(29);
+                state.sound_manager.play_sfx_at(29, self.x, self.y);
             }
             21 => {
                 if self.action_counter > 0 {
@@ -346,7 +346,7 @@ impl NPC {
                 self.action_num = 11;
                 self.anim_num = 2;
 
-                state.sound_manager.play_sfx(71);
+                state.sound_manager.play_sfx_at(71, self.x, self.y);
 
                 let mut npc = NPC::create(4, &state.npc_table);
                 npc.cond.set_alive(true);
@@ -368,7 +368,7 @@ impl NPC {
                 self.action_num = 21;
                 self.action_counter = 63;
 
-                state.sound_manager.play_sfx(29);
+                state.sound_manager.play_sfx_at(29, self.x, self.y);
             }
             21 => {
                 if self.action_counter > 0 {
@@ -58,7 +58,7 @@ impl NPC {
             0 | 1 => {
                 if self.action_num == 0 {
                     self.action_num = 1;
-                    state.sound_manager.play_sfx(44);
+                    state.sound_manager.play_sfx_at(44, self.x, self.y);
                     self.vel_x = self.direction.vector_x() * 0x400;
                 }
                 self.animate(1, 0, 2);
@@ -73,7 +73,7 @@ impl NPC {
                     npc.vel_y = -0x400;
                     let _ = npc_list.spawn(0x100, npc);
 
-                    state.sound_manager.play_sfx(12);
+                    state.sound_manager.play_sfx_at(12, self.x, self.y);
                 }
             }
             _ => (),
@@ -103,7 +103,7 @@ impl NPC {
                     self.target_x = self.x;
                     self.target_y = self.y;
                     self.y = self.get_closest_player_mut(players).y;
-                    state.sound_manager.play_sfx(103);
+                    state.sound_manager.play_sfx_at(103, self.x, self.y);
                 }
                 self.action_counter += 1;
                 self.anim_num = 1 - (self.action_counter & 2) / 2;
@@ -308,7 +308,7 @@ impl NPC {
                     self.damage = 10;
 
                     self.face_player(player);
-                    state.sound_manager.play_sfx(25);
+                    state.sound_manager.play_sfx_at(25, self.x, self.y);
                 }
 
                 self.vel_x = if self.direction == Direction::Left { -0x800 } else { 0x800 };
@@ -324,7 +324,7 @@ impl NPC {
                     self.damage = 3;
                     state.super_quake_counter = 10;
                     state.super_quake_rumble_counter = 10;
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                 }
 
                 if self.action_counter2 < 4 && player.x > self.x - 0x2000 && player.x < self.x + 0x2000 {
@@ -349,7 +349,7 @@ impl NPC {
                     self.vel_x = 0;
                     self.damage = 10;
                     self.direction = if self.action_num == 221 { Direction::Left } else { Direction::Right };
-                    state.sound_manager.play_sfx(25);
+                    state.sound_manager.play_sfx_at(25, self.x, self.y);
                 }
                 self.vel_y = if self.action_num == 221 { -0x800 } else { 0x800 };
 
@@ -397,7 +397,7 @@ impl NPC {
 
                     state.super_quake_counter = 10;
                     state.super_quake_rumble_counter = 10;
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                 }
 
                 if self.action_counter2 < 4 && player.y > self.y - 0x2000 && player.y < self.y + 0x2000 {
@@ -526,7 +526,7 @@ impl NPC {
                     // I think Pixel meant for the smoke radius to be 16 pixels (0x2000) instead of 16 units,
                     // because as it is, this just gets divided by 0x200 units/px and becomes 0
                     npc_list.create_death_smoke(self.x, self.y, 16, 16, state, &self.rng);
-                    state.sound_manager.play_sfx(72);
+                    state.sound_manager.play_sfx_at(72, self.x, self.y);
                 }
                 self.vel_y += 0x20;
                 self.clamp_fall_speed();
@@ -566,7 +566,7 @@ impl NPC {
                     self.action_num = 1005;
                     self.action_counter = 0;
                     flash.set_blink();
-                    state.sound_manager.play_sfx(29);
+                    state.sound_manager.play_sfx_at(29, self.x, self.y);
                 }
                 self.anim_num = self.anim_num.max(8);
                 self.animate(1, 8, 9);
@@ -679,7 +679,7 @@ impl NPC {
                     self.action_num = 22;
                     self.npc_flags.set_shootable(false);
                     npc_list.create_death_smoke(self.x, self.y, 0x2000, 32, state, &self.rng);
-                    state.sound_manager.play_sfx(71);
+                    state.sound_manager.play_sfx_at(71, self.x, self.y);
                 }
 
                 boss.parts[0].action_counter2 = 4;
@@ -760,7 +760,7 @@ impl NPC {
                     self.npc_flags.set_ignore_solidity(false);
                     self.npc_flags.set_shootable(false);
                     npc_list.create_death_smoke(self.x, self.y, 0x2000, 32, state, &self.rng);
-                    state.sound_manager.play_sfx(71);
+                    state.sound_manager.play_sfx_at(71, self.x, self.y);
                 }
 
                 if self.flags.hit_left_wall() {
@@ -775,7 +775,7 @@ impl NPC {
                     }
 
                     self.vel_y = -0x800;
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                 }
 
                 self.vel_y += 0x20;
@@ -802,7 +802,7 @@ impl NPC {
                     }
                 } else {
                     npc_list.create_death_smoke(self.x, self.y, 0x2000, 32, state, &self.rng);
-                    state.sound_manager.play_sfx(71);
+                    state.sound_manager.play_sfx_at(71, self.x, self.y);
                     self.vanish(state);
                     return Ok(());
                 }
@@ -825,7 +825,7 @@ impl NPC {
                     npc.y = self.y + 0x1800;
                     let _ = npc_list.spawn(0x100, npc);
 
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                 }
                 (Direction::Up, 268) => {
                     let mut npc = NPC::create(4, &state.npc_table);
@@ -840,7 +840,7 @@ impl NPC {
                     npc.y = self.y - 0x1000;
                     let _ = npc_list.spawn(0x100, npc);
 
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                 }
                 (Direction::Right, 396) => {
                     let mut npc = NPC::create(4, &state.npc_table);
@@ -861,7 +861,7 @@ impl NPC {
                     npc.y = self.y - 0x1800;
                     let _ = npc_list.spawn(0x100, npc);
 
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                 }
                 (Direction::Bottom, 12) => {
                     let mut npc = NPC::create(4, &state.npc_table);
@@ -876,7 +876,7 @@ impl NPC {
                     npc.y = self.y - 0x1000;
                     let _ = npc_list.spawn(0x100, npc);
 
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
                 }
                 _ => (),
             }
@@ -966,7 +966,7 @@ impl NPC {
                     self.action_num = 110;
                     self.vel_y = -0x200;
                     self.npc_flags.set_ignore_solidity(true);
-                    state.sound_manager.play_sfx(12);
+                    state.sound_manager.play_sfx_at(12, self.x, self.y);
                     state.quake_counter = 10;
                     state.quake_rumble_counter = 10;
 
@@ -1383,8 +1383,8 @@ impl NPC {
                     self.action_counter = 0;
                     state.quake_counter = 20;
                     state.quake_rumble_counter = 20;
-                    state.sound_manager.play_sfx(26);
-                    state.sound_manager.play_sfx(12);
+                    state.sound_manager.play_sfx_at(26, self.x, self.y);
+                    state.sound_manager.play_sfx_at(12, self.x, self.y);
 
                     self.x += 0x2000 * self.direction.vector_x();
 
@@ -1506,7 +1506,7 @@ impl BossNPC {
                     self.parts[0].action_counter = 0;
                     state.super_quake_counter = 30;
                     state.super_quake_rumble_counter = 30;
-                    state.sound_manager.play_sfx(44);
+                    state.sound_manager.play_sfx_at(44, self.parts[0].x, self.parts[0].y);
 
                     if player.y > self.parts[0].y + 0x6000
                         && player.x < self.parts[0].x + 0x3000
@@ -1589,8 +1589,8 @@ impl BossNPC {
                     self.parts[0].action_counter = 0;
                     state.super_quake_counter = 30;
                     state.super_quake_rumble_counter = 30;
-                    state.sound_manager.play_sfx(26);
-                    state.sound_manager.play_sfx(44);
+                    state.sound_manager.play_sfx_at(26, self.parts[0].x, self.parts[0].y);
+                    state.sound_manager.play_sfx_at(44, self.parts[0].x, self.parts[0].y);
 
                     if player.y > self.parts[0].y + 0x7000 {
                         player.damage(16, state, npc_list);
@@ -1646,7 +1646,7 @@ impl BossNPC {
                     self.parts[0].action_counter = 0;
                     state.super_quake_counter = 30;
                     state.super_quake_rumble_counter = 30;
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.parts[0].x, self.parts[0].y);
 
                     for _ in 0..16 {
                         let mut npc = NPC::create(4, &state.npc_table);
@@ -1821,7 +1821,7 @@ impl BossNPC {
                 }
 
                 if (self.parts[0].action_counter / 3 % 2) > 0 {
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.parts[0].x, self.parts[0].y);
                 }
 
                 if self.parts[0].action_counter > 540 {
@@ -1835,7 +1835,7 @@ impl BossNPC {
                     self.parts[0].anim_counter = 0;
                     state.super_quake_counter = 30;
                     state.super_quake_rumble_counter = 30;
-                    state.sound_manager.play_sfx(35);
+                    state.sound_manager.play_sfx_at(35, self.parts[0].x, self.parts[0].y);
 
                     self.parts[1].action_num = 102;
                     self.parts[2].action_num = 102;
@@ -1880,7 +1880,7 @@ impl BossNPC {
                 self.parts[0].action_counter += 1;
 
                 if self.parts[0].action_counter % 12 == 0 {
-                    state.sound_manager.play_sfx(44);
+                    state.sound_manager.play_sfx_at(44, self.parts[0].x, self.parts[0].y);
                 }
 
                 npc_list.create_death_smoke(
@@ -1896,7 +1896,7 @@ impl BossNPC {
                     self.parts[0].action_counter = 0;
                     self.parts[0].action_num = 1002;
                     flash.set_cross(self.parts[0].x, self.parts[0].y);
-                    state.sound_manager.play_sfx(35);
+                    state.sound_manager.play_sfx_at(35, self.parts[0].x, self.parts[0].y);
                 }
             }
             1002 => {
@@ -1951,7 +1951,7 @@ impl BossNPC {
                     npc.direction = Direction::Up;
                     let _ = npc_list.spawn(0x100, npc);
 
-                    state.sound_manager.play_sfx(39);
+                    state.sound_manager.play_sfx_at(39, self.parts[0].x, self.parts[0].y);
 
                     for _ in 0..4 {
                         let mut npc = NPC::create(4, &state.npc_table);
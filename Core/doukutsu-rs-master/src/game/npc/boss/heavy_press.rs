@@ -17,7 +17,7 @@ impl NPC {
             0 | 1 => {
                 if self.action_num == 0 {
                     self.action_num = 1;
-                    state.sound_manager.play_sfx(29);
+                    state.sound_manager.play_sfx_at(29, self.x, self.y);
                 }
 
                 self.animate(0, 0, 2);
@@ -30,7 +30,7 @@ impl NPC {
                     self.damage = 10;
                     self.display_bounds.left = 0x1000;
                     self.display_bounds.top = 0x1800;
-                    state.sound_manager.play_sfx(101);
+                    state.sound_manager.play_sfx_at(101, self.x, self.y);
                     npc_list.create_death_smoke(self.x, self.y + 0xA800, 0, 3, state, &self.rng);
                 }
             }
@@ -120,7 +120,7 @@ impl BossNPC {
                     self.parts[0].y = 0x33A00;
                     self.parts[0].anim_num = 0;
                     self.parts[0].action_num = 20;
-                    state.sound_manager.play_sfx(44);
+                    state.sound_manager.play_sfx_at(44, self.parts[0].x, self.parts[0].y);
 
                     for _ in 0..5 {
                         let mut npc = NPC::create(4, &state.npc_table);
@@ -171,7 +171,7 @@ impl BossNPC {
                             state,
                             &self.parts[0].rng,
                         );
-                        state.sound_manager.play_sfx(12);
+                        state.sound_manager.play_sfx_at(12, self.parts[0].x, self.parts[0].y);
                     }
                 }
 
@@ -217,7 +217,7 @@ impl BossNPC {
                 self.parts[0].action_counter += 1;
 
                 if self.parts[0].action_counter % 16 == 0 {
-                    state.sound_manager.play_sfx(12);
+                    state.sound_manager.play_sfx_at(12, self.parts[0].x, self.parts[0].y);
                     npc_list.create_death_smoke(
                         self.parts[0].x + self.parts[0].rng.range(-40..40) * 0x200,
                         self.parts[0].y + self.parts[0].rng.range(-60..60) * 0x200,
@@ -252,7 +252,7 @@ impl BossNPC {
                         stage.change_tile(i + 7, 14, 0);
                         // This should be called with an amount of 0, but change_tile also needs to make smoke
                         npc_list.create_death_smoke((i as i32 + 7) * 0x2000, 0x1C000, 0, 3, state, &self.parts[0].rng);
-                        state.sound_manager.play_sfx(12);
+                        state.sound_manager.play_sfx_at(12, self.parts[0].x, self.parts[0].y);
                     }
                 }
 
@@ -66,6 +66,59 @@ impl BossNPC {
             );
         }
     }
+
+    /// Draws the normal map layer of the boss spritesheet, returns false if it doesn't have one.
+    pub fn draw_normal_map(&self, state: &mut SharedGameState, ctx: &mut Context, frame: &Frame) -> GameResult<bool> {
+        self.draw_parts(state, ctx, frame, true)
+    }
+
+    fn draw_parts(
+        &self,
+        state: &mut SharedGameState,
+        ctx: &mut Context,
+        frame: &Frame,
+        normal_map: bool,
+    ) -> GameResult<bool> {
+        let batch = state.texture_set.get_or_load_batch(
+            ctx,
+            &state.constants,
+            &state.npc_table.stage_textures.deref().borrow().npc2,
+        )?;
+        let batch = if normal_map {
+            match batch.normal() {
+                Some(batch) => batch,
+                None => return Ok(false),
+            }
+        } else {
+            batch.as_mut()
+        };
+
+        for npc in self.parts.iter().rev() {
+            if !npc.cond.alive() || npc.cond.hidden() {
+                continue;
+            }
+
+            let off_x =
+                if npc.direction == Direction::Left { npc.display_bounds.left } else { npc.display_bounds.right }
+                    as i32;
+            let shock = if npc.shock > 0 { (2 * ((npc.shock as i32 / 2) & 1) - 1) as f32 } else { 0.0 };
+            let (frame_x, frame_y) = frame.xy_interpolated(state.frame_time);
+
+            batch.add_rect(
+                interpolate_fix9_scale(npc.prev_x - off_x, npc.x - off_x, state.frame_time) + shock - frame_x,
+                interpolate_fix9_scale(
+                    npc.prev_y - npc.display_bounds.top as i32,
+                    npc.y - npc.display_bounds.top as i32,
+                    state.frame_time,
+                ) - frame_y,
+                &npc.anim_rect,
+            );
+        }
+
+        batch.draw(ctx)?;
+
+        Ok(true)
+    }
 }
 
 impl GameEntity<([&mut Player; 2], &NPCList, &mut Stage, &BulletManager, &mut Flash)> for BossNPC {
@@ -114,36 +167,6 @@ impl GameEntity<([&mut Player; 2], &NPCList, &mut Stage, &BulletManager, &mut Fl
     }
 
     fn draw(&self, state: &mut SharedGameState, ctx: &mut Context, frame: &Frame) -> GameResult {
-        let batch = state.texture_set.get_or_load_batch(
-            ctx,
-            &state.constants,
-            &state.npc_table.stage_textures.deref().borrow().npc2,
-        )?;
-
-        for npc in self.parts.iter().rev() {
-            if !npc.cond.alive() || npc.cond.hidden() {
-                continue;
-            }
-
-            let off_x =
-                if npc.direction == Direction::Left { npc.display_bounds.left } else { npc.display_bounds.right }
-                    as i32;
-            let shock = if npc.shock > 0 { (2 * ((npc.shock as i32 / 2) & 1) - 1) as f32 } else { 0.0 };
-            let (frame_x, frame_y) = frame.xy_interpolated(state.frame_time);
-
-            batch.add_rect(
-                interpolate_fix9_scale(npc.prev_x - off_x, npc.x - off_x, state.frame_time) + shock - frame_x,
-                interpolate_fix9_scale(
-                    npc.prev_y - npc.display_bounds.top as i32,
-                    npc.y - npc.display_bounds.top as i32,
-                    state.frame_time,
-                ) - frame_y,
-                &npc.anim_rect,
-            );
-        }
-
-        batch.draw(ctx)?;
-
-        Ok(())
+        self.draw_parts(state, ctx, frame, false).map(|_| ())
     }
 }
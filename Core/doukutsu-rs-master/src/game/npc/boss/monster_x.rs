@@ -465,7 +465,7 @@ impl BossNPC {
 
                 self.parts[0].action_counter += 1;
                 if self.parts[0].action_counter % 8 == 0 {
-                    state.sound_manager.play_sfx(52);
+                    state.sound_manager.play_sfx_at(52, self.parts[0].x, self.parts[0].y);
                 }
 
                 let x = self.parts[0].x + self.parts[0].rng.range(-72..72) as i32 * 0x200;
@@ -476,7 +476,7 @@ impl BossNPC {
                     self.parts[0].action_num = 1001;
                     self.parts[0].action_counter = 0;
                     flash.set_cross(self.parts[0].x, self.parts[0].y);
-                    state.sound_manager.play_sfx(35);
+                    state.sound_manager.play_sfx_at(35, self.parts[0].x, self.parts[0].y);
                 }
             }
             1001 => {
@@ -683,11 +683,11 @@ impl BossNPC {
         }
 
         if self.parts[i].action_counter % 2 == 1 && [101, 201, 301, 401].contains(&self.parts[i].action_num) {
-            state.sound_manager.play_sfx(112);
+            state.sound_manager.play_sfx_at(112, self.parts[0].x, self.parts[0].y);
         }
 
         if self.parts[i].action_counter % 4 == 1 && [103, 203].contains(&self.parts[i].action_num) {
-            state.sound_manager.play_sfx(111);
+            state.sound_manager.play_sfx_at(111, self.parts[0].x, self.parts[0].y);
         }
 
         let player_idx = self.parts[i].get_closest_player_idx_mut(players);
@@ -719,7 +719,7 @@ impl BossNPC {
                     self.parts[i].action_counter -= 1;
                 } else {
                     self.parts[i].action_counter = 120;
-                    state.sound_manager.play_sfx(39);
+                    state.sound_manager.play_sfx_at(39, self.parts[0].x, self.parts[0].y);
 
                     let mut npc = NPC::create(158, &state.npc_table);
                     npc.cond.set_alive(true);
@@ -863,7 +863,7 @@ impl BossNPC {
 
                     let _ = npc_list.spawn(0x100, npc);
 
-                    state.sound_manager.play_sfx(39);
+                    state.sound_manager.play_sfx_at(39, self.parts[0].x, self.parts[0].y);
                     self.parts[i].action_counter = 40;
                 }
             }
@@ -121,7 +121,7 @@ impl BossNPC {
                 state.quake_rumble_counter = 2;
 
                 if self.parts[0].action_counter % 4 == 0 {
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.parts[0].x, self.parts[0].y);
                 }
 
                 if self.parts[0].action_counter == 48 {
@@ -148,7 +148,7 @@ impl BossNPC {
                     self.parts[0].anim_counter = 0;
                     self.parts[5].hit_bounds.top = 0x2000;
 
-                    state.sound_manager.play_sfx(102);
+                    state.sound_manager.play_sfx_at(102, self.parts[0].x, self.parts[0].y);
                 }
             }
             50 => {
@@ -178,14 +178,14 @@ impl BossNPC {
                     npc.direction = if self.parts[0].rng.range(0..9) <= 7 { Direction::Left } else { Direction::Right };
 
                     let _ = npc_list.spawn(0x100, npc);
-                    state.sound_manager.play_sfx(39);
+                    state.sound_manager.play_sfx_at(39, self.parts[0].x, self.parts[0].y);
                 }
 
                 if self.parts[0].action_counter == 200 || bullet_manager.count_bullets_type_idx_all(6) > 0 {
                     self.parts[0].action_num = 70;
                     self.parts[0].anim_counter = 0;
 
-                    state.sound_manager.play_sfx(102);
+                    state.sound_manager.play_sfx_at(102, self.parts[0].x, self.parts[0].y);
                 }
             }
             70 => {
@@ -198,7 +198,7 @@ impl BossNPC {
                         1 => self.parts[0].damage = 20,
                         0 => {
                             state.sound_manager.stop_sfx(102);
-                            state.sound_manager.play_sfx(12);
+                            state.sound_manager.play_sfx_at(12, self.parts[0].x, self.parts[0].y);
                             self.parts[0].action_num = 80;
                             self.parts[0].action_counter = 0;
                             self.parts[0].npc_flags.set_shootable(false);
@@ -226,7 +226,7 @@ impl BossNPC {
                 self.parts[0].action_counter += 1;
 
                 if self.parts[0].action_counter % 4 == 0 {
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, self.parts[0].x, self.parts[0].y);
                 }
 
                 if self.parts[0].action_counter == 48 {
@@ -264,7 +264,7 @@ impl BossNPC {
                     self.parts[0].action_counter = 0;
                     self.parts[0].anim_counter = 0;
 
-                    state.sound_manager.play_sfx(102);
+                    state.sound_manager.play_sfx_at(102, self.parts[0].x, self.parts[0].y);
                 }
 
                 if self.parts[0].action_counter < 30 && self.parts[0].action_counter % 5 == 0 {
@@ -277,7 +277,7 @@ impl BossNPC {
                     npc.direction = Direction::Left;
 
                     let _ = npc_list.spawn(0x100, npc);
-                    state.sound_manager.play_sfx(39);
+                    state.sound_manager.play_sfx_at(39, self.parts[0].x, self.parts[0].y);
                 }
             }
             130 => {
@@ -299,8 +299,8 @@ impl BossNPC {
                         self.parts[0].damage = 0;
                         self.parts[5].hit_bounds.top = 0x4800;
 
-                        state.sound_manager.play_sfx(12);
-                        state.sound_manager.play_sfx(25);
+                        state.sound_manager.play_sfx_at(12, self.parts[0].x, self.parts[0].y);
+                        state.sound_manager.play_sfx_at(25, self.parts[0].x, self.parts[0].y);
                         state.sound_manager.stop_sfx(102);
                     }
                     1 => {
@@ -325,8 +325,8 @@ impl BossNPC {
                     self.parts[5].hit_bounds.top = 0x2000;
                     self.parts[5].damage = 0;
 
-                    state.sound_manager.play_sfx(26);
-                    state.sound_manager.play_sfx(12);
+                    state.sound_manager.play_sfx_at(26, self.parts[0].x, self.parts[0].y);
+                    state.sound_manager.play_sfx_at(12, self.parts[0].x, self.parts[0].y);
                     state.quake_counter = 30;
                     state.quake_rumble_counter = 30;
                 }
@@ -337,7 +337,7 @@ impl BossNPC {
 
                 self.parts[0].action_counter += 1;
                 if self.parts[0].action_counter % 12 == 0 {
-                    state.sound_manager.play_sfx(52);
+                    state.sound_manager.play_sfx_at(52, self.parts[0].x, self.parts[0].y);
                 }
 
                 let dest_x = self.parts[0].x + self.parts[0].rng.range(-0x30..0x30) * 0x200;
@@ -349,7 +349,7 @@ impl BossNPC {
                     self.parts[0].action_num = 160;
                     self.parts[0].action_counter = 0;
                     flash.set_cross(self.parts[0].x, self.parts[0].y);
-                    state.sound_manager.play_sfx(35);
+                    state.sound_manager.play_sfx_at(35, self.parts[0].x, self.parts[0].y);
                 }
             }
             160 => {
@@ -219,7 +219,7 @@ impl BossNPC {
                     self.parts[0].action_counter = 0;
 
                     flash.set_cross(self.parts[0].x, self.parts[0].y);
-                    state.sound_manager.play_sfx(35);
+                    state.sound_manager.play_sfx_at(35, self.parts[0].x, self.parts[0].y);
                 }
             }
             1020 => {
@@ -315,7 +315,7 @@ impl BossNPC {
                     part.anim_num = 3;
                     part.hit_bounds.left = 0x2000;
 
-                    state.sound_manager.play_sfx(51);
+                    state.sound_manager.play_sfx_at(51, part.x, part.y);
                     
                     npc_list.create_death_smoke(
                         part.x,
@@ -345,7 +345,7 @@ impl BossNPC {
 
                     let _ = npc_list.spawn(0x100, npc);
 
-                    state.sound_manager.play_sfx(33);
+                    state.sound_manager.play_sfx_at(33, part.x, part.y);
                 }
 
                 if part.action_counter > 50 {
@@ -395,7 +395,7 @@ impl BossNPC {
 
                     let _ = npc_list.spawn(0x100, npc);
 
-                    state.sound_manager.play_sfx(33);
+                    state.sound_manager.play_sfx_at(33, part.x, part.y);
                 }
             }
             1000 => {
@@ -277,7 +277,7 @@ impl NPC {
                     self.vel_y = 0;
 
                     self.display_bounds = Rect::new(0x1800, 0x1800, 0x1800, 0x1800);
-                    state.sound_manager.play_sfx(44);
+                    state.sound_manager.play_sfx_at(44, self.x, self.y);
                 }
 
                 self.anim_num += 1;
@@ -453,7 +453,7 @@ impl BossNPC {
                     && self.parts[0].action_counter > 200
                 {
                     self.parts[0].action_counter2 += 1;
-                    state.sound_manager.play_sfx(115);
+                    state.sound_manager.play_sfx_at(115, self.parts[0].x, self.parts[0].y);
 
                     if self.parts[0].life >= 200 {
                         self.parts[0].action_num = if self.parts[0].action_counter2 <= 2 { 210 } else { 220 };
@@ -547,7 +547,7 @@ impl BossNPC {
                         },
                     };
 
-                    state.sound_manager.play_sfx(25);
+                    state.sound_manager.play_sfx_at(25, self.parts[0].x, self.parts[0].y);
 
                     let mut npc = NPC::create(285, &state.npc_table);
                     npc.cond.set_alive(true);
@@ -586,7 +586,7 @@ impl BossNPC {
                     self.parts[10].npc_flags.set_invulnerable(true);
                     self.parts[11].npc_flags.set_shootable(true);
 
-                    state.sound_manager.play_sfx(25);
+                    state.sound_manager.play_sfx_at(25, self.parts[0].x, self.parts[0].y);
 
                     let mut npc = NPC::create(285, &state.npc_table);
                     npc.cond.set_alive(true);
@@ -721,7 +721,7 @@ impl BossNPC {
 
                 self.parts[0].action_counter += 1;
                 if self.parts[0].action_counter % 8 == 0 {
-                    state.sound_manager.play_sfx(44);
+                    state.sound_manager.play_sfx_at(44, self.parts[0].x, self.parts[0].y);
                 }
 
                 npc_list.create_death_smoke(
@@ -737,7 +737,7 @@ impl BossNPC {
                     self.parts[0].action_counter = 0;
                     self.parts[0].action_num = 1001;
 
-                    state.sound_manager.play_sfx(35);
+                    state.sound_manager.play_sfx_at(35, self.parts[0].x, self.parts[0].y);
                     flash.set_cross(self.parts[0].x, self.parts[0].y);
                 }
             }
@@ -762,7 +762,7 @@ impl BossNPC {
             state.quake_counter = 20;
             state.quake_rumble_counter = 20;
 
-            state.sound_manager.play_sfx(26);
+            state.sound_manager.play_sfx_at(26, self.parts[0].x, self.parts[0].y);
 
             if self.parts[0].action_num == 201 {
                 self.parts[7].action_num = 10;
@@ -877,11 +877,11 @@ impl BossNPC {
                 }
 
                 if part.action_counter > 250 && part.action_counter % 16 == 1 {
-                    state.sound_manager.play_sfx(26);
+                    state.sound_manager.play_sfx_at(26, part.x, part.y);
                 }
 
                 if part.action_counter > 250 && part.action_counter % 16 == 7 {
-                    state.sound_manager.play_sfx(101);
+                    state.sound_manager.play_sfx_at(101, part.x, part.y);
 
                     let mut npc = NPC::create(293, &state.npc_table);
                     npc.cond.set_alive(true);
@@ -892,7 +892,7 @@ impl BossNPC {
                 }
 
                 if part.action_counter == 200 {
-                    state.sound_manager.play_sfx(116);
+                    state.sound_manager.play_sfx_at(116, part.x, part.y);
                 }
 
                 part.anim_num = if part.action_counter > 200 && part.action_counter & 1 != 0 { 4 } else { 3 };
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use num_traits::clamp;
+
+use crate::common::{Direction, Rect};
+use crate::framework::context::Context;
+use crate::framework::error::{GameError, GameResult};
+use crate::framework::filesystem;
+use crate::game::npc::{NPCFlag, NPCTable, NPCTableEntry, NPC};
+use crate::game::player::Player;
+use crate::game::shared_game_state::SharedGameState;
+
+/// Spritesheet ids from this one onwards refer to textures named by `npcs.json` files.
+pub const CUSTOM_TEXTURE_BASE: u16 = 64;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomNPCMovement {
+    /// Stays in place, gravity still applies.
+    None,
+    /// Walks in the facing direction and turns around at walls.
+    Walk,
+    /// Walks towards the closest player when it's in range.
+    Chase,
+    /// Flies towards the closest player when it's in range, ignores gravity.
+    Fly,
+    /// Jumps towards the closest player when it's in range, like critters do.
+    Hop,
+}
+
+/// NPC type definition loaded from `npcs.json`.
+///
+/// Table values left out are taken from `base_type` if it's set, otherwise they default to zero.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct CustomNPC {
+    #[serde(default)]
+    pub name: String,
+    /// Existing NPC type whose AI routine is reused, `movement` is ignored if set.
+    #[serde(default)]
+    pub base_type: Option<u16>,
+    pub flags: Option<u16>,
+    pub life: Option<u16>,
+    pub spritesheet_id: Option<u8>,
+    /// Texture to draw the NPC from, takes precedence over `spritesheet_id`.
+    pub texture: Option<String>,
+    pub death_sound: Option<u8>,
+    pub hurt_sound: Option<u8>,
+    pub size: Option<u8>,
+    pub experience: Option<u32>,
+    pub damage: Option<u32>,
+    pub display_bounds: Option<Rect<u8>>,
+    pub hit_bounds: Option<Rect<u8>>,
+    /// Animation frames, indexed by the animation number.
+    #[serde(default)]
+    pub frames: Vec<Rect<u16>>,
+    /// Frames used while facing right, `frames` are used if empty.
+    #[serde(default)]
+    pub frames_right: Vec<Rect<u16>>,
+    /// Ticks between animation frames, 0 disables the animation.
+    #[serde(default)]
+    pub animation_speed: u16,
+    #[serde(default = "default_movement")]
+    pub movement: CustomNPCMovement,
+    #[serde(default = "default_speed")]
+    pub speed: i32,
+    #[serde(default = "default_gravity")]
+    pub gravity: i32,
+    #[serde(default = "default_max_fall_speed")]
+    pub max_fall_speed: i32,
+    #[serde(default = "default_jump_speed")]
+    pub jump_speed: i32,
+    /// Ticks between jumps of the `hop` movement.
+    #[serde(default = "default_jump_interval")]
+    pub jump_interval: u16,
+    /// Distance in pixels at which the NPC notices the player.
+    #[serde(default = "default_range")]
+    pub range: i32,
+    /// NPC types spawned when the NPC is killed.
+    #[serde(default)]
+    pub drops: Vec<u16>,
+    /// Amount of smoke created on death, defaults to the amount for the NPC size.
+    #[serde(default)]
+    pub death_smoke: Option<u8>,
+}
+
+fn default_movement() -> CustomNPCMovement {
+    CustomNPCMovement::None
+}
+
+fn default_speed() -> i32 {
+    0x200
+}
+
+fn default_gravity() -> i32 {
+    0x40
+}
+
+fn default_max_fall_speed() -> i32 {
+    0x5ff
+}
+
+fn default_jump_speed() -> i32 {
+    0x600
+}
+
+fn default_jump_interval() -> u16 {
+    40
+}
+
+fn default_range() -> i32 {
+    160
+}
+
+fn blank_entry() -> NPCTableEntry {
+    NPCTableEntry {
+        npc_flags: NPCFlag(0),
+        life: 0,
+        spritesheet_id: 0,
+        death_sound: 0,
+        hurt_sound: 0,
+        size: 0,
+        experience: 0,
+        damage: 0,
+        display_bounds: Rect::new(0, 0, 0, 0),
+        hit_bounds: Rect::new(0, 0, 0, 0),
+    }
+}
+
+impl NPCTable {
+    /// Registers the NPC types defined in `npcs.json` files in data directories, definitions in mod
+    /// directories replace the ones with the same id. Must be called after the vanilla table is loaded.
+    pub fn load_custom_npcs(&mut self, ctx: &mut Context, base_paths: &[String]) -> GameResult {
+        let vanilla_count = self.entries.len();
+
+        for root in base_paths.iter().rev() {
+            let path = [root, "npcs.json"].join("");
+            if !filesystem::exists(ctx, &path) {
+                continue;
+            }
+
+            let npcs: HashMap<String, CustomNPC> = serde_json::from_reader(filesystem::open(ctx, &path)?)
+                .map_err(|e| GameError::ParseError(format!("{}: {}", path, e)))?;
+
+            for (key, npc) in npcs {
+                let id = match key.parse::<u16>() {
+                    Ok(id) if id as usize >= vanilla_count => id,
+                    _ => {
+                        return Err(GameError::InvalidValue(format!(
+                            "{}: NPC type must be a number of at least {}, found {}",
+                            path, vanilla_count, key
+                        )))
+                    }
+                };
+
+                if let Some(base_type) = npc.base_type {
+                    if base_type as usize >= vanilla_count {
+                        return Err(GameError::InvalidValue(format!(
+                            "{}: NPC type {}: unknown base type {}",
+                            path, id, base_type
+                        )));
+                    }
+                }
+
+                log::info!("Loaded custom NPC type {} ({}) from {}", id, npc.name, path);
+                self.register_custom_npc(&path, id, npc)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn register_custom_npc(&mut self, path: &str, id: u16, npc: CustomNPC) -> GameResult {
+        let mut entry = match npc.base_type.and_then(|t| self.entries.get(t as usize)) {
+            Some(base) => base.clone(),
+            None => blank_entry(),
+        };
+
+        if let Some(flags) = npc.flags {
+            entry.npc_flags = NPCFlag(flags);
+        }
+        entry.life = npc.life.unwrap_or(entry.life);
+        entry.spritesheet_id = npc.spritesheet_id.unwrap_or(entry.spritesheet_id);
+        entry.death_sound = npc.death_sound.unwrap_or(entry.death_sound);
+        entry.hurt_sound = npc.hurt_sound.unwrap_or(entry.hurt_sound);
+        entry.size = npc.size.unwrap_or(entry.size);
+        entry.experience = npc.experience.unwrap_or(entry.experience);
+        entry.damage = npc.damage.unwrap_or(entry.damage);
+        entry.display_bounds = npc.display_bounds.unwrap_or(entry.display_bounds);
+        entry.hit_bounds = npc.hit_bounds.unwrap_or(entry.hit_bounds);
+
+        if let Some(texture) = &npc.texture {
+            let idx = match self.custom_textures.iter().position(|t| t == texture) {
+                Some(idx) => idx,
+                None => {
+                    // spritesheet ids are stored as u8 in the NPC table
+                    if CUSTOM_TEXTURE_BASE as usize + self.custom_textures.len() > u8::MAX as usize {
+                        return Err(GameError::InvalidValue(format!(
+                            "{}: NPC type {}: too many custom textures, at most {} are supported",
+                            path,
+                            id,
+                            u8::MAX as usize + 1 - CUSTOM_TEXTURE_BASE as usize
+                        )));
+                    }
+
+                    self.custom_textures.push(texture.clone());
+                    self.custom_textures.len() - 1
+                }
+            };
+
+            entry.spritesheet_id = (CUSTOM_TEXTURE_BASE as usize + idx) as u8;
+        }
+
+        while self.entries.len() <= id as usize {
+            self.entries.push(blank_entry());
+        }
+
+        self.entries[id as usize] = entry;
+        self.custom_npcs.insert(id, Rc::new(npc));
+
+        Ok(())
+    }
+
+    pub fn get_custom_npc(&self, npc_type: u16) -> Option<Rc<CustomNPC>> {
+        self.custom_npcs.get(&npc_type).cloned()
+    }
+
+    pub(super) fn get_custom_texture(&self, spritesheet_id: u16) -> Option<&str> {
+        spritesheet_id
+            .checked_sub(CUSTOM_TEXTURE_BASE)
+            .and_then(|idx| self.custom_textures.get(idx as usize))
+            .map(String::as_str)
+    }
+}
+
+impl NPC {
+    /// Runs the movement preset of a custom NPC type which doesn't reuse an existing AI routine.
+    pub(crate) fn tick_custom(
+        &mut self,
+        def: &CustomNPC,
+        state: &mut SharedGameState,
+        players: [&mut Player; 2],
+    ) -> GameResult {
+        if self.action_num == 0 {
+            self.action_num = 1;
+            self.action_counter = 0;
+        }
+
+        let player = self.get_closest_player_mut(players);
+        let range = def.range * 0x200;
+        let in_range = (self.x - player.x).abs() < range && (self.y - player.y).abs() < range;
+
+        match def.movement {
+            CustomNPCMovement::None => {}
+            CustomNPCMovement::Walk => {
+                if self.flags.hit_left_wall() {
+                    self.direction = Direction::Right;
+                } else if self.flags.hit_right_wall() {
+                    self.direction = Direction::Left;
+                }
+
+                self.vel_x = self.direction.vector_x() * def.speed;
+            }
+            CustomNPCMovement::Chase => {
+                if in_range {
+                    self.face_player(player);
+                    self.vel_x = self.direction.vector_x() * def.speed;
+                } else {
+                    self.vel_x = 0;
+                }
+            }
+            CustomNPCMovement::Fly => {
+                if in_range {
+                    self.face_player(player);
+                    self.vel_x += if self.x > player.x { -0x20 } else { 0x20 };
+                    self.vel_y += if self.y > player.y { -0x20 } else { 0x20 };
+                }
+
+                self.vel_x = clamp(self.vel_x, -def.speed, def.speed);
+                self.vel_y = clamp(self.vel_y, -def.speed, def.speed);
+            }
+            CustomNPCMovement::Hop => {
+                self.action_counter = self.action_counter.saturating_add(1);
+
+                if self.flags.hit_bottom_wall() {
+                    self.vel_x = 0;
+
+                    if in_range && self.action_counter > def.jump_interval {
+                        self.action_counter = 0;
+                        self.face_player(player);
+                        self.vel_x = self.direction.vector_x() * def.speed;
+                        self.vel_y = -def.jump_speed;
+                        state.sound_manager.play_sfx_at(30, self.x, self.y);
+                    }
+                }
+            }
+        }
+
+        if def.movement != CustomNPCMovement::Fly {
+            self.vel_y = (self.vel_y + def.gravity).min(def.max_fall_speed);
+        }
+
+        self.x += self.vel_x;
+        self.y += self.vel_y;
+
+        if def.animation_speed > 0 && !def.frames.is_empty() {
+            self.animate(def.animation_speed, 0, def.frames.len() as u16 - 1);
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the sprite set by the AI routine with the frames of a custom NPC type.
+    pub(crate) fn apply_custom_frames(&mut self, def: &CustomNPC) {
+        let frames = if self.direction == Direction::Right && !def.frames_right.is_empty() {
+            &def.frames_right
+        } else {
+            &def.frames
+        };
+
+        if !frames.is_empty() {
+            self.anim_rect = frames[self.anim_num as usize % frames.len()];
+        }
+    }
+}
+
+#[test]
+fn test_custom_npc_defaults() {
+    let npc: CustomNPC = serde_json::from_str(r#"{"base_type": 5, "life": 10, "frames": [[0, 0, 16, 16]]}"#).unwrap();
+
+    assert_eq!(npc.base_type, Some(5));
+    assert_eq!(npc.life, Some(10));
+    assert!(npc.damage.is_none());
+    assert_eq!(npc.movement, CustomNPCMovement::None);
+    assert_eq!(npc.frames.len(), 1);
+    assert_eq!(npc.frames[0].right, 16);
+}
+
+#[test]
+fn test_custom_texture_limit() {
+    let mut table = NPCTable::new();
+    let slots = u8::MAX as usize + 1 - CUSTOM_TEXTURE_BASE as usize;
+
+    for i in 0..=slots {
+        let json = format!(r#"{{"texture": "Npc/Custom{}"}}"#, i);
+        let npc: CustomNPC = serde_json::from_str(&json).unwrap();
+        let result = table.register_custom_npc("npcs.json", 400 + i as u16, npc);
+
+        assert_eq!(result.is_ok(), i < slots);
+    }
+
+    assert_eq!(table.get_custom_texture(u8::MAX as u16), Some("Npc/Custom191"));
+}
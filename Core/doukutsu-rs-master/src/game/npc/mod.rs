@@ -1,4 +1,5 @@
 use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
 use std::io;
 use std::io::Cursor;
 use std::ops::Deref;
@@ -17,6 +18,7 @@ use crate::framework::context::Context;
 use crate::framework::error::GameResult;
 use crate::game::frame::Frame;
 use crate::game::npc::boss::BossNPC;
+use crate::game::npc::custom::CustomNPC;
 use crate::game::npc::list::NPCList;
 use crate::game::physics::PhysicalEntity;
 use crate::game::player::Player;
@@ -27,6 +29,7 @@ use crate::util::rng::Xoroshiro32PlusPlus;
 
 pub mod ai;
 pub mod boss;
+pub mod custom;
 pub mod list;
 pub mod utils;
 
@@ -219,6 +222,39 @@ impl NPC {
         Ok(())
     }
 
+    /// Draws the normal map layer of the NPC's spritesheet, returns false if it doesn't have one.
+    pub fn draw_normal_map(&self, state: &mut SharedGameState, ctx: &mut Context, frame: &Frame) -> GameResult<bool> {
+        if !self.cond.alive() || self.cond.hidden() {
+            return Ok(false);
+        }
+
+        let texture = &*state.npc_table.get_texture_ref(self.spritesheet_id);
+
+        if let Some(batch) = state.texture_set.get_or_load_batch(ctx, &state.constants, texture)?.normal() {
+            let off_x =
+                if self.direction == Direction::Left { self.display_bounds.left } else { self.display_bounds.right }
+                    as i32;
+            let shock = if self.shock > 0 { (2 * ((self.shock as i32 / 2) % 2) - 1) as f32 } else { 0.0 };
+
+            let (frame_x, frame_y) = frame.xy_interpolated(state.frame_time);
+
+            batch.add_rect(
+                interpolate_fix9_scale(self.prev_x - off_x, self.x - off_x, state.frame_time) + shock - frame_x,
+                interpolate_fix9_scale(
+                    self.prev_y - self.display_bounds.top as i32,
+                    self.y - self.display_bounds.top as i32,
+                    state.frame_time,
+                ) - frame_y,
+                &self.anim_rect,
+            );
+
+            batch.draw(ctx)?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
     fn is_sue(&self) -> bool {
         [42, 92, 280, 284].contains(&self.npc_type)
     }
@@ -249,7 +285,10 @@ impl GameEntity<([&mut Player; 2], &NPCList, &mut Stage, &mut BulletManager, &mu
                 npc_hook_ran = state.lua.try_run_npc_hook(self.id, self.npc_type);
             }
 
-        match self.npc_type {
+        let custom = state.npc_table.get_custom_npc(self.npc_type);
+        let npc_type = custom.as_ref().and_then(|def| def.base_type).unwrap_or(self.npc_type);
+
+        match npc_type {
             _ if npc_hook_ran => Ok(()),
             0 => self.tick_n000_null(),
             1 => self.tick_n001_experience(state, stage),
@@ -622,9 +661,16 @@ impl GameEntity<([&mut Player; 2], &NPCList, &mut Stage, &mut BulletManager, &mu
             368 => self.tick_n368_gclone(state, players, npc_list),
             369 => self.tick_n369_gclone_curly_clone(state, players, npc_list),
             370 => self.tick_n370_second_quote(state, players, npc_list),
-            _ => Ok(()),
+            _ => match &custom {
+                Some(def) => self.tick_custom(def, state, players),
+                None => Ok(()),
+            },
         }?;
 
+        if let Some(def) = custom.as_ref().filter(|_| !npc_hook_ran) {
+            self.apply_custom_frames(def);
+        }
+
         // I don't know where the best place to put this is, but let's try putting it here
         if self.shock == 0 && self.npc_flags.show_damage() && self.popup.value != 0 {
             self.popup.update_displayed_value();
@@ -799,6 +845,7 @@ impl PhysicalEntity for NPC {
     }
 }
 
+#[derive(Clone)]
 pub struct NPCTableEntry {
     pub npc_flags: NPCFlag,
     pub life: u16,
@@ -814,13 +861,20 @@ pub struct NPCTableEntry {
 
 pub struct NPCTable {
     entries: Vec<NPCTableEntry>,
+    custom_npcs: HashMap<u16, Rc<CustomNPC>>,
+    custom_textures: Vec<String>,
     pub stage_textures: Rc<RefCell<StageTexturePaths>>,
 }
 
 impl NPCTable {
     #[allow(clippy::new_without_default)]
     pub fn new() -> NPCTable {
-        NPCTable { entries: Vec::new(), stage_textures: Rc::new(RefCell::new(StageTexturePaths::new())) }
+        NPCTable {
+            entries: Vec::new(),
+            custom_npcs: HashMap::new(),
+            custom_textures: Vec::new(),
+            stage_textures: Rc::new(RefCell::new(StageTexturePaths::new())),
+        }
     }
 
     pub fn load_from<R: io::Read>(mut data: R) -> GameResult<NPCTable> {
@@ -945,7 +999,10 @@ impl NPCTable {
             23 => TexRef::from_str("Npc/NpcRegu"),
             26 => TexRef::from_str("TextBox"),
             27 => TexRef::from_str("Face"),
-            _ => TexRef::from_str("Npc/Npc0"),
+            _ => match self.get_custom_texture(spritesheet_id) {
+                Some(name) => TexRef { variant: TexRefVariant::Custom(name) },
+                None => TexRef::from_str("Npc/Npc0"),
+            },
         }
     }
 }
@@ -956,6 +1013,7 @@ pub struct TexRef<'a> {
 
 enum TexRefVariant<'a> {
     Str(&'static str),
+    Custom(&'a str),
     StageTileset(Ref<'a, StageTexturePaths>),
     StageNPC1(Ref<'a, StageTexturePaths>),
     StageNPC2(Ref<'a, StageTexturePaths>),
@@ -975,6 +1033,7 @@ impl Deref for TexRef<'_> {
     fn deref(&self) -> &Self::Target {
         match &self.variant {
             TexRefVariant::Str(str) => str,
+            TexRefVariant::Custom(str) => str,
             TexRefVariant::StageTileset(paths) => &paths.tileset_fg,
             TexRefVariant::StageNPC1(paths) => &paths.npc1,
             TexRefVariant::StageNPC2(paths) => &paths.npc2,
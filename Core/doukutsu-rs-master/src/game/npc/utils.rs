@@ -261,7 +261,7 @@ impl NPCList {
 
             if smoke {
                 if let Some(table_entry) = state.npc_table.get_entry(npc.npc_type) {
-                    state.sound_manager.play_sfx(table_entry.death_sound);
+                    state.sound_manager.play_sfx_at(table_entry.death_sound, npc.x, npc.y);
                 }
 
                 match npc.size {
@@ -284,21 +284,34 @@ impl NPCList {
     pub fn kill_npc(&self, id: usize, vanish: bool, can_drop_missile: bool, state: &mut SharedGameState) {
         if let Some(npc) = self.get_npc(id) {
             if let Some(table_entry) = state.npc_table.get_entry(npc.npc_type) {
-                state.sound_manager.play_sfx(table_entry.death_sound);
+                state.sound_manager.play_sfx_at(table_entry.death_sound, npc.x, npc.y);
             }
 
-            match npc.size {
-                1 => {
-                    self.create_death_smoke(npc.x, npc.y, npc.display_bounds.right as usize, 3, state, &npc.rng);
-                }
-                2 => {
-                    self.create_death_smoke(npc.x, npc.y, npc.display_bounds.right as usize, 7, state, &npc.rng);
-                }
-                3 => {
-                    self.create_death_smoke(npc.x, npc.y, npc.display_bounds.right as usize, 12, state, &npc.rng);
-                }
-                _ => {}
-            };
+            let custom = state.npc_table.get_custom_npc(npc.npc_type);
+
+            if let Some(amount) = custom.as_ref().and_then(|def| def.death_smoke) {
+                self.create_death_smoke(
+                    npc.x,
+                    npc.y,
+                    npc.display_bounds.right as usize,
+                    amount as usize,
+                    state,
+                    &npc.rng,
+                );
+            } else {
+                match npc.size {
+                    1 => {
+                        self.create_death_smoke(npc.x, npc.y, npc.display_bounds.right as usize, 3, state, &npc.rng);
+                    }
+                    2 => {
+                        self.create_death_smoke(npc.x, npc.y, npc.display_bounds.right as usize, 7, state, &npc.rng);
+                    }
+                    3 => {
+                        self.create_death_smoke(npc.x, npc.y, npc.display_bounds.right as usize, 12, state, &npc.rng);
+                    }
+                    _ => {}
+                };
+            }
 
             if npc.exp != 0 {
                 let rng = npc.rng.range(0..4);
@@ -329,6 +342,18 @@ impl NPCList {
                 }
             }
 
+            if let Some(def) = &custom {
+                for &drop_type in def.drops.iter() {
+                    let mut drop = NPC::create(drop_type, &state.npc_table);
+                    drop.cond.set_alive(true);
+                    drop.direction = Direction::Left;
+                    drop.x = npc.x;
+                    drop.y = npc.y;
+
+                    let _ = self.spawn(0x100, drop);
+                }
+            }
+
             state.set_flag(npc.flag_num as usize, true);
 
             if npc.npc_flags.show_damage() {
@@ -15,6 +15,7 @@ use crate::game::npc::NPC;
 use crate::game::player::skin::basic::BasicPlayerSkin;
 use crate::game::player::skin::{PlayerAnimationState, PlayerAppearanceState, PlayerSkin};
 use crate::game::shared_game_state::SharedGameState;
+use crate::game::weapon::custom::weapon_sprite_origin;
 use crate::input::dummy_player_controller::DummyPlayerController;
 use crate::input::player_controller::PlayerController;
 use crate::util::rng::RNG;
@@ -22,7 +23,7 @@ use crate::util::rng::RNG;
 mod player_hit;
 pub mod skin;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, serde::Serialize, serde::Deserialize)]
 #[repr(u8)]
 pub enum ControlMode {
     Normal = 0,
@@ -825,8 +826,9 @@ impl Player {
         }
 
         self.weapon_offset_y = 0;
-        self.weapon_rect.left = (self.current_weapon as u16 % 13) * 24;
-        self.weapon_rect.top = (self.current_weapon as u16 / 13) * 96;
+        let (weapon_x, weapon_y) = weapon_sprite_origin(&state.constants, self.current_weapon);
+        self.weapon_rect.left = weapon_x;
+        self.weapon_rect.top = weapon_y;
         self.weapon_rect.right = self.weapon_rect.left + 24;
         self.weapon_rect.bottom = self.weapon_rect.top + 16;
 
@@ -896,6 +898,9 @@ impl Player {
         self.damage_popup.add_value(-(self.damage as i16));
         self.damage_popup.update_displayed_value();
 
+        #[cfg(feature = "scripting-lua")]
+        state.lua.call_event("playerDamage", &[final_hp, self.life as i32]);
+
         if self.life == 0 {
             state.sound_manager.play_sfx(17);
             self.cond.0 = 0;
@@ -925,6 +930,36 @@ impl Player {
             self.teleport_counter = 0;
         }
     }
+
+    /// Draws the normal map layer of the skin, returns false if it doesn't have one.
+    pub fn draw_normal_map(&self, state: &mut SharedGameState, ctx: &mut Context, frame: &Frame) -> GameResult<bool> {
+        if !self.cond.alive() || self.cond.hidden() || self.shock_counter / 2 % 2 != 0 {
+            return Ok(false);
+        }
+
+        let (frame_x, frame_y) = frame.xy_interpolated(state.frame_time);
+        let batch = state.texture_set.get_or_load_batch(ctx, &state.constants, self.skin.get_skin_texture_name())?;
+
+        if let Some(batch) = batch.normal() {
+            batch.add_rect(
+                interpolate_fix9_scale(
+                    self.prev_x - self.display_bounds.left as i32,
+                    self.x - self.display_bounds.left as i32,
+                    state.frame_time,
+                ) - frame_x,
+                interpolate_fix9_scale(
+                    self.prev_y - self.display_bounds.top as i32,
+                    self.y - self.display_bounds.top as i32,
+                    state.frame_time,
+                ) - frame_y,
+                &self.anim_rect,
+            );
+            batch.draw(ctx)?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
 }
 
 impl GameEntity<&NPCList> for Player {
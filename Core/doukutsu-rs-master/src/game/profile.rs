@@ -1,7 +1,7 @@
 use std::io;
 
 use byteorder::{BE, LE, ReadBytesExt, WriteBytesExt};
-use num_traits::{clamp, FromPrimitive};
+use num_traits::clamp;
 
 use crate::common::{Direction, FadeState, get_timestamp};
 use crate::framework::context::Context;
@@ -64,7 +64,7 @@ impl GameProfile {
             }
 
             let _ = state.mod_requirements.append_weapon(ctx, weapon.weapon_id as u16);
-            let weapon_type: Option<WeaponType> = FromPrimitive::from_u8(weapon.weapon_id as u8);
+            let weapon_type = WeaponType::from_id(weapon.weapon_id as u16, &state.constants);
 
             if let Some(wtype) = weapon_type {
                 game_scene.inventory_player1.add_weapon_data(
@@ -78,6 +78,8 @@ impl GameProfile {
                         _ => WeaponLevel::Level1,
                     },
                 );
+            } else {
+                log::warn!("Skipping unknown weapon {} in save.", weapon.weapon_id);
             }
         }
 
@@ -205,7 +207,7 @@ impl GameProfile {
 
         for (idx, weap) in weapon_data.iter_mut().enumerate() {
             if let Some(weapon) = inventory_player.get_weapon(idx) {
-                weap.weapon_id = weapon.wtype as u32;
+                weap.weapon_id = weapon.wtype.id() as u32;
                 weap.level = weapon.level as u32;
                 weap.exp = weapon.experience as u32;
                 weap.max_ammo = weapon.max_ammo as u32;
@@ -78,6 +78,13 @@ end
 
 __doukutsu_rs_runtime_dont_touch._registered = {
     tick = {},
+    stageLoad = {},
+    playerDamage = {},
+    itemGet = {},
+    bossDefeat = {},
+    eventStart = {},
+    bulletHit = {},
+    drawHUD = {},
 }
 
 __doukutsu_rs_runtime_dont_touch._handlers = setmetatable({
@@ -96,6 +103,34 @@ __doukutsu_rs_runtime_dont_touch._handlers = setmetatable({
     end,
 })
 
+-- converts raw arguments passed by the engine into the ones received by handlers
+__doukutsu_rs_runtime_dont_touch._eventArgs = {
+    bulletHit = function(npc_id, bullet_type, damage)
+        return __doukutsu_rs_runtime_dont_touch._getNPCRef(npc_id), bullet_type, damage
+    end,
+}
+
+for event, handlers in pairs(__doukutsu_rs_runtime_dont_touch._registered) do
+    if rawget(__doukutsu_rs_runtime_dont_touch._handlers, event) == nil then
+        local convert = __doukutsu_rs_runtime_dont_touch._eventArgs[event]
+
+        __doukutsu_rs_runtime_dont_touch._handlers[event] = function(...)
+            local args = { ... }
+            if convert ~= nil then
+                args = { convert(...) }
+            end
+
+            for _, h in pairs(handlers) do
+                local status, err = pcall(h, table.unpack(args))
+
+                if not status then
+                    print("error in " .. event .. " handler: " .. err)
+                end
+            end
+        end
+    end
+end
+
 __doukutsu_rs_runtime_dont_touch._registeredNPCHooks = {}
 
 __doukutsu_rs_runtime_dont_touch._tryNPCHook = function(npc_id, npc_type)
@@ -116,6 +151,51 @@ __doukutsu_rs_runtime_dont_touch._tryNPCHook = function(npc_id, npc_type)
     return false
 end
 
+__doukutsu_rs_runtime_dont_touch._registeredWeaponHooks = {}
+
+__doukutsu_rs_runtime_dont_touch._tryWeaponHook = function(weapon_id, player_id, level)
+    local hook = __doukutsu_rs_runtime_dont_touch._registeredWeaponHooks[weapon_id]
+    if hook == nil then
+        return false
+    end
+
+    local player = player_id == 1 and __doukutsu_rs_runtime_dont_touch._playerRef1 or __doukutsu_rs_runtime_dont_touch._playerRef0
+    local status, err = pcall(hook, player, level)
+
+    if not status then
+        print("error in weapon handler:" .. err)
+    end
+
+    return true
+end
+
+__doukutsu_rs_runtime_dont_touch._registeredTSCOpcodes = {}
+
+__doukutsu_rs_runtime_dont_touch._tryTSCOpcode = function(name, ...)
+    local handler = __doukutsu_rs_runtime_dont_touch._registeredTSCOpcodes[name]
+    if handler == nil then
+        return false, -1
+    end
+
+    local status, result = pcall(handler, ...)
+    if not status then
+        print("error in <" .. name .. " handler: " .. result)
+    elseif type(result) == "number" then
+        return true, result
+    end
+
+    return true, -1
+end
+
+__doukutsu_rs_runtime_dont_touch._listTSCOpcodes = function()
+    local names = {}
+    for name, _ in pairs(__doukutsu_rs_runtime_dont_touch._registeredTSCOpcodes) do
+        table.insert(names, name)
+    end
+
+    return table.concat(names, "\n")
+end
+
 __doukutsu_rs_runtime_dont_touch._initializeScript = function(script)
     -- for compatibility with Lua 5.2+, copy-pasted from Lua mailing list
     -- http://lua-users.org/lists/lua-l/2010-06/msg00313.html
@@ -152,6 +232,68 @@ __doukutsu_rs_runtime_dont_touch._createPlayerRef = function(player_id)
         __doukutsu_rs:playerCommand(rawget(self, "id"), 0x200, value)
     end
 
+    function player_ref.hasItem(self, item_id)
+        return __doukutsu_rs:playerCommand(rawget(self, "id"), 0x300, item_id)
+    end
+
+    function player_ref.getItemAmount(self, item_id)
+        return __doukutsu_rs:playerCommand(rawget(self, "id"), 0x301, item_id)
+    end
+
+    function player_ref.items(self)
+        local items = {}
+        local idx = 0
+
+        while true do
+            local item_id = __doukutsu_rs:playerCommand(rawget(self, "id"), 0x302, idx)
+            if item_id == nil then
+                break
+            end
+
+            table.insert(items, item_id)
+            idx = idx + 1
+        end
+
+        return items
+    end
+
+    function player_ref.addItem(self, item_id, amount)
+        __doukutsu_rs:playerCommand(rawget(self, "id"), 0x400, item_id, amount or 1)
+    end
+
+    function player_ref.removeItem(self, item_id)
+        __doukutsu_rs:playerCommand(rawget(self, "id"), 0x401, item_id)
+    end
+
+    function player_ref.hasWeapon(self, weapon_id)
+        return __doukutsu_rs:playerCommand(rawget(self, "id"), 0x304, weapon_id)
+    end
+
+    function player_ref.weapons(self)
+        local weapons = {}
+        local idx = 0
+
+        while true do
+            local weapon_id = __doukutsu_rs:playerCommand(rawget(self, "id"), 0x303, idx)
+            if weapon_id == nil then
+                break
+            end
+
+            table.insert(weapons, weapon_id)
+            idx = idx + 1
+        end
+
+        return weapons
+    end
+
+    function player_ref.addWeapon(self, weapon_id, ammo)
+        __doukutsu_rs:playerCommand(rawget(self, "id"), 0x402, weapon_id, ammo or 0)
+    end
+
+    function player_ref.removeWeapon(self, weapon_id)
+        __doukutsu_rs:playerCommand(rawget(self, "id"), 0x403, weapon_id)
+    end
+
     setmetatable(player_ref, {
         __index = function(self, property)
             if property == "x" then
@@ -407,12 +549,49 @@ function doukutsu.setSetting(key, value)
     end
 end
 
+function doukutsu.spawnNPC(npc_type, x, y, direction)
+    local id = __doukutsu_rs:stageCommand(0x200, npc_type, x, y, direction or 0)
+    if id == nil then
+        return nil
+    end
+
+    return __doukutsu_rs_runtime_dont_touch._getNPCRef(id)
+end
+
+function doukutsu.spawnBullet(bullet_type, x, y, direction, owner)
+    __doukutsu_rs:stageCommand(0x201, bullet_type, x, y, direction or 0, owner or 0)
+end
+
+function doukutsu.drawText(text, x, y, r, g, b, a)
+    __doukutsu_rs:drawText(tostring(text), x, y, r, g, b, a)
+end
+
+function doukutsu.drawSprite(texture, x, y, l, t, r, b)
+    if type(l) == "table" then
+        __doukutsu_rs:drawSprite(texture, x, y, l[1], l[2], l[3], l[4])
+    else
+        __doukutsu_rs:drawSprite(texture, x, y, l, t, r, b)
+    end
+end
+
+function doukutsu.setTSCOpcode(name, handler)
+    assert(type(name) == "string" and #name == 3, "opcode name must be a 3 character string.")
+
+    __doukutsu_rs_runtime_dont_touch._registeredTSCOpcodes[name] = handler
+end
+
 function doukutsu.setNPCHandler(npc_type, handler)
     assert(type(npc_type) == "number", "npc type must be an integer.")
 
     __doukutsu_rs_runtime_dont_touch._registeredNPCHooks[npc_type] = handler
 end
 
+function doukutsu.setWeaponHandler(weapon_id, handler)
+    assert(type(weapon_id) == "number", "weapon id must be an integer.")
+
+    __doukutsu_rs_runtime_dont_touch._registeredWeaponHooks[weapon_id] = handler
+end
+
 function doukutsu.on(event, handler)
     assert(type(event) == "string", "event type must be a string.")
     assert(type(handler) == "function", "event handler must be a function.")
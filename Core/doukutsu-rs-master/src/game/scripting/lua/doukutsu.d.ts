@@ -182,6 +182,60 @@ declare interface DoukutsuPlayer {
      * @param value number of health points to subtract.
      */
     damage(value: number): void;
+
+    /**
+     * Returns true if player has the specified item in inventory.
+     * @param itemId the item number
+     */
+    hasItem(itemId: number): boolean;
+
+    /**
+     * Returns the amount of specified item in inventory, 0 if player doesn't have it.
+     * @param itemId the item number
+     */
+    getItemAmount(itemId: number): number;
+
+    /**
+     * Returns a list of item numbers in inventory order.
+     */
+    items(): number[];
+
+    /**
+     * Adds an item to inventory, without playing a sound or showing a message.
+     * @param itemId the item number
+     * @param amount amount of the item to add, defaults to 1.
+     */
+    addItem(itemId: number, amount?: number): void;
+
+    /**
+     * Removes all instances of an item from inventory.
+     * @param itemId the item number
+     */
+    removeItem(itemId: number): void;
+
+    /**
+     * Returns true if player has the specified weapon.
+     * @param weaponId the weapon number
+     */
+    hasWeapon(weaponId: number): boolean;
+
+    /**
+     * Returns a list of weapon numbers in inventory order.
+     */
+    weapons(): number[];
+
+    /**
+     * Adds a weapon to inventory, or adds ammo to it if player already has it.
+     * @param weaponId the weapon number
+     * @param ammo maximum ammo, 0 means unlimited.
+     */
+    addWeapon(weaponId: number, ammo?: number): void;
+
+    /**
+     * Removes a weapon from inventory.
+     * @param weaponId the weapon number
+     */
+    removeWeapon(weaponId: number): void;
 }
 
 declare interface DoukutsuRSApi {
@@ -291,6 +345,77 @@ declare namespace doukutsu {
      */
     function setNPCHandler(npcType: number, handler: (this: void, npc: NPC) => void | null): void;
 
+    /**
+     * Sets the handler firing a weapon defined in weapons.json with "fire_pattern": "lua". The engine takes care of
+     * the fire rate, bullet limit, ammo and sound, the handler only spawns the bullets, eg. with spawnBullet.
+     * Passing a null removes the handler.
+     * @param weaponId
+     * @param handler receives the firing player and the weapon level (1-3).
+     */
+    function setWeaponHandler(weaponId: number, handler: (this: void, player: DoukutsuPlayer, level: number) => void | null): void;
+
+    /**
+     * Spawns a NPC and returns a reference to it, or null if the NPC list is full.
+     * @param npcType the NPC type ID
+     * @param x position in X axis (as floating point, not internal fixed point representation)
+     * @param y position in Y axis (as floating point, not internal fixed point representation)
+     * @param direction direction, same as in <SNP, defaults to 0 (left).
+     */
+    function spawnNPC(npcType: number, x: number, y: number, direction?: number): NPC | null;
+
+    /**
+     * Spawns a bullet.
+     * @param bulletType the bullet type ID, an index in bullet table
+     * @param x position in X axis (as floating point, not internal fixed point representation)
+     * @param y position in Y axis (as floating point, not internal fixed point representation)
+     * @param direction 0 - left, 1 - up, 2 - right, 3 - bottom.
+     * @param owner ID of player the bullet belongs to, defaults to 0.
+     */
+    function spawnBullet(bulletType: number, x: number, y: number, direction?: number, owner?: number): void;
+
+    /**
+     * Draws text on screen. Only has effect inside drawHUD event handlers.
+     * @param text the text
+     * @param x position in X axis, in screen pixels
+     * @param y position in Y axis, in screen pixels
+     * @param r red color component (0-255), text is white if omitted.
+     * @param g green color component (0-255)
+     * @param b blue color component (0-255)
+     * @param a alpha (0-255), defaults to 255.
+     */
+    function drawText(text: string, x: number, y: number, r?: number, g?: number, b?: number, a?: number): void;
+
+    /**
+     * Draws a part of a texture on screen. Only has effect inside drawHUD event handlers.
+     * @param texture texture name, eg. "TextBox" or "Npc/NpcSym"
+     * @param x position in X axis, in screen pixels
+     * @param y position in Y axis, in screen pixels
+     * @param rect [left, top, right, bottom] source rectangle.
+     */
+    function drawSprite(texture: string, x: number, y: number, rect: [number, number, number, number]): void;
+
+    /**
+     * Draws a part of a texture on screen. Only has effect inside drawHUD event handlers.
+     */
+    function drawSprite(
+        texture: string,
+        x: number,
+        y: number,
+        left: number,
+        top: number,
+        right: number,
+        bottom: number,
+    ): void;
+
+    /**
+     * Implements a TSC opcode not known to the engine, eg. <XYZ0001:0002. Operands are 4 digit numbers separated
+     * by colons and are passed to the handler. If the handler returns a number, the script jumps to that event.
+     * Passing a null removes the handler.
+     * @param name the 3 character opcode name, without the leading '<'.
+     * @param handler
+     */
+    function setTSCOpcode(name: string, handler: (this: void, ...operands: number[]) => number | void | null): void;
+
     /**
      * Registers an event handler called after all scripts are loaded.
      * @param event event name
@@ -305,5 +430,61 @@ declare namespace doukutsu {
      */
     function on(event: "tick", handler: EventHandler<DoukutsuStage>): EventHandler<DoukutsuStage>;
 
+    /**
+     * Registers an event handler called after a stage has been loaded, receives the stage number.
+     * @param event event name
+     * @param handler event handler procedure
+     */
+    function on(event: "stageLoad", handler: EventHandler<number>): EventHandler<number>;
+
+    /**
+     * Registers an event handler called after the player has been damaged.
+     * @param event event name
+     * @param handler receives the damage dealt and remaining health
+     */
+    function on(
+        event: "playerDamage",
+        handler: (this: void, damage: number, life: number) => void,
+    ): (this: void, damage: number, life: number) => void;
+
+    /**
+     * Registers an event handler called when an item is given by <IT+ or <I+N, receives the item number.
+     * @param event event name
+     * @param handler event handler procedure
+     */
+    function on(event: "itemGet", handler: EventHandler<number>): EventHandler<number>;
+
+    /**
+     * Registers an event handler called when the main part of a boss is destroyed, receives the boss type.
+     * @param event event name
+     * @param handler event handler procedure
+     */
+    function on(event: "bossDefeat", handler: EventHandler<number>): EventHandler<number>;
+
+    /**
+     * Registers an event handler called when a TSC event starts running, receives the event number.
+     * @param event event name
+     * @param handler event handler procedure
+     */
+    function on(event: "eventStart", handler: EventHandler<number>): EventHandler<number>;
+
+    /**
+     * Registers an event handler called when a bullet hits a shootable NPC.
+     * @param event event name
+     * @param handler receives the NPC, bullet type and damage dealt
+     */
+    function on(
+        event: "bulletHit",
+        handler: (this: void, npc: NPC, bulletType: number, damage: number) => void,
+    ): (this: void, npc: NPC, bulletType: number, damage: number) => void;
+
+    /**
+     * Registers an event handler called on each tick, used to draw custom HUD elements
+     * with drawText and drawSprite, which are displayed until the next tick.
+     * @param event event name
+     * @param handler event handler procedure
+     */
+    function on(event: "drawHUD", handler: EventHandler<void>): EventHandler<void>;
+
     function on<T>(event: string, handler: EventHandler<T>): EventHandler<T>;
 }
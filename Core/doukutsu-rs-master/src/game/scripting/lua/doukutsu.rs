@@ -7,8 +7,12 @@ use lua_ffi::lua_method;
 
 use crate::common::{Direction, Rect};
 use crate::framework::filesystem;
+use crate::game::npc::NPC;
+use crate::game::player::TargetPlayer;
 use crate::game::scripting::lua::{check_status, DRS_RUNTIME_GLOBAL, LuaScriptingState};
+use crate::game::scripting::lua::hud::LuaHudCommand;
 use crate::game::scripting::tsc::text_script::TextScriptEncoding;
+use crate::game::weapon::WeaponType;
 use crate::scene::game_scene::LightingMode;
 use crate::util::rng::RNG;
 
@@ -401,11 +405,12 @@ impl Doukutsu {
         }
 
         if let (Some(player_id), Some(param_type)) = (state.to_int(2), state.to_int(3)) {
+            let game_state = &mut (*(*self.ptr).state_ptr);
             let game_scene = &mut *(*self.ptr).game_scene;
 
-            let player = match player_id {
-                0 => &mut game_scene.player1,
-                1 => &mut game_scene.player2,
+            let (player, inventory) = match player_id {
+                0 => (&mut game_scene.player1, &mut game_scene.inventory_player1),
+                1 => (&mut game_scene.player2, &mut game_scene.inventory_player2),
                 _ => {
                     state.push_nil();
                     return 1;
@@ -450,6 +455,81 @@ impl Doukutsu {
 
                     state.push_nil();
                 }
+                0x300 => {
+                    // has item
+                    if let Some(item_id) = state.to_int(4) {
+                        state.push(inventory.has_item(item_id as u16));
+                    } else {
+                        state.push_nil();
+                    }
+                }
+                0x301 => {
+                    // item amount
+                    if let Some(item_id) = state.to_int(4) {
+                        state.push(inventory.get_item(item_id as u16).map(|item| item.1 as i32).unwrap_or(0));
+                    } else {
+                        state.push_nil();
+                    }
+                }
+                0x302 => {
+                    // item id at inventory slot
+                    match state.to_int(4).and_then(|idx| inventory.get_item_idx(idx.max(0) as usize)) {
+                        Some(item) => state.push(item.0 as i32),
+                        None => state.push_nil(),
+                    }
+                }
+                0x303 => {
+                    // weapon id at inventory slot
+                    match state.to_int(4).and_then(|idx| inventory.get_weapon(idx.max(0) as usize)) {
+                        Some(weapon) => state.push(weapon.wtype.id() as i32),
+                        None => state.push_nil(),
+                    }
+                }
+                0x304 => {
+                    // has weapon
+                    match state.to_int(4).and_then(|id| WeaponType::from_id(id as u16, &game_state.constants)) {
+                        Some(wtype) => state.push(inventory.has_weapon(wtype)),
+                        None => state.push(false),
+                    }
+                }
+                0x400 => {
+                    // add item
+                    if let Some(item_id) = state.to_int(4) {
+                        let amount = state.to_int(5).unwrap_or(1).max(1);
+                        inventory.add_item_amount(item_id as u16, amount as u16);
+                    }
+
+                    state.push_nil();
+                }
+                0x401 => {
+                    // remove item
+                    if let Some(item_id) = state.to_int(4) {
+                        inventory.remove_item(item_id as u16);
+                    }
+
+                    state.push_nil();
+                }
+                0x402 => {
+                    // add weapon
+                    let wtype = state.to_int(4).and_then(|id| WeaponType::from_id(id as u16, &game_state.constants));
+                    if let Some(wtype) = wtype {
+                        let ammo = state.to_int(5).unwrap_or(0).max(0);
+                        inventory.add_weapon(wtype, ammo as u16);
+                    } else {
+                        state.error("Invalid weapon id.");
+                    }
+
+                    state.push_nil();
+                }
+                0x403 => {
+                    // remove weapon
+                    let wtype = state.to_int(4).and_then(|id| WeaponType::from_id(id as u16, &game_state.constants));
+                    if let Some(wtype) = wtype {
+                        inventory.remove_weapon(wtype);
+                    }
+
+                    state.push_nil();
+                }
                 _ => state.push_nil(),
             }
         } else {
@@ -489,6 +569,58 @@ impl Doukutsu {
 
                     state.push_nil();
                 }
+                0x200 => {
+                    // spawn npc
+                    let args = (state.to_int(3), state.to_float(4), state.to_float(5));
+                    if let (Some(npc_type), Some(x), Some(y)) = args {
+                        let tsc_direction = state.to_int(6).unwrap_or(0).max(0) as u16;
+                        let free_slot = game_scene
+                            .npc_list
+                            .iter()
+                            .enumerate()
+                            .skip(0x100)
+                            .find(|(_, npc)| !npc.cond.alive())
+                            .map(|(id, _)| id as u16);
+
+                        let mut npc = NPC::create(npc_type as u16, &game_state.npc_table);
+                        npc.cond.set_alive(true);
+                        npc.x = (x * 512.0) as i32;
+                        npc.y = (y * 512.0) as i32;
+                        npc.tsc_direction = tsc_direction;
+                        npc.direction = Direction::from_int_facing(tsc_direction as usize).unwrap_or(Direction::Left);
+
+                        match free_slot.map(|id| (id, game_scene.npc_list.spawn_at_slot(id, npc))) {
+                            Some((id, Ok(()))) => state.push(id as i32),
+                            _ => state.push_nil(),
+                        }
+                    } else {
+                        state.error("Invalid parameters supplied.");
+                        state.push_nil();
+                    }
+                }
+                0x201 => {
+                    // spawn bullet
+                    let args = (state.to_int(3), state.to_float(4), state.to_float(5));
+                    if let (Some(btype), Some(x), Some(y)) = args {
+                        let direction = Direction::from_int(state.to_int(6).unwrap_or(0).max(0) as usize)
+                            .unwrap_or(Direction::Left);
+                        let owner =
+                            if state.to_int(7) == Some(1) { TargetPlayer::Player2 } else { TargetPlayer::Player1 };
+
+                        game_scene.bullet_manager.create_bullet(
+                            (x * 512.0) as i32,
+                            (y * 512.0) as i32,
+                            btype as u16,
+                            owner,
+                            direction,
+                            &game_state.constants,
+                        );
+                    } else {
+                        state.error("Invalid parameters supplied.");
+                    }
+
+                    state.push_nil();
+                }
                 _ => state.push_nil(),
             }
         } else {
@@ -498,6 +630,40 @@ impl Doukutsu {
         1
     }
 
+    unsafe fn lua_draw_text(&self, state: &mut State) -> c_int {
+        let text = state.to_str(2).map(str::to_owned);
+        let args = (state.to_float(3), state.to_float(4));
+
+        if let (Some(text), (Some(x), Some(y))) = (text, args) {
+            let mut color = (255, 255, 255, 255);
+            if let (Some(r), Some(g), Some(b)) = (state.to_int(5), state.to_int(6), state.to_int(7)) {
+                color = (r as u8, g as u8, b as u8, state.to_int(8).unwrap_or(255) as u8);
+            }
+
+            (*self.ptr).push_hud_command(LuaHudCommand::Text { text, x, y, color });
+        } else {
+            state.error("Invalid parameters supplied.");
+        }
+
+        0
+    }
+
+    unsafe fn lua_draw_sprite(&self, state: &mut State) -> c_int {
+        let texture = state.to_str(2).map(str::to_owned);
+        let pos = (state.to_float(3), state.to_float(4));
+        let rect = (state.to_int(5), state.to_int(6), state.to_int(7), state.to_int(8));
+
+        if let (Some(texture), (Some(x), Some(y)), (Some(l), Some(t), Some(r), Some(b))) = (texture, pos, rect) {
+            let rect = Rect { left: l as u16, top: t as u16, right: r as u16, bottom: b as u16 };
+
+            (*self.ptr).push_hud_command(LuaHudCommand::Sprite { texture, x, y, rect });
+        } else {
+            state.error("Invalid parameters supplied.");
+        }
+
+        0
+    }
+
     unsafe fn lua_load_script(&mut self, state: &mut State) -> c_int {
         let lua_state = &mut (*self.ptr);
 
@@ -563,6 +729,8 @@ impl LuaObject for Doukutsu {
             lua_method!("playerCommand", Doukutsu, Doukutsu::lua_player_command),
             lua_method!("npcCommand", Doukutsu, Doukutsu::lua_npc_command),
             lua_method!("stageCommand", Doukutsu, Doukutsu::lua_stage_command),
+            lua_method!("drawText", Doukutsu, Doukutsu::lua_draw_text),
+            lua_method!("drawSprite", Doukutsu, Doukutsu::lua_draw_sprite),
             lua_method!("loadScript", Doukutsu, Doukutsu::lua_load_script),
         ]
     }
@@ -579,12 +747,78 @@ impl LuaScriptingState {
             state.push(npc_id as i32);
             state.push(npc_type as i32);
 
-            if let Err((_, err)) = state.pcall(2, 1, 0) {
-                log::error!("npc_hook error: {}", err);
+            // a failed call leaves the error in place of the results
+            match state.pcall(2, 1, 0) {
+                Ok(()) => {
+                    if let Some(val) = state.to_bool(-1) {
+                        result = val;
+                    }
+                }
+                Err((_, err)) => {
+                    log::error!("npc_hook error: {}", err);
+                }
+            }
+
+            state.pop(2);
+        }
+
+        result
+    }
+
+    /// Runs the Lua handler of a custom TSC opcode, returns whether a handler was found
+    /// and the event to jump to, if the handler returned one.
+    pub fn try_run_tsc_opcode(&mut self, name: &str, operands: &[i32]) -> (bool, Option<u16>) {
+        let mut result = (false, None);
+
+        if let Some(state) = &mut self.state {
+            state.get_global(DRS_RUNTIME_GLOBAL);
+            state.get_field(-1, "_tryTSCOpcode");
+
+            state.push(name);
+            for &operand in operands {
+                state.push(operand);
             }
 
-            if let Some(val) = state.to_bool(-1) {
-                result = val;
+            match state.pcall(operands.len() as c_int + 1, 2, 0) {
+                Ok(()) => {
+                    if let Some(val) = state.to_bool(-2) {
+                        result.0 = val;
+                    }
+
+                    if let Some(event_num) = state.to_int(-1).filter(|&event_num| event_num >= 0) {
+                        result.1 = Some(event_num as u16);
+                    }
+
+                    state.pop(3);
+                }
+                Err((_, err)) => {
+                    log::error!("tsc_opcode error: {}", err);
+
+                    state.pop(2);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Returns the names of TSC opcodes registered by the loaded scripts.
+    pub fn registered_tsc_opcodes(&mut self) -> Vec<String> {
+        let mut result = Vec::new();
+
+        if let Some(state) = &mut self.state {
+            state.get_global(DRS_RUNTIME_GLOBAL);
+            state.get_field(-1, "_listTSCOpcodes");
+
+            match state.pcall(0, 1, 0) {
+                Ok(()) => {
+                    if let Some(names) = state.to_str(-1) {
+                        result.extend(names.split('\n').filter(|name| !name.is_empty()).map(str::to_owned));
+                    }
+                }
+                Err((_, err)) => {
+                    log::error!("tsc_opcode list error: {}", err);
+                }
             }
 
             state.pop(2);
@@ -592,4 +826,57 @@ impl LuaScriptingState {
 
         result
     }
+
+    /// Runs the Lua handler firing a custom weapon with the `lua` fire pattern, returns whether a handler was found.
+    pub fn try_run_weapon_hook(&mut self, weapon_id: u8, player_id: u8, level: u8) -> bool {
+        let mut result = false;
+
+        if let Some(state) = &mut self.state {
+            state.get_global(DRS_RUNTIME_GLOBAL);
+            state.get_field(-1, "_tryWeaponHook");
+
+            state.push(weapon_id as i32);
+            state.push(player_id as i32);
+            state.push(level as i32);
+
+            match state.pcall(3, 1, 0) {
+                Ok(()) => {
+                    if let Some(val) = state.to_bool(-1) {
+                        result = val;
+                    }
+                }
+                Err((_, err)) => {
+                    log::error!("weapon_hook error: {}", err);
+                }
+            }
+
+            state.pop(2);
+        }
+
+        result
+    }
+}
+
+#[test]
+fn test_failing_hooks_keep_stack() {
+    let mut state = State::new();
+    state.open_libs();
+
+    let script = format!(
+        "local function fail() error('boom') end
+        {} = {{ _handlers = {{ test = fail }}, _tryNPCHook = fail, _tryTSCOpcode = fail, _tryWeaponHook = fail }}",
+        DRS_RUNTIME_GLOBAL
+    );
+    assert!(check_status(state.do_string(&script), &mut state).is_ok());
+
+    let top = state.get_top();
+    let mut scripting = LuaScriptingState::new();
+    scripting.state = Some(state);
+
+    assert!(!scripting.try_run_npc_hook(1, 2));
+    assert_eq!(scripting.try_run_tsc_opcode("<XYZ", &[1, 2]), (false, None));
+    assert!(!scripting.try_run_weapon_hook(14, 0, 1));
+    scripting.call_event("test", &[1]);
+
+    assert_eq!(scripting.state.as_mut().unwrap().get_top(), top);
 }
@@ -0,0 +1,52 @@
+use crate::common::Rect;
+use crate::framework::context::Context;
+use crate::framework::error::GameResult;
+use crate::game::scripting::lua::LuaScriptingState;
+use crate::game::shared_game_state::SharedGameState;
+use crate::graphics::font::Font;
+
+/// Draw call queued by a `drawHUD` event handler.
+pub enum LuaHudCommand {
+    Text { text: String, x: f32, y: f32, color: (u8, u8, u8, u8) },
+    Sprite { texture: String, x: f32, y: f32, rect: Rect<u16> },
+}
+
+impl LuaScriptingState {
+    /// Rebuilds the HUD draw list by running the `drawHUD` handlers, the list is kept until the next tick.
+    pub fn hud_tick(&mut self) {
+        self.hud_commands.clear();
+        self.call_event("drawHUD", &[]);
+    }
+
+    pub(crate) fn push_hud_command(&mut self, command: LuaHudCommand) {
+        self.hud_commands.push(command);
+    }
+
+    pub fn draw_hud(state: &mut SharedGameState, ctx: &mut Context) -> GameResult {
+        let commands = std::mem::take(&mut state.lua.hud_commands);
+
+        for command in commands.iter() {
+            match command {
+                LuaHudCommand::Text { text, x, y, color } => {
+                    state.font.builder().position(*x, *y).color(*color).shadow(true).draw(
+                        text,
+                        ctx,
+                        &state.constants,
+                        &mut state.texture_set,
+                    )?;
+                }
+                LuaHudCommand::Sprite { texture, x, y, rect } => {
+                    // missing textures are skipped, the script gets no feedback while drawing anyway
+                    if let Ok(batch) = state.texture_set.get_or_load_batch(ctx, &state.constants, texture) {
+                        batch.add_rect(*x, *y, rect);
+                        batch.draw(ctx)?;
+                    }
+                }
+            }
+        }
+
+        state.lua.hud_commands = commands;
+
+        Ok(())
+    }
+}
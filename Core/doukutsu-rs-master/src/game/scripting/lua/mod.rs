@@ -12,10 +12,12 @@ use crate::framework::error::{GameError, GameResult};
 use crate::framework::filesystem;
 use crate::framework::filesystem::File;
 use crate::game::scripting::lua::doukutsu::Doukutsu;
+use crate::game::scripting::lua::hud::LuaHudCommand;
 use crate::game::shared_game_state::SharedGameState;
 use crate::scene::game_scene::GameScene;
 
 mod doukutsu;
+mod hud;
 mod scene;
 
 pub struct LuaScriptingState {
@@ -23,6 +25,7 @@ pub struct LuaScriptingState {
     state_ptr: *mut SharedGameState,
     ctx_ptr: *mut Context,
     game_scene: *mut GameScene,
+    hud_commands: Vec<LuaHudCommand>,
 }
 
 pub(crate) static DRS_API_GLOBAL: &str = "__doukutsu_rs";
@@ -62,7 +65,13 @@ fn print(state: &mut State) -> c_int {
 
 impl LuaScriptingState {
     pub fn new() -> LuaScriptingState {
-        LuaScriptingState { state: None, state_ptr: null_mut(), ctx_ptr: null_mut(), game_scene: null_mut() }
+        LuaScriptingState {
+            state: None,
+            state_ptr: null_mut(),
+            ctx_ptr: null_mut(),
+            game_scene: null_mut(),
+            hud_commands: Vec::new(),
+        }
     }
 
     pub fn update_refs(&mut self, state: *mut SharedGameState, ctx: *mut Context) {
@@ -72,6 +81,7 @@ impl LuaScriptingState {
 
     pub fn set_game_scene(&mut self, game_scene: *mut GameScene) {
         self.game_scene = game_scene;
+        self.hud_commands.clear();
     }
 
     fn load_script(state: &mut State, path: &str, mut script: File) -> bool {
@@ -51,6 +51,27 @@ impl LuaScriptingState {
             state.push(val);
             if let Err((_, err)) = state.pcall(1, 0, 0) {
                 println!("scene_tick error: {}", err);
+                state.pop(1);
+            }
+
+            state.pop(2);
+        }
+    }
+
+    /// Calls handlers registered for given event with integer arguments.
+    pub fn call_event(&mut self, event: &str, args: &[i32]) {
+        if let Some(state) = &mut self.state {
+            state.get_global(DRS_RUNTIME_GLOBAL);
+            state.get_field(-1, "_handlers");
+            state.get_field(-1, event);
+
+            for &arg in args {
+                state.push(arg);
+            }
+
+            if let Err((_, err)) = state.pcall(args.len() as c_int, 0, 0) {
+                log::error!("{} event error: {}", event, err);
+                state.pop(1);
             }
 
             state.pop(2);
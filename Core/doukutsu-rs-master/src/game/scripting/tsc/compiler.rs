@@ -137,175 +137,64 @@ impl TextScript {
         iter: &mut Peekable<I>,
         out: &mut Vec<u8>,
     ) -> GameResult {
-        let instr = TSCOpCode::from_str(code).map_err(|_| ParseError(format!("Unknown opcode: {}", code)))?;
-
-        match instr {
-            // Zero operand codes
-            TSCOpCode::AEp
-            | TSCOpCode::CAT
-            | TSCOpCode::CIL
-            | TSCOpCode::CLO
-            | TSCOpCode::CLR
-            | TSCOpCode::CPS
-            | TSCOpCode::CRE
-            | TSCOpCode::CSS
-            | TSCOpCode::END
-            | TSCOpCode::ESC
-            | TSCOpCode::FLA
-            | TSCOpCode::FMU
-            | TSCOpCode::FRE
-            | TSCOpCode::HMC
-            | TSCOpCode::INI
-            | TSCOpCode::KEY
-            | TSCOpCode::LDP
-            | TSCOpCode::MLP
-            | TSCOpCode::MM0
-            | TSCOpCode::MNA
-            | TSCOpCode::MS2
-            | TSCOpCode::MS3
-            | TSCOpCode::MSG
-            | TSCOpCode::NOD
-            | TSCOpCode::PRI
-            | TSCOpCode::RMU
-            | TSCOpCode::SAT
-            | TSCOpCode::SLP
-            | TSCOpCode::SMC
-            | TSCOpCode::SPS
-            | TSCOpCode::STC
-            | TSCOpCode::SVP
-            | TSCOpCode::TUR
-            | TSCOpCode::WAS
-            | TSCOpCode::ZAM
-            | TSCOpCode::HM2
-            | TSCOpCode::POP
-            | TSCOpCode::KE2
-            | TSCOpCode::FR2 => {
-                put_varint(instr as i32, out);
-            }
-            // One operand codes
-            TSCOpCode::BOA
-            | TSCOpCode::BSL
-            | TSCOpCode::FOM
-            | TSCOpCode::QUA
-            | TSCOpCode::UNI
-            | TSCOpCode::MYB
-            | TSCOpCode::MYD
-            | TSCOpCode::FAI
-            | TSCOpCode::FAO
-            | TSCOpCode::WAI
-            | TSCOpCode::FAC
-            | TSCOpCode::GIT
-            | TSCOpCode::NUM
-            | TSCOpCode::DNA
-            | TSCOpCode::DNP
-            | TSCOpCode::FLm
-            | TSCOpCode::FLp
-            | TSCOpCode::MPp
-            | TSCOpCode::SKm
-            | TSCOpCode::SKp
-            | TSCOpCode::EQp
-            | TSCOpCode::EQm
-            | TSCOpCode::MLp
-            | TSCOpCode::ITp
-            | TSCOpCode::ITm
-            | TSCOpCode::AMm
-            | TSCOpCode::MPJ
-            | TSCOpCode::YNJ
-            | TSCOpCode::EVE
-            | TSCOpCode::XX1
-            | TSCOpCode::SIL
-            | TSCOpCode::LIp
-            | TSCOpCode::SOU
-            | TSCOpCode::CMU
-            | TSCOpCode::SSS
-            | TSCOpCode::ACH
-            | TSCOpCode::S2MV
-            | TSCOpCode::S2PJ
-            | TSCOpCode::PSH => {
-                let operand = read_number(iter)?;
-                put_varint(instr as i32, out);
-                put_varint(operand as i32, out);
-            }
-            // Two operand codes
-            TSCOpCode::FON
-            | TSCOpCode::FOB
-            | TSCOpCode::MOV
-            | TSCOpCode::AMp
-            | TSCOpCode::NCJ
-            | TSCOpCode::ECJ
-            | TSCOpCode::FLJ
-            | TSCOpCode::ITJ
-            | TSCOpCode::SKJ
-            | TSCOpCode::AMJ
-            | TSCOpCode::UNJ
-            | TSCOpCode::SMP
-            | TSCOpCode::PSp
-            | TSCOpCode::IpN
-            | TSCOpCode::FFm => {
-                let operand_a = read_number(iter)?;
-                if strict {
-                    expect_char(b':', iter)?;
-                } else {
-                    iter.next().ok_or_else(|| ParseError("Script unexpectedly ended.".to_owned()))?;
-                }
-                let operand_b = read_number(iter)?;
+        let instr = match TSCOpCode::from_str(code) {
+            Ok(instr) => instr,
+            #[cfg(feature = "scripting-lua")]
+            Err(_) if !strict => return TextScript::compile_custom_code(code, iter, out),
+            Err(_) => return Err(ParseError(format!("Unknown opcode: {}", code))),
+        };
+
+        if let TSCOpCode::_NOP | TSCOpCode::_UNI | TSCOpCode::_STR | TSCOpCode::_END | TSCOpCode::_CUS = instr {
+            unreachable!()
+        }
 
-                put_varint(instr as i32, out);
-                put_varint(operand_a as i32, out);
-                put_varint(operand_b as i32, out);
-            }
-            // Three operand codes
-            TSCOpCode::ANP | TSCOpCode::CNP | TSCOpCode::INP | TSCOpCode::TAM | TSCOpCode::CMP | TSCOpCode::INJ => {
-                let operand_a = read_number(iter)?;
-                if strict {
-                    expect_char(b':', iter)?;
-                } else {
-                    iter.next().ok_or_else(|| ParseError("Script unexpectedly ended.".to_owned()))?;
-                }
-                let operand_b = read_number(iter)?;
-                if strict {
-                    expect_char(b':', iter)?;
-                } else {
-                    iter.next().ok_or_else(|| ParseError("Script unexpectedly ended.".to_owned()))?;
-                }
-                let operand_c = read_number(iter)?;
+        put_varint(instr as i32, out);
 
-                put_varint(instr as i32, out);
-                put_varint(operand_a as i32, out);
-                put_varint(operand_b as i32, out);
-                put_varint(operand_c as i32, out);
-            }
-            // Four operand codes
-            TSCOpCode::TRA | TSCOpCode::MNP | TSCOpCode::SNP => {
-                let operand_a = read_number(iter)?;
-                if strict {
-                    expect_char(b':', iter)?;
-                } else {
-                    iter.next().ok_or_else(|| ParseError("Script unexpectedly ended.".to_owned()))?;
-                }
-                let operand_b = read_number(iter)?;
-                if strict {
-                    expect_char(b':', iter)?;
-                } else {
-                    iter.next().ok_or_else(|| ParseError("Script unexpectedly ended.".to_owned()))?;
-                }
-                let operand_c = read_number(iter)?;
+        for idx in 0..instr.operand_count() {
+            if idx > 0 {
                 if strict {
                     expect_char(b':', iter)?;
                 } else {
                     iter.next().ok_or_else(|| ParseError("Script unexpectedly ended.".to_owned()))?;
                 }
-                let operand_d = read_number(iter)?;
-
-                put_varint(instr as i32, out);
-                put_varint(operand_a as i32, out);
-                put_varint(operand_b as i32, out);
-                put_varint(operand_c as i32, out);
-                put_varint(operand_d as i32, out);
             }
-            TSCOpCode::_NOP | TSCOpCode::_UNI | TSCOpCode::_STR | TSCOpCode::_END => {
-                unreachable!()
+
+            let operand = read_number(iter)?;
+            put_varint(operand as i32, out);
+        }
+
+        Ok(())
+    }
+
+    /// Compiles an opcode unknown to the engine, so it can be implemented by a Lua script.
+    /// Operands are read as long as they're 4 digit numbers separated by colons.
+    #[cfg(feature = "scripting-lua")]
+    fn compile_custom_code<I: Iterator<Item=u8>>(
+        code: &str,
+        iter: &mut Peekable<I>,
+        out: &mut Vec<u8>,
+    ) -> GameResult {
+        let mut operands = Vec::new();
+
+        while iter.peek().map_or(false, |chr| chr.is_ascii_digit()) {
+            operands.push(read_number(iter)?);
+
+            if iter.peek() != Some(&b':') {
+                break;
             }
+
+            iter.next();
+        }
+
+        put_varint(TSCOpCode::_CUS as i32, out);
+        put_varint(code.chars().count() as i32, out);
+        for chr in code.chars() {
+            put_varint(chr as i32, out);
+        }
+
+        put_varint(operands.len() as i32, out);
+        for operand in operands {
+            put_varint(operand, out);
         }
 
         Ok(())
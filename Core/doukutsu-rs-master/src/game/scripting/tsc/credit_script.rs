@@ -8,7 +8,7 @@ use crate::engine_constants::EngineConstants;
 use crate::framework::context::Context;
 use crate::framework::error::GameResult;
 use crate::game::scripting::tsc::bytecode_utils::{put_varint, read_cur_varint};
-use crate::game::scripting::tsc::encryption::decrypt_tsc;
+use crate::game::scripting::tsc::encoding::prepare_script;
 use crate::game::scripting::tsc::opcodes::CreditOpCode;
 use crate::game::shared_game_state::SharedGameState;
 
@@ -32,11 +32,9 @@ impl CreditScript {
         let mut buf = Vec::new();
         data.read_to_end(&mut buf)?;
 
-        if constants.textscript.encrypted {
-            decrypt_tsc(&mut buf);
-        }
+        let (encoding, header) = prepare_script(&mut buf, constants);
 
-        CreditScript::compile(&buf, false, constants.textscript.encoding)
+        CreditScript::compile(&buf[header..], false, encoding)
     }
 }
 
@@ -173,6 +173,21 @@ impl TextScript {
                         TSCOpCode::_NOP => result.push_str("%no_op()\n"),
                         TSCOpCode::_UNI => result.push_str("%unimplemented()\n"),
                         TSCOpCode::_END => result.push_str("%end_marker()\n"),
+                        TSCOpCode::_CUS => {
+                            let len = read_cur_varint(&mut cursor)?;
+                            let mut name = String::new();
+                            for _ in 0..len {
+                                name.push(std::char::from_u32(read_cur_varint(&mut cursor)? as u32).unwrap_or('?'));
+                            }
+
+                            let count = read_cur_varint(&mut cursor)?;
+                            let mut operands = Vec::new();
+                            for _ in 0..count {
+                                operands.push(read_cur_varint(&mut cursor)?.to_string());
+                            }
+
+                            writeln!(&mut result, "%custom(<{}, {})", name, operands.join(", ")).unwrap();
+                        }
                     }
                 } else {
                     break;
@@ -0,0 +1,170 @@
+use crate::engine_constants::EngineConstants;
+use crate::game::scripting::tsc::encryption::{decrypt_tsc, encrypt_tsc};
+use crate::game::scripting::tsc::text_script::TextScriptEncoding;
+use crate::util::encoding::{decode_shift_jis, encode_shift_jis, is_shift_jis};
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+const DIRECTIVE: &[u8] = b"#encoding:";
+
+/// Whether a script read from a file has to be decrypted, `encrypted` tells if the game's scripts are encrypted.
+///
+/// Scripts written for such games can still be stored as plain text if they start with an UTF-8 byte order mark or an
+/// `#encoding` directive, which no encrypted script starting with an event number does.
+pub fn is_encrypted(data: &[u8], encrypted: bool) -> bool {
+    let has_directive = |data: &[u8]| data.get(..DIRECTIVE.len()).map_or(false, |d| d.eq_ignore_ascii_case(DIRECTIVE));
+
+    encrypted && !data.starts_with(UTF8_BOM) && !has_directive(data)
+}
+
+/// Detects the encoding of a decrypted script.
+///
+/// In order of precedence, the encoding is taken from an `#encoding: utf-8` (or `shift-jis`) directive on the first
+/// line, an UTF-8 byte order mark, UTF-8 validity of the text and finally Shift-JIS heuristics. Scripts that are
+/// plain ASCII or can't be recognized use given fallback.
+///
+/// Returns the encoding and the length of the header (BOM and directive) that has to be skipped before compiling.
+pub fn detect_encoding(data: &[u8], fallback: TextScriptEncoding) -> (TextScriptEncoding, usize) {
+    let mut header = 0;
+    let mut encoding = None;
+
+    if data.starts_with(UTF8_BOM) {
+        header = UTF8_BOM.len();
+        encoding = Some(TextScriptEncoding::UTF8);
+    }
+
+    if data.get(header..header + DIRECTIVE.len()).map_or(false, |d| d.eq_ignore_ascii_case(DIRECTIVE)) {
+        let line_end = match data[header..].iter().position(|&b| b == b'\n') {
+            Some(pos) => header + pos + 1,
+            None => data.len(),
+        };
+
+        let name = String::from_utf8_lossy(&data[header + DIRECTIVE.len()..line_end]);
+        match TextScriptEncoding::from_name(&name) {
+            Some(directive) => encoding = Some(directive),
+            None => log::warn!("Unsupported text script encoding: {}", name.trim()),
+        }
+
+        header = line_end;
+    }
+
+    if let Some(encoding) = encoding {
+        return (encoding, header);
+    }
+
+    let body = &data[header..];
+    let encoding = if body.is_ascii() {
+        fallback
+    } else if std::str::from_utf8(body).is_ok() {
+        TextScriptEncoding::UTF8
+    } else if is_shift_jis(body) {
+        TextScriptEncoding::ShiftJIS
+    } else {
+        fallback
+    };
+
+    (encoding, header)
+}
+
+/// Decrypts a script read from a file if it's encrypted and detects its encoding, see [detect_encoding].
+pub fn prepare_script(buf: &mut [u8], constants: &EngineConstants) -> (TextScriptEncoding, usize) {
+    if is_encrypted(buf, constants.textscript.encrypted) {
+        decrypt_tsc(buf);
+    }
+
+    detect_encoding(buf, constants.textscript.encoding)
+}
+
+pub struct ConvertedScript {
+    pub data: Vec<u8>,
+    pub source_encoding: TextScriptEncoding,
+    /// Number of characters that can't be represented in the target encoding and were replaced with `?`.
+    pub lost_chars: usize,
+}
+
+/// Re-encodes a script to given encoding, prepending an `#encoding` directive so it doesn't rely on detection.
+/// `encrypted` tells if the game's scripts are encrypted, see [is_encrypted]. Encrypted scripts stay encrypted.
+pub fn convert_script(
+    data: &[u8],
+    encrypted: bool,
+    fallback: TextScriptEncoding,
+    target: TextScriptEncoding,
+) -> ConvertedScript {
+    let mut buf = data.to_vec();
+    let encrypted = is_encrypted(&buf, encrypted);
+    if encrypted {
+        decrypt_tsc(&mut buf);
+    }
+
+    let (source_encoding, header) = detect_encoding(&buf, fallback);
+    let source = decode(&buf[header..], source_encoding);
+    let line_break = if source.contains("\r\n") { "\r\n" } else { "\n" };
+
+    let mut out = format!("#encoding: {}{}", target.name(), line_break).into_bytes();
+    let body = encode(&source, target);
+    let lost_chars = decode(&body, target).matches('?').count() - source.matches('?').count();
+    out.extend_from_slice(&body);
+
+    if encrypted {
+        encrypt_tsc(&mut out);
+    }
+
+    ConvertedScript { data: out, source_encoding, lost_chars }
+}
+
+pub fn decode(data: &[u8], encoding: TextScriptEncoding) -> String {
+    match encoding {
+        TextScriptEncoding::UTF8 => String::from_utf8_lossy(data).into_owned(),
+        TextScriptEncoding::ShiftJIS => decode_shift_jis(data),
+    }
+}
+
+pub fn encode(text: &str, encoding: TextScriptEncoding) -> Vec<u8> {
+    match encoding {
+        TextScriptEncoding::UTF8 => text.as_bytes().to_vec(),
+        TextScriptEncoding::ShiftJIS => encode_shift_jis(text),
+    }
+}
+
+#[test]
+fn test_detect_encoding() {
+    let utf8 = "#0100\n<MSGこんにちは<NOD<END\n";
+    let sjis = encode_shift_jis(utf8);
+
+    assert_eq!(detect_encoding(utf8.as_bytes(), TextScriptEncoding::ShiftJIS), (TextScriptEncoding::UTF8, 0));
+    assert_eq!(detect_encoding(&sjis, TextScriptEncoding::UTF8), (TextScriptEncoding::ShiftJIS, 0));
+    assert_eq!(detect_encoding(b"#0100\n<END\n", TextScriptEncoding::ShiftJIS), (TextScriptEncoding::ShiftJIS, 0));
+    assert_eq!(
+        detect_encoding(b"\xef\xbb\xbf#0100\n<END\n", TextScriptEncoding::ShiftJIS),
+        (TextScriptEncoding::UTF8, 3)
+    );
+    assert_eq!(
+        detect_encoding(b"#Encoding: Shift-JIS\r\n#0100\n<END\n", TextScriptEncoding::UTF8),
+        (TextScriptEncoding::ShiftJIS, 22)
+    );
+}
+
+#[test]
+fn test_convert_script() {
+    let mut source = encode_shift_jis("#0100\r\n<MSGこんにちは<NOD<END\r\n");
+    encrypt_tsc(&mut source);
+
+    let converted = convert_script(&source, true, TextScriptEncoding::ShiftJIS, TextScriptEncoding::UTF8);
+    assert_eq!(converted.source_encoding, TextScriptEncoding::ShiftJIS);
+    assert_eq!(converted.lost_chars, 0);
+
+    let mut data = converted.data.clone();
+    assert!(is_encrypted(&data, true));
+    decrypt_tsc(&mut data);
+    assert_eq!(data, "#encoding: utf-8\r\n#0100\r\n<MSGこんにちは<NOD<END\r\n".as_bytes());
+
+    // plain scripts of games that don't encrypt them are never decrypted
+    let converted =
+        convert_script(b"#0100\n<MSG\xc3\xa9<END\n", false, TextScriptEncoding::UTF8, TextScriptEncoding::ShiftJIS);
+    assert_eq!(converted.data, b"#encoding: shift-jis\n#0100\n<MSG?<END\n");
+    assert_eq!(converted.lost_chars, 1);
+
+    assert!(is_encrypted(b"#0100\n<END\n", true));
+    assert!(!is_encrypted(b"#0100\n<END\n", false));
+    assert!(!is_encrypted(b"\xef\xbb\xbf#0100\n<END\n", true));
+    assert!(!is_encrypted(b"#encoding: utf-8\n#0100\n<END\n", true));
+}
@@ -11,3 +11,30 @@ pub fn decrypt_tsc(buf: &mut [u8]) {
         *byte = byte.wrapping_sub(key);
     }
 }
+
+/// Inverse of [decrypt_tsc], the byte in the middle of the buffer is used as the key and left as-is.
+pub fn encrypt_tsc(buf: &mut [u8]) {
+    let half = buf.len() / 2;
+    let key = if let Some(0) = buf.get(half) { 0x7 } else { *buf.get(half).unwrap() };
+    log::info!("Encrypting TSC using key {:#x}", key);
+
+    for (idx, byte) in buf.iter_mut().enumerate() {
+        if idx == half {
+            continue;
+        }
+
+        *byte = byte.wrapping_add(key);
+    }
+}
+
+#[test]
+fn tsc_encryption_roundtrip_test() {
+    let source = b"#0090\r\n<MNA<CMU0008<FAI0000<END\r\n".to_vec();
+
+    let mut buf = source.clone();
+    encrypt_tsc(&mut buf);
+    assert_ne!(buf, source);
+
+    decrypt_tsc(&mut buf);
+    assert_eq!(buf, source);
+}
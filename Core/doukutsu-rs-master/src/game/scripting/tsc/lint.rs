@@ -0,0 +1,489 @@
+use std::collections::HashSet;
+use std::io::Read;
+use std::str::FromStr;
+
+use crate::engine_constants::EngineConstants;
+use crate::framework::context::Context;
+use crate::framework::error::GameResult;
+use crate::framework::filesystem;
+use crate::game::map::NPCData;
+use crate::game::scripting::tsc::encoding::prepare_script;
+use crate::game::scripting::tsc::opcodes::TSCOpCode;
+use crate::game::stage::StageData;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// Single problem found in a script, serialized as one JSON object per line by the `--lint` command.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LintIssue {
+    pub file: String,
+    pub line: usize,
+    pub event: Option<u16>,
+    pub severity: LintSeverity,
+    pub code: &'static str,
+    pub message: String,
+}
+
+struct Jump {
+    line: usize,
+    /// Target map for `<TRA`, `None` for jumps within the current script.
+    map: Option<u16>,
+    event: u16,
+}
+
+struct ParsedEvent {
+    id: u16,
+    line: usize,
+    last_opcode: Option<TSCOpCode>,
+    jumps: Vec<Jump>,
+}
+
+struct ParsedScript {
+    file: String,
+    events: Vec<ParsedEvent>,
+}
+
+impl ParsedScript {
+    fn has_event(&self, id: u16) -> bool {
+        self.events.iter().any(|e| e.id == id)
+    }
+
+    fn jumps(&self) -> impl Iterator<Item = (&ParsedEvent, &Jump)> {
+        self.events.iter().flat_map(|e| e.jumps.iter().map(move |j| (e, j)))
+    }
+
+    fn issue(
+        &self,
+        line: usize,
+        event: Option<u16>,
+        severity: LintSeverity,
+        code: &'static str,
+        message: String,
+    ) -> LintIssue {
+        LintIssue { file: self.file.clone(), line, event, severity, code, message }
+    }
+}
+
+fn read_operand(data: &[u8]) -> u16 {
+    data.iter().fold(0i32, |acc, &v| acc * 10 + v.wrapping_sub(b'0') as i32) as u16
+}
+
+fn is_terminator(op: TSCOpCode) -> bool {
+    matches!(
+        op,
+        TSCOpCode::END
+            | TSCOpCode::EVE
+            | TSCOpCode::TRA
+            | TSCOpCode::INI
+            | TSCOpCode::LDP
+            | TSCOpCode::ESC
+            | TSCOpCode::POP
+    )
+}
+
+/// Returns the target map and event of a jump instruction.
+fn jump_target(op: TSCOpCode, operands: &[u16]) -> Option<(Option<u16>, u16)> {
+    match op {
+        TSCOpCode::EVE | TSCOpCode::YNJ | TSCOpCode::MPJ | TSCOpCode::PSH => Some((None, operands[0])),
+        TSCOpCode::FLJ
+        | TSCOpCode::ITJ
+        | TSCOpCode::SKJ
+        | TSCOpCode::AMJ
+        | TSCOpCode::UNJ
+        | TSCOpCode::NCJ
+        | TSCOpCode::ECJ => Some((None, operands[1])),
+        TSCOpCode::INJ => Some((None, operands[2])),
+        TSCOpCode::TRA => Some((Some(operands[0]), operands[1])),
+        _ => None,
+    }
+}
+
+/// Parses a decrypted script following the same rules as `TextScript::compile`, but instead of stopping at
+/// the first problem it records every one of them. `first_line` is the line number of the first byte of `data`,
+/// scripts may start with a header that isn't parsed. `custom_opcodes` are implemented by Lua scripts.
+fn parse_script(
+    file: &str,
+    data: &[u8],
+    first_line: usize,
+    custom_opcodes: &HashSet<String>,
+    issues: &mut Vec<LintIssue>,
+) -> ParsedScript {
+    let is_digits = |range: Option<&[u8]>| range.map_or(false, |d| d.iter().all(u8::is_ascii_digit));
+    let mut script = ParsedScript { file: file.to_owned(), events: Vec::new() };
+    let mut line = first_line;
+    let mut idx = 0;
+    let mut allow_next_event = true;
+    let mut reported_stray_text = false;
+
+    while idx < data.len() {
+        match data[idx] {
+            b'#' if allow_next_event => {
+                let number = match data.get(idx + 1..idx + 5) {
+                    Some(number) => number,
+                    None => {
+                        issues.push(script.issue(
+                            line,
+                            None,
+                            LintSeverity::Error,
+                            "unexpected-eof",
+                            "Script unexpectedly ended.".to_owned(),
+                        ));
+                        break;
+                    }
+                };
+
+                let id = read_operand(number);
+                if script.has_event(id) {
+                    issues.push(script.issue(
+                        line,
+                        Some(id),
+                        LintSeverity::Warning,
+                        "duplicate-event",
+                        format!("Event #{:04} has been defined twice, only the first definition is used.", id),
+                    ));
+                }
+
+                script.events.push(ParsedEvent { id, line, last_opcode: None, jumps: Vec::new() });
+
+                // the rest of the header line is ignored
+                idx += 5;
+                while idx < data.len() && data[idx] != b'\n' {
+                    idx += 1;
+                }
+            }
+            b'<' => {
+                allow_next_event = false;
+                let start = idx;
+                let start_line = line;
+                let event_id = script.events.last().map(|e| e.id);
+
+                let code = match data.get(idx + 1..idx + 4) {
+                    Some(code) => String::from_utf8_lossy(code).into_owned(),
+                    None => {
+                        issues.push(script.issue(
+                            line,
+                            event_id,
+                            LintSeverity::Error,
+                            "unexpected-eof",
+                            "Script unexpectedly ended.".to_owned(),
+                        ));
+                        break;
+                    }
+                };
+                idx += 4;
+
+                let op = match TSCOpCode::from_str(&code) {
+                    Ok(op) => op,
+                    Err(_) if custom_opcodes.contains(&code) => {
+                        // same as the compiler, operands are read as long as they're numbers separated by colons
+                        while is_digits(data.get(idx..idx + 4)) {
+                            idx += 4;
+
+                            if data.get(idx) != Some(&b':') {
+                                break;
+                            }
+                            idx += 1;
+                        }
+
+                        if let Some(event) = script.events.last_mut() {
+                            event.last_opcode = None;
+                        }
+                        continue;
+                    }
+                    Err(_) => {
+                        issues.push(script.issue(
+                            line,
+                            event_id,
+                            LintSeverity::Error,
+                            "unknown-opcode",
+                            format!("Unknown opcode: <{}", code),
+                        ));
+                        continue;
+                    }
+                };
+
+                let count = op.operand_count();
+                let mut operands = Vec::with_capacity(count);
+                let mut malformed = false;
+
+                for i in 0..count {
+                    if i != 0 {
+                        malformed |= data.get(idx) != Some(&b':');
+                        idx += 1;
+                    }
+
+                    match data.get(idx..idx + 4) {
+                        Some(number) => {
+                            malformed |= number.iter().any(|c| matches!(c, b'<' | b'#' | b'\r' | b'\n'));
+                            operands.push(read_operand(number));
+                            idx += 4;
+                        }
+                        None => {
+                            malformed = true;
+                            idx = data.len();
+                            break;
+                        }
+                    }
+                }
+
+                if data.get(idx) == Some(&b':') && is_digits(data.get(idx + 1..idx + 5)) {
+                    malformed = true;
+                }
+
+                line += data[start..idx.min(data.len())].iter().filter(|&&c| c == b'\n').count();
+
+                if malformed {
+                    issues.push(script.issue(
+                        start_line,
+                        event_id,
+                        LintSeverity::Error,
+                        "operand-count",
+                        format!("<{} expects {} operand(s).", code, count),
+                    ));
+                    continue;
+                }
+
+                let event = match script.events.last_mut() {
+                    Some(event) => event,
+                    None => {
+                        issues.push(script.issue(
+                            start_line,
+                            None,
+                            LintSeverity::Warning,
+                            "code-outside-event",
+                            format!("<{} is placed before the first event and will never run.", code),
+                        ));
+                        continue;
+                    }
+                };
+
+                event.last_opcode = Some(op);
+                if let Some((map, target)) = jump_target(op, &operands) {
+                    event.jumps.push(Jump { line: start_line, map, event: target });
+                }
+            }
+            b'\n' => {
+                allow_next_event = true;
+                line += 1;
+                idx += 1;
+            }
+            b'\r' | b' ' | b'\t' => {
+                idx += 1;
+            }
+            _ => {
+                if script.events.is_empty() && !reported_stray_text {
+                    reported_stray_text = true;
+                    issues.push(script.issue(
+                        line,
+                        None,
+                        LintSeverity::Warning,
+                        "text-outside-event",
+                        "Text before the first event is ignored.".to_owned(),
+                    ));
+                }
+
+                idx += 1;
+            }
+        }
+    }
+
+    for event in &script.events {
+        if !event.last_opcode.map_or(false, is_terminator) {
+            issues.push(script.issue(
+                event.line,
+                Some(event.id),
+                LintSeverity::Warning,
+                "missing-end",
+                format!("Event #{:04} never reaches <END and falls through.", event.id),
+            ));
+        }
+    }
+
+    script
+}
+
+fn load_script(
+    ctx: &mut Context,
+    constants: &EngineConstants,
+    path: &str,
+    custom_opcodes: &HashSet<String>,
+    issues: &mut Vec<LintIssue>,
+) -> GameResult<Option<ParsedScript>> {
+    let mut file = match filesystem::open_find(ctx, &constants.base_paths, path) {
+        Ok(file) => file,
+        Err(e) => {
+            issues.push(LintIssue {
+                file: path.to_owned(),
+                line: 0,
+                event: None,
+                severity: LintSeverity::Error,
+                code: "missing-file",
+                message: e.to_string(),
+            });
+            return Ok(None);
+        }
+    };
+
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+
+    let (_, header) = prepare_script(&mut buf, constants);
+    let first_line = 1 + buf[..header].iter().filter(|&&b| b == b'\n').count();
+
+    Ok(Some(parse_script(path, &buf[header..], first_line, custom_opcodes, issues)))
+}
+
+/// Checks `Head.tsc`, `ArmsItem.tsc` and the scripts of every stage in the stage table. `custom_opcodes` are the
+/// opcodes registered by Lua scripts, which aren't reported as unknown.
+pub fn lint_scripts(
+    ctx: &mut Context,
+    constants: &EngineConstants,
+    custom_opcodes: &HashSet<String>,
+) -> GameResult<Vec<LintIssue>> {
+    let mut issues = Vec::new();
+    let stages = StageData::load_stage_table(ctx, &constants.base_paths, constants.is_switch)?;
+
+    let head = load_script(ctx, constants, "Head.tsc", custom_opcodes, &mut issues)?;
+    let arms_item = load_script(ctx, constants, "ArmsItem.tsc", custom_opcodes, &mut issues)?;
+
+    let mut stage_scripts = Vec::with_capacity(stages.len());
+    for stage in &stages {
+        if stage.map.is_empty() {
+            stage_scripts.push(None);
+            continue;
+        }
+
+        let path = ["Stage/", &stage.map, ".tsc"].join("");
+        stage_scripts.push(load_script(ctx, constants, &path, custom_opcodes, &mut issues)?);
+    }
+
+    let in_head = |id: u16| head.as_ref().map_or(false, |h| h.has_event(id));
+
+    let mut check_jumps = |script: &ParsedScript, fallback_head: bool, severity: LintSeverity| {
+        for (event, jump) in script.jumps() {
+            match jump.map {
+                Some(map) if map as usize >= stages.len() => {
+                    issues.push(script.issue(
+                        jump.line,
+                        Some(event.id),
+                        LintSeverity::Error,
+                        "invalid-map",
+                        format!("<TRA targets map {} but the stage table has only {} entries.", map, stages.len()),
+                    ));
+                }
+                Some(map) => {
+                    if let Some(target) = &stage_scripts[map as usize] {
+                        if !target.has_event(jump.event) && !in_head(jump.event) {
+                            issues.push(script.issue(
+                                jump.line,
+                                Some(event.id),
+                                LintSeverity::Error,
+                                "undefined-event",
+                                format!("<TRA targets undefined event #{:04} in {}.", jump.event, target.file),
+                            ));
+                        }
+                    }
+                }
+                None => {
+                    if !script.has_event(jump.event) && !(fallback_head && in_head(jump.event)) {
+                        issues.push(script.issue(
+                            jump.line,
+                            Some(event.id),
+                            severity,
+                            "undefined-event",
+                            format!("Jump to undefined event #{:04}.", jump.event),
+                        ));
+                    }
+                }
+            }
+        }
+    };
+
+    // Head.tsc events run within map scripts, so they may legitimately jump to events defined per-stage.
+    if let Some(head) = &head {
+        check_jumps(head, false, LintSeverity::Warning);
+    }
+
+    // The inventory only ever looks up events in ArmsItem.tsc.
+    if let Some(arms_item) = &arms_item {
+        check_jumps(arms_item, false, LintSeverity::Error);
+    }
+
+    for script in stage_scripts.iter().flatten() {
+        check_jumps(script, true, LintSeverity::Error);
+    }
+
+    // Events are considered reachable if anything jumps or transports to them, or if an entity of the map uses them.
+    for (stage_id, script) in stage_scripts.iter().enumerate() {
+        let script = match script {
+            Some(script) => script,
+            None => continue,
+        };
+
+        let mut referenced: HashSet<u16> =
+            script.jumps().map(|(_, j)| j).filter(|j| j.map.is_none()).map(|j| j.event).collect();
+
+        for other in head.iter().chain(stage_scripts.iter().flatten()) {
+            for (_, jump) in other.jumps() {
+                if jump.map == Some(stage_id as u16) || (jump.map.is_none() && other.file == "Head.tsc") {
+                    referenced.insert(jump.event);
+                }
+            }
+        }
+
+        if let Ok(pxe_file) =
+            filesystem::open_find(ctx, &constants.base_paths, ["Stage/", &stages[stage_id].map, ".pxe"].join(""))
+        {
+            if let Ok(npcs) = NPCData::load_from(pxe_file) {
+                referenced.extend(npcs.iter().map(|npc| npc.event_num));
+            }
+        }
+
+        for event in &script.events {
+            if !referenced.contains(&event.id) {
+                issues.push(script.issue(
+                    event.line,
+                    Some(event.id),
+                    LintSeverity::Info,
+                    "unreferenced-event",
+                    format!("Event #{:04} is not referenced by any jump, transport or entity.", event.id),
+                ));
+            }
+        }
+    }
+
+    Ok(issues)
+}
+
+#[test]
+fn lint_parse_test() {
+    let source = b"#0090\n<MNA<CMU0008<FAI0000<END\n#0091\n<EVE0092\n#0092\n<XYZ<FL+0001:0002<TRA0001:0094\n";
+    let mut issues = Vec::new();
+    let script = parse_script("test.tsc", source, 1, &HashSet::new(), &mut issues);
+
+    assert_eq!(script.events.len(), 3);
+    assert_eq!(script.events[1].jumps.len(), 1);
+    assert_eq!(script.events[1].jumps[0].event, 92);
+
+    let codes: Vec<&str> = issues.iter().map(|i| i.code).collect();
+    assert_eq!(codes, vec!["unknown-opcode", "operand-count", "operand-count", "missing-end"]);
+    assert!(issues.iter().all(|i| i.line == 6 || i.event == Some(92)));
+}
+
+#[test]
+fn lint_custom_opcode_test() {
+    let source = b"#0090\n<XYZ0001:0002<MSG<END\n#0091\n<XYZ<NOD<XYZ0003\n";
+    let custom_opcodes: HashSet<String> = ["XYZ".to_owned()].into_iter().collect();
+    let mut issues = Vec::new();
+    parse_script("test.tsc", source, 1, &custom_opcodes, &mut issues);
+
+    let codes: Vec<&str> = issues.iter().map(|i| i.code).collect();
+    assert_eq!(codes, vec!["missing-end"]);
+    assert_eq!(issues[0].event, Some(91));
+}
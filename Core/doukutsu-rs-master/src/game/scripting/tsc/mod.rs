@@ -2,7 +2,9 @@ mod bytecode_utils;
 mod compiler;
 pub mod credit_script;
 mod decompiler;
-mod encryption;
-mod opcodes;
+pub mod encoding;
+pub mod encryption;
+pub mod lint;
+pub mod opcodes;
 mod parse_utils;
 pub mod text_script;
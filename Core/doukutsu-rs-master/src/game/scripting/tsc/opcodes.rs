@@ -12,6 +12,8 @@ pub enum TSCOpCode {
     _STR,
     /// internal: implicit END marker
     _END,
+    /// internal: opcode unknown to the engine, dispatched to a Lua handler
+    _CUS,
 
     // ---- Vanilla opcodes ----
     /// <BOAxxxx, Starts boss animation
@@ -267,6 +269,71 @@ pub enum TSCOpCode {
     // ---- Custom opcodes, for use by modders ----
 }
 
+impl TSCOpCode {
+    /// Number of 4 digit operands the opcode takes in TSC source, must be kept in sync with the compiler.
+    pub fn operand_count(self) -> usize {
+        match self {
+            TSCOpCode::BOA
+            | TSCOpCode::BSL
+            | TSCOpCode::FOM
+            | TSCOpCode::QUA
+            | TSCOpCode::UNI
+            | TSCOpCode::MYB
+            | TSCOpCode::MYD
+            | TSCOpCode::FAI
+            | TSCOpCode::FAO
+            | TSCOpCode::WAI
+            | TSCOpCode::FAC
+            | TSCOpCode::GIT
+            | TSCOpCode::NUM
+            | TSCOpCode::DNA
+            | TSCOpCode::DNP
+            | TSCOpCode::FLm
+            | TSCOpCode::FLp
+            | TSCOpCode::MPp
+            | TSCOpCode::SKm
+            | TSCOpCode::SKp
+            | TSCOpCode::EQp
+            | TSCOpCode::EQm
+            | TSCOpCode::MLp
+            | TSCOpCode::ITp
+            | TSCOpCode::ITm
+            | TSCOpCode::AMm
+            | TSCOpCode::MPJ
+            | TSCOpCode::YNJ
+            | TSCOpCode::EVE
+            | TSCOpCode::XX1
+            | TSCOpCode::SIL
+            | TSCOpCode::LIp
+            | TSCOpCode::SOU
+            | TSCOpCode::CMU
+            | TSCOpCode::SSS
+            | TSCOpCode::ACH
+            | TSCOpCode::S2MV
+            | TSCOpCode::S2PJ
+            | TSCOpCode::PSH => 1,
+            TSCOpCode::FON
+            | TSCOpCode::FOB
+            | TSCOpCode::MOV
+            | TSCOpCode::AMp
+            | TSCOpCode::NCJ
+            | TSCOpCode::ECJ
+            | TSCOpCode::FLJ
+            | TSCOpCode::ITJ
+            | TSCOpCode::SKJ
+            | TSCOpCode::AMJ
+            | TSCOpCode::UNJ
+            | TSCOpCode::SMP
+            | TSCOpCode::PSp
+            | TSCOpCode::IpN
+            | TSCOpCode::FFm => 2,
+            TSCOpCode::ANP | TSCOpCode::CNP | TSCOpCode::INP | TSCOpCode::TAM | TSCOpCode::CMP | TSCOpCode::INJ => 3,
+            TSCOpCode::TRA | TSCOpCode::MNP | TSCOpCode::SNP => 4,
+            _ => 0,
+        }
+    }
+}
+
 #[derive(FromPrimitive, PartialEq, Copy, Clone)]
 pub enum CreditOpCode {
     /// Internal, no operation
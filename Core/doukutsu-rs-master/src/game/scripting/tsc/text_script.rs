@@ -21,7 +21,7 @@ use crate::game::frame::UpdateTarget;
 use crate::game::npc::NPC;
 use crate::game::player::{ControlMode, TargetPlayer};
 use crate::game::scripting::tsc::bytecode_utils::read_cur_varint;
-use crate::game::scripting::tsc::encryption::decrypt_tsc;
+use crate::game::scripting::tsc::encoding::prepare_script;
 use crate::game::scripting::tsc::opcodes::TSCOpCode;
 use crate::game::shared_game_state::ReplayState;
 use crate::game::shared_game_state::SharedGameState;
@@ -43,10 +43,12 @@ bitfield! {
     pub cutscene_skip, set_cutscene_skip: 7;
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
 #[repr(u8)]
 pub enum TextScriptEncoding {
+    #[serde(rename = "utf-8")]
     UTF8 = 0,
+    #[serde(rename = "shift-jis")]
     ShiftJIS,
 }
 
@@ -69,6 +71,22 @@ impl TextScriptEncoding {
 
         encoding != required_encoding
     }
+
+    /// Parses an encoding name as written in `#encoding` directives, returns `None` if it's not supported.
+    pub fn from_name(name: &str) -> Option<TextScriptEncoding> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(TextScriptEncoding::UTF8),
+            "shift-jis" | "shift_jis" | "shiftjis" | "sjis" => Some(TextScriptEncoding::ShiftJIS),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TextScriptEncoding::UTF8 => "utf-8",
+            TextScriptEncoding::ShiftJIS => "shift-jis",
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -327,6 +345,13 @@ impl TextScriptVM {
                 TextScriptExecutionState::Running(event, ip) => {
                     state.control_flags.set_interactions_disabled(true);
 
+                    #[cfg(feature = "scripting-lua")]
+                    {
+                        if ip == 0 {
+                            state.lua.call_event("eventStart", &[event as i32]);
+                        }
+                    }
+
                     // The `!event` case gets optimized out on None match
                     match (cached_event, !event) {
                         (None, bevent) | (Some((bevent, _)), _) if bevent != event => {
@@ -716,6 +741,38 @@ impl TextScriptVM {
                     exec_state = TextScriptExecutionState::Ended;
                 }
             }
+            TSCOpCode::_CUS => {
+                let len = read_cur_varint(&mut cursor)?;
+                let mut name = String::new();
+                for _ in 0..len {
+                    name.push(std::char::from_u32(read_cur_varint(&mut cursor)? as u32).unwrap_or('?'));
+                }
+
+                let count = read_cur_varint(&mut cursor)?;
+                let mut operands = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    operands.push(read_cur_varint(&mut cursor)?);
+                }
+
+                exec_state = TextScriptExecutionState::Running(event, cursor.position() as u32);
+
+                #[allow(unused_mut)]
+                let mut handled = false;
+                #[cfg(feature = "scripting-lua")]
+                {
+                    let (found, jump) = state.lua.try_run_tsc_opcode(&name, &operands);
+                    handled = found;
+
+                    if let Some(event_num) = jump {
+                        state.textscript_vm.clear_text_box();
+                        exec_state = TextScriptExecutionState::Running(event_num, 0);
+                    }
+                }
+
+                if !handled {
+                    log::warn!("No handler registered for opcode <{} {:?}", name, operands);
+                }
+            }
             TSCOpCode::END => {
                 state.textscript_vm.flags.set_cutscene_skip(false);
                 state.control_flags.set_tick_world(true);
@@ -974,7 +1031,7 @@ impl TextScriptVM {
             TSCOpCode::AMJ => {
                 let weapon = read_cur_varint(&mut cursor)? as u8;
                 let event_num = read_cur_varint(&mut cursor)? as u16;
-                let weapon_type: Option<WeaponType> = FromPrimitive::from_u8(weapon);
+                let weapon_type = WeaponType::from_id(weapon as u16, &state.constants);
 
                 if weapon_type.is_some() && game_scene.inventory_player1.has_weapon(weapon_type.unwrap()) {
                     state.textscript_vm.clear_text_box();
@@ -1619,6 +1676,9 @@ impl TextScriptVM {
                     state.mod_requirements.append_item(ctx, item_id)?;
                 }
 
+                #[cfg(feature = "scripting-lua")]
+                state.lua.call_event("itemGet", &[item_id as i32]);
+
                 exec_state = TextScriptExecutionState::Running(event, cursor.position() as u32);
             }
             TSCOpCode::IpN => {
@@ -1637,6 +1697,9 @@ impl TextScriptVM {
                     state.mod_requirements.append_item(ctx, item_id)?;
                 }
 
+                #[cfg(feature = "scripting-lua")]
+                state.lua.call_event("itemGet", &[item_id as i32]);
+
                 exec_state = TextScriptExecutionState::Running(event, cursor.position() as u32);
             }
             TSCOpCode::ITm => {
@@ -1652,7 +1715,7 @@ impl TextScriptVM {
             TSCOpCode::AMp => {
                 let weapon_id = read_cur_varint(&mut cursor)? as u8;
                 let max_ammo = read_cur_varint(&mut cursor)? as u16;
-                let weapon_type: Option<WeaponType> = FromPrimitive::from_u8(weapon_id);
+                let weapon_type = WeaponType::from_id(weapon_id as u16, &state.constants);
 
                 state.textscript_vm.numbers[0] = max_ammo;
 
@@ -1668,7 +1731,7 @@ impl TextScriptVM {
             }
             TSCOpCode::AMm => {
                 let weapon_id = read_cur_varint(&mut cursor)? as u8;
-                let weapon_type: Option<WeaponType> = FromPrimitive::from_u8(weapon_id);
+                let weapon_type = WeaponType::from_id(weapon_id as u16, &state.constants);
 
                 if let Some(wtype) = weapon_type {
                     game_scene.inventory_player1.remove_weapon(wtype);
@@ -1687,8 +1750,8 @@ impl TextScriptVM {
                 let old_weapon_id = read_cur_varint(&mut cursor)? as u8;
                 let new_weapon_id = read_cur_varint(&mut cursor)? as u8;
                 let max_ammo = read_cur_varint(&mut cursor)? as u16;
-                let old_weapon_type: Option<WeaponType> = FromPrimitive::from_u8(old_weapon_id);
-                let new_weapon_type: Option<WeaponType> = FromPrimitive::from_u8(new_weapon_id);
+                let old_weapon_type = WeaponType::from_id(old_weapon_id as u16, &state.constants);
+                let new_weapon_type = WeaponType::from_id(new_weapon_id as u16, &state.constants);
 
                 if let Some(wtype) = new_weapon_type {
                     game_scene.inventory_player1.trade_weapon(old_weapon_type, wtype, max_ammo);
@@ -1900,11 +1963,9 @@ impl TextScript {
         let mut buf = Vec::new();
         data.read_to_end(&mut buf)?;
 
-        if constants.textscript.encrypted {
-            decrypt_tsc(&mut buf);
-        }
+        let (encoding, header) = prepare_script(&mut buf, constants);
 
-        TextScript::compile(&buf, false, constants.textscript.encoding)
+        TextScript::compile(&buf[header..], false, encoding)
     }
 
     pub fn get_event_ids(&self) -> Vec<u16> {
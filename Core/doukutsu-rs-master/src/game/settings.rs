@@ -4,6 +4,7 @@ use crate::framework::filesystem::{user_create, user_open};
 use crate::framework::gamepad::{Axis, AxisDirection, Button, PlayerControllerInputType};
 use crate::framework::graphics::VSyncMode;
 use crate::framework::keyboard::ScanCode;
+use crate::framework::post_process::PostProcessPass;
 use crate::game::player::TargetPlayer;
 use crate::game::shared_game_state::{CutsceneSkipMode, ScreenShakeIntensity, TimingMode, WindowMode};
 use crate::input::combined_player_controller::CombinedPlayerController;
@@ -20,9 +21,15 @@ pub struct Settings {
     #[serde(default = "default_true")]
     pub seasonal_textures: bool,
     pub original_textures: bool,
+    /// Id of the texture pack from the user `texture_packs/` directory layered above game data, if any.
+    #[serde(default)]
+    pub texture_pack: Option<String>,
     pub shader_effects: bool,
     #[serde(default = "default_true")]
     pub light_cone: bool,
+    /// Whether lights shade `.normal` texture layers, if the textures in use have them.
+    #[serde(default = "default_true")]
+    pub normal_mapping: bool,
     #[serde(default = "default_true")]
     pub subpixel_coords: bool,
     #[serde(default = "default_true")]
@@ -41,6 +48,12 @@ pub struct Settings {
     pub pause_on_focus_loss: bool,
     #[serde(default = "default_interpolation")]
     pub organya_interpolation: InterpolationMode,
+    /// Whether underwater and stage effects are applied to the sound.
+    #[serde(default)]
+    pub environmental_audio: bool,
+    /// Whether sound effects are panned by the position of their source, classic behavior plays them all centered.
+    #[serde(default)]
+    pub positional_sfx: bool,
     #[serde(default = "default_p1_controller_type")]
     pub player1_controller_type: ControllerType,
     #[serde(default = "default_p2_controller_type")]
@@ -75,6 +88,9 @@ pub struct Settings {
     pub window_mode: WindowMode,
     #[serde(default = "default_vsync")]
     pub vsync_mode: VSyncMode,
+    /// Shader passes the final frame is rendered through, in order.
+    #[serde(default)]
+    pub post_process: Vec<PostProcessPass>,
     #[serde(default = "default_screen_shake_intensity")]
     pub screen_shake_intensity: ScreenShakeIntensity,
     pub debug_mode: bool,
@@ -87,6 +103,11 @@ pub struct Settings {
     pub discord_rpc: bool,
     #[serde(default = "default_true")]
     pub allow_strafe: bool,
+    /// Load order of the mods installed in the user `mods/` directory.
+    #[serde(default)]
+    pub mod_order: Vec<String>,
+    #[serde(default)]
+    pub disabled_mods: Vec<String>,
 }
 
 fn default_true() -> bool {
@@ -95,7 +116,7 @@ fn default_true() -> bool {
 
 #[inline(always)]
 fn current_version() -> u32 {
-    24
+    29
 }
 
 #[inline(always)]
@@ -347,6 +368,31 @@ impl Settings {
             self.allow_strafe = true;
         }
 
+        if self.version == 24 {
+            self.version = 25;
+            self.environmental_audio = false;
+        }
+
+        if self.version == 25 {
+            self.version = 26;
+            self.positional_sfx = false;
+        }
+
+        if self.version == 26 {
+            self.version = 27;
+            self.post_process = Vec::new();
+        }
+
+        if self.version == 27 {
+            self.version = 28;
+            self.normal_mapping = true;
+        }
+
+        if self.version == 28 {
+            self.version = 29;
+            self.texture_pack = None;
+        }
+
         // Force keyboardless implementations to use controllers regardless of what the settings were initially (in case a portable version was imported)
         #[cfg(any(target_os = "horizon", feature = "backend-libretro"))]
         {
@@ -430,8 +476,10 @@ impl Default for Settings {
             version: current_version(),
             seasonal_textures: true,
             original_textures: false,
+            texture_pack: None,
             shader_effects: false,
             light_cone: true,
+            normal_mapping: true,
             subpixel_coords: true,
             motion_interpolation: true,
             touch_controls: cfg!(all(target_os = "android", not(feature = "backend-libretro"))),//(target_os = "android"),
@@ -442,6 +490,8 @@ impl Default for Settings {
             timing_mode: default_timing(),
             pause_on_focus_loss: default_pause_on_focus_loss(),
             organya_interpolation: InterpolationMode::Linear,
+            environmental_audio: false,
+            positional_sfx: false,
             player1_controller_type: default_p1_controller_type(),
             player2_controller_type: default_p2_controller_type(),
             player1_key_map: p1_default_keymap(),
@@ -460,6 +510,7 @@ impl Default for Settings {
             locale: default_locale(),
             window_mode: WindowMode::Windowed,
             vsync_mode: VSyncMode::VSync,
+            post_process: Vec::new(),
             screen_shake_intensity: ScreenShakeIntensity::Full,
             debug_mode: false,
             noclip: false,
@@ -467,6 +518,8 @@ impl Default for Settings {
             cutscene_skip_mode: CutsceneSkipMode::Hold,
             discord_rpc: true,
             allow_strafe: true,
+            mod_order: Vec::new(),
+            disabled_mods: Vec::new(),
         }
     }
 }
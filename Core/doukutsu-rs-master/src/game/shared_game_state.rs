@@ -1,4 +1,4 @@
-use std::{cmp, ops::Div};
+use std::{cmp, io, ops::Div};
 
 use chrono::{Datelike, Local};
 
@@ -12,6 +12,7 @@ use crate::framework::backend::BackendTexture;
 use crate::framework::context::Context;
 use crate::framework::error::GameResult;
 use crate::framework::graphics::{create_texture_mutable, set_render_target};
+use crate::framework::post_process;
 use crate::framework::vfs::OpenOptions;
 use crate::framework::{filesystem, graphics};
 use crate::game::caret::{Caret, CaretType};
@@ -25,20 +26,23 @@ use crate::game::scripting::tsc::credit_script::{CreditScript, CreditScriptVM};
 use crate::game::scripting::tsc::text_script::{ScriptMode, TextScript, TextScriptEncoding, TextScriptExecutionState, TextScriptVM};
 use crate::game::settings::Settings;
 use crate::game::stage::StageData;
-use crate::graphics::bmfont::BMFont;
+use crate::graphics::font::GameFont;
+use crate::graphics::texture_pack::{TexturePack, TexturePackChanges, TEXTURE_PACKS_DIR};
 use crate::graphics::texture_set::TextureSet;
 use crate::i18n::Locale;
 use crate::input::touch_controls::TouchControls;
 use crate::mod_list::ModList;
+use crate::mod_manager::ModManager;
 use crate::mod_requirements::ModRequirements;
 use crate::scene::game_scene::GameScene;
 use crate::scene::title_scene::TitleScene;
 use crate::scene::Scene;
 use crate::sound::backend::{init_sound_backend, SoundManager};
+use crate::sound::effects::AudioEffectsTable;
 use crate::util::bitvec::BitVec;
 use crate::util::rng::XorShift;
 
-use super::filesystem_container::FilesystemContainer;
+use super::filesystem_container::{create_data_file, FilesystemContainer};
 
 #[derive(PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
 pub enum TimingMode {
@@ -152,11 +156,13 @@ pub struct FontData {
     pub path: String,
     pub scale: f32,
     pub space_offset: f32,
+    /// Size in pixels TrueType/OpenType fonts are rendered at, unused by bitmap fonts.
+    pub size: f32,
 }
 
 impl FontData {
     pub fn new(path: String, scale: f32, space_offset: f32) -> FontData {
-        FontData { path, scale, space_offset }
+        FontData { path, scale, space_offset, size: 12.0 }
     }
 }
 
@@ -310,12 +316,14 @@ pub struct SharedGameState {
     pub touch_controls: TouchControls,
     pub mod_path: Option<String>,
     pub mod_list: ModList,
+    pub mod_manager: ModManager,
     pub npc_table: NPCTable,
     pub npc_super_pos: (i32, i32),
     pub npc_curly_target: (i32, i32),
     pub npc_curly_counter: u16,
     pub water_level: i32,
     pub stages: Vec<StageData>,
+    pub audio_effects: AudioEffectsTable,
     pub frame_time: f64,
     pub debugger: bool,
     pub command_line: bool,
@@ -327,11 +335,13 @@ pub struct SharedGameState {
     pub textscript_vm: TextScriptVM,
     pub creditscript_vm: CreditScriptVM,
     pub lightmap_canvas: Option<Box<dyn BackendTexture>>,
+    /// Normal map layers of the visible textures, only created if the renderer can shade them.
+    pub normal_canvas: Option<Box<dyn BackendTexture>>,
     pub season: Season,
     pub menu_character: MenuCharacter,
     pub fs_container: Option<FilesystemContainer>,
     pub constants: EngineConstants,
-    pub font: BMFont,
+    pub font: GameFont,
     pub texture_set: TextureSet,
     #[cfg(feature = "scripting-lua")]
     pub lua: LuaScriptingState,
@@ -350,6 +360,8 @@ pub struct SharedGameState {
     #[cfg(feature = "discord-rpc")]
     pub discord_rpc: DiscordRPC,
     pub shutdown: bool,
+    /// Set when the locale changes, the global scripts are reloaded by the next loaded stage.
+    pub global_scripts_outdated: bool,
 }
 
 impl SharedGameState {
@@ -418,6 +430,8 @@ impl SharedGameState {
         constants.rebuild_path_list(None, season, &settings);
 
         constants.load_locales(ctx)?;
+        // fallback chains are only known once the locales are loaded
+        constants.rebuild_text_script_paths(&settings);
 
         let locale = SharedGameState::get_locale(&constants, &settings.locale).unwrap_or_default();
         if (locale.code == "jp" || locale.code == "en") && constants.is_base() {
@@ -426,12 +440,13 @@ impl SharedGameState {
             constants.textscript.encoding =  TextScriptEncoding::UTF8
         }
         
-        let font = BMFont::load(&constants.base_paths, &locale.font.path, ctx, locale.font.scale).or_else(|e| {
+        let font = GameFont::load(&constants.base_paths, &locale.font, ctx).or_else(|e| {
             log::warn!("Failed to load font, using built-in: {}", e);
-            BMFont::load(&vec!["/".to_owned()], "builtin/builtin_font.fnt", ctx, 1.0)
+            GameFont::load_builtin(ctx)
         })?;
 
         let mod_list = ModList::load(ctx, &constants.string_table)?;
+        let mod_manager = ModManager::discover(ctx, &settings);
 
         for i in 0..0xffu8 {
             let path = format!("pxt/fx{:02x}.pxt", i);
@@ -478,12 +493,14 @@ impl SharedGameState {
             touch_controls: TouchControls::new(),
             mod_path: None,
             mod_list,
+            mod_manager,
             npc_table: NPCTable::new(),
             npc_super_pos: (0, 0),
             npc_curly_target: (0, 0),
             npc_curly_counter: 0,
             water_level: 0,
             stages: Vec::with_capacity(96),
+            audio_effects: AudioEffectsTable::new(),
             frame_time: 0.0,
             debugger: false,
             command_line: false,
@@ -495,6 +512,7 @@ impl SharedGameState {
             textscript_vm: TextScriptVM::new(),
             creditscript_vm: CreditScriptVM::new(),
             lightmap_canvas: None,
+            normal_canvas: None,
             season,
             menu_character: MenuCharacter::Quote,
             fs_container: None,
@@ -518,11 +536,13 @@ impl SharedGameState {
             #[cfg(feature = "discord-rpc")]
             discord_rpc: DiscordRPC::new(discord_rpc_app_id),
             shutdown: false,
+            global_scripts_outdated: false,
         })
     }
 
     pub fn reload_resources(&mut self, ctx: &mut Context) -> GameResult {
         self.constants.rebuild_path_list(self.mod_path.clone(), self.season, &self.settings);
+        self.constants.revert_constant_json_files();
         if !self.constants.is_demo {
             //TODO find a more elegant way to handle this
             self.constants.special_treatment_for_csplus_mods(self.mod_path.as_ref());
@@ -530,44 +550,100 @@ impl SharedGameState {
         self.constants.load_csplus_tables(ctx)?;
         self.constants.load_animated_faces(ctx)?;
         self.constants.load_texture_size_hints(ctx)?;
+        self.constants.apply_constant_json_files(ctx)?;
+        self.constants.load_custom_weapons(ctx)?;
         let stages = StageData::load_stage_table(ctx, &self.constants.base_paths, self.constants.is_switch)?;
         self.stages = stages;
+        self.audio_effects = AudioEffectsTable::load(ctx, &self.constants.base_paths)?;
 
         let npc_tbl = filesystem::open_find(ctx, &self.constants.base_paths, "npc.tbl")?;
-        let npc_table = NPCTable::load_from(npc_tbl)?;
+        let mut npc_table = NPCTable::load_from(npc_tbl)?;
+        npc_table.load_custom_npcs(ctx, &self.constants.base_paths)?;
         self.npc_table = npc_table;
 
-        let head_tsc = filesystem::open_find(ctx, &self.constants.base_paths, "Head.tsc")?;
+        self.reload_global_scripts(ctx)?;
+
+        self.texture_set.unload_all();
+        self.load_texture_pack();
+
+        self.sound_manager.load_custom_sound_effects(ctx, &self.constants.base_paths)?;
+
+        Ok(())
+    }
+
+    /// Loads the scripts used across stages: `Head.tsc`, `ArmsItem.tsc`, `StageSelect.tsc` and `Credit.tsc`.
+    pub fn reload_global_scripts(&mut self, ctx: &mut Context) -> GameResult {
+        let head_tsc = filesystem::open_find(ctx, &self.constants.text_script_paths, "Head.tsc")?;
         let head_script = TextScript::load_from(head_tsc, &self.constants)?;
         self.textscript_vm.set_global_script(head_script);
 
-        let arms_item_tsc = filesystem::open_find(ctx, &self.constants.base_paths, "ArmsItem.tsc")?;
+        let arms_item_tsc = filesystem::open_find(ctx, &self.constants.text_script_paths, "ArmsItem.tsc")?;
         let arms_item_script = TextScript::load_from(arms_item_tsc, &self.constants)?;
         self.textscript_vm.set_inventory_script(arms_item_script);
 
-        let stage_select_tsc = filesystem::open_find(ctx, &self.constants.base_paths, "StageSelect.tsc")?;
+        let stage_select_tsc = filesystem::open_find(ctx, &self.constants.text_script_paths, "StageSelect.tsc")?;
         let stage_select_script = TextScript::load_from(stage_select_tsc, &self.constants)?;
         self.textscript_vm.set_stage_select_script(stage_select_script);
 
         let substitution_rect_map = [('=', self.constants.textscript.textbox_item_marker_rect)];
         self.textscript_vm.set_substitution_rect_map(substitution_rect_map);
 
-        if filesystem::exists_find(ctx, &self.constants.base_paths, "Credit.tsc") {
-            let credit_tsc = filesystem::open_find(ctx, &self.constants.base_paths, "Credit.tsc")?;
+        if filesystem::exists_find(ctx, &self.constants.text_script_paths, "Credit.tsc") {
+            let credit_tsc = filesystem::open_find(ctx, &self.constants.text_script_paths, "Credit.tsc")?;
             let credit_script = CreditScript::load_from(credit_tsc, &self.constants)?;
             self.creditscript_vm.set_script(credit_script);
         }
 
-        self.texture_set.unload_all();
-
-        self.sound_manager.load_custom_sound_effects(ctx, &self.constants.base_paths)?;
+        self.global_scripts_outdated = false;
 
         Ok(())
     }
 
+    /// Mounts the enabled mods from the user `mods/` directory, call `reload_resources` afterwards.
+    pub fn mount_mods(&mut self, ctx: &mut Context) {
+        if let Some(fs_container) = &self.fs_container {
+            let mods_path = fs_container.user_path.join("mods");
+            self.mod_manager.mount(ctx, &mods_path);
+            fs_container.mount_editor_data(ctx);
+        }
+
+        // mounted mods can ship challenges in their own mods.txt
+        match ModList::load(ctx, &self.constants.string_table) {
+            Ok(mod_list) => self.mod_list = mod_list,
+            Err(e) => log::warn!("Failed to load mods.txt: {}", e),
+        }
+    }
+
     pub fn reload_graphics(&mut self) {
         self.constants.rebuild_path_list(self.mod_path.clone(), self.season, &self.settings);
         self.texture_set.unload_all();
+        self.load_texture_pack();
+    }
+
+    /// Mounts the texture pack selected in settings from the user `texture_packs/` directory.
+    fn load_texture_pack(&mut self) {
+        self.texture_set.pack = match (&self.settings.texture_pack, &self.fs_container) {
+            (Some(id), Some(fs_container)) => {
+                let root = fs_container.user_path.join(TEXTURE_PACKS_DIR.trim_start_matches('/')).join(id);
+                match TexturePack::load(id, &root) {
+                    Ok(pack) => Some(pack),
+                    Err(e) => {
+                        log::warn!("Failed to load texture pack {}: {}", id, e);
+                        // keep the already mounted version of the pack, so it's still watched for a fixed manifest
+                        self.texture_set.pack.take().filter(|pack| pack.id == *id)
+                    }
+                }
+            }
+            _ => None,
+        };
+    }
+
+    /// Reloads the textures changed in the mounted texture pack, called every frame.
+    pub fn poll_texture_pack(&mut self) {
+        if let TexturePackChanges::Manifest = self.texture_set.poll_pack() {
+            log::info!("Texture pack manifest changed, reloading graphics.");
+            self.reload_graphics();
+        }
     }
 
     pub fn update_locale(&mut self, ctx: &mut Context) {
@@ -580,14 +656,19 @@ impl SharedGameState {
             }
         }
 
-        let font = BMFont::load(&self.constants.base_paths, &self.loc.font.path, ctx, self.loc.font.scale)
+        let font = GameFont::load(&self.constants.base_paths, &self.loc.font, ctx)
             .or_else(|e| {
                 log::warn!("Failed to load font, using built-in: {}", e);
-                BMFont::load(&vec!["/".to_owned()], "builtin/builtin_font.fnt", ctx, 1.0)
+                GameFont::load_builtin(ctx)
             })
             .unwrap();
 
         self.font = font;
+
+        // scripts are translated through per-locale overlays, they're switched on the next stage load along with the
+        // stage script so a running game never mixes scripts of two locales
+        self.constants.rebuild_path_list(self.mod_path.clone(), self.season, &self.settings);
+        self.global_scripts_outdated = true;
     }
 
     pub fn graphics_reset(&mut self) {
@@ -721,10 +802,26 @@ impl SharedGameState {
         // ensure no texture is bound before destroying them.
         set_render_target(ctx, None)?;
         self.lightmap_canvas = Some(create_texture_mutable(ctx, width, height)?);
+        self.normal_canvas = if graphics::supports_normal_lighting(ctx) {
+            Some(create_texture_mutable(ctx, width, height)?)
+        } else {
+            None
+        };
+
+        self.apply_post_process(ctx);
 
         Ok(())
     }
 
+    /// Hands the post-process chain from settings to the renderer, failing passes are logged and left out.
+    pub fn apply_post_process(&self, ctx: &mut Context) {
+        let shaders = post_process::load_chain(ctx, &self.constants.base_paths, &self.settings.post_process);
+
+        if let Err(err) = graphics::set_post_process(ctx, &shaders, self.scale) {
+            log::error!("Failed to set up post-processing: {}", err);
+        }
+    }
+
     pub fn tick_carets(&mut self) {
         for caret in &mut self.carets {
             caret.tick(&self.effect_rng, &self.constants);
@@ -853,6 +950,12 @@ impl SharedGameState {
         Ok(())
     }
 
+    /// Opens a data file (eg. `Stage/Cave.tsc`) for writing. Saved files go into the editor data directory, which
+    /// is mounted in front of game data and mods.
+    pub fn create_data_file(&self, ctx: &mut Context, path: &str) -> GameResult<Box<dyn io::Write>> {
+        Ok(Box::new(create_data_file(ctx, self.mod_path.as_deref(), path)?))
+    }
+
     pub fn get_damage(&self, hp: i32) -> i32 {
         match self.difficulty {
             GameDifficulty::Easy => cmp::max(hp / 2, 1),
@@ -881,23 +984,14 @@ impl SharedGameState {
     }
 
     fn get_locale(constants: &EngineConstants, user_locale: &str) -> Option<Locale> {
-        let mut out_locale = None;
-
-        for locale in &constants.locales {
-            if locale.code == "en" {
-                out_locale = Some(locale.clone());
-            }
-
-            if locale.code == user_locale {
-                out_locale = Some(locale.clone());
-                break;
-            }
-        }
-
-        out_locale
+        Locale::resolve(&constants.locales, user_locale)
     }
 
     pub fn tt(&self, key: &str, args: &[(&str, &str)]) -> String {
         return self.loc.tt(key, args);
     }
+
+    pub fn tn(&self, key: &str, count: i64, args: &[(&str, &str)]) -> String {
+        return self.loc.tn(key, count, args);
+    }
 }
@@ -1,4 +1,5 @@
-use std::io::{Cursor, Read};
+use std::io;
+use std::io::{Cursor, Read, Write};
 use std::str::from_utf8;
 
 use byteorder::LE;
@@ -163,6 +164,23 @@ impl From<u8> for PxPackScroll {
     }
 }
 
+impl From<PxPackScroll> for u8 {
+    fn from(val: PxPackScroll) -> Self {
+        match val {
+            PxPackScroll::Normal => 0,
+            PxPackScroll::ThreeQuarters => 1,
+            PxPackScroll::Half => 2,
+            PxPackScroll::Quarter => 3,
+            PxPackScroll::Eighth => 4,
+            PxPackScroll::Zero => 5,
+            PxPackScroll::HThreeQuarters => 6,
+            PxPackScroll::HHalf => 7,
+            PxPackScroll::HQuarter => 8,
+            PxPackScroll::V0Half => 9,
+        }
+    }
+}
+
 impl PxPackScroll {
     pub fn transform_camera_pos(self, x: f32, y: f32) -> (f32, f32) {
         match self {
@@ -193,6 +211,30 @@ pub struct PxPackStageData {
     pub size_bg: (u16, u16),
     pub offset_mg: u32,
     pub offset_bg: u32,
+    /// Names of the neighbouring maps (left, right, up, down), unused by the engine but kept for saving.
+    pub neighbour_maps: [String; 4],
+    /// Name of the spritesheet, unused by the engine but kept for saving.
+    pub spritesheet: String,
+    /// Unknown header bytes following the spritesheet name.
+    pub header_unknown: [u8; 5],
+    /// Per-layer visibility bytes stored next to tileset names.
+    pub visibility: [u8; 3],
+    /// Whether the map name was empty in the file (and taken from the stage table instead).
+    pub empty_name: bool,
+    /// Whether the tileset names were empty in the file (and taken from the stage table instead).
+    pub empty_tilesets: [bool; 3],
+    /// Tile attribute file the map was loaded with.
+    pub attrib_file: PxPackAttribFile,
+}
+
+#[derive(Debug, Clone)]
+pub enum PxPackAttribFile {
+    /// No attribute file was found, nothing is written back on save.
+    None,
+    /// Cave Story `.pxa` file.
+    Pxa,
+    /// Kero Blaster `.pxattr` file, kept as loaded since converting it to Cave Story attributes is lossy.
+    PxAttr(Vec<u8>),
 }
 
 #[derive(Debug)]
@@ -584,6 +626,61 @@ impl Stage {
         Ok(npc_data)
     }
 
+    /// Writes the map and its tile attributes back in the format they were loaded from.
+    /// `create` receives a path relative to the data root (eg. `Stage/Cave.pxm`) and opens it for writing.
+    pub fn save(&self, create: &mut dyn FnMut(&str) -> GameResult<Box<dyn io::Write>>) -> GameResult {
+        if let Some(pxpack_data) = &self.data.pxpack_data {
+            let map_file = create(&["Stage/", &self.data.map, ".pxpack"].join(""))?;
+            self.map.write_pxpack(map_file, &self.data)?;
+
+            match &pxpack_data.attrib_file {
+                PxPackAttribFile::None => {}
+                PxPackAttribFile::Pxa => {
+                    let attrib_file = create(&["Stage/", &pxpack_data.tileset_fg, ".pxa"].join(""))?;
+                    self.map.write_pxa(attrib_file)?;
+                }
+                PxPackAttribFile::PxAttr(raw_data) => {
+                    let mut attrib_file = create(&["Stage/", &pxpack_data.tileset_fg, ".pxattr"].join(""))?;
+                    attrib_file.write_all(raw_data)?;
+                }
+            }
+        } else {
+            let map_file = create(&["Stage/", &self.data.map, ".pxm"].join(""))?;
+            self.map.write_pxm(map_file)?;
+
+            let attrib_file = create(&["Stage/", &self.data.tileset.name, ".pxa"].join(""))?;
+            self.map.write_pxa(attrib_file)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the entity list into the stage's `.pxe` file, see [`Stage::save`].
+    pub fn save_npcs(
+        &self,
+        npcs: &[NPCData],
+        create: &mut dyn FnMut(&str) -> GameResult<Box<dyn io::Write>>,
+    ) -> GameResult {
+        let pxe_file = create(&["Stage/", &self.data.map, ".pxe"].join(""))?;
+        NPCData::write_to(npcs, pxe_file)?;
+
+        Ok(())
+    }
+
+    /// Resizes the foreground layer and moves its contents by given offset (in tiles).
+    pub fn resize(&mut self, width: u16, height: u16, offset_x: i32, offset_y: i32) {
+        let old_size = self.map.width as u32 * self.map.height as u32;
+        let new_size = width as u32 * height as u32;
+
+        self.map.resize(width, height, offset_x, offset_y);
+
+        if let Some(pxpack_data) = &mut self.data.pxpack_data {
+            pxpack_data.size_fg = (width, height);
+            pxpack_data.offset_mg = pxpack_data.offset_mg - old_size + new_size;
+            pxpack_data.offset_bg = pxpack_data.offset_bg - old_size + new_size;
+        }
+    }
+
     /// Returns map tile from foreground layer.
     pub fn tile_at(&self, x: usize, y: usize) -> u8 {
         if let Some(&tile) = self.map.tiles.get(y.wrapping_mul(self.map.width as usize).wrapping_add(x)) {
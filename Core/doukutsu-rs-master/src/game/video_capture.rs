@@ -0,0 +1,189 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::framework::context::Context;
+use crate::framework::error::GameResult;
+use crate::framework::graphics;
+use crate::sound::backend_libretro::Runner;
+use crate::sound::wav::{WavFormat, WavWriter};
+
+/// Makes the game mix its audio into a `Runner` handed back here, instead of playing it on an output device.
+pub struct AudioCapture {
+    pub sample_rate: u32,
+    pub runner: Option<Runner>,
+}
+
+/// Writes frames as a YUV4MPEG2 stream with full range 4:4:4 chroma, which keeps the game's pixel art intact.
+pub struct Y4mWriter<W: Write> {
+    f: W,
+    width: usize,
+    height: usize,
+    planes: Vec<u8>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(mut f: W, width: u16, height: u16, fps: usize) -> io::Result<Y4mWriter<W>> {
+        writeln!(f, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444 XCOLORRANGE=FULL", width, height, fps)?;
+
+        Ok(Y4mWriter { f, width: width as usize, height: height as usize, planes: Vec::new() })
+    }
+
+    /// Writes a frame of RGBA pixels, top row first. Frames of another size are scaled to fit the stream.
+    pub fn write_frame(&mut self, width: usize, height: usize, rgba: &[u8]) -> io::Result<()> {
+        let size = self.width * self.height;
+        self.planes.clear();
+        self.planes.resize(size * 3, 0);
+
+        if width > 0 && height > 0 {
+            for y in 0..self.height {
+                let src_y = y * height / self.height;
+
+                for x in 0..self.width {
+                    let src_x = x * width / self.width;
+                    let i = (src_y * width + src_x) * 4;
+                    let [luma, cb, cr] = rgb_to_ycbcr(rgba[i], rgba[i + 1], rgba[i + 2]);
+
+                    let pos = y * self.width + x;
+                    self.planes[pos] = luma;
+                    self.planes[size + pos] = cb;
+                    self.planes[size * 2 + pos] = cr;
+                }
+            }
+        }
+
+        self.f.write_all(b"FRAME\n")?;
+        self.f.write_all(&self.planes)
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.f.flush()?;
+
+        Ok(self.f)
+    }
+}
+
+/// Full range BT.601 conversion, the same one JPEG uses.
+fn rgb_to_ycbcr(r: u8, g: u8, b: u8) -> [u8; 3] {
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+
+    let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+    let cb = 128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b;
+    let cr = 128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b;
+
+    [luma, cb, cr].map(|c| c.round().clamp(0.0, 255.0) as u8)
+}
+
+/// Records each rendered frame and the audio mixed during its tick to `<path>.y4m` and `<path>.wav`.
+///
+/// `Game` drives the capture one frame at a time regardless of the wall clock, so the same replay always gives the same
+/// output. Each frame runs as many ticks as live playback would in `1 / tps` seconds at the current game speed, so
+/// skipped cutscenes pass as quickly as they do in game. The audio comes from a `Runner` owned by the capture and is split at exact tick boundaries, the video stream
+/// is created once the size of the first frame is known.
+pub struct VideoCapture {
+    path: PathBuf,
+    tps: usize,
+    sample_rate: u32,
+    video: Option<Y4mWriter<BufWriter<File>>>,
+    audio: WavWriter<BufWriter<File>>,
+    runner: Runner,
+    ticks: u64,
+    /// Fraction of a tick carried over to the next frame at speeds that aren't whole numbers.
+    pending_ticks: f64,
+}
+
+impl VideoCapture {
+    pub fn create(path: &Path, tps: usize, sample_rate: u32, runner: Runner) -> GameResult<VideoCapture> {
+        let format = WavFormat { channels: 2, sample_rate, bit_depth: 16 };
+        let audio = WavWriter::new(BufWriter::new(File::create(path.with_extension("wav"))?), format)?;
+
+        log::info!("Capturing video to {:?} at {} fps.", path, tps);
+
+        Ok(VideoCapture {
+            path: path.to_path_buf(),
+            tps,
+            sample_rate,
+            video: None,
+            audio,
+            runner,
+            ticks: 0,
+            pending_ticks: 0.0,
+        })
+    }
+
+    /// Returns the number of game ticks to run before the next frame at given game speed.
+    pub fn ticks_per_frame(&mut self, speed: f64) -> u32 {
+        self.pending_ticks += speed;
+        let ticks = self.pending_ticks.floor();
+        self.pending_ticks -= ticks;
+
+        ticks as u32
+    }
+
+    /// Records the frame drawn so far, along with the audio of the tick it shows.
+    pub fn capture_tick(&mut self, ctx: &mut Context) -> GameResult {
+        let (width, height, pixels) = graphics::capture_frame(ctx)?;
+
+        let video = match &mut self.video {
+            Some(video) => video,
+            None => {
+                let file = BufWriter::new(File::create(self.path.with_extension("y4m"))?);
+                self.video.insert(Y4mWriter::new(file, width, height, self.tps)?)
+            }
+        };
+        video.write_frame(width as usize, height as usize, &pixels)?;
+
+        // computed from the total so ticks that don't divide the sample rate evenly don't drift
+        let start = self.ticks * self.sample_rate as u64 / self.tps as u64;
+        self.ticks += 1;
+        let end = self.ticks * self.sample_rate as u64 / self.tps as u64;
+
+        let samples = self.runner.render((end - start) as usize);
+        self.audio.write_samples(samples)?;
+
+        Ok(())
+    }
+
+    /// Flushes both streams, returns the number of captured frames.
+    pub fn finish(self) -> GameResult<u64> {
+        if let Some(video) = self.video {
+            video.finish()?;
+        }
+        self.audio.finish()?;
+
+        log::info!("Captured {} frames to {:?}.", self.ticks, self.path);
+
+        Ok(self.ticks)
+    }
+}
+
+#[test]
+fn test_capture_writers() {
+    use std::io::Cursor;
+
+    use crate::sound::wav::WavSample;
+
+    assert_eq!(rgb_to_ycbcr(0, 0, 0), [0, 128, 128]);
+    assert_eq!(rgb_to_ycbcr(255, 255, 255), [255, 128, 128]);
+    assert_eq!(rgb_to_ycbcr(255, 0, 0), [76, 85, 255]);
+
+    // a 1x2 frame is stretched over a 2x2 stream
+    let mut y4m = Y4mWriter::new(Vec::new(), 2, 2, 50).unwrap();
+    y4m.write_frame(1, 2, &[255, 255, 255, 255, 0, 0, 0, 255]).unwrap();
+    let data = y4m.finish().unwrap();
+
+    let header = b"YUV4MPEG2 W2 H2 F50:1 Ip A1:1 C444 XCOLORRANGE=FULL\nFRAME\n";
+    assert_eq!(&data[..header.len()], header);
+    assert_eq!(&data[header.len()..], &[255, 255, 0, 0, 128, 128, 128, 128, 128, 128, 128, 128]);
+
+    let format = WavFormat { channels: 2, sample_rate: 44100, bit_depth: 16 };
+    let mut wav = WavWriter::new(Cursor::new(Vec::new()), format).unwrap();
+    wav.write_samples(&[1, -1]).unwrap();
+    wav.write_samples(&[0x1234, -0x1234]).unwrap();
+    let data = wav.finish().unwrap().into_inner();
+
+    let sample = WavSample::read_from(&data[..]).unwrap();
+    assert_eq!(sample.format, format);
+    assert_eq!(sample.data, [1, 0, 0xff, 0xff, 0x34, 0x12, 0xcc, 0xed]);
+}
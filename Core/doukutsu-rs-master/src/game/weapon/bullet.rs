@@ -388,7 +388,7 @@ impl Bullet {
         {
             self.cond.set_alive(false);
             state.create_caret(self.x, self.y, CaretType::ProjectileDissipation, Direction::Left);
-            state.sound_manager.play_sfx(28);
+            state.sound_manager.play_sfx_at(28, self.x, self.y);
             return;
         }
 
@@ -448,7 +448,7 @@ impl Bullet {
             self.y += self.vel_y;
 
             if self.flags.hit_left_wall() || self.flags.hit_right_wall() || self.flags.hit_bottom_wall() {
-                state.sound_manager.play_sfx(34);
+                state.sound_manager.play_sfx_at(34, self.x, self.y);
             }
         }
 
@@ -707,7 +707,7 @@ impl Bullet {
                 _ => 0,
             };
 
-            state.sound_manager.play_sfx(44);
+            state.sound_manager.play_sfx_at(44, self.x, self.y);
         }
 
         if self.action_counter % 3 == 0 {
@@ -854,7 +854,7 @@ impl Bullet {
         if self.action_counter > 100 || !player.controller.shoot() {
             self.cond.set_alive(false);
             state.create_caret(self.x, self.y, CaretType::ProjectileDissipation, Direction::Left);
-            state.sound_manager.play_sfx(100);
+            state.sound_manager.play_sfx_at(100, self.x, self.y);
 
             match () {
                 _ if player.up => {
@@ -1030,7 +1030,7 @@ impl Bullet {
         }
 
         if self.action_counter % 5 == 1 {
-            state.sound_manager.play_sfx(34);
+            state.sound_manager.play_sfx_at(34, self.x, self.y);
         }
 
         if self.action_num == 0 {
@@ -1075,7 +1075,7 @@ impl Bullet {
         }
 
         if self.action_counter % 7 == 1 {
-            state.sound_manager.play_sfx(106);
+            state.sound_manager.play_sfx_at(106, self.x, self.y);
         }
 
         if self.action_num == 0 {
@@ -1133,7 +1133,7 @@ impl Bullet {
 
                 self.action_counter += 1;
                 if self.action_counter % 4 == 1 {
-                    state.sound_manager.play_sfx(106);
+                    state.sound_manager.play_sfx_at(106, self.x, self.y);
 
                     self.counter1 += 1;
                     let direction = if self.counter1 % 2 != 0 { Direction::Left } else { Direction::Right };
@@ -1158,7 +1158,7 @@ impl Bullet {
 
                 self.action_counter += 1;
                 if self.rng.range(-1..1) == 0 {
-                    state.sound_manager.play_sfx(106);
+                    state.sound_manager.play_sfx_at(106, self.x, self.y);
 
                     let x = self.rng.range(-64..64) * 0x200 + self.x;
                     let y = self.rng.range(-64..64) * 0x200 + self.y;
@@ -1347,7 +1347,7 @@ impl Bullet {
                 _ => 0,
             };
 
-            state.sound_manager.play_sfx(44);
+            state.sound_manager.play_sfx_at(44, self.x, self.y);
         }
 
         if self.action_counter % 3 == 0 {
@@ -1693,7 +1693,7 @@ impl Bullet {
         match self.btype {
             // spur is a special case
             37 | 38 | 39 => state.create_caret(self.x, self.y, CaretType::ProjectileDissipation, Direction::Up),
-            _ => state.sound_manager.play_sfx(28),
+            _ => state.sound_manager.play_sfx_at(28, self.x, self.y),
         }
 
         self.cond.set_alive(false);
@@ -1948,7 +1948,7 @@ impl PhysicalEntity for Bullet {
                         }
 
                         state.create_caret(self.x, self.y, CaretType::ProjectileDissipation, Direction::Left);
-                        state.sound_manager.play_sfx(12);
+                        state.sound_manager.play_sfx_at(12, self.x, self.y);
 
                         let mut npc = NPC::create(4, &state.npc_table);
                         npc.cond.set_alive(true);
@@ -0,0 +1,254 @@
+use crate::common::{Direction, Rect};
+use crate::engine_constants::EngineConstants;
+use crate::game::caret::CaretType;
+use crate::game::player::{Player, TargetPlayer};
+use crate::game::shared_game_state::SharedGameState;
+use crate::game::weapon::bullet::BulletManager;
+use crate::game::weapon::{Weapon, WeaponLevel};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomWeaponFireMode {
+    /// One volley per press of the shoot button.
+    Single,
+    /// Fires every `fire_rate` ticks while the shoot button is held.
+    Auto,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomWeaponPattern {
+    /// `shots` parallel bullets fired in the direction the player is facing.
+    Straight,
+    /// Bullets are spawned by the handler registered with `doukutsu.setWeaponHandler`.
+    Lua,
+}
+
+/// Weapon definition loaded from `weapons.json`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct CustomWeapon {
+    #[serde(default)]
+    pub name: String,
+    /// Bullet type fired at each level, indexes `bullet_table`.
+    pub bullets: [u16; 3],
+    /// Experience needed to advance past each level.
+    pub level_exp: [u16; 3],
+    #[serde(default = "default_fire_mode")]
+    pub fire_mode: CustomWeaponFireMode,
+    #[serde(default = "default_fire_rate")]
+    pub fire_rate: u16,
+    /// Maximum number of this weapon's bullets on screen at once.
+    #[serde(default = "default_max_bullets")]
+    pub max_bullets: u16,
+    #[serde(default = "default_fire_pattern")]
+    pub fire_pattern: CustomWeaponPattern,
+    #[serde(default = "default_shots")]
+    pub shots: u16,
+    /// Distance between parallel shots, in pixels.
+    #[serde(default = "default_spacing")]
+    pub spacing: u16,
+    #[serde(default = "default_ammo_cost")]
+    pub ammo_cost: u16,
+    /// Ticks between refilling one unit of ammo while not shooting, 0 disables regeneration.
+    #[serde(default)]
+    pub ammo_regen: u16,
+    /// Sound effect played when firing at each level.
+    #[serde(default = "default_sounds")]
+    pub sounds: [u8; 3],
+    /// Icon rect in `ArmsImage`, defaults to the slot matching the weapon id.
+    #[serde(default)]
+    pub icon_rect: Option<Rect<u16>>,
+    /// Top left corner of the 24x96 block in `Arms` holding the sprites of the weapon.
+    #[serde(default)]
+    pub sprite_origin: Option<(u16, u16)>,
+}
+
+fn default_fire_mode() -> CustomWeaponFireMode {
+    CustomWeaponFireMode::Single
+}
+
+fn default_fire_rate() -> u16 {
+    6
+}
+
+fn default_max_bullets() -> u16 {
+    2
+}
+
+fn default_fire_pattern() -> CustomWeaponPattern {
+    CustomWeaponPattern::Straight
+}
+
+fn default_shots() -> u16 {
+    1
+}
+
+fn default_spacing() -> u16 {
+    4
+}
+
+fn default_ammo_cost() -> u16 {
+    1
+}
+
+fn default_sounds() -> [u8; 3] {
+    [32, 32, 32]
+}
+
+/// Icon of a weapon in `ArmsImage`.
+pub fn weapon_icon_rect(constants: &EngineConstants, id: u8) -> Rect<u16> {
+    if let Some(rect) = constants.custom_weapons.get(&id).and_then(|w| w.icon_rect) {
+        return rect;
+    }
+
+    let left = (id as u16 % 16) * 16;
+    let top = (id as u16 / 16) * 16;
+
+    Rect { left, top, right: left + 16, bottom: top + 16 }
+}
+
+/// Top left corner of the sprites of a weapon in `Arms`.
+pub fn weapon_sprite_origin(constants: &EngineConstants, id: u8) -> (u16, u16) {
+    if let Some(origin) = constants.custom_weapons.get(&id).and_then(|w| w.sprite_origin) {
+        return origin;
+    }
+
+    ((id as u16 % 13) * 24, (id as u16 / 13) * 96)
+}
+
+impl Weapon {
+    pub(crate) fn tick_custom(
+        &mut self,
+        id: u8,
+        player: &mut Player,
+        player_id: TargetPlayer,
+        bullet_manager: &mut BulletManager,
+        state: &mut SharedGameState,
+    ) {
+        let def = match state.constants.custom_weapons.get(&id) {
+            Some(def) => def.clone(),
+            None => return,
+        };
+
+        let level_idx = match self.level {
+            WeaponLevel::Level1 => 0,
+            WeaponLevel::Level2 => 1,
+            WeaponLevel::Level3 => 2,
+            WeaponLevel::None => unreachable!(),
+        };
+
+        match def.fire_mode {
+            CustomWeaponFireMode::Single => {
+                if !player.controller.shoot() {
+                    self.tick_custom_regen(&def);
+                }
+
+                if !player.controller.trigger_shoot() {
+                    return;
+                }
+            }
+            CustomWeaponFireMode::Auto => {
+                // self.counter1 : autofire counter
+                if !player.controller.shoot() {
+                    self.counter1 = def.fire_rate;
+                    self.tick_custom_regen(&def);
+                    return;
+                }
+
+                self.counter1 += 1;
+                if self.counter1 < def.fire_rate {
+                    return;
+                }
+                self.counter1 = 0;
+            }
+        }
+
+        if bullet_manager.count_bullets_multi(&def.bullets, player_id) >= def.max_bullets as usize {
+            return;
+        }
+
+        if !self.consume_ammo(def.ammo_cost) {
+            self.draw_empty(state, player.x, player.y);
+            return;
+        }
+
+        match def.fire_pattern {
+            CustomWeaponPattern::Straight => {
+                let btype = def.bullets[level_idx];
+                let shots = def.shots.max(1) as i32;
+
+                let (x, y, direction) = match player.direction {
+                    Direction::Left if player.up => (player.x - 0x200, player.y - 0x1000, Direction::Up),
+                    Direction::Right if player.up => (player.x + 0x200, player.y - 0x1000, Direction::Up),
+                    Direction::Left if player.down => (player.x - 0x200, player.y + 0x1000, Direction::Bottom),
+                    Direction::Right if player.down => (player.x + 0x200, player.y + 0x1000, Direction::Bottom),
+                    Direction::Left => (player.x - 0xc00, player.y + 0x600, Direction::Left),
+                    _ => (player.x + 0xc00, player.y + 0x600, Direction::Right),
+                };
+
+                for i in 0..shots {
+                    // parallel shots are spread perpendicular to the firing direction
+                    let offset = (2 * i - (shots - 1)) * def.spacing as i32 * 0x100;
+                    let (bx, by) = match direction {
+                        Direction::Up | Direction::Bottom => (x + offset, y),
+                        _ => (x, y + offset),
+                    };
+
+                    bullet_manager.create_bullet(bx, by, btype, player_id, direction, &state.constants);
+                }
+
+                match direction {
+                    Direction::Left => state.create_caret(player.x - 0x1800, y, CaretType::Shoot, Direction::Left),
+                    Direction::Right => state.create_caret(player.x + 0x1800, y, CaretType::Shoot, Direction::Left),
+                    _ => state.create_caret(x, y, CaretType::Shoot, Direction::Left),
+                }
+            }
+            CustomWeaponPattern::Lua => {
+                #[cfg(feature = "scripting-lua")]
+                {
+                    let player_idx = if player_id == TargetPlayer::Player2 { 1 } else { 0 };
+                    if !state.lua.try_run_weapon_hook(id, player_idx, level_idx as u8 + 1) {
+                        log::warn!("No Lua handler registered for weapon {}.", id);
+                    }
+                }
+            }
+        }
+
+        state.sound_manager.play_sfx(def.sounds[level_idx]);
+    }
+
+    fn tick_custom_regen(&mut self, def: &CustomWeapon) {
+        if def.ammo_regen == 0 {
+            return;
+        }
+
+        // self.counter2 : recharge time counter
+        self.counter2 += 1;
+        if self.counter2 >= def.ammo_regen {
+            self.counter2 = 0;
+            self.refill_ammo(1);
+        }
+    }
+}
+
+#[test]
+fn test_custom_weapon_defaults() {
+    use crate::game::weapon::WeaponType;
+
+    let weapon: CustomWeapon = serde_json::from_str(r#"{"bullets": [4, 5, 6], "level_exp": [10, 20, 30]}"#).unwrap();
+
+    assert_eq!(weapon.fire_mode, CustomWeaponFireMode::Single);
+    assert_eq!(weapon.fire_pattern, CustomWeaponPattern::Straight);
+    assert_eq!(weapon.shots, 1);
+    assert_eq!(weapon.ammo_regen, 0);
+    assert_eq!(weapon.sounds, [32, 32, 32]);
+    assert!(weapon.icon_rect.is_none());
+
+    let mut constants = EngineConstants::defaults();
+    constants.custom_weapons.insert(14, weapon);
+
+    assert_eq!(WeaponType::from_id(2, &constants), Some(WeaponType::PolarStar));
+    assert_eq!(WeaponType::from_id(14, &constants), Some(WeaponType::Custom(14)));
+    assert_eq!(WeaponType::from_id(15, &constants), None);
+    assert_eq!(WeaponType::from_id(256, &constants), None);
+}
@@ -1,4 +1,4 @@
-use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
 
 use crate::common::Direction;
 use crate::engine_constants::EngineConstants;
@@ -10,6 +10,7 @@ use crate::game::weapon::bullet::BulletManager;
 mod blade;
 mod bubbler;
 pub mod bullet;
+pub mod custom;
 mod fireball;
 mod machine_gun;
 mod missile_launcher;
@@ -19,20 +20,76 @@ mod snake;
 mod spur;
 mod super_missile_launcher;
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, FromPrimitive)]
-#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum WeaponType {
-    None = 0,
-    Snake = 1,
-    PolarStar = 2,
-    Fireball = 3,
-    MachineGun = 4,
-    MissileLauncher = 5,
-    Bubbler = 7,
-    Blade = 9,
-    SuperMissileLauncher = 10,
-    Nemesis = 12,
-    Spur = 13,
+    None,
+    Snake,
+    PolarStar,
+    Fireball,
+    MachineGun,
+    MissileLauncher,
+    Bubbler,
+    Blade,
+    SuperMissileLauncher,
+    Nemesis,
+    Spur,
+    /// Weapon defined in `weapons.json`, uses ids 14 and above.
+    Custom(u8),
+}
+
+impl WeaponType {
+    /// Weapon number, as used by TSC and profiles.
+    pub fn id(self) -> u8 {
+        match self {
+            WeaponType::None => 0,
+            WeaponType::Snake => 1,
+            WeaponType::PolarStar => 2,
+            WeaponType::Fireball => 3,
+            WeaponType::MachineGun => 4,
+            WeaponType::MissileLauncher => 5,
+            WeaponType::Bubbler => 7,
+            WeaponType::Blade => 9,
+            WeaponType::SuperMissileLauncher => 10,
+            WeaponType::Nemesis => 12,
+            WeaponType::Spur => 13,
+            WeaponType::Custom(id) => id,
+        }
+    }
+
+    /// Weapon with given number, numbers of custom weapons are only valid if a `weapons.json` defines them.
+    pub fn from_id(id: u16, constants: &EngineConstants) -> Option<WeaponType> {
+        match id {
+            14..=255 if constants.custom_weapons.contains_key(&(id as u8)) => Some(WeaponType::Custom(id as u8)),
+            _ => FromPrimitive::from_u16(id),
+        }
+    }
+}
+
+impl FromPrimitive for WeaponType {
+    fn from_i64(n: i64) -> Option<WeaponType> {
+        if n < 0 {
+            return None;
+        }
+
+        WeaponType::from_u64(n as u64)
+    }
+
+    fn from_u64(n: u64) -> Option<WeaponType> {
+        match n {
+            0 => Some(WeaponType::None),
+            1 => Some(WeaponType::Snake),
+            2 => Some(WeaponType::PolarStar),
+            3 => Some(WeaponType::Fireball),
+            4 => Some(WeaponType::MachineGun),
+            5 => Some(WeaponType::MissileLauncher),
+            7 => Some(WeaponType::Bubbler),
+            9 => Some(WeaponType::Blade),
+            10 => Some(WeaponType::SuperMissileLauncher),
+            12 => Some(WeaponType::Nemesis),
+            13 => Some(WeaponType::Spur),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -118,13 +175,21 @@ impl Weapon {
         }
     }
 
+    /// Experience needed to advance past each level.
+    pub fn level_table(&self, constants: &EngineConstants) -> [u16; 3] {
+        match self.wtype {
+            WeaponType::Custom(id) => constants.custom_weapons.get(&id).map(|w| w.level_exp).unwrap_or([0; 3]),
+            wtype => constants.weapon.level_table[wtype.id() as usize],
+        }
+    }
+
     pub fn get_max_exp(&self, constants: &EngineConstants) -> (u16, u16, bool) {
         if self.level == WeaponLevel::None {
             return (0, 0, false);
         }
 
         let level_idx = self.level as usize - 1;
-        let max_exp = constants.weapon.level_table[self.wtype as usize][level_idx];
+        let max_exp = self.level_table(constants)[level_idx];
         let max = self.level == WeaponLevel::Level3 && self.experience == max_exp;
 
         (self.experience, max_exp, max)
@@ -132,7 +197,7 @@ impl Weapon {
 
     pub fn add_xp(&mut self, exp: u16, player: &mut Player, state: &mut SharedGameState) {
         let curr_level_idx = self.level as usize - 1;
-        let lvl_table = state.constants.weapon.level_table[self.wtype as usize];
+        let lvl_table = self.level_table(&state.constants);
 
         self.experience = self.experience.saturating_add(exp);
 
@@ -199,6 +264,7 @@ impl Weapon {
             }
             WeaponType::Nemesis => self.tick_nemesis(player, player_id, bullet_manager, state),
             WeaponType::Spur => self.tick_spur(player, player_id, bullet_manager, state),
+            WeaponType::Custom(id) => self.tick_custom(id, player, player_id, bullet_manager, state),
         }
     }
 }
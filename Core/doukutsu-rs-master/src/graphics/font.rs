@@ -3,7 +3,11 @@ use crate::common::Rect;
 use crate::engine_constants::EngineConstants;
 use crate::framework::context::Context;
 use crate::framework::error::GameResult;
+use crate::game::shared_game_state::FontData;
+use crate::graphics::bmfont::BMFont;
 use crate::graphics::texture_set::TextureSet;
+#[cfg(feature = "font-ttf")]
+use crate::graphics::ttf_font::TTFFont;
 
 bitfield! {
     #[derive(Clone, Copy)]
@@ -206,3 +210,90 @@ impl<'a, 'b> TextBuilder<'a, 'b> {
         )
     }
 }
+
+/// Font used to draw game text, picked by the file extension of the locale font.
+pub enum GameFont {
+    BMFont(BMFont),
+    #[cfg(feature = "font-ttf")]
+    TTF(TTFFont),
+}
+
+impl GameFont {
+    pub fn load(roots: &Vec<String>, font: &FontData, ctx: &mut Context) -> GameResult<GameFont> {
+        #[cfg(feature = "font-ttf")]
+        if TTFFont::is_ttf_path(&font.path) {
+            return Ok(GameFont::TTF(TTFFont::load(roots, &font.path, ctx, font.size, font.scale)?));
+        }
+
+        Ok(GameFont::BMFont(BMFont::load(roots, &font.path, ctx, font.scale)?))
+    }
+
+    pub fn load_builtin(ctx: &mut Context) -> GameResult<GameFont> {
+        Ok(GameFont::BMFont(BMFont::load(&vec!["/".to_owned()], "builtin/builtin_font.fnt", ctx, 1.0)?))
+    }
+
+    pub fn scale(&mut self, scale: f32) {
+        match self {
+            GameFont::BMFont(font) => font.scale(scale),
+            #[cfg(feature = "font-ttf")]
+            GameFont::TTF(font) => font.scale(scale),
+        }
+    }
+
+    pub fn get_scale(&self) -> f32 {
+        match self {
+            GameFont::BMFont(font) => font.get_scale(),
+            #[cfg(feature = "font-ttf")]
+            GameFont::TTF(font) => font.get_scale(),
+        }
+    }
+
+    fn inner(&self) -> &dyn Font {
+        match self {
+            GameFont::BMFont(font) => font,
+            #[cfg(feature = "font-ttf")]
+            GameFont::TTF(font) => font,
+        }
+    }
+}
+
+impl Font for GameFont {
+    fn line_height(&self) -> f32 {
+        self.inner().line_height()
+    }
+
+    fn compute_width(&self, text: &mut dyn Iterator<Item = char>, symbols: Option<&Symbols>) -> f32 {
+        self.inner().compute_width(text, symbols)
+    }
+
+    fn draw(
+        &self,
+        text: &mut dyn Iterator<Item = char>,
+        x: f32,
+        y: f32,
+        scale: f32,
+        box_width: f32,
+        shadow_color: (u8, u8, u8, u8),
+        color: (u8, u8, u8, u8),
+        flags: TextBuilderFlag,
+        constants: &EngineConstants,
+        texture_set: &mut TextureSet,
+        symbols: Option<Symbols>,
+        ctx: &mut Context,
+    ) -> GameResult {
+        self.inner().draw(
+            text,
+            x,
+            y,
+            scale,
+            box_width,
+            shadow_color,
+            color,
+            flags,
+            constants,
+            texture_set,
+            symbols,
+            ctx,
+        )
+    }
+}
@@ -1,3 +1,6 @@
 pub mod bmfont;
 pub mod font;
+pub mod texture_pack;
 pub mod texture_set;
+#[cfg(feature = "font-ttf")]
+pub mod ttf_font;
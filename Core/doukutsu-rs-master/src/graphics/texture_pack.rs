@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::common::FILE_TYPES;
+use crate::framework::context::Context;
+use crate::framework::error::{GameError, GameResult};
+use crate::framework::filesystem;
+use crate::framework::filesystem::File;
+use crate::framework::vfs::{PhysicalFS, VFS};
+
+/// Directory in the user directory texture packs are installed in.
+pub const TEXTURE_PACKS_DIR: &str = "/texture_packs";
+
+/// How often the files of the mounted pack are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Contents of the `pack.json` manifest of a texture pack.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct TexturePackManifest {
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    /// Resolution of the pack's textures relative to the original ones, eg. `2.0` for textures twice as large.
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    /// Per-texture overrides of `scale`, keyed by texture name without extension, eg. `Npc/NpcSym`.
+    #[serde(default)]
+    pub textures: HashMap<String, f32>,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+impl TexturePackManifest {
+    /// Returns the resolution of given texture relative to the original one.
+    pub fn texture_scale(&self, name: &str) -> f32 {
+        let scale = self
+            .textures
+            .iter()
+            .find(|(texture, _)| texture.eq_ignore_ascii_case(name))
+            .map_or(self.scale, |(_, scale)| *scale);
+
+        if scale > 0.0 {
+            scale
+        } else {
+            1.0
+        }
+    }
+}
+
+/// What has changed in the mounted pack since the last poll.
+pub enum TexturePackChanges {
+    None,
+    /// Names of loaded textures whose files have been added, modified or removed.
+    Textures(Vec<String>),
+    /// The manifest has been modified, everything has to be reloaded.
+    Manifest,
+}
+
+/// A directory of textures in the user `texture_packs/` directory, layered above game data and mods.
+///
+/// Packs mirror the layout of the data directory (eg. `Npc/NpcSym.png`) and have a `pack.json` manifest in their
+/// root. Files of the mounted pack are watched while the game runs, so changed textures are reloaded without
+/// restarting.
+pub struct TexturePack {
+    pub id: String,
+    pub manifest: TexturePackManifest,
+    fs: PhysicalFS,
+    root: PathBuf,
+    manifest_mtime: Option<SystemTime>,
+    /// Modification times of the files each loaded texture could be read from.
+    watched: HashMap<String, Vec<Option<SystemTime>>>,
+    last_poll: Instant,
+}
+
+impl TexturePack {
+    /// Lists the ids and manifests of installed texture packs.
+    pub fn discover(ctx: &Context) -> Vec<(String, TexturePackManifest)> {
+        let entries = match filesystem::user_read_dir(ctx, TEXTURE_PACKS_DIR) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut packs = Vec::new();
+        for path in entries {
+            let id = match path.file_name().and_then(|name| name.to_str()) {
+                Some(id) => id.to_owned(),
+                None => continue,
+            };
+
+            let manifest_path = format!("{}/{}/pack.json", TEXTURE_PACKS_DIR, id);
+            if !filesystem::user_is_file(ctx, &manifest_path) {
+                continue;
+            }
+
+            let manifest = filesystem::user_open(ctx, &manifest_path).and_then(|file| {
+                serde_json::from_reader(file).map_err(|e| GameError::ParseError(format!("{}: {}", manifest_path, e)))
+            });
+
+            match manifest {
+                Ok(manifest) => packs.push((id, manifest)),
+                Err(e) => log::warn!("Skipping texture pack {}: {}", id, e),
+            }
+        }
+
+        packs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        packs
+    }
+
+    /// Opens the pack installed in given directory of the user `texture_packs/` directory.
+    pub fn load(id: &str, root: &Path) -> GameResult<TexturePack> {
+        let fs = PhysicalFS::new(root, true);
+        let manifest = read_manifest(&fs)?;
+
+        log::info!("Mounting texture pack {} ({}) from {:?}", id, manifest.name, fs);
+
+        Ok(TexturePack {
+            id: id.to_owned(),
+            manifest,
+            fs,
+            root: root.to_path_buf(),
+            manifest_mtime: mtime(&root.join("pack.json")),
+            watched: HashMap::new(),
+            last_poll: Instant::now(),
+        })
+    }
+
+    /// Checks whether the pack contains given file, `path` is relative to the pack root.
+    pub fn exists(&self, path: &str) -> bool {
+        self.fs.exists(&Path::new("/").join(path))
+    }
+
+    pub fn open(&self, path: &str) -> Option<File> {
+        self.fs.open(&Path::new("/").join(path)).ok().map(File::VfsFile)
+    }
+
+    /// Starts watching the files given texture can be loaded from.
+    pub fn watch(&mut self, name: &str) {
+        let fingerprint = self.fingerprint(name);
+        self.watched.insert(name.to_owned(), fingerprint);
+    }
+
+    pub fn unwatch_all(&mut self) {
+        self.watched.clear();
+    }
+
+    /// Checks the watched files for changes, at most once every `POLL_INTERVAL`. A modified manifest is only reported
+    /// once it parses.
+    pub fn poll(&mut self) -> TexturePackChanges {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return TexturePackChanges::None;
+        }
+        self.last_poll = Instant::now();
+
+        let manifest_mtime = mtime(&self.root.join("pack.json"));
+        if manifest_mtime != self.manifest_mtime {
+            self.manifest_mtime = manifest_mtime;
+
+            // the manifest can be briefly invalid while it's being edited, keep using the previous one until it's fixed
+            match read_manifest(&self.fs) {
+                Ok(manifest) => {
+                    self.manifest = manifest;
+                    return TexturePackChanges::Manifest;
+                }
+                Err(e) => log::warn!("Failed to reload texture pack manifest: {}", e),
+            }
+        }
+
+        let mut changed = Vec::new();
+        for (name, fingerprint) in self.watched.iter() {
+            if self.fingerprint(name) != *fingerprint {
+                changed.push(name.clone());
+            }
+        }
+
+        for name in changed.iter() {
+            self.watched.remove(name);
+        }
+
+        if changed.is_empty() {
+            TexturePackChanges::None
+        } else {
+            TexturePackChanges::Textures(changed)
+        }
+    }
+
+    fn fingerprint(&self, name: &str) -> Vec<Option<SystemTime>> {
+        ["", ".glow", ".normal"]
+            .iter()
+            .flat_map(|layer| FILE_TYPES.iter().map(move |ext| [name, layer, ext].join("")))
+            .map(|path| mtime(&self.root.join(path)))
+            .collect()
+    }
+}
+
+fn read_manifest(fs: &dyn VFS) -> GameResult<TexturePackManifest> {
+    let file = fs.open(Path::new("/pack.json"))?;
+    serde_json::from_reader(file).map_err(|e| GameError::ParseError(format!("{:?}: pack.json: {}", fs, e)))
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[test]
+fn test_texture_scale() {
+    let manifest: TexturePackManifest =
+        serde_json::from_str(r#"{"name": "HD", "scale": 4, "textures": {"Npc/NpcSym": 2, "Face": 0}}"#).unwrap();
+
+    assert_eq!(manifest.texture_scale("MyChar"), 4.0);
+    assert_eq!(manifest.texture_scale("npc/npcsym"), 2.0);
+    assert_eq!(manifest.texture_scale("Face"), 1.0);
+
+    let manifest: TexturePackManifest = serde_json::from_str(r#"{"name": "Recolor"}"#).unwrap();
+    assert_eq!(manifest.texture_scale("MyChar"), 1.0);
+}
+
+#[test]
+fn test_poll_keeps_invalid_manifest() {
+    let root = std::env::temp_dir().join(format!("drs-texture-pack-test-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("pack.json"), r#"{"name": "Test", "scale": 2}"#).unwrap();
+
+    let mut pack = TexturePack::load("test", &root).unwrap();
+
+    std::fs::write(root.join("pack.json"), r#"{"name": "Te"#).unwrap();
+    pack.manifest_mtime = None;
+    pack.last_poll = Instant::now() - POLL_INTERVAL;
+    assert!(matches!(pack.poll(), TexturePackChanges::None));
+    assert_eq!(pack.manifest.scale, 2.0);
+
+    std::fs::write(root.join("pack.json"), r#"{"name": "Test", "scale": 4}"#).unwrap();
+    pack.manifest_mtime = None;
+    pack.last_poll = Instant::now() - POLL_INTERVAL;
+    assert!(matches!(pack.poll(), TexturePackChanges::Manifest));
+    assert_eq!(pack.manifest.scale, 4.0);
+
+    let _ = std::fs::remove_dir_all(root);
+}
@@ -13,6 +13,7 @@ use crate::framework::context::Context;
 use crate::framework::error::{GameError, GameResult};
 use crate::framework::filesystem;
 use crate::framework::graphics::{create_texture, FilterMode};
+use crate::graphics::texture_pack::{TexturePack, TexturePackChanges};
 
 pub static mut I_MAG: f32 = 1.0;
 pub static mut G_MAG: f32 = 1.0;
@@ -174,6 +175,7 @@ pub struct SubBatch {
 pub struct CombinedBatch {
     main_batch: SubBatch,
     glow_batch: Option<SubBatch>,
+    normal_batch: Option<SubBatch>,
 }
 
 impl SpriteBatch for SubBatch {
@@ -403,13 +405,17 @@ impl SpriteBatch for CombinedBatch {
     }
 
     fn has_normal_layer(&self) -> bool {
-        false
+        self.normal_batch.is_some()
     }
 
     fn glow(&mut self) -> Option<&mut dyn SpriteBatch> {
         self.glow_batch.as_mut().map(|batch| batch as &mut dyn SpriteBatch)
     }
 
+    fn normal(&mut self) -> Option<&mut dyn SpriteBatch> {
+        self.normal_batch.as_mut().map(|batch| batch as &mut dyn SpriteBatch)
+    }
+
     fn to_rect(&self) -> Rect<usize> {
         self.main_batch.to_rect()
     }
@@ -477,16 +483,53 @@ impl SpriteBatch for CombinedBatch {
 
 pub struct TextureSet {
     pub tex_map: HashMap<String, Box<dyn SpriteBatch>>,
+    /// Texture pack looked up before the data directories, if one is selected.
+    pub pack: Option<TexturePack>,
     dummy_batch: Box<dyn SpriteBatch>,
+    /// Whether any of the loaded textures has a `.normal` layer.
+    normal_layers_loaded: bool,
 }
 
 impl TextureSet {
     pub fn new() -> TextureSet {
-        TextureSet { tex_map: HashMap::new(), dummy_batch: Box::new(DummyBatch) }
+        TextureSet {
+            tex_map: HashMap::new(),
+            pack: None,
+            dummy_batch: Box::new(DummyBatch),
+            normal_layers_loaded: false,
+        }
+    }
+
+    /// Returns true if any loaded texture has a normal map layer, so drawing normal maps can be skipped otherwise.
+    pub fn has_normal_layers(&self) -> bool {
+        self.normal_layers_loaded
     }
 
     pub fn unload_all(&mut self) {
         self.tex_map.clear();
+        self.normal_layers_loaded = false;
+        if let Some(pack) = &mut self.pack {
+            pack.unwatch_all();
+        }
+    }
+
+    /// Unloads textures whose files in the texture pack have changed, so they're loaded again on next use.
+    pub fn poll_pack(&mut self) -> TexturePackChanges {
+        let changes = match &mut self.pack {
+            Some(pack) => pack.poll(),
+            None => TexturePackChanges::None,
+        };
+
+        if let TexturePackChanges::Textures(names) = &changes {
+            for name in names {
+                info!("Reloading texture: {}", name);
+                self.tex_map.remove(name);
+            }
+
+            self.normal_layers_loaded = self.tex_map.values().any(|batch| batch.has_normal_layer());
+        }
+
+        changes
     }
 
     fn make_transparent(rgba: &mut RgbaImage) {
@@ -500,7 +543,10 @@ impl TextureSet {
     fn load_image(&self, ctx: &mut Context, roots: &Vec<String>, path: &str) -> GameResult<Box<dyn BackendTexture>> {
         let img = {
             let mut buf = [0u8; 8];
-            let mut reader = filesystem::open_find(ctx, roots, path)?;
+            let mut reader = match self.pack.as_ref().and_then(|pack| pack.open(path)) {
+                Some(reader) => reader,
+                None => filesystem::open_find(ctx, roots, path)?,
+            };
             reader.read_exact(&mut buf)?;
             reader.seek(SeekFrom::Start(0))?;
 
@@ -517,9 +563,24 @@ impl TextureSet {
     }
 
     pub fn find_texture(&self, ctx: &mut Context, roots: &Vec<String>, name: &str) -> Option<String> {
+        // the pack takes precedence even if data has the texture in a different format
+        if let Some(pack) = &self.pack {
+            if let Some(path) = FILE_TYPES.iter().map(|ext| [name, ext].join("")).find(|path| pack.exists(path)) {
+                return Some(path);
+            }
+        }
+
         FILE_TYPES.iter().map(|ext| [name, ext].join("")).find(|path| filesystem::exists_find(ctx, roots, path))
     }
 
+    /// Returns the scale of a texture read from given path, if the path is provided by the texture pack.
+    fn pack_scale(&self, name: &str, path: &str) -> Option<f32> {
+        match &self.pack {
+            Some(pack) if pack.exists(path) => Some(1.0 / pack.manifest.texture_scale(name)),
+            _ => None,
+        }
+    }
+
     pub fn load_texture(
         &self,
         ctx: &mut Context,
@@ -531,26 +592,33 @@ impl TextureSet {
             .ok_or_else(|| GameError::ResourceLoadError(format!("Texture \"{}\" is missing.", name)))?;
 
         let glow_path = self.find_texture(ctx, &constants.base_paths, &[name, ".glow"].join(""));
+        let normal_path = self.find_texture(ctx, &constants.base_paths, &[name, ".normal"].join(""));
 
         info!("Loading texture: {} -> {}", name, path);
 
-        fn make_batch(name: &str, constants: &EngineConstants, batch: Box<dyn BackendTexture>) -> SubBatch {
+        fn make_batch(
+            name: &str,
+            constants: &EngineConstants,
+            pack_scale: Option<f32>,
+            batch: Box<dyn BackendTexture>,
+        ) -> SubBatch {
             let size = batch.dimensions();
 
             let orig_dimensions = constants.tex_sizes.get(name).unwrap_or(&size);
 
-            let scale =
-                if f32::abs((orig_dimensions.0 as f32 / size.0 as f32) - (orig_dimensions.1 as f32 / size.1 as f32))
-                    <= f32::EPSILON
-                {
-                    orig_dimensions.0 as f32 / size.0 as f32
-                } else if constants.is_cs_plus && constants.base_paths.iter().any(|p| p.contains("/ogph")) {
-                    1.0
-                } else if constants.is_cs_plus {
-                    0.5
-                } else {
-                    1.0
-                };
+            let scale = if let Some(pack_scale) = pack_scale {
+                pack_scale
+            } else if f32::abs((orig_dimensions.0 as f32 / size.0 as f32) - (orig_dimensions.1 as f32 / size.1 as f32))
+                <= f32::EPSILON
+            {
+                orig_dimensions.0 as f32 / size.0 as f32
+            } else if constants.is_cs_plus && constants.base_paths.iter().any(|p| p.contains("/ogph")) {
+                1.0
+            } else if constants.is_cs_plus {
+                0.5
+            } else {
+                1.0
+            };
 
             let width = (size.0 as f32 * scale) as _;
             let height = (size.1 as f32 * scale) as _;
@@ -566,14 +634,29 @@ impl TextureSet {
             }
         }
 
-        let main_batch = make_batch(name, constants, self.load_image(ctx, &constants.base_paths, &path)?);
+        let main_batch = make_batch(
+            name,
+            constants,
+            self.pack_scale(name, &path),
+            self.load_image(ctx, &constants.base_paths, &path)?,
+        );
         let glow_batch = if let Some(glow_path) = glow_path {
-            self.load_image(ctx, &constants.base_paths, &glow_path).ok().map(|b| make_batch(name, constants, b))
+            self.load_image(ctx, &constants.base_paths, &glow_path)
+                .ok()
+                .map(|b| make_batch(name, constants, self.pack_scale(name, &glow_path), b))
+        } else {
+            None
+        };
+
+        let normal_batch = if let Some(normal_path) = normal_path {
+            self.load_image(ctx, &constants.base_paths, &normal_path)
+                .ok()
+                .map(|b| make_batch(name, constants, self.pack_scale(name, &normal_path), b))
         } else {
             None
         };
 
-        Ok(Box::new(CombinedBatch { main_batch, glow_batch }))
+        Ok(Box::new(CombinedBatch { main_batch, glow_batch, normal_batch }))
     }
 
     pub fn get_or_load_batch(
@@ -588,7 +671,11 @@ impl TextureSet {
 
         if !self.tex_map.contains_key(name) {
             let batch = self.load_texture(ctx, constants, name)?;
+            self.normal_layers_loaded |= batch.has_normal_layer();
             self.tex_map.insert(name.to_owned(), batch);
+            if let Some(pack) = &mut self.pack {
+                pack.watch(name);
+            }
         }
 
         Ok(self.tex_map.get_mut(name).unwrap())
@@ -0,0 +1,466 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::common::Rect;
+use crate::engine_constants::EngineConstants;
+use crate::framework::backend::{BackendTexture, SpriteBatchCommand};
+use crate::framework::context::Context;
+use crate::framework::error::{GameError, GameResult};
+use crate::framework::filesystem;
+use crate::framework::graphics::create_texture;
+use crate::graphics::font::{Font, Symbols, TextBuilderFlag, EMPTY_SYMBOLS};
+use crate::graphics::texture_set::{TextureSet, I_MAG};
+
+/// Initial width and height of the glyph atlas texture.
+const ATLAS_SIZE: usize = 1024;
+/// Size the atlas can grow to before glyphs have to be evicted.
+const MAX_ATLAS_SIZE: usize = 2048;
+/// Space left between glyphs in the atlas to avoid bleeding when filtering.
+const GLYPH_PADDING: usize = 1;
+
+#[derive(Debug, Clone, Copy)]
+struct TTFGlyph {
+    /// Position of the glyph in the atlas, empty for glyphs without pixels like spaces.
+    rect: Rect<u16>,
+    /// Offset of the glyph from the pen position on the baseline, in atlas pixels.
+    x_offset: f32,
+    y_offset: f32,
+    advance: f32,
+}
+
+/// Cache of rasterized glyphs, new ones are uploaded to the texture before drawing.
+struct GlyphAtlas {
+    pixels: Vec<u8>,
+    /// Width and height of the atlas.
+    size: usize,
+    glyphs: HashMap<char, Option<TTFGlyph>>,
+    cursor_x: usize,
+    cursor_y: usize,
+    shelf_height: usize,
+    /// Scale of the pixels the glyphs were rasterized for, the cache is flushed when the window scale changes.
+    raster_scale: f32,
+    /// Incremented each time the atlas is flushed.
+    generation: u32,
+    texture: Option<Box<dyn BackendTexture>>,
+    /// Region of the atlas changed since the last upload.
+    dirty: Option<Rect<usize>>,
+    /// Set when the atlas has been resized and the texture has to be created again.
+    resized: bool,
+}
+
+impl GlyphAtlas {
+    fn new() -> GlyphAtlas {
+        GlyphAtlas {
+            pixels: vec![0; ATLAS_SIZE * ATLAS_SIZE * 4],
+            size: ATLAS_SIZE,
+            glyphs: HashMap::new(),
+            cursor_x: 0,
+            cursor_y: 0,
+            shelf_height: 0,
+            raster_scale: 0.0,
+            generation: 0,
+            texture: None,
+            dirty: None,
+            resized: false,
+        }
+    }
+
+    fn flush(&mut self, raster_scale: f32) {
+        self.pixels.iter_mut().for_each(|p| *p = 0);
+        self.glyphs.clear();
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.shelf_height = 0;
+        self.raster_scale = raster_scale;
+        self.generation = self.generation.wrapping_add(1);
+        self.dirty = Some(Rect::new(0, 0, self.size, self.size));
+    }
+
+    /// Doubles the size of the atlas, keeping glyphs in place. Returns false if it's already at its maximum size.
+    fn grow(&mut self) -> bool {
+        if self.size >= MAX_ATLAS_SIZE {
+            return false;
+        }
+
+        let new_size = self.size * 2;
+        let mut pixels = vec![0; new_size * new_size * 4];
+        for (row, line) in self.pixels.chunks(self.size * 4).enumerate() {
+            pixels[row * new_size * 4..(row * new_size + self.size) * 4].copy_from_slice(line);
+        }
+
+        log::info!("Glyph atlas is full, growing it to {}x{}.", new_size, new_size);
+        self.pixels = pixels;
+        self.size = new_size;
+        self.resized = true;
+
+        true
+    }
+
+    /// Finds a free spot for a glyph of given size, glyphs are packed into rows as tall as the tallest glyph in them.
+    fn allocate(&mut self, width: usize, height: usize) -> Option<Rect<u16>> {
+        if width + GLYPH_PADDING > self.size || height + GLYPH_PADDING > self.size {
+            return None;
+        }
+
+        if self.cursor_x + width + GLYPH_PADDING > self.size {
+            self.cursor_x = 0;
+            self.cursor_y += self.shelf_height;
+            self.shelf_height = 0;
+        }
+
+        if self.cursor_y + height + GLYPH_PADDING > self.size {
+            return None;
+        }
+
+        let rect = Rect::new_size(self.cursor_x as u16, self.cursor_y as u16, width as u16, height as u16);
+        self.cursor_x += width + GLYPH_PADDING;
+        self.shelf_height = self.shelf_height.max(height + GLYPH_PADDING);
+
+        Some(rect)
+    }
+
+    /// Copies the coverage bitmap of a glyph into the atlas as white pixels with matching alpha.
+    fn blit(&mut self, rect: &Rect<u16>, coverage: &[u8]) {
+        let width = rect.width() as usize;
+
+        for (row, line) in coverage.chunks(width.max(1)).enumerate() {
+            for (col, alpha) in line.iter().enumerate() {
+                let idx = ((rect.top as usize + row) * self.size + rect.left as usize + col) * 4;
+                self.pixels[idx..idx + 4].copy_from_slice(&[255, 255, 255, *alpha]);
+            }
+        }
+
+        if rect.width() == 0 || rect.height() == 0 {
+            return;
+        }
+
+        let (left, top, right, bottom) =
+            (rect.left as usize, rect.top as usize, rect.right as usize, rect.bottom as usize);
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => {
+                Rect::new(dirty.left.min(left), dirty.top.min(top), dirty.right.max(right), dirty.bottom.max(bottom))
+            }
+            None => Rect::new(left, top, right, bottom),
+        });
+    }
+
+    /// Uploads the changed part of the atlas to the texture, creating it if needed.
+    fn upload(&mut self, ctx: &mut Context) -> GameResult {
+        let dirty = self.dirty.take();
+
+        if let (false, Some(texture), Some(dirty)) = (self.resized, self.texture.as_mut(), dirty) {
+            let mut data = Vec::with_capacity(dirty.width() * dirty.height() * 4);
+            for row in dirty.top..dirty.bottom {
+                data.extend_from_slice(
+                    &self.pixels[(row * self.size + dirty.left) * 4..(row * self.size + dirty.right) * 4],
+                );
+            }
+
+            match texture.update(
+                dirty.left as u16,
+                dirty.top as u16,
+                dirty.width() as u16,
+                dirty.height() as u16,
+                &data,
+            ) {
+                Ok(()) => return Ok(()),
+                // backends which can't update textures get the whole atlas uploaded again
+                Err(_) => self.resized = true,
+            }
+        }
+
+        if self.resized || self.texture.is_none() {
+            self.texture = Some(create_texture(ctx, self.size as u16, self.size as u16, &self.pixels)?);
+            self.resized = false;
+        }
+
+        Ok(())
+    }
+}
+
+/// TrueType/OpenType font rasterized at runtime, for scripts the shipped bitmap fonts don't cover.
+pub struct TTFFont {
+    font: fontdue::Font,
+    /// Font size in pixels of the game canvas.
+    size: f32,
+    font_scale: f32,
+    atlas: RefCell<GlyphAtlas>,
+}
+
+impl TTFFont {
+    pub fn is_ttf_path(path: &str) -> bool {
+        let path = path.to_ascii_lowercase();
+        path.ends_with(".ttf") || path.ends_with(".otf") || path.ends_with(".ttc")
+    }
+
+    pub fn load(roots: &Vec<String>, path: &str, ctx: &mut Context, size: f32, font_scale: f32) -> GameResult<TTFFont> {
+        let mut data = Vec::new();
+        filesystem::open_find(ctx, roots, path)?.read_to_end(&mut data)?;
+
+        let font = fontdue::Font::from_bytes(data, fontdue::FontSettings::default())
+            .map_err(|e| GameError::ResourceLoadError(format!("Cannot load font {}: {}", path, e)))?;
+
+        Ok(TTFFont { font, size, font_scale, atlas: RefCell::new(GlyphAtlas::new()) })
+    }
+
+    #[inline]
+    fn pixel_size(&self) -> f32 {
+        self.size * self.font_scale
+    }
+
+    fn ascent(&self) -> f32 {
+        match self.font.horizontal_line_metrics(self.pixel_size()) {
+            Some(metrics) => metrics.ascent,
+            None => self.pixel_size(),
+        }
+    }
+
+    /// Returns the glyph of a character, rasterizing it into the atlas if it isn't there yet.
+    fn glyph(&self, atlas: &mut GlyphAtlas, chr: char) -> Option<TTFGlyph> {
+        if let Some(glyph) = atlas.glyphs.get(&chr) {
+            return *glyph;
+        }
+
+        if self.font.lookup_glyph_index(chr) == 0 {
+            atlas.glyphs.insert(chr, None);
+            return None;
+        }
+
+        let (metrics, coverage) = self.font.rasterize(chr, self.pixel_size() * atlas.raster_scale);
+
+        let rect = loop {
+            if let Some(rect) = atlas.allocate(metrics.width, metrics.height) {
+                break rect;
+            }
+
+            if atlas.grow() {
+                continue;
+            }
+
+            log::warn!("Glyph atlas is full, evicting all glyphs.");
+            atlas.flush(atlas.raster_scale);
+
+            match atlas.allocate(metrics.width, metrics.height) {
+                Some(rect) => break rect,
+                None => {
+                    log::warn!("Glyph {:?} is too large for the glyph atlas.", chr);
+                    break Rect::new(0, 0, 0, 0);
+                }
+            }
+        };
+        atlas.blit(&rect, &coverage);
+
+        let glyph = TTFGlyph {
+            rect,
+            x_offset: metrics.xmin as f32,
+            y_offset: -(metrics.ymin as f32 + metrics.height as f32),
+            advance: metrics.advance_width,
+        };
+        atlas.glyphs.insert(chr, Some(glyph));
+
+        Some(glyph)
+    }
+
+    fn draw_text_line(
+        &self,
+        text: &[char],
+        x: f32,
+        y: f32,
+        scale: f32,
+        color: (u8, u8, u8, u8),
+        constants: &EngineConstants,
+        texture_set: &mut TextureSet,
+        symbols: Option<&Symbols>,
+        ctx: &mut Context,
+    ) -> GameResult {
+        let syms = symbols.unwrap_or(&EMPTY_SYMBOLS);
+        let mag = unsafe { I_MAG };
+
+        let mut atlas = self.atlas.borrow_mut();
+        if atlas.raster_scale != mag {
+            atlas.flush(mag);
+        }
+
+        // rasterize everything first, if the atlas fills up in the middle the line is laid out again
+        let mut glyphs = Vec::with_capacity(text.len());
+        for _ in 0..2 {
+            let generation = atlas.generation;
+            glyphs.clear();
+            glyphs.extend(text.iter().map(|chr| self.glyph(&mut atlas, *chr)));
+
+            if atlas.generation == generation {
+                break;
+            }
+        }
+
+        atlas.upload(ctx)?;
+
+        let baseline = y + self.ascent() * scale;
+        let raster_scale = atlas.raster_scale;
+        let texture = atlas.texture.as_mut().unwrap();
+
+        let mut sym_rects = Vec::new();
+        let mut offset_x = x;
+
+        for (chr, glyph) in text.iter().zip(glyphs.iter()) {
+            if let Some((_, rect)) = syms.symbols.iter().find(|(c, _)| *c == *chr) {
+                sym_rects.push((offset_x, y + self.line_height() / 2.0 - rect.height() as f32 / 2.0, rect));
+                offset_x += rect.width() as f32;
+                continue;
+            }
+
+            let glyph = match glyph {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+
+            if glyph.rect.width() > 0 && glyph.rect.height() > 0 {
+                let left = offset_x + glyph.x_offset / raster_scale * scale;
+                let top = baseline + glyph.y_offset / raster_scale * scale;
+
+                texture.add(SpriteBatchCommand::DrawRectTinted(
+                    Rect {
+                        left: glyph.rect.left as f32,
+                        top: glyph.rect.top as f32,
+                        right: glyph.rect.right as f32,
+                        bottom: glyph.rect.bottom as f32,
+                    },
+                    Rect {
+                        left: left * mag,
+                        top: top * mag,
+                        right: (left + glyph.rect.width() as f32 / raster_scale * scale) * mag,
+                        bottom: (top + glyph.rect.height() as f32 / raster_scale * scale) * mag,
+                    },
+                    color.into(),
+                ));
+            }
+
+            offset_x += glyph.advance / raster_scale * scale;
+        }
+
+        texture.draw()?;
+        texture.clear();
+
+        if !sym_rects.is_empty() && !syms.texture.is_empty() {
+            let sprite_batch = texture_set.get_or_load_batch(ctx, constants, syms.texture)?;
+
+            for (x, y, rect) in sym_rects {
+                sprite_batch.add_rect_scaled(x, y, scale, scale, rect);
+            }
+
+            sprite_batch.draw(ctx)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn scale(&mut self, scale: f32) {
+        self.font_scale = scale;
+        self.atlas.borrow_mut().flush(0.0);
+    }
+
+    pub fn get_scale(&self) -> f32 {
+        self.font_scale
+    }
+}
+
+impl Font for TTFFont {
+    fn line_height(&self) -> f32 {
+        match self.font.horizontal_line_metrics(self.pixel_size()) {
+            Some(metrics) => metrics.new_line_size,
+            None => self.pixel_size(),
+        }
+    }
+
+    fn compute_width(&self, text: &mut dyn Iterator<Item = char>, symbols: Option<&Symbols>) -> f32 {
+        let syms = symbols.unwrap_or(&EMPTY_SYMBOLS);
+        let mut offset_x = 0.0;
+
+        for chr in text {
+            if let Some((_, rect)) = syms.symbols.iter().find(|(c, _)| *c == chr) {
+                offset_x += rect.width() as f32;
+            } else if self.font.lookup_glyph_index(chr) != 0 {
+                offset_x += self.font.metrics(chr, self.pixel_size()).advance_width;
+            }
+        }
+
+        offset_x
+    }
+
+    fn draw(
+        &self,
+        text: &mut dyn Iterator<Item = char>,
+        mut x: f32,
+        y: f32,
+        scale: f32,
+        box_width: f32,
+        shadow_color: (u8, u8, u8, u8),
+        color: (u8, u8, u8, u8),
+        flags: TextBuilderFlag,
+        constants: &EngineConstants,
+        texture_set: &mut TextureSet,
+        symbols: Option<Symbols>,
+        ctx: &mut Context,
+    ) -> GameResult {
+        if ctx.headless {
+            return Ok(());
+        }
+
+        let text: Vec<char> = text.collect();
+
+        if flags.centered() {
+            let text_width = self.compute_width(&mut text.iter().copied(), symbols.as_ref());
+
+            x += (box_width - text_width) * 0.5;
+        }
+
+        if flags.shadow() {
+            self.draw_text_line(
+                &text,
+                x + scale,
+                y + scale,
+                scale,
+                shadow_color,
+                constants,
+                texture_set,
+                symbols.as_ref(),
+                ctx,
+            )?;
+        }
+
+        self.draw_text_line(&text, x, y, scale, color, constants, texture_set, symbols.as_ref(), ctx)
+    }
+}
+
+#[test]
+fn test_glyph_atlas_allocation() {
+    let mut atlas = GlyphAtlas::new();
+
+    let first = atlas.allocate(10, 20).unwrap();
+    let second = atlas.allocate(10, 12).unwrap();
+    assert_eq!((first.left, first.top), (0, 0));
+    assert_eq!((second.left, second.top), (11, 0));
+
+    // doesn't fit in the rest of the row, so it goes to a new one below the tallest glyph
+    let third = atlas.allocate(ATLAS_SIZE - 10, 5).unwrap();
+    assert_eq!((third.left, third.top), (0, 21));
+
+    assert!(atlas.allocate(ATLAS_SIZE, 5).is_none());
+}
+
+#[test]
+fn test_glyph_atlas_grow() {
+    let mut atlas = GlyphAtlas::new();
+
+    let rect = atlas.allocate(2, 1).unwrap();
+    atlas.blit(&rect, &[128, 255]);
+    assert!(atlas.allocate(ATLAS_SIZE, 5).is_none());
+
+    assert!(atlas.grow());
+    assert_eq!(atlas.size, ATLAS_SIZE * 2);
+    assert_eq!(&atlas.pixels[4..8], &[255, 255, 255, 255]);
+    assert!(atlas.allocate(ATLAS_SIZE, 5).is_some());
+
+    assert!(!atlas.grow());
+}
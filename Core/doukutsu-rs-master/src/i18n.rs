@@ -4,12 +4,17 @@ use crate::framework::context::Context;
 use crate::framework::filesystem;
 use crate::game::shared_game_state::FontData;
 
+/// Keys describing the locale itself rather than translated text.
+const META_KEYS: [&str; 5] = ["name", "font", "font_scale", "font_size", "fallback"];
+
 #[derive(Debug, Clone)]
 pub struct Locale {
     pub code: String,
     pub name: String,
     pub font: FontData,
     strings: HashMap<String, String>,
+    /// Locale used for strings missing from this one.
+    fallback: Option<Box<Locale>>,
 }
 
 impl Default for Locale {
@@ -20,9 +25,11 @@ impl Default for Locale {
             font: FontData {
                 path: String::new(),
                 scale: 1.0,
-                space_offset: 0.0
+                space_offset: 0.0,
+                size: 12.0,
             },
             strings: HashMap::new(),
+            fallback: None,
         }
     }
 }
@@ -32,15 +39,23 @@ impl Locale {
         let file = filesystem::open_find(ctx, base_paths, &format!("locale/{}.json", code)).unwrap();
         let json: serde_json::Value = serde_json::from_reader(file).unwrap();
 
-        let strings = Locale::flatten(&json);
+        Locale::from_json(code, &json)
+    }
 
-        let name = strings["name"].clone();
+    fn from_json(code: &str, json: &serde_json::Value) -> Locale {
+        let strings = Locale::flatten(json);
 
-        let font_name = strings["font"].clone();
-        let font_scale = strings["font_scale"].parse::<f32>().unwrap_or(1.0);
-        let font = FontData::new(font_name, font_scale, 0.0);
+        let name = strings.get("name").cloned().unwrap_or_else(|| code.to_owned());
 
-        Locale { code: code.to_string(), name, font, strings }
+        // an empty font path is replaced by the font of the fallback locale
+        let font_name = strings.get("font").cloned().unwrap_or_default();
+        let font_scale = strings.get("font_scale").and_then(|s| s.parse::<f32>().ok()).unwrap_or(1.0);
+        let mut font = FontData::new(font_name, font_scale, 0.0);
+        if let Some(size) = strings.get("font_size").and_then(|s| s.parse::<f32>().ok()) {
+            font.size = size;
+        }
+
+        Locale { code: code.to_string(), name, font, strings, fallback: None }
     }
 
     fn flatten(json: &serde_json::Value) -> HashMap<String, String> {
@@ -65,8 +80,61 @@ impl Locale {
         strings
     }
 
+    /// Codes of the locales strings are looked up in, in order, eg. `pt_BR`, `pt`, `en`.
+    ///
+    /// A locale falls back to the one named by its `fallback` key, or to the language without the region.
+    /// English always ends the chain.
+    pub fn fallback_chain(locales: &[Locale], code: &str) -> Vec<String> {
+        let mut chain: Vec<String> = Vec::new();
+        let mut next = Some(code.to_owned());
+
+        while let Some(code) = next.take() {
+            if chain.contains(&code) {
+                break;
+            }
+
+            let explicit = locales.iter().find(|l| l.code == code).and_then(|l| l.strings.get("fallback").cloned());
+            next = explicit.or_else(|| code.rfind(|c| c == '_' || c == '-').map(|idx| code[..idx].to_owned()));
+            chain.push(code);
+        }
+
+        if !chain.iter().any(|code| code == "en") {
+            chain.push("en".to_owned());
+        }
+
+        chain
+    }
+
+    /// Returns given locale with its fallback chain attached, or the closest available one if it doesn't exist.
+    pub fn resolve(locales: &[Locale], code: &str) -> Option<Locale> {
+        let mut result: Option<Locale> = None;
+
+        for code in Locale::fallback_chain(locales, code).iter().rev() {
+            if let Some(locale) = locales.iter().find(|l| l.code == *code) {
+                let mut locale = locale.clone();
+                if let Some(fallback) = result.take() {
+                    if locale.font.path.is_empty() {
+                        locale.font = fallback.font.clone();
+                    }
+                    locale.fallback = Some(Box::new(fallback));
+                }
+
+                result = Some(locale);
+            }
+        }
+
+        result
+    }
+
+    fn lookup(&self, key: &str) -> Option<&str> {
+        match self.strings.get(key) {
+            Some(string) => Some(string),
+            None => self.fallback.as_ref().and_then(|fallback| fallback.lookup(key)),
+        }
+    }
+
     pub fn t<'a: 'b, 'b>(&'a self, key: &'b str) -> &'b str {
-        if let Some(str) = self.strings.get(key) {
+        if let Some(str) = self.lookup(key) {
             str
         } else {
             key
@@ -74,16 +142,199 @@ impl Locale {
     }
 
     pub fn tt(&self, key: &str, args: &[(&str, &str)]) -> String {
-        let mut string = self.t(key).to_owned();
+        format_string(self.t(key), args)
+    }
+
+    /// Translates a string depending on given count, using the plural form of the locale language.
+    ///
+    /// Plural forms are stored as `key.one`, `key.few`, `key.many` and `key.other`, `key.zero` takes precedence
+    /// when the count is zero. `{count}` is substituted along with the other arguments.
+    pub fn tn(&self, key: &str, count: i64, args: &[(&str, &str)]) -> String {
+        let count_str = count.to_string();
+        let mut all_args = vec![("count", count_str.as_str())];
+        all_args.extend_from_slice(args);
+
+        let mut categories = Vec::with_capacity(3);
+        if count == 0 {
+            categories.push("zero");
+        }
+        categories.push(plural_category(&self.code, count));
+        categories.push("other");
+
+        for category in categories {
+            if let Some(string) = self.lookup(&format!("{}.{}", key, category)) {
+                return format_string(string, &all_args);
+            }
+        }
+
+        format_string(self.t(key), &all_args)
+    }
 
-        for (key, value) in args.iter() {
-            string = string.replace(&format!("{{{}}}", key), &value);
+    /// Keys present in `base` but missing from this locale, ignoring its fallbacks.
+    pub fn missing_keys(&self, base: &Locale) -> Vec<String> {
+        let mut keys: Vec<String> = base
+            .strings
+            .keys()
+            .filter(|key| !META_KEYS.contains(&key.as_str()) && !self.has_key(key))
+            .cloned()
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Keys present in this locale but not in `base`, usually leftovers of renamed or removed strings.
+    pub fn extra_keys(&self, base: &Locale) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .strings
+            .keys()
+            .filter(|key| !META_KEYS.contains(&key.as_str()) && !base.has_key(key))
+            .cloned()
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Whether the key or any of its plural forms is defined.
+    fn has_key(&self, key: &str) -> bool {
+        if self.strings.contains_key(key) {
+            return true;
         }
 
-        string
+        // plural forms differ between languages, so any of them counts
+        match key.rsplit_once('.') {
+            Some((parent, "zero" | "one" | "few" | "many" | "other")) => {
+                self.strings.keys().any(|k| k.rsplit_once('.').map(|(p, _)| p == parent).unwrap_or(false))
+            }
+            _ => false,
+        }
     }
 
     pub fn set_font(&mut self, font: FontData) {
         self.font = font;
     }
 }
+
+/// Plural category of a count in the language of given locale code, following the CLDR rules.
+fn plural_category(code: &str, count: i64) -> &'static str {
+    let language = code.split(|c| c == '_' || c == '-').next().unwrap_or(code);
+    let n = count.abs();
+
+    match language {
+        "jp" | "ja" | "zh" | "ko" | "vi" | "th" | "id" => "other",
+        "fr" | "pt" if n <= 1 => "one",
+        "ru" | "uk" | "be" | "sr" | "hr" | "bs" => match (n % 10, n % 100) {
+            (1, r) if r != 11 => "one",
+            (2..=4, r) if !(12..=14).contains(&r) => "few",
+            _ => "many",
+        },
+        "pl" => match (n, n % 10, n % 100) {
+            (1, _, _) => "one",
+            (_, 2..=4, r) if !(12..=14).contains(&r) => "few",
+            _ => "many",
+        },
+        "cs" | "sk" => match n {
+            1 => "one",
+            2..=4 => "few",
+            _ => "other",
+        },
+        _ if n == 1 => "one",
+        _ => "other",
+    }
+}
+
+/// Substitutes `{name}` placeholders with given arguments. `{name:N}` pads the value to N characters, with zeros
+/// if N starts with one, and `{{` and `}}` produce literal braces.
+pub fn format_string(template: &str, args: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    placeholder.push(c);
+                }
+
+                let (name, spec) = match placeholder.split_once(':') {
+                    Some((name, spec)) => (name, Some(spec)),
+                    None => (placeholder.as_str(), None),
+                };
+
+                match args.iter().find(|(key, _)| *key == name) {
+                    Some((_, value)) if closed => {
+                        let width = spec.and_then(|spec| spec.parse::<usize>().ok()).unwrap_or(0);
+                        let fill = if spec.map(|spec| spec.starts_with('0')).unwrap_or(false) { '0' } else { ' ' };
+                        for _ in value.chars().count()..width {
+                            result.push(fill);
+                        }
+                        result.push_str(value);
+                    }
+                    // unknown placeholders are left as they are
+                    _ => {
+                        result.push('{');
+                        result.push_str(&placeholder);
+                        if closed {
+                            result.push('}');
+                        }
+                    }
+                }
+            }
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+#[test]
+fn test_locale_fallback() {
+    let en = Locale::from_json(
+        "en",
+        &serde_json::json!({"name": "English", "font": "csfont.fnt", "a": "A", "b": {"c": "C"},
+            "items": {"one": "{count} item", "other": "{count} items"}}),
+    );
+    let pt = Locale::from_json("pt", &serde_json::json!({"name": "Português", "a": "A (pt)", "old": "x"}));
+    let pt_br = Locale::from_json("pt_BR", &serde_json::json!({"name": "Português (Brasil)", "b": {"c": "C (br)"}}));
+    let locales = vec![en.clone(), pt.clone(), pt_br];
+
+    assert_eq!(Locale::fallback_chain(&locales, "pt_BR"), vec!["pt_BR", "pt", "en"]);
+
+    let locale = Locale::resolve(&locales, "pt_BR").unwrap();
+    assert_eq!(locale.code, "pt_BR");
+    assert_eq!(locale.font.path, "csfont.fnt");
+    assert_eq!(locale.t("a"), "A (pt)");
+    assert_eq!(locale.t("b.c"), "C (br)");
+    assert_eq!(locale.t("missing"), "missing");
+    assert_eq!(locale.tn("items", 1, &[]), "1 item");
+    assert_eq!(Locale::resolve(&locales, "de").unwrap().code, "en");
+
+    assert_eq!(pt.missing_keys(&en), vec!["b.c", "items.one", "items.other"]);
+    assert_eq!(pt.extra_keys(&en), vec!["old"]);
+}
+
+#[test]
+fn test_format_string() {
+    assert_eq!(format_string("{a} and {b}", &[("a", "1"), ("b", "2")]), "1 and 2");
+    assert_eq!(format_string("{n:03}|{n:3}", &[("n", "7")]), "007|  7");
+    assert_eq!(format_string("{{literal}} {unknown}", &[]), "{literal} {unknown}");
+
+    assert_eq!(plural_category("en", 1), "one");
+    assert_eq!(plural_category("fr", 0), "one");
+    assert_eq!(plural_category("ru", 22), "few");
+    assert_eq!(plural_category("ru", 12), "many");
+    assert_eq!(plural_category("jp", 1), "other");
+}
@@ -24,6 +24,7 @@ mod live_debugger;
 mod macros;
 mod menu;
 mod mod_list;
+mod mod_manager;
 mod mod_requirements;
 pub mod scene; // originally private
 pub mod sound; // originally private
@@ -8,8 +8,16 @@ use crate::game::shared_game_state::SharedGameState;
 use crate::scene::game_scene::GameScene;
 
 use self::command_line::CommandLineParser;
+use self::pixtone_editor::PixToneEditor;
+use self::tsc_editor::TscEditor;
 
 pub mod command_line;
+pub mod org_editor;
+pub mod pixtone_editor;
+pub mod tsc_editor;
+
+const COLOR_ERROR: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+const COLOR_SUCCESS: [f32; 4] = [0.4, 1.0, 0.4, 1.0];
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 #[repr(u8)]
@@ -27,6 +35,8 @@ pub struct LiveDebugger {
     npc_inspector_visible: bool,
     hotkey_list_visible: bool,
     command_line_parser: CommandLineParser,
+    tsc_editor: TscEditor,
+    pixtone_editor: PixToneEditor,
     command_line_focused: bool,
     last_stage_id: usize,
     stages: Vec<ImString>,
@@ -47,6 +57,8 @@ impl LiveDebugger {
             npc_inspector_visible: false,
             hotkey_list_visible: false,
             command_line_parser: CommandLineParser::new(),
+            tsc_editor: TscEditor::new(),
+            pixtone_editor: PixToneEditor::new(),
             command_line_focused: false,
             last_stage_id: usize::MAX,
             stages: Vec::new(),
@@ -229,6 +241,11 @@ impl LiveDebugger {
                     state.command_line = !state.command_line;
                 }
 
+                ui.same_line();
+                if ui.button("PixTone") {
+                    self.pixtone_editor.visible = !self.pixtone_editor.visible;
+                }
+
                 ui.checkbox("noclip", &mut state.settings.noclip);
                 ui.same_line();
                 ui.checkbox("more rust", &mut state.more_rust);
@@ -350,6 +367,11 @@ impl LiveDebugger {
                         }
                     }
 
+                    ui.same_line();
+                    if ui.button("Edit") {
+                        self.tsc_editor.visible = !self.tsc_editor.visible;
+                    }
+
                     ui.same_line();
                     if ui.button("Decompile") {
                         if let Some((stype, event_num)) = self.event_ids.get(self.selected_event as usize) {
@@ -503,6 +525,13 @@ impl LiveDebugger {
                 });
         }
 
+        if self.tsc_editor.draw(game_scene, state, ctx, ui) {
+            self.events.clear();
+            self.selected_event = -1;
+        }
+
+        self.pixtone_editor.draw(state, ctx, ui);
+
         let mut remove = -1;
         for (idx, (_, title, contents)) in self.text_windows.iter().enumerate() {
             let mut opened = true;
@@ -574,3 +603,14 @@ fn cond_flags(ui: &imgui::Ui, cond: &mut crate::common::Condition) {
     ui.checkbox_flags("Unknown (0x40)", &mut cond.0, 64);
     ui.checkbox_flags("Alive", &mut cond.0, 128);
 }
+
+/// Clears `pending` and returns true once no widget is active. Editors use this to wait for sliders and drags
+/// to be released, otherwise dragging them would restart the sound every frame.
+fn take_pending_apply(ui: &imgui::Ui, pending: &mut bool) -> bool {
+    if *pending && !ui.is_any_item_active() {
+        *pending = false;
+        return true;
+    }
+
+    false
+}
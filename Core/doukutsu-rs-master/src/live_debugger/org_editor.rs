@@ -0,0 +1,494 @@
+use std::io::Write;
+
+use imgui::{Condition, ImString, MouseButton};
+
+use crate::framework::context::Context;
+use crate::framework::error::{GameError, GameResult};
+use crate::framework::filesystem;
+use crate::game::shared_game_state::SharedGameState;
+use crate::sound::backend::{song_paths, SongFormat};
+use crate::sound::organya::{Note, Song, Track};
+
+use super::{take_pending_apply, COLOR_ERROR, COLOR_SUCCESS};
+
+const COLOR_WHITE_KEY: [f32; 4] = [0.16, 0.16, 0.18, 1.0];
+const COLOR_BLACK_KEY: [f32; 4] = [0.11, 0.11, 0.13, 1.0];
+const COLOR_BEAT: [f32; 4] = [0.25, 0.25, 0.28, 1.0];
+const COLOR_MEASURE: [f32; 4] = [0.45, 0.45, 0.5, 1.0];
+const COLOR_OUTSIDE_LOOP: [f32; 4] = [0.0, 0.0, 0.0, 0.35];
+const COLOR_LOOP: [f32; 4] = [0.4, 1.0, 0.4, 1.0];
+const COLOR_PLAYHEAD: [f32; 4] = [1.0, 0.8, 0.3, 1.0];
+const COLOR_SELECTION: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+const TRACK_COLORS: [[f32; 4]; 8] = [
+    [0.4, 0.7, 1.0, 1.0],
+    [1.0, 0.5, 0.5, 1.0],
+    [0.5, 1.0, 0.5, 1.0],
+    [1.0, 0.8, 0.3, 1.0],
+    [0.8, 0.5, 1.0, 1.0],
+    [0.3, 0.9, 0.9, 1.0],
+    [1.0, 0.6, 0.9, 1.0],
+    [0.8, 0.8, 0.8, 1.0],
+];
+
+/// Melodic tracks are numbered and drum tracks are lettered like in OrgMaker.
+const TRACK_NAMES: [&str; 16] = ["1", "2", "3", "4", "5", "6", "7", "8", "Q", "W", "E", "R", "T", "Y", "U", "I"];
+
+/// Number of keys the piano roll shows, 8 octaves.
+const KEY_COUNT: i32 = 96;
+const ROW_HEIGHT: f32 = 6.0;
+
+/// Note properties with this value are left unchanged, notes with such key only change volume or panning.
+const NO_CHANGE: u8 = 255;
+
+/// Returns the index of a note of the track covering given position and key.
+fn note_at(track: &Track, drum: bool, pos: i32, key: i32) -> Option<usize> {
+    track.notes.iter().position(|note| {
+        let len = if drum { 1 } else { note.len.max(1) as i32 };
+        note.key as i32 == key && pos >= note.pos && pos < note.pos + len
+    })
+}
+
+/// Organya plays a single event per step in each track, replaces other notes at the same position as given one.
+/// Returns the new index of the note.
+fn place_note(track: &mut Track, idx: usize) -> usize {
+    let note = track.notes.remove(idx);
+    track.notes.retain(|other| other.pos != note.pos);
+    track.notes.push(note);
+    track.notes.len() - 1
+}
+
+/// Piano roll view and editor of the Organya song being played.
+pub struct OrgEditor {
+    pub visible: bool,
+    song_id: usize,
+    path: String,
+    song: Option<Song>,
+    selected_track: usize,
+    selected_note: Option<usize>,
+    /// Distance in steps between the cursor and the start of the note being dragged.
+    drag_offset: Option<i32>,
+    muted: u16,
+    solo: u16,
+    zoom: f32,
+    follow_playhead: bool,
+    scroll_to_center: bool,
+    /// Set when the song has been edited, the playing song is replaced once editing is done.
+    pending_update: bool,
+    status: Option<(ImString, [f32; 4])>,
+}
+
+impl OrgEditor {
+    pub fn new() -> OrgEditor {
+        OrgEditor {
+            visible: false,
+            song_id: usize::MAX,
+            path: String::new(),
+            song: None,
+            selected_track: 0,
+            selected_note: None,
+            drag_offset: None,
+            muted: 0,
+            solo: 0,
+            zoom: 8.0,
+            follow_playhead: true,
+            scroll_to_center: true,
+            pending_update: false,
+            status: None,
+        }
+    }
+
+    fn load(&mut self, state: &mut SharedGameState, ctx: &mut Context, song_id: usize) -> GameResult {
+        self.song_id = song_id;
+        self.song = None;
+        self.selected_note = None;
+        self.drag_offset = None;
+        self.muted = 0;
+        self.solo = 0;
+        self.scroll_to_center = true;
+        state.sound_manager.set_org_muted_tracks(0);
+
+        let song_name = state
+            .constants
+            .music_table
+            .get(song_id)
+            .ok_or_else(|| GameError::InvalidValue(format!("Invalid song id: {}", song_id)))?;
+
+        self.path = song_paths(&state.constants, &state.settings.soundtrack, song_name)
+            .into_iter()
+            .filter(|(format, _)| matches!(format, SongFormat::Organya))
+            .filter_map(|(_, mut paths)| paths.pop())
+            .find(|path| filesystem::exists(ctx, path))
+            .ok_or_else(|| GameError::ResourceLoadError(format!("No Organya file found for {}.", song_name)))?;
+
+        self.song = Some(Song::load_from(filesystem::open(ctx, &self.path)?)?);
+
+        Ok(())
+    }
+
+    fn save(&self, state: &mut SharedGameState, ctx: &mut Context) -> GameResult {
+        let song = match &self.song {
+            Some(song) => song,
+            None => return Ok(()),
+        };
+
+        let mut data = Vec::new();
+        song.write_to(&mut data)?;
+
+        state.create_data_file(ctx, self.path.trim_start_matches('/'))?.write_all(&data)?;
+        log::info!("Saved {}", self.path);
+
+        Ok(())
+    }
+
+    /// Hides the editor and unmutes all tracks.
+    pub fn close(&mut self, state: &mut SharedGameState) {
+        self.visible = false;
+        self.muted = 0;
+        self.solo = 0;
+        state.sound_manager.set_org_muted_tracks(0);
+    }
+
+    pub fn draw(&mut self, state: &mut SharedGameState, ctx: &mut Context, ui: &imgui::Ui) {
+        if !self.visible {
+            return;
+        }
+
+        let song_id = state.sound_manager.current_song();
+        if self.song_id != song_id {
+            self.status = match self.load(state, ctx, song_id) {
+                Ok(()) => None,
+                Err(e) => Some((ImString::new(format!("Error loading song: {}", e)), COLOR_ERROR)),
+            };
+        }
+
+        let mut opened = true;
+        let mut mute_changed = false;
+
+        ui.window("Organya Editor")
+            .position([40.0, 40.0], Condition::FirstUseEver)
+            .size([720.0, 480.0], Condition::FirstUseEver)
+            .opened(&mut opened)
+            .build(|| {
+                ui.text(&self.path);
+
+                if ui.button("Save") {
+                    self.status = match self.save(state, ctx) {
+                        Ok(()) => Some((ImString::new(format!("Saved {}.", self.path)), COLOR_SUCCESS)),
+                        Err(e) => Some((ImString::new(format!("Error saving song: {}", e)), COLOR_ERROR)),
+                    };
+                }
+
+                ui.same_line();
+                if ui.button("Revert") {
+                    self.status = match self.load(state, ctx, song_id) {
+                        Ok(()) => {
+                            self.pending_update = true;
+                            None
+                        }
+                        Err(e) => Some((ImString::new(format!("Error loading song: {}", e)), COLOR_ERROR)),
+                    };
+                }
+
+                ui.same_line();
+                ui.checkbox("Follow playhead", &mut self.follow_playhead);
+
+                ui.same_line();
+                {
+                    let _iw = ui.push_item_width(120.0);
+                    ui.slider("Zoom", 2.0, 24.0, &mut self.zoom);
+                }
+
+                if let Some((status, color)) = &self.status {
+                    ui.text_colored(*color, status);
+                }
+
+                let song = match &mut self.song {
+                    Some(song) => song,
+                    None => return,
+                };
+
+                let steps = song.display.steps.max(1) as i32;
+                let steps_per_measure = steps * song.display.beats.max(1) as i32;
+                let loop_range = song.time.loop_range;
+                let wait = song.time.wait.max(1);
+
+                ui.text(format!(
+                    "Tempo: {} ms per step ({:.1} BPM), {}/{}    Loop: {}:{} - {}:{}",
+                    wait,
+                    60000.0 / (wait as f32 * steps as f32),
+                    song.display.beats,
+                    song.display.steps,
+                    loop_range.start / steps_per_measure,
+                    loop_range.start % steps_per_measure,
+                    loop_range.end / steps_per_measure,
+                    loop_range.end % steps_per_measure,
+                ));
+
+                ui.child_window("##org_tracks").size([180.0, 0.0]).build(|| {
+                    for (idx, name) in TRACK_NAMES.iter().enumerate() {
+                        let bit = 1 << idx;
+
+                        if ui.radio_button(format!("Track {}##org_track", name), &mut self.selected_track, idx) {
+                            self.selected_note = None;
+                        }
+
+                        ui.same_line_with_pos(90.0);
+                        let mut muted = self.muted & bit != 0;
+                        if ui.checkbox(format!("M##org_mute{}", idx), &mut muted) {
+                            self.muted ^= bit;
+                            mute_changed = true;
+                        }
+
+                        ui.same_line();
+                        let mut solo = self.solo & bit != 0;
+                        if ui.checkbox(format!("S##org_solo{}", idx), &mut solo) {
+                            self.solo ^= bit;
+                            mute_changed = true;
+                        }
+                    }
+
+                    let drum = self.selected_track >= 8;
+                    let track = &mut song.tracks[self.selected_track];
+
+                    ui.separator();
+
+                    let mut inst = track.inst.inst as i32;
+                    if ui.input_int("Instrument", &mut inst).build() {
+                        track.inst.inst = inst.clamp(0, if drum { 255 } else { 99 }) as u8;
+                        self.pending_update = true;
+                    }
+
+                    let mut freq = track.inst.freq as i32;
+                    if ui.input_int("Frequency", &mut freq).build() {
+                        track.inst.freq = freq.clamp(100, 1900) as u16;
+                        self.pending_update = true;
+                    }
+
+                    if !drum {
+                        let mut pipi = track.inst.pipi != 0;
+                        if ui.checkbox("Pizzicato", &mut pipi) {
+                            track.inst.pipi = pipi as u8;
+                            self.pending_update = true;
+                        }
+                    }
+
+                    let note = match self.selected_note.and_then(|idx| track.notes.get_mut(idx)) {
+                        Some(note) => note,
+                        None => return,
+                    };
+
+                    ui.separator();
+                    ui.text(format!("Note at {}:{}", note.pos / steps_per_measure, note.pos % steps_per_measure));
+
+                    let mut len = note.len as i32;
+                    if ui.slider("Length", 1, 254, &mut len) {
+                        note.len = len as u8;
+                        self.pending_update = true;
+                    }
+
+                    // -1 keeps the volume or panning of the previous note
+                    for (label, value, max) in [("Volume", &mut note.vol, 254), ("Pan", &mut note.pan, 12)] {
+                        let mut v = if *value == NO_CHANGE { -1 } else { *value as i32 };
+                        if ui.slider(label, -1, max, &mut v) {
+                            *value = if v < 0 { NO_CHANGE } else { v as u8 };
+                            self.pending_update = true;
+                        }
+                    }
+
+                    if ui.button("Delete note") {
+                        if let Some(idx) = self.selected_note.take() {
+                            track.notes.remove(idx);
+                            self.pending_update = true;
+                        }
+                    }
+                });
+
+                ui.same_line();
+                ui.child_window("##org_roll").horizontal_scrollbar(true).build(|| {
+                    let zoom = self.zoom;
+                    let song_end = song
+                        .tracks
+                        .iter()
+                        .flat_map(|track| track.notes.iter().map(|note| note.pos + note.len as i32))
+                        .fold(loop_range.end, i32::max);
+                    let length = (song_end / steps_per_measure + 2) * steps_per_measure;
+                    let size = [length as f32 * zoom, KEY_COUNT as f32 * ROW_HEIGHT];
+
+                    let origin = ui.cursor_screen_pos();
+                    ui.invisible_button("##org_canvas", size);
+                    let hovered = ui.is_item_hovered();
+
+                    let [view_width, view_height] = ui.window_size();
+                    let playhead = state.sound_manager.org_position();
+
+                    if self.scroll_to_center {
+                        self.scroll_to_center = false;
+                        ui.set_scroll_y((size[1] - view_height) / 2.0);
+                    }
+
+                    if self.follow_playhead && self.drag_offset.is_none() {
+                        if let Some(pos) = playhead {
+                            ui.set_scroll_x((pos as f32 * zoom - view_width / 2.0).max(0.0));
+                        }
+                    }
+
+                    let scroll_x = ui.scroll_x();
+                    let first_step = (scroll_x / zoom) as i32;
+                    let last_step = ((scroll_x + view_width) / zoom) as i32 + 1;
+                    let left = origin[0] + scroll_x;
+                    let right = left + view_width;
+                    let step_x = |step: i32| origin[0] + step as f32 * zoom;
+                    let key_y = |key: i32| origin[1] + (KEY_COUNT - 1 - key) as f32 * ROW_HEIGHT;
+
+                    let mouse = ui.io().mouse_pos;
+                    let mouse_step = ((mouse[0] - origin[0]) / zoom).floor() as i32;
+                    let mouse_key = KEY_COUNT - 1 - ((mouse[1] - origin[1]) / ROW_HEIGHT).floor() as i32;
+                    let drum = self.selected_track >= 8;
+
+                    {
+                        let track = &mut song.tracks[self.selected_track];
+
+                        if hovered && ui.is_mouse_clicked(MouseButton::Left) {
+                            let idx = match note_at(track, drum, mouse_step, mouse_key) {
+                                Some(idx) => idx,
+                                None => {
+                                    let len = if drum { 1 } else { steps.min(254) as u8 };
+                                    track.notes.push(Note {
+                                        pos: mouse_step.max(0),
+                                        key: mouse_key.clamp(0, KEY_COUNT - 1) as u8,
+                                        len,
+                                        vol: 200,
+                                        pan: 6,
+                                    });
+                                    track.notes.len() - 1
+                                }
+                            };
+
+                            self.drag_offset = Some(mouse_step - track.notes[idx].pos);
+                            self.selected_note = Some(idx);
+                        }
+
+                        if hovered && ui.is_mouse_clicked(MouseButton::Right) {
+                            if let Some(idx) = note_at(track, drum, mouse_step, mouse_key) {
+                                track.notes.remove(idx);
+                                self.selected_note = None;
+                                self.pending_update = true;
+                            }
+                        }
+
+                        if let (Some(offset), Some(idx)) = (self.drag_offset, self.selected_note) {
+                            if ui.is_mouse_down(MouseButton::Left) {
+                                if let Some(note) = track.notes.get_mut(idx) {
+                                    note.pos = (mouse_step - offset).max(0);
+                                    note.key = mouse_key.clamp(0, KEY_COUNT - 1) as u8;
+                                }
+                            } else {
+                                self.drag_offset = None;
+                                self.selected_note = Some(place_note(track, idx));
+                                self.pending_update = true;
+                            }
+                        }
+                    }
+
+                    let draw_list = ui.get_window_draw_list();
+
+                    for key in 0..KEY_COUNT {
+                        let black = matches!(key % 12, 1 | 3 | 6 | 8 | 10);
+                        let color = if black { COLOR_BLACK_KEY } else { COLOR_WHITE_KEY };
+                        let y = key_y(key);
+                        draw_list.add_rect([left, y], [right, y + ROW_HEIGHT], color).filled(true).build();
+                    }
+
+                    for step in first_step..=last_step {
+                        if step % steps == 0 {
+                            let color = if step % steps_per_measure == 0 { COLOR_MEASURE } else { COLOR_BEAT };
+                            let x = step_x(step);
+                            draw_list.add_line([x, origin[1]], [x, origin[1] + size[1]], color).build();
+                        }
+                    }
+
+                    let (loop_start, loop_end) = (step_x(loop_range.start), step_x(loop_range.end));
+                    draw_list
+                        .add_rect([left, origin[1]], [loop_start.max(left), origin[1] + size[1]], COLOR_OUTSIDE_LOOP)
+                        .filled(true)
+                        .build();
+                    draw_list
+                        .add_rect([loop_end.min(right), origin[1]], [right, origin[1] + size[1]], COLOR_OUTSIDE_LOOP)
+                        .filled(true)
+                        .build();
+                    for x in [loop_start, loop_end] {
+                        draw_list.add_line([x, origin[1]], [x, origin[1] + size[1]], COLOR_LOOP).thickness(2.0).build();
+                    }
+
+                    // the selected track is drawn last so its notes stay on top
+                    let order = (0..16).filter(|&idx| idx != self.selected_track).chain([self.selected_track]);
+                    let mute_mask = if self.solo != 0 { !self.solo } else { self.muted };
+
+                    for idx in order {
+                        let mut color = TRACK_COLORS[idx % 8];
+                        if idx != self.selected_track {
+                            color[3] = 0.35;
+                        }
+                        if mute_mask & (1 << idx) != 0 {
+                            color[3] *= 0.3;
+                        }
+
+                        for (note_idx, note) in song.tracks[idx].notes.iter().enumerate() {
+                            let len = if idx >= 8 { 1 } else { note.len.max(1) as i32 };
+                            if note.key == NO_CHANGE || note.pos + len < first_step || note.pos > last_step {
+                                continue;
+                            }
+
+                            let p1 = [step_x(note.pos), key_y(note.key as i32)];
+                            let p2 = [step_x(note.pos + len) - 1.0, p1[1] + ROW_HEIGHT - 1.0];
+                            draw_list.add_rect(p1, p2, color).filled(true).build();
+
+                            if idx == self.selected_track && self.selected_note == Some(note_idx) {
+                                draw_list.add_rect(p1, p2, COLOR_SELECTION).build();
+                            }
+                        }
+                    }
+
+                    if let Some(pos) = playhead {
+                        let x = step_x(pos);
+                        draw_list
+                            .add_line([x, origin[1]], [x, origin[1] + size[1]], COLOR_PLAYHEAD)
+                            .thickness(2.0)
+                            .build();
+                    }
+                });
+
+                if take_pending_apply(ui, &mut self.pending_update) {
+                    state.sound_manager.replace_org_song(song.clone());
+                }
+            });
+
+        if mute_changed {
+            let mask = if self.solo != 0 { !self.solo } else { self.muted };
+            state.sound_manager.set_org_muted_tracks(mask);
+        }
+
+        if !opened {
+            self.close(state);
+        }
+    }
+}
+
+#[test]
+fn test_place_note() {
+    let note = |pos, key| Note { pos, key, len: 1, vol: 200, pan: 6 };
+    let mut track = Track {
+        inst: crate::sound::organya::Instrument { freq: 1000, inst: 0, pipi: 0, notes: 0 },
+        notes: vec![note(0, 10), note(4, 20), note(8, 30)],
+    };
+
+    track.notes[0].pos = 4;
+    let idx = place_note(&mut track, 0);
+
+    let notes: Vec<(i32, u8)> = track.notes.iter().map(|n| (n.pos, n.key)).collect();
+    assert_eq!(notes, vec![(8, 30), (4, 10)]);
+    assert_eq!(idx, 1);
+    assert_eq!(note_at(&track, false, 4, 10), Some(1));
+    assert_eq!(note_at(&track, false, 5, 10), None);
+}
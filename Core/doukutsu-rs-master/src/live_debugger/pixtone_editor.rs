@@ -0,0 +1,245 @@
+use std::io::Write;
+
+use imgui::{CollapsingHeader, Condition, ImStr, ImString};
+
+use crate::framework::context::Context;
+use crate::framework::error::GameResult;
+use crate::game::shared_game_state::SharedGameState;
+use crate::sound::pixtone::{Envelope, PixToneParameters, Waveform};
+
+use super::{take_pending_apply, COLOR_ERROR, COLOR_SUCCESS};
+
+const WAVEFORM_NAMES: [&str; 6] = ["Sine", "Triangle", "Saw up", "Saw down", "Square", "Noise"];
+
+/// Longest sound the editor allows, 8 seconds at the PixTone sample rate.
+const MAX_LENGTH: i32 = 22050 * 8;
+
+/// Number of points the waveform preview is reduced to.
+const PREVIEW_POINTS: usize = 512;
+
+fn draw_waveform(ui: &imgui::Ui, name: &str, channel: usize, waveform: &mut Waveform) -> bool {
+    let mut changed = false;
+    let label = format!("{}{}", name, channel);
+
+    ui.text(name);
+
+    let mut waveform_type = waveform.waveform_type as usize % WAVEFORM_NAMES.len();
+    if ui.combo_simple_string(format!("Waveform##{}", label), &mut waveform_type, &WAVEFORM_NAMES) {
+        waveform.waveform_type = waveform_type as u8;
+        changed = true;
+    }
+
+    if ui.input_float(format!("Pitch##{}", label), &mut waveform.pitch).build() {
+        waveform.pitch = waveform.pitch.max(0.0);
+        changed = true;
+    }
+
+    changed |= ui.slider(format!("Level##{}", label), -64, 64, &mut waveform.level);
+    changed |= ui.slider(format!("Offset##{}", label), 0, 255, &mut waveform.offset);
+
+    changed
+}
+
+fn draw_envelope(ui: &imgui::Ui, channel: usize, envelope: &mut Envelope) -> bool {
+    let mut changed = false;
+    let label = format!("envelope{}", channel);
+
+    ui.text("Envelope");
+    changed |= ui.slider(format!("Initial##{}", label), 0, 63, &mut envelope.initial);
+
+    for (name, time, value) in [
+        ("A", &mut envelope.time_a, &mut envelope.value_a),
+        ("B", &mut envelope.time_b, &mut envelope.value_b),
+        ("C", &mut envelope.time_c, &mut envelope.value_c),
+    ] {
+        changed |= ui.slider(format!("Time {}##{}", name, label), 0, 255, time);
+        changed |= ui.slider(format!("Value {}##{}", name, label), 0, 63, value);
+    }
+
+    changed
+}
+
+/// Reduces synthesized samples to the loudest sample of each chunk, scaled to `-1.0..1.0`.
+fn preview_points(samples: &[i16]) -> Vec<f32> {
+    let chunk_size = (samples.len() + PREVIEW_POINTS - 1) / PREVIEW_POINTS;
+    if chunk_size == 0 {
+        return Vec::new();
+    }
+
+    samples
+        .chunks(chunk_size)
+        .map(|chunk| {
+            let peak = chunk.iter().copied().max_by_key(|s| (*s as i32).abs()).unwrap_or(0);
+            peak as f32 / i16::MAX as f32
+        })
+        .collect()
+}
+
+/// Editor for the parameters PixTone sound effects are synthesized from.
+pub struct PixToneEditor {
+    pub visible: bool,
+    sfx_names: Vec<ImString>,
+    selected_sfx: i32,
+    /// Id of the sound effect currently in the editor, -1 if it has to be reloaded.
+    loaded_sfx: i32,
+    params: PixToneParameters,
+    live_preview: bool,
+    /// Set when the sound has been changed with live preview enabled, it's played once editing is done.
+    pending_play: bool,
+    preview: Vec<f32>,
+    status: Option<(ImString, [f32; 4])>,
+}
+
+impl PixToneEditor {
+    pub fn new() -> PixToneEditor {
+        PixToneEditor {
+            visible: false,
+            sfx_names: (0..=0xffu8).map(|id| ImString::new(format!("fx{:02x}", id))).collect(),
+            selected_sfx: 0,
+            loaded_sfx: -1,
+            params: PixToneParameters::empty(),
+            live_preview: true,
+            pending_play: false,
+            preview: Vec::new(),
+            status: None,
+        }
+    }
+
+    fn id(&self) -> u8 {
+        self.selected_sfx.clamp(0, 0xff) as u8
+    }
+
+    fn set_params(&mut self, params: PixToneParameters) {
+        self.params = params;
+        self.preview = preview_points(&params.synth());
+    }
+
+    fn path(&self) -> String {
+        format!("pxt/fx{:02x}.pxt", self.id())
+    }
+
+    fn apply(&self, state: &mut SharedGameState) -> GameResult {
+        state.sound_manager.set_sample_params(self.id(), self.params)
+    }
+
+    fn save(&self, state: &mut SharedGameState, ctx: &mut Context) -> GameResult {
+        self.apply(state)?;
+
+        let mut data = Vec::new();
+        self.params.write_pxt(&mut data)?;
+
+        let path = self.path();
+        state.create_data_file(ctx, &path)?.write_all(&data)?;
+        log::info!("Saved {}", path);
+
+        Ok(())
+    }
+
+    pub fn draw(&mut self, state: &mut SharedGameState, ctx: &mut Context, ui: &imgui::Ui) {
+        if !self.visible {
+            return;
+        }
+
+        if self.loaded_sfx != self.selected_sfx {
+            self.loaded_sfx = self.selected_sfx;
+            self.set_params(state.sound_manager.get_sample_params(self.id()));
+        }
+
+        let mut opened = true;
+
+        ui.window("PixTone Editor")
+            .position([140.0, 60.0], Condition::FirstUseEver)
+            .size([420.0, 520.0], Condition::FirstUseEver)
+            .opened(&mut opened)
+            .build(|| {
+                let names: Vec<&ImStr> = self.sfx_names.iter().map(|e| e.as_ref()).collect();
+                {
+                    let _iw = ui.push_item_width(-1.0);
+                    ui.list_box("##pxt_sounds", &mut self.selected_sfx, &names, 6);
+                }
+
+                let mut changed = false;
+
+                if ui.button("Play") {
+                    state.sound_manager.play_sfx(self.id());
+                }
+
+                ui.same_line();
+                if ui.button("Apply") {
+                    self.status = match self.apply(state) {
+                        Ok(()) => Some((ImString::new("Sound updated."), COLOR_SUCCESS)),
+                        Err(e) => Some((ImString::new(format!("Error updating sound: {}", e)), COLOR_ERROR)),
+                    };
+                }
+
+                ui.same_line();
+                if ui.button("Save") {
+                    self.status = match self.save(state, ctx) {
+                        Ok(()) => Some((ImString::new(format!("Saved {}.", self.path())), COLOR_SUCCESS)),
+                        Err(e) => Some((ImString::new(format!("Error saving sound: {}", e)), COLOR_ERROR)),
+                    };
+                }
+
+                ui.same_line();
+                if ui.button("Revert") {
+                    self.loaded_sfx = -1;
+                }
+
+                ui.same_line();
+                if ui.button("Default") {
+                    self.params = PixToneParameters::default_for(self.id());
+                    changed = true;
+                }
+
+                ui.checkbox("Live preview", &mut self.live_preview);
+
+                if let Some((status, color)) = &self.status {
+                    ui.text_colored(*color, status);
+                }
+
+                ui.plot_lines("##pxt_preview", &self.preview)
+                    .scale_min(-1.0)
+                    .scale_max(1.0)
+                    .graph_size([0.0, 80.0])
+                    .build();
+
+                for (idx, channel) in self.params.channels.iter_mut().enumerate() {
+                    if !CollapsingHeader::new(format!("Channel {}", idx + 1)).default_open(idx == 0).build(ui) {
+                        continue;
+                    }
+
+                    changed |= ui.checkbox(format!("Enabled##ch{}", idx), &mut channel.enabled);
+
+                    let mut length = channel.length as i32;
+                    if ui.input_int(format!("Length##ch{}", idx), &mut length).build() {
+                        channel.length = length.clamp(0, MAX_LENGTH) as u32;
+                        changed = true;
+                    }
+
+                    changed |= draw_waveform(ui, "Main", idx, &mut channel.carrier);
+                    changed |= draw_waveform(ui, "Pitch", idx, &mut channel.frequency);
+                    changed |= draw_waveform(ui, "Volume", idx, &mut channel.amplitude);
+                    changed |= draw_envelope(ui, idx, &mut channel.envelope);
+                }
+
+                if changed {
+                    self.set_params(self.params);
+
+                    if self.live_preview {
+                        if let Err(e) = self.apply(state) {
+                            self.status = Some((ImString::new(format!("Error updating sound: {}", e)), COLOR_ERROR));
+                        }
+                        self.pending_play = true;
+                    }
+                }
+
+                if take_pending_apply(ui, &mut self.pending_play) {
+                    state.sound_manager.play_sfx(self.id());
+                }
+            });
+
+        if !opened {
+            self.visible = false;
+        }
+    }
+}
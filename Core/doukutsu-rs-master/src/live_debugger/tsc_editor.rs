@@ -0,0 +1,367 @@
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use imgui::{Condition, ImStr, ImString};
+
+use crate::framework::context::Context;
+use crate::framework::error::GameResult;
+use crate::framework::filesystem;
+use crate::game::scripting::tsc::encoding::{decode, encode, is_encrypted, prepare_script};
+use crate::game::scripting::tsc::encryption::encrypt_tsc;
+use crate::game::scripting::tsc::opcodes::TSCOpCode;
+use crate::game::scripting::tsc::text_script::{TextScript, TextScriptEncoding};
+use crate::game::shared_game_state::SharedGameState;
+use crate::scene::game_scene::GameScene;
+
+use super::{COLOR_ERROR, COLOR_SUCCESS};
+
+const COLOR_EVENT: [f32; 4] = [1.0, 0.8, 0.3, 1.0];
+const COLOR_OPCODE: [f32; 4] = [0.4, 0.7, 1.0, 1.0];
+const COLOR_UNKNOWN_OPCODE: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+const COLOR_ARGUMENT: [f32; 4] = [0.6, 1.0, 0.6, 1.0];
+const COLOR_TEXT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum TokenKind {
+    Event,
+    Opcode,
+    UnknownOpcode,
+    Argument,
+    Text,
+}
+
+impl TokenKind {
+    fn color(self) -> [f32; 4] {
+        match self {
+            TokenKind::Event => COLOR_EVENT,
+            TokenKind::Opcode => COLOR_OPCODE,
+            TokenKind::UnknownOpcode => COLOR_UNKNOWN_OPCODE,
+            TokenKind::Argument => COLOR_ARGUMENT,
+            TokenKind::Text => COLOR_TEXT,
+        }
+    }
+}
+
+/// Splits a single line of TSC source into highlighted tokens.
+fn tokenize_line(line: &str) -> Vec<(TokenKind, &str)> {
+    let mut tokens = Vec::new();
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let offset_of = |idx: usize| chars.get(idx).map(|(offset, _)| *offset).unwrap_or(line.len());
+    let is_digits =
+        |from: usize, to: usize| to <= chars.len() && chars[from..to].iter().all(|(_, c)| c.is_ascii_digit());
+
+    if line.starts_with('#') {
+        return vec![(TokenKind::Event, line)];
+    }
+
+    let mut idx = 0;
+    let mut text_start = 0;
+
+    while idx < chars.len() {
+        if chars[idx].1 != '<' || idx + 4 > chars.len() {
+            idx += 1;
+            continue;
+        }
+
+        if text_start < idx {
+            tokens.push((TokenKind::Text, &line[offset_of(text_start)..offset_of(idx)]));
+        }
+
+        let code = &line[offset_of(idx)..offset_of(idx + 4)];
+        let kind = match TSCOpCode::from_str(&code[1..]) {
+            Ok(_) => TokenKind::Opcode,
+            Err(_) => TokenKind::UnknownOpcode,
+        };
+        tokens.push((kind, code));
+        idx += 4;
+
+        // operands are 4 digit numbers separated by colons
+        let arg_start = idx;
+        while is_digits(idx, idx + 4) {
+            idx += 4;
+
+            if chars.get(idx).map(|(_, c)| *c) == Some(':') && is_digits(idx + 1, idx + 5) {
+                idx += 1;
+            } else {
+                break;
+            }
+        }
+
+        if arg_start < idx {
+            tokens.push((TokenKind::Argument, &line[offset_of(arg_start)..offset_of(idx)]));
+        }
+
+        text_start = idx;
+    }
+
+    if text_start < chars.len() {
+        tokens.push((TokenKind::Text, &line[offset_of(text_start)..]));
+    }
+
+    tokens
+}
+
+struct TscEventSource {
+    /// Event header line, eg. `#0200`.
+    header: String,
+    body: String,
+}
+
+/// Splits the script into text preceding the first event and a list of events, the split is lossless.
+fn split_events(source: &str) -> (String, Vec<TscEventSource>) {
+    let mut preamble = String::new();
+    let mut events: Vec<TscEventSource> = Vec::new();
+
+    for line in source.split_inclusive('\n') {
+        if line.starts_with('#') {
+            let header = line.trim_end_matches('\n').to_owned();
+            events.push(TscEventSource { header, body: String::new() });
+            continue;
+        }
+
+        match events.last_mut() {
+            Some(event) => event.body.push_str(line),
+            None => preamble.push_str(line),
+        }
+    }
+
+    (preamble, events)
+}
+
+fn join_events(preamble: &str, events: &[TscEventSource]) -> String {
+    let mut source = preamble.to_owned();
+
+    for event in events {
+        source.push_str(&event.header);
+        source.push('\n');
+        source.push_str(&event.body);
+    }
+
+    source
+}
+
+/// Editor for the TSC script of the currently loaded stage.
+pub struct TscEditor {
+    pub visible: bool,
+    stage_id: usize,
+    path: String,
+    encoding: TextScriptEncoding,
+    /// Byte order mark and encoding directive preceding the script, kept as-is on save.
+    header: Vec<u8>,
+    encrypted: bool,
+    crlf: bool,
+    /// Whether the file ended with a line break, restored on save.
+    trailing_newline: bool,
+    preamble: String,
+    events: Vec<TscEventSource>,
+    event_names: Vec<ImString>,
+    selected_event: i32,
+    status: Option<(ImString, [f32; 4])>,
+}
+
+impl TscEditor {
+    pub fn new() -> TscEditor {
+        TscEditor {
+            visible: false,
+            stage_id: usize::MAX,
+            path: String::new(),
+            encoding: TextScriptEncoding::ShiftJIS,
+            header: Vec::new(),
+            encrypted: true,
+            crlf: true,
+            trailing_newline: true,
+            preamble: String::new(),
+            events: Vec::new(),
+            event_names: Vec::new(),
+            selected_event: -1,
+            status: None,
+        }
+    }
+
+    fn load(&mut self, game_scene: &GameScene, state: &SharedGameState, ctx: &mut Context) -> GameResult {
+        self.stage_id = game_scene.stage_id;
+        self.path = ["Stage/", &game_scene.stage.data.map, ".tsc"].join("");
+
+        let mut buf = Vec::new();
+        filesystem::open_find(ctx, &state.constants.base_paths, &self.path)?.read_to_end(&mut buf)?;
+
+        self.encrypted = is_encrypted(&buf, state.constants.textscript.encrypted);
+        let (encoding, header) = prepare_script(&mut buf, &state.constants);
+        self.encoding = encoding;
+        self.header = buf[..header].to_vec();
+
+        let source = decode(&buf[header..], self.encoding);
+
+        self.crlf = source.contains("\r\n");
+        self.trailing_newline = source.ends_with('\n');
+
+        let (preamble, events) = split_events(&source.replace("\r\n", "\n"));
+        self.preamble = preamble;
+        self.events = events;
+        self.event_names = self.events.iter().map(|e| ImString::new(e.header.trim())).collect();
+        self.selected_event = if self.events.is_empty() { -1 } else { 0 };
+
+        Ok(())
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut source = join_events(&self.preamble, &self.events);
+        if !self.trailing_newline && source.ends_with('\n') {
+            source.pop();
+        }
+
+        if self.crlf {
+            source = source.replace('\n', "\r\n");
+        }
+
+        let mut data = self.header.clone();
+        data.extend_from_slice(&encode(&source, self.encoding));
+        data
+    }
+
+    /// Compiles the edited script and swaps it in place of the running scene script.
+    fn apply(&self, state: &mut SharedGameState) -> GameResult<Vec<u8>> {
+        let data = self.encode();
+        let script = TextScript::compile(&data[self.header.len()..], false, self.encoding)?;
+        state.textscript_vm.set_scene_script(script);
+
+        Ok(data)
+    }
+
+    fn save(&self, state: &mut SharedGameState, ctx: &mut Context) -> GameResult {
+        let mut data = self.apply(state)?;
+
+        if self.encrypted {
+            encrypt_tsc(&mut data);
+        }
+
+        state.create_data_file(ctx, &self.path)?.write_all(&data)?;
+        log::info!("Saved {}", self.path);
+
+        Ok(())
+    }
+
+    /// Returns true if the scene script has been replaced.
+    pub fn draw(
+        &mut self,
+        game_scene: &GameScene,
+        state: &mut SharedGameState,
+        ctx: &mut Context,
+        ui: &imgui::Ui,
+    ) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        if self.stage_id != game_scene.stage_id {
+            self.status = match self.load(game_scene, state, ctx) {
+                Ok(()) => None,
+                Err(e) => Some((ImString::new(format!("Error loading {}: {}", self.path, e)), COLOR_ERROR)),
+            };
+        }
+
+        let mut swapped = false;
+        let mut opened = true;
+
+        ui.window("TSC Editor")
+            .position([120.0, 80.0], Condition::FirstUseEver)
+            .size([520.0, 480.0], Condition::FirstUseEver)
+            .opened(&mut opened)
+            .build(|| {
+                ui.text(&self.path);
+
+                if ui.button("Apply") {
+                    self.status = match self.apply(state) {
+                        Ok(_) => {
+                            swapped = true;
+                            Some((ImString::new("Script reloaded."), COLOR_SUCCESS))
+                        }
+                        Err(e) => Some((ImString::new(format!("Compile error: {}", e)), COLOR_ERROR)),
+                    };
+                }
+
+                ui.same_line();
+                if ui.button("Save") {
+                    self.status = match self.save(state, ctx) {
+                        Ok(()) => {
+                            swapped = true;
+                            Some((ImString::new(format!("Saved {}.", self.path)), COLOR_SUCCESS))
+                        }
+                        Err(e) => Some((ImString::new(format!("Error saving script: {}", e)), COLOR_ERROR)),
+                    };
+                }
+
+                ui.same_line();
+                if ui.button("Revert") {
+                    self.stage_id = usize::MAX;
+                }
+
+                if let Some((status, color)) = &self.status {
+                    ui.text_colored(*color, status);
+                }
+
+                let names: Vec<&ImStr> = self.event_names.iter().map(|e| e.as_ref()).collect();
+                {
+                    let _iw = ui.push_item_width(-1.0);
+                    ui.list_box("##tsc_events", &mut self.selected_event, &names, 6);
+                }
+
+                if let Some(event) = self.events.get_mut(self.selected_event as usize) {
+                    ui.input_text_multiline("##tsc_source", &mut event.body, [-1.0, 160.0]).build();
+
+                    ui.separator();
+                    ui.child_window("##tsc_highlight").build(|| {
+                        ui.text_colored(COLOR_EVENT, &event.header);
+
+                        for line in event.body.lines() {
+                            for (idx, (kind, token)) in tokenize_line(line).into_iter().enumerate() {
+                                if idx != 0 {
+                                    ui.same_line_with_spacing(0.0, 0.0);
+                                }
+
+                                ui.text_colored(kind.color(), token);
+                            }
+
+                            if line.is_empty() {
+                                ui.new_line();
+                            }
+                        }
+                    });
+                }
+            });
+
+        if !opened {
+            self.visible = false;
+        }
+
+        swapped
+    }
+}
+
+#[test]
+fn tsc_split_roundtrip_test() {
+    let source = "junk\n#0090\n<MNA<CMU0008<FAI0000<END\n\n#0091\n<MNA<CMU0008<FAI0001<END\n";
+    let (preamble, events) = split_events(source);
+
+    assert_eq!(preamble, "junk\n");
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].header, "#0091");
+    assert_eq!(join_events(&preamble, &events), source);
+}
+
+#[test]
+fn tsc_tokenize_test() {
+    let tokens = tokenize_line("<FL+0123<XYZHello<TRA0001:0094:0001:0016");
+
+    assert_eq!(
+        tokens,
+        vec![
+            (TokenKind::Opcode, "<FL+"),
+            (TokenKind::Argument, "0123"),
+            (TokenKind::UnknownOpcode, "<XYZ"),
+            (TokenKind::Text, "Hello"),
+            (TokenKind::Opcode, "<TRA"),
+            (TokenKind::Argument, "0001:0094:0001:0016"),
+        ]
+    );
+}
@@ -1,13 +1,29 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::process::exit;
+use std::str::FromStr;
+
+use doukutsu_rs::game::shared_game_state::ReplayKind;
+use doukutsu_rs::sound::backend::InterpolationMode;
+use doukutsu_rs::sound::render::RenderOptions;
 
 
 fn main() {
     let args = std::env::args();
-    let mut options = doukutsu_rs::game::LaunchOptions { server_mode: false, editor: false, return_types: false, external_timer: false, resource_dir: None, usr_dir: None};
+    let mut options = doukutsu_rs::game::LaunchOptions { server_mode: false, editor: false, return_types: false, external_timer: false, resource_dir: None, usr_dir: None, audio_capture: None};
+
+    let mut lint = None;
+    let mut locale_report = None;
+    let mut convert_tsc = None;
+    let mut render_music = None;
+    let mut render_replay = None;
+    let mut replay_mod = None;
+    let mut replay_kind = ReplayKind::Best;
+    let mut soundtrack = None;
+    let mut render_options = RenderOptions::default();
+    let mut paths = Vec::new();
 
-    for arg in args {
+    for arg in args.skip(1) {
         if arg == "--server-mode" {
             options.server_mode = true;
         }
@@ -15,6 +31,121 @@ fn main() {
         if arg == "--editor" {
             options.editor = true;
         }
+
+        if arg == "--lint" {
+            lint = Some(None);
+        } else if let Some(mod_id) = arg.strip_prefix("--lint=") {
+            lint = Some(Some(mod_id.to_owned()));
+        }
+
+        if arg == "--locale-report" {
+            locale_report = Some(None);
+        } else if let Some(code) = arg.strip_prefix("--locale-report=") {
+            locale_report = Some(Some(code.to_owned()));
+        }
+
+        if let Some(output) = arg.strip_prefix("--render-music=") {
+            render_music = Some(output.to_owned());
+        } else if let Some(name) = arg.strip_prefix("--soundtrack=") {
+            soundtrack = Some(name.to_owned());
+        } else if let Some(mode) = arg.strip_prefix("--interpolation=") {
+            match InterpolationMode::from_name(mode) {
+                Some(mode) => render_options.interpolation = mode,
+                None => {
+                    eprintln!("Unknown interpolation mode: {}", mode);
+                    exit(2);
+                }
+            }
+        } else if let Some(value) = arg.strip_prefix("--loops=") {
+            render_options.loops = parse_arg("--loops", value);
+        } else if let Some(value) = arg.strip_prefix("--fade-out=") {
+            render_options.fade_out = parse_arg("--fade-out", value);
+        } else if let Some(value) = arg.strip_prefix("--sample-rate=") {
+            render_options.sample_rate = parse_arg("--sample-rate", value);
+        }
+
+        if let Some(output) = arg.strip_prefix("--render-replay=") {
+            render_replay = Some(output.to_owned());
+        } else if let Some(mod_id) = arg.strip_prefix("--mod=") {
+            replay_mod = Some(mod_id.to_owned());
+        } else if let Some(kind) = arg.strip_prefix("--replay=") {
+            replay_kind = match kind {
+                "best" => ReplayKind::Best,
+                "last" => ReplayKind::Last,
+                _ => {
+                    eprintln!("Unknown replay kind: {}", kind);
+                    exit(2);
+                }
+            };
+        }
+
+        if let Some(encoding) = arg.strip_prefix("--convert-tsc=") {
+            convert_tsc = Some(encoding.to_owned());
+        } else if !arg.starts_with("--") {
+            paths.push(arg);
+        }
+    }
+
+    if let Some(mod_id) = lint {
+        match doukutsu_rs::game::lint(options, mod_id) {
+            Ok(0) => exit(0),
+            Ok(_) => exit(1),
+            Err(e) => {
+                eprintln!("Lint error: {}", e);
+                exit(2);
+            }
+        }
+    }
+
+    if let Some(code) = locale_report {
+        match doukutsu_rs::game::locale_report(options, code) {
+            Ok(0) => exit(0),
+            Ok(_) => exit(1),
+            Err(e) => {
+                eprintln!("Locale report error: {}", e);
+                exit(2);
+            }
+        }
+    }
+
+    if let Some(encoding) = convert_tsc {
+        match doukutsu_rs::game::convert_tsc(options, encoding, paths) {
+            Ok(0) => exit(0),
+            Ok(_) => exit(1),
+            Err(e) => {
+                eprintln!("Conversion error: {}", e);
+                exit(2);
+            }
+        }
+    }
+
+    if let Some(output) = render_music {
+        match doukutsu_rs::game::render_music(options, output, paths, soundtrack, render_options) {
+            Ok(0) => exit(0),
+            Ok(_) => exit(1),
+            Err(e) => {
+                eprintln!("Render error: {}", e);
+                exit(2);
+            }
+        }
+    }
+
+    if let Some(output) = render_replay {
+        let mod_id = match replay_mod {
+            Some(mod_id) => mod_id,
+            None => {
+                eprintln!("--render-replay requires the challenge to be given with --mod=<id>.");
+                exit(2);
+            }
+        };
+
+        match doukutsu_rs::game::render_replay(options, output, mod_id, replay_kind, render_options.sample_rate) {
+            Ok(_) => exit(0),
+            Err(e) => {
+                eprintln!("Render error: {}", e);
+                exit(2);
+            }
+        }
     }
 
     if options.server_mode && options.editor {
@@ -50,3 +181,13 @@ fn main() {
         exit(1);
     }
 }
+
+fn parse_arg<T: FromStr>(name: &str, value: &str) -> T {
+    match value.parse() {
+        Ok(value) => value,
+        Err(_) => {
+            eprintln!("Invalid value for {}: {}", name, value);
+            exit(2);
+        }
+    }
+}
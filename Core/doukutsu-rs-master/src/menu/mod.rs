@@ -6,12 +6,14 @@ use crate::framework::context::Context;
 use crate::framework::error::GameResult;
 use crate::framework::graphics;
 use crate::game::shared_game_state::{GameDifficulty, MenuCharacter, SharedGameState};
+use crate::game::weapon::custom::weapon_icon_rect;
 use crate::graphics::font::Font;
 use crate::input::combined_menu_controller::CombinedMenuController;
 use crate::menu::save_select_menu::MenuSaveInfo;
 
 pub mod controls_menu;
 pub mod coop_menu;
+pub mod mods_menu;
 pub mod pause_menu;
 pub mod save_select_menu;
 pub mod settings_menu;
@@ -700,10 +702,8 @@ impl<T: std::cmp::PartialEq + std::default::Default + Clone> Menu<T> {
                         for weapon_slot in 0..save.weapon_count {
                             let wtype = save.weapon_id[weapon_slot];
                             let pos_x = weapon_slot as f32 * 16.0 - (16 * save.weapon_count.saturating_sub(4)) as f32;
-                            let mut rect = Rect::new(0, 0, 0, 16);
                             if wtype != 0 {
-                                rect.left = wtype as u16 * 16;
-                                rect.right = rect.left + 16;
+                                let rect = weapon_icon_rect(&state.constants, wtype as u8);
                                 batch.add_rect(right_edge + pos_x - 60.0, y + 8.0, &rect);
                             }
                         }
@@ -0,0 +1,396 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::common::Rect;
+use crate::framework::backend::{BackendTexture, SpriteBatchCommand};
+use crate::framework::context::Context;
+use crate::framework::error::GameResult;
+use crate::game::shared_game_state::SharedGameState;
+use crate::graphics::font::Font;
+use crate::input::combined_menu_controller::CombinedMenuController;
+use crate::menu::{Menu, MenuEntry, MenuSelectionResult};
+use crate::scene::title_scene::TitleScene;
+
+/// Size of mod icons on the canvas.
+const ICON_SIZE: f32 = 32.0;
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+enum CurrentMenu {
+    ModList,
+    ModActions,
+    ConfirmRemove,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ModsMenuEntry {
+    Mod(usize),
+    NoMods,
+    Import,
+    Back,
+}
+
+impl Default for ModsMenuEntry {
+    fn default() -> Self {
+        ModsMenuEntry::Back
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ModActionEntry {
+    Toggle,
+    Remove,
+    Back,
+}
+
+impl Default for ModActionEntry {
+    fn default() -> Self {
+        ModActionEntry::Toggle
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ConfirmRemoveEntry {
+    Title,
+    Yes,
+    No,
+}
+
+impl Default for ConfirmRemoveEntry {
+    fn default() -> Self {
+        ConfirmRemoveEntry::No
+    }
+}
+
+pub struct ModsMenu {
+    current_menu: CurrentMenu,
+    menu: Menu<ModsMenuEntry>,
+    action_menu: Menu<ModActionEntry>,
+    confirm_remove: Menu<ConfirmRemoveEntry>,
+    action_mod: usize,
+    changed: bool,
+    message: Option<String>,
+    /// Icons of installed mods by id, loaded once a mod gets selected.
+    icons: RefCell<HashMap<String, Option<Box<dyn BackendTexture>>>>,
+}
+
+impl ModsMenu {
+    pub fn new() -> ModsMenu {
+        ModsMenu {
+            current_menu: CurrentMenu::ModList,
+            menu: Menu::new(0, 0, 220, 0),
+            action_menu: Menu::new(0, 0, 120, 0),
+            confirm_remove: Menu::new(0, 0, 160, 0),
+            action_mod: 0,
+            changed: false,
+            message: None,
+            icons: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn init(&mut self, state: &mut SharedGameState) -> GameResult {
+        self.current_menu = CurrentMenu::ModList;
+        self.changed = false;
+        self.message = None;
+        self.icons.borrow_mut().clear();
+        self.rebuild(state);
+        self.menu.selected =
+            if state.mod_manager.mods.is_empty() { ModsMenuEntry::Import } else { ModsMenuEntry::Mod(0) };
+
+        Ok(())
+    }
+
+    fn rebuild(&mut self, state: &SharedGameState) {
+        let selected = self.menu.selected;
+        self.menu = Menu::new(0, 0, 220, 0);
+
+        let values = vec![
+            state.loc.t("common.on").to_owned(),
+            state.loc.t("common.off").to_owned(),
+            state.loc.t("menus.mods_menu.error").to_owned(),
+        ];
+
+        for (idx, installed) in state.mod_manager.mods.iter().enumerate() {
+            let label = if installed.manifest.version.is_empty() {
+                installed.manifest.name.clone()
+            } else {
+                format!("{} {}", installed.manifest.name, installed.manifest.version)
+            };
+
+            let value = match (installed.enabled, installed.problems.is_empty()) {
+                (true, true) => 0,
+                (false, _) => 1,
+                (true, false) => 2,
+            };
+
+            self.menu.push_entry(ModsMenuEntry::Mod(idx), MenuEntry::Options(label, value, values.clone()));
+        }
+
+        if state.mod_manager.mods.is_empty() {
+            self.menu.push_entry(
+                ModsMenuEntry::NoMods,
+                MenuEntry::Disabled(state.loc.t("menus.mods_menu.no_mods").to_owned()),
+            );
+        }
+
+        self.menu
+            .push_entry(ModsMenuEntry::Import, MenuEntry::Active(state.loc.t("menus.mods_menu.import").to_owned()));
+        self.menu.push_entry(ModsMenuEntry::Back, MenuEntry::Active(state.loc.t("common.back").to_owned()));
+
+        self.menu.selected = match selected {
+            ModsMenuEntry::Mod(idx) if idx >= state.mod_manager.mods.len() => match state.mod_manager.mods.len() {
+                0 => ModsMenuEntry::Import,
+                len => ModsMenuEntry::Mod(len - 1),
+            },
+            selected => selected,
+        };
+    }
+
+    fn open_actions(&mut self, idx: usize, state: &SharedGameState) {
+        let enabled = state.mod_manager.mods.get(idx).map(|m| m.enabled).unwrap_or(false);
+        let toggle = if enabled { "menus.mods_menu.disable" } else { "menus.mods_menu.enable" };
+
+        self.action_menu = Menu::new(0, 0, 120, 0);
+        self.action_menu.push_entry(ModActionEntry::Toggle, MenuEntry::Active(state.loc.t(toggle).to_owned()));
+        self.action_menu
+            .push_entry(ModActionEntry::Remove, MenuEntry::Active(state.loc.t("menus.mods_menu.remove").to_owned()));
+        self.action_menu.push_entry(ModActionEntry::Back, MenuEntry::Active(state.loc.t("common.back").to_owned()));
+        self.action_menu.selected = ModActionEntry::Toggle;
+
+        self.action_mod = idx;
+        self.current_menu = CurrentMenu::ModActions;
+    }
+
+    fn open_confirm_remove(&mut self, state: &SharedGameState) {
+        let name = state.mod_manager.mods.get(self.action_mod).map(|m| m.manifest.name.as_str()).unwrap_or("");
+
+        self.confirm_remove = Menu::new(0, 0, 160, 0);
+        self.confirm_remove.push_entry(
+            ConfirmRemoveEntry::Title,
+            MenuEntry::Disabled(state.tt("menus.mods_menu.remove_confirm", &[("name", name)])),
+        );
+        self.confirm_remove
+            .push_entry(ConfirmRemoveEntry::Yes, MenuEntry::Active(state.loc.t("common.yes").to_owned()));
+        self.confirm_remove.push_entry(ConfirmRemoveEntry::No, MenuEntry::Active(state.loc.t("common.no").to_owned()));
+        self.confirm_remove.selected = ConfirmRemoveEntry::No;
+
+        self.current_menu = CurrentMenu::ConfirmRemove;
+    }
+
+    fn import_packages(&mut self, state: &mut SharedGameState, ctx: &mut Context) {
+        let results = state.mod_manager.import_packages(ctx);
+        if results.is_empty() {
+            self.message = Some(state.loc.t("menus.mods_menu.import_none").to_owned());
+            return;
+        }
+
+        // importing unmounts the mods, so they have to be mounted again when leaving the menu
+        self.changed = true;
+        self.icons.borrow_mut().clear();
+        self.rebuild(state);
+
+        let count = results.iter().filter(|(_, result)| result.is_ok()).count();
+        let mut message = state.tn("menus.mods_menu.import_done", count as i64, &[]);
+
+        if let Some((file, Err(e))) = results.iter().find(|(_, result)| result.is_err()) {
+            let error = e.to_string();
+            message.push('\n');
+            message.push_str(
+                &state.tt("menus.mods_menu.import_failed", &[("file", file.as_str()), ("error", error.as_str())]),
+            );
+        }
+
+        self.message = Some(message);
+    }
+
+    fn selected_mod(&self) -> Option<usize> {
+        match self.current_menu {
+            CurrentMenu::ModList => match self.menu.selected {
+                ModsMenuEntry::Mod(idx) => Some(idx),
+                _ => None,
+            },
+            CurrentMenu::ModActions | CurrentMenu::ConfirmRemove => Some(self.action_mod),
+        }
+    }
+
+    fn load_icon(&mut self, idx: usize, state: &SharedGameState, ctx: &mut Context) {
+        let id = match state.mod_manager.mods.get(idx) {
+            Some(installed) => &installed.id,
+            None => return,
+        };
+
+        if self.icons.borrow().contains_key(id) {
+            return;
+        }
+
+        let icon = match state.mod_manager.load_icon(ctx, idx) {
+            Ok(icon) => icon,
+            Err(e) => {
+                log::warn!("Failed to load icon of mod {}: {}", id, e);
+                None
+            }
+        };
+        self.icons.borrow_mut().insert(id.clone(), icon);
+    }
+
+    fn update_sizes(&mut self, state: &SharedGameState) {
+        self.menu.update_width(state);
+        self.menu.update_height(state);
+        self.menu.x = ((state.canvas_size.0 - self.menu.width as f32) / 2.0).floor() as isize;
+        self.menu.y = ((state.canvas_size.1 - self.menu.height as f32) / 2.0).floor() as isize;
+
+        self.action_menu.update_width(state);
+        self.action_menu.update_height(state);
+        self.action_menu.x = ((state.canvas_size.0 - self.action_menu.width as f32) / 2.0).floor() as isize;
+        self.action_menu.y = ((state.canvas_size.1 - self.action_menu.height as f32) / 2.0).floor() as isize;
+
+        self.confirm_remove.update_width(state);
+        self.confirm_remove.update_height(state);
+        self.confirm_remove.x = ((state.canvas_size.0 - self.confirm_remove.width as f32) / 2.0).floor() as isize;
+        self.confirm_remove.y = ((state.canvas_size.1 - self.confirm_remove.height as f32) / 2.0).floor() as isize;
+    }
+
+    pub fn tick(
+        &mut self,
+        exit_action: &mut dyn FnMut(),
+        controller: &mut CombinedMenuController,
+        state: &mut SharedGameState,
+        ctx: &mut Context,
+    ) -> GameResult {
+        self.update_sizes(state);
+
+        if let Some(idx) = self.selected_mod() {
+            self.load_icon(idx, state, ctx);
+        }
+
+        match self.current_menu {
+            CurrentMenu::ModList => match self.menu.tick(controller, state) {
+                MenuSelectionResult::Selected(ModsMenuEntry::Mod(idx), _) => {
+                    self.message = None;
+                    self.open_actions(idx, state);
+                }
+                MenuSelectionResult::Left(ModsMenuEntry::Mod(idx), _, offset)
+                | MenuSelectionResult::Right(ModsMenuEntry::Mod(idx), _, offset) => {
+                    let new_idx = state.mod_manager.move_mod(idx, offset as isize);
+                    self.changed = true;
+                    self.rebuild(state);
+                    self.menu.selected = ModsMenuEntry::Mod(new_idx);
+                }
+                MenuSelectionResult::Selected(ModsMenuEntry::Import, _) => {
+                    self.import_packages(state, ctx);
+                }
+                MenuSelectionResult::Selected(ModsMenuEntry::Back, _) | MenuSelectionResult::Canceled => {
+                    if self.changed {
+                        self.changed = false;
+
+                        state.mod_manager.store_settings(&mut state.settings);
+                        state.settings.save(ctx)?;
+                        state.mount_mods(ctx);
+                        state.reload_resources(ctx)?;
+                        state.reload_graphics();
+
+                        // the title menus are rebuilt with the challenges of the mounted mods
+                        state.next_scene = Some(Box::new(TitleScene::new()));
+                    }
+
+                    exit_action();
+                }
+                _ => (),
+            },
+            CurrentMenu::ModActions => match self.action_menu.tick(controller, state) {
+                MenuSelectionResult::Selected(ModActionEntry::Toggle, _) => {
+                    let enabled = state.mod_manager.mods.get(self.action_mod).map(|m| m.enabled).unwrap_or(false);
+                    state.mod_manager.set_enabled(self.action_mod, !enabled);
+                    self.changed = true;
+                    self.rebuild(state);
+                    self.current_menu = CurrentMenu::ModList;
+                }
+                MenuSelectionResult::Selected(ModActionEntry::Remove, _) => {
+                    self.open_confirm_remove(state);
+                }
+                MenuSelectionResult::Selected(ModActionEntry::Back, _) | MenuSelectionResult::Canceled => {
+                    self.current_menu = CurrentMenu::ModList;
+                }
+                _ => (),
+            },
+            CurrentMenu::ConfirmRemove => match self.confirm_remove.tick(controller, state) {
+                MenuSelectionResult::Selected(ConfirmRemoveEntry::Yes, _) => {
+                    if let Err(e) = state.mod_manager.remove_mod(ctx, self.action_mod) {
+                        self.message = Some(e.to_string());
+                    }
+
+                    self.changed = true;
+                    self.icons.borrow_mut().clear();
+                    self.rebuild(state);
+                    self.current_menu = CurrentMenu::ModList;
+                }
+                MenuSelectionResult::Selected(ConfirmRemoveEntry::No, _) | MenuSelectionResult::Canceled => {
+                    self.current_menu = CurrentMenu::ModActions;
+                }
+                _ => (),
+            },
+        }
+
+        Ok(())
+    }
+
+    pub fn draw(&self, state: &mut SharedGameState, ctx: &mut Context) -> GameResult {
+        match self.current_menu {
+            CurrentMenu::ModList => self.menu.draw(state, ctx)?,
+            CurrentMenu::ModActions => self.action_menu.draw(state, ctx)?,
+            CurrentMenu::ConfirmRemove => self.confirm_remove.draw(state, ctx)?,
+        }
+
+        let selected_mod = self.selected_mod();
+
+        let mut lines = Vec::new();
+        if let Some(installed) = selected_mod.and_then(|idx| state.mod_manager.mods.get(idx)) {
+            if !installed.manifest.author.is_empty() {
+                lines.push(state.tt("menus.mods_menu.author", &[("author", installed.manifest.author.as_str())]));
+            }
+            if !installed.manifest.description.is_empty() {
+                lines.push(installed.manifest.description.clone());
+            }
+            lines.extend(installed.problems.iter().cloned());
+            if self.current_menu == CurrentMenu::ModList {
+                lines.push(state.loc.t("menus.mods_menu.order_info").to_owned());
+            }
+        }
+
+        if let Some(message) = &self.message {
+            lines.extend(message.lines().map(str::to_owned));
+        }
+
+        if let Some(installed) = selected_mod.and_then(|idx| state.mod_manager.mods.get(idx)) {
+            if let Some(Some(icon)) = self.icons.borrow_mut().get_mut(&installed.id) {
+                let (width, height) = icon.dimensions();
+                let (x, y) = (8.0, state.canvas_size.1 - 8.0 - ICON_SIZE);
+
+                icon.clear();
+                icon.add(SpriteBatchCommand::DrawRect(
+                    Rect::new(0.0, 0.0, width as f32, height as f32),
+                    Rect::new(
+                        x * state.scale,
+                        y * state.scale,
+                        (x + ICON_SIZE) * state.scale,
+                        (y + ICON_SIZE) * state.scale,
+                    ),
+                ));
+                icon.draw()?;
+            }
+        }
+
+        let line_height = state.font.line_height();
+        let mut y = state.canvas_size.1 - 8.0 - line_height * lines.len() as f32;
+        for line in lines.iter() {
+            state.font.builder().center(state.canvas_size.0).y(y).shadow(true).draw(
+                line,
+                ctx,
+                &state.constants,
+                &mut state.texture_set,
+            )?;
+            y += line_height;
+        }
+
+        Ok(())
+    }
+}
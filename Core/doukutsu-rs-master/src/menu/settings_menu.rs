@@ -3,9 +3,11 @@ use itertools::Itertools;
 use crate::framework::context::Context;
 use crate::framework::error::GameResult;
 use crate::framework::graphics::VSyncMode;
+use crate::framework::post_process::{self, PostProcessPass};
 use crate::framework::{filesystem, graphics};
 use crate::game::shared_game_state::{CutsceneSkipMode, ScreenShakeIntensity, SharedGameState, TimingMode, WindowMode};
 use crate::graphics::font::Font;
+use crate::graphics::texture_pack::TexturePack;
 use crate::input::combined_menu_controller::CombinedMenuController;
 use crate::menu::MenuEntry;
 use crate::menu::{Menu, MenuSelectionResult};
@@ -55,11 +57,14 @@ enum GraphicsMenuEntry {
     WindowMode,
     LightingEffects,
     WeaponLightCone,
+    NormalMapping,
     ScreenShake,
+    PostProcess,
     MotionInterpolation,
     SubpixelScrolling,
     OriginalTextures, 
     SeasonalTextures,
+    TexturePack,
     Renderer,
     Back,
 }
@@ -75,6 +80,8 @@ enum SoundMenuEntry {
     MusicVolume,
     EffectsVolume,
     BGMInterpolation,
+    EnvironmentalAudio,
+    PositionalSfx,
     Soundtrack,
     Back,
 }
@@ -172,6 +179,28 @@ impl Default for PortableMenuEntry {
     }
 }
 
+/// Switches to the post-process preset at given option, 0 turns post-processing off.
+fn set_post_process_option(state: &mut SharedGameState, ctx: &mut Context, value: usize) {
+    state.settings.post_process = match value {
+        0 => Vec::new(),
+        v => PostProcessPass::preset(post_process::PRESETS[v - 1].0).into_iter().collect(),
+    };
+    state.apply_post_process(ctx);
+
+    let _ = state.settings.save(ctx);
+}
+
+/// Switches to the texture pack at given option, 0 turns texture packs off.
+fn set_texture_pack_option(state: &mut SharedGameState, ctx: &mut Context, packs: &[String], value: usize) {
+    state.settings.texture_pack = match value {
+        0 => None,
+        v => Some(packs[v - 1].clone()),
+    };
+    state.reload_graphics();
+
+    let _ = state.settings.save(ctx);
+}
+
 pub struct SettingsMenu {
     current: CurrentMenu,
     main: Menu<MainMenuEntry>,
@@ -184,6 +213,8 @@ pub struct SettingsMenu {
     advanced: Menu<AdvancedMenuEntry>,
     portable: Menu<PortableMenuEntry>,
     controls_menu: ControlsMenu,
+    /// Ids of the installed texture packs, in the order they're listed in the graphics menu.
+    texture_packs: Vec<String>,
     pub on_title: bool,
 }
 
@@ -221,6 +252,7 @@ impl SettingsMenu {
             advanced,
             controls_menu,
             portable,
+            texture_packs: Vec::new(),
             on_title: false,
         }
     }
@@ -273,6 +305,13 @@ impl SettingsMenu {
                 state.settings.light_cone,
             ),
         );
+        self.graphics.push_entry(
+            GraphicsMenuEntry::NormalMapping,
+            MenuEntry::Toggle(
+                state.loc.t("menus.options_menu.graphics_menu.normal_mapping").to_owned(),
+                state.settings.normal_mapping,
+            ),
+        );
         self.graphics.push_entry(
             GraphicsMenuEntry::ScreenShake,
             MenuEntry::Options(
@@ -285,6 +324,33 @@ impl SettingsMenu {
                 ],
             ),
         );
+
+        let mut post_process_options =
+            vec![state.loc.t("menus.options_menu.graphics_menu.post_process.off").to_owned()];
+        for (name, _, _) in post_process::PRESETS.iter() {
+            post_process_options
+                .push(state.loc.t(&format!("menus.options_menu.graphics_menu.post_process.{}", name)).to_owned());
+        }
+
+        let post_process_value = match post_process::chain_preset(&state.settings.post_process) {
+            Some(preset) => post_process::PRESETS.iter().position(|(name, _, _)| *name == preset).unwrap() + 1,
+            None if state.settings.post_process.is_empty() => 0,
+            None => {
+                // a chain set up by hand in settings can't be picked again once cycled away from
+                post_process_options
+                    .push(state.loc.t("menus.options_menu.graphics_menu.post_process.custom").to_owned());
+                post_process_options.len() - 1
+            }
+        };
+
+        self.graphics.push_entry(
+            GraphicsMenuEntry::PostProcess,
+            MenuEntry::Options(
+                state.loc.t("menus.options_menu.graphics_menu.post_process.entry").to_owned(),
+                post_process_value,
+                post_process_options,
+            ),
+        );
         self.graphics.push_entry(
             GraphicsMenuEntry::MotionInterpolation,
             MenuEntry::Toggle(
@@ -328,6 +394,28 @@ impl SettingsMenu {
             );
         }
 
+        let texture_packs = TexturePack::discover(ctx);
+        if !texture_packs.is_empty() {
+            let mut texture_pack_options =
+                vec![state.loc.t("menus.options_menu.graphics_menu.texture_pack.off").to_owned()];
+            texture_pack_options.extend(texture_packs.iter().map(|(_, manifest)| manifest.name.clone()));
+
+            let texture_pack_value = match &state.settings.texture_pack {
+                Some(id) => texture_packs.iter().position(|(pack, _)| pack == id).map_or(0, |pos| pos + 1),
+                None => 0,
+            };
+
+            self.graphics.push_entry(
+                GraphicsMenuEntry::TexturePack,
+                MenuEntry::Options(
+                    state.loc.t("menus.options_menu.graphics_menu.texture_pack.entry").to_owned(),
+                    texture_pack_value,
+                    texture_pack_options,
+                ),
+            );
+        }
+        self.texture_packs = texture_packs.into_iter().map(|(id, _)| id).collect();
+
         self.graphics.push_entry(
             GraphicsMenuEntry::Renderer,
             MenuEntry::Disabled(format!(
@@ -498,6 +586,20 @@ impl SettingsMenu {
                 ],
             ),
         );
+        self.sound.push_entry(
+            SoundMenuEntry::EnvironmentalAudio,
+            MenuEntry::Toggle(
+                state.loc.t("menus.options_menu.sound_menu.environmental_audio").to_owned(),
+                state.settings.environmental_audio,
+            ),
+        );
+        self.sound.push_entry(
+            SoundMenuEntry::PositionalSfx,
+            MenuEntry::Toggle(
+                state.loc.t("menus.options_menu.sound_menu.positional_sfx").to_owned(),
+                state.settings.positional_sfx,
+            ),
+        );
         self.sound.push_entry(
             SoundMenuEntry::Soundtrack,
             MenuEntry::Active(
@@ -751,6 +853,14 @@ impl SettingsMenu {
                         *value = state.settings.light_cone;
                     }
                 }
+                MenuSelectionResult::Selected(GraphicsMenuEntry::NormalMapping, toggle) => {
+                    if let MenuEntry::Toggle(_, value) = toggle {
+                        state.settings.normal_mapping = !state.settings.normal_mapping;
+                        let _ = state.settings.save(ctx);
+
+                        *value = state.settings.normal_mapping;
+                    }
+                }
                 MenuSelectionResult::Selected(GraphicsMenuEntry::ScreenShake, toggle)
                 | MenuSelectionResult::Right(GraphicsMenuEntry::ScreenShake, toggle, _) => {
                     if let MenuEntry::Options(_, value, _) = toggle {
@@ -780,6 +890,23 @@ impl SettingsMenu {
                         let _ = state.settings.save(ctx);
                     }
                 }
+                MenuSelectionResult::Selected(GraphicsMenuEntry::PostProcess, toggle)
+                | MenuSelectionResult::Right(GraphicsMenuEntry::PostProcess, toggle, _) => {
+                    if let MenuEntry::Options(_, value, _) = toggle {
+                        *value = if *value >= post_process::PRESETS.len() { 0 } else { *value + 1 };
+                        set_post_process_option(state, ctx, *value);
+                    }
+                }
+                MenuSelectionResult::Left(GraphicsMenuEntry::PostProcess, toggle, _) => {
+                    if let MenuEntry::Options(_, value, _) = toggle {
+                        *value = match *value {
+                            0 => post_process::PRESETS.len(),
+                            v if v > post_process::PRESETS.len() => 0,
+                            v => v - 1,
+                        };
+                        set_post_process_option(state, ctx, *value);
+                    }
+                }
                 MenuSelectionResult::Selected(GraphicsMenuEntry::MotionInterpolation, toggle) => {
                     if let MenuEntry::Toggle(_, value) = toggle {
                         state.settings.motion_interpolation = !state.settings.motion_interpolation;
@@ -818,6 +945,19 @@ impl SettingsMenu {
                         *value = state.settings.seasonal_textures;
                     }
                 }
+                MenuSelectionResult::Selected(GraphicsMenuEntry::TexturePack, toggle)
+                | MenuSelectionResult::Right(GraphicsMenuEntry::TexturePack, toggle, _) => {
+                    if let MenuEntry::Options(_, value, _) = toggle {
+                        *value = if *value >= self.texture_packs.len() { 0 } else { *value + 1 };
+                        set_texture_pack_option(state, ctx, &self.texture_packs, *value);
+                    }
+                }
+                MenuSelectionResult::Left(GraphicsMenuEntry::TexturePack, toggle, _) => {
+                    if let MenuEntry::Options(_, value, _) = toggle {
+                        *value = if *value == 0 { self.texture_packs.len() } else { *value - 1 };
+                        set_texture_pack_option(state, ctx, &self.texture_packs, *value);
+                    }
+                }
                 MenuSelectionResult::Selected(GraphicsMenuEntry::Back, _) | MenuSelectionResult::Canceled => {
                     self.current = CurrentMenu::MainMenu
                 }
@@ -877,6 +1017,22 @@ impl SettingsMenu {
                         let _ = state.settings.save(ctx);
                     }
                 }
+                MenuSelectionResult::Selected(SoundMenuEntry::EnvironmentalAudio, toggle) => {
+                    if let MenuEntry::Toggle(_, value) = toggle {
+                        state.settings.environmental_audio = !state.settings.environmental_audio;
+                        let _ = state.settings.save(ctx);
+
+                        *value = state.settings.environmental_audio;
+                    }
+                }
+                MenuSelectionResult::Selected(SoundMenuEntry::PositionalSfx, toggle) => {
+                    if let MenuEntry::Toggle(_, value) = toggle {
+                        state.settings.positional_sfx = !state.settings.positional_sfx;
+                        let _ = state.settings.save(ctx);
+
+                        *value = state.settings.positional_sfx;
+                    }
+                }
                 MenuSelectionResult::Selected(SoundMenuEntry::Soundtrack, _) => {
                     let mut active_soundtrack = SoundtrackMenuEntry::Soundtrack(0);
 
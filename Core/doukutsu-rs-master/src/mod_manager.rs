@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::framework::backend::BackendTexture;
+use crate::framework::context::Context;
+use crate::framework::error::{GameError, GameResult};
+use crate::framework::filesystem;
+use crate::framework::graphics::create_texture;
+use crate::framework::vfs::{PhysicalFS, ZipFS, VFS};
+use crate::game::settings::Settings;
+
+/// Directory in the user `mods/` directory scanned for packages to install.
+const IMPORT_DIR: &str = "/mods/import";
+
+/// Contents of the `mod.json` manifest of a mod installed in the user `mods/` directory.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ModManifest {
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    /// Path of the icon image, relative to the mod directory.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Oldest engine version the mod works with, eg. `0.101.0`.
+    #[serde(default)]
+    pub engine_version: Option<String>,
+    /// Ids of mods which have to be enabled and loaded before this one.
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    /// Ids of mods which can't be enabled together with this one.
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+    /// SHA-256 hashes of files in a mod package, keyed by their path relative to the package root.
+    #[serde(default)]
+    pub checksums: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+pub struct InstalledMod {
+    /// Name of the mod directory.
+    pub id: String,
+    /// File name of the package in the `mods/` directory, `None` if the mod is installed as a directory.
+    pub package: Option<String>,
+    pub manifest: ModManifest,
+    pub enabled: bool,
+    /// Reasons why the mod can't be mounted, empty if it can.
+    pub problems: Vec<String>,
+}
+
+/// Keeps track of the mods installed in the user `mods/` directory and mounts the enabled ones on top of game data.
+///
+/// Unlike challenge mods listed in `mods.txt`, which replace the game, these mods mirror the layout of the data
+/// directory and are layered over it, mods later in the load order take precedence. A mod is either a directory or
+/// a `.zip` package, both with `mod.json` in their root.
+pub struct ModManager {
+    /// Installed mods in load order.
+    pub mods: Vec<InstalledMod>,
+    mounted: Vec<PathBuf>,
+}
+
+impl ModManager {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> ModManager {
+        ModManager { mods: Vec::new(), mounted: Vec::new() }
+    }
+
+    /// Reads the manifests of installed mods, applying the load order and enabled state stored in settings.
+    pub fn discover(ctx: &mut Context, settings: &Settings) -> ModManager {
+        let mut manager = ModManager::new();
+
+        let entries = match filesystem::user_read_dir(ctx, "/mods") {
+            Ok(entries) => entries,
+            Err(_) => return manager,
+        };
+
+        for path in entries {
+            let file_name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(file_name) => file_name.to_owned(),
+                None => continue,
+            };
+
+            let (id, package) = if is_package(&file_name) {
+                let id = file_name[..file_name.len() - 4].to_owned();
+                (id, Some(file_name))
+            } else if filesystem::user_is_file(ctx, format!("/mods/{}/mod.json", file_name)) {
+                (file_name, None)
+            } else {
+                continue;
+            };
+
+            if manager.mods.iter().any(|m| m.id == id) {
+                log::warn!("Skipping mod {}: another mod with the same name is installed.", id);
+                continue;
+            }
+
+            let manifest = match &package {
+                Some(package) => open_package(ctx, package).and_then(|fs| read_manifest(&fs)),
+                None => read_dir_manifest(ctx, &id),
+            };
+
+            match manifest {
+                Ok(manifest) => {
+                    let enabled = !settings.disabled_mods.contains(&id);
+                    manager.mods.push(InstalledMod { id, package, manifest, enabled, problems: Vec::new() });
+                }
+                Err(e) => log::warn!("Skipping mod {}: {}", id, e),
+            }
+        }
+
+        // mods missing from the stored order (eg. newly installed ones) go last
+        manager.mods.sort_by(|a, b| {
+            let pos_a = settings.mod_order.iter().position(|id| *id == a.id).unwrap_or(usize::MAX);
+            let pos_b = settings.mod_order.iter().position(|id| *id == b.id).unwrap_or(usize::MAX);
+
+            pos_a.cmp(&pos_b).then_with(|| a.id.cmp(&b.id))
+        });
+
+        manager.validate();
+        manager
+    }
+
+    /// Updates the list of problems preventing each mod from being mounted.
+    pub fn validate(&mut self) {
+        let engine_version = option_env!("DRS_BUILD_VERSION_OVERRIDE").unwrap_or(env!("CARGO_PKG_VERSION"));
+
+        for idx in 0..self.mods.len() {
+            let mut problems = Vec::new();
+            let manifest = &self.mods[idx].manifest;
+
+            if let Some(required) = &manifest.engine_version {
+                if parse_version(engine_version) < parse_version(required) {
+                    problems.push(format!("Requires engine version {} or newer.", required));
+                }
+            }
+
+            for dependency in manifest.dependencies.iter() {
+                match self.mods.iter().position(|m| m.id == *dependency) {
+                    None => problems.push(format!("Missing dependency {}.", dependency)),
+                    Some(pos) if !self.mods[pos].enabled => {
+                        problems.push(format!("Dependency {} is disabled.", dependency))
+                    }
+                    Some(pos) if pos > idx => problems.push(format!("Dependency {} must be loaded first.", dependency)),
+                    Some(_) => (),
+                }
+            }
+
+            for conflict in manifest.conflicts.iter() {
+                if self.mods.iter().any(|m| m.id == *conflict && m.enabled) {
+                    problems.push(format!("Conflicts with {}.", conflict));
+                }
+            }
+
+            self.mods[idx].problems = problems;
+        }
+    }
+
+    /// Mounts enabled mods without problems from given directory, replacing the previously mounted ones.
+    /// Packages failing verification are left out and get a problem added.
+    pub fn mount(&mut self, ctx: &mut Context, mods_path: &Path) {
+        self.unmount(ctx);
+        self.validate();
+
+        for installed in self.mods.iter_mut() {
+            if !installed.enabled {
+                continue;
+            }
+
+            if !installed.problems.is_empty() {
+                log::warn!("Not mounting mod {}: {}", installed.id, installed.problems.join(" "));
+                continue;
+            }
+
+            let vfs: Box<dyn VFS> = match &installed.package {
+                // the package might have been modified or damaged since it was imported
+                Some(package) => match open_package(ctx, package).and_then(|fs| {
+                    verify_package(&fs, &installed.manifest)?;
+                    Ok(fs)
+                }) {
+                    Ok(fs) => Box::new(fs),
+                    Err(e) => {
+                        log::warn!("Not mounting mod {}: {}", installed.id, e);
+                        installed.problems.push(format!("Damaged package: {}", e));
+                        continue;
+                    }
+                },
+                None => Box::new(PhysicalFS::new(&mods_path.join(&installed.id), true)),
+            };
+
+            log::info!("Mounting mod {} ({}) from {:?}", installed.id, installed.manifest.name, vfs);
+
+            // each mod goes in front of the ones mounted before it, so the last one in load order wins
+            if let Some(root) = vfs.to_path_buf() {
+                self.mounted.push(root);
+            }
+            filesystem::mount_vfs_front(ctx, vfs);
+        }
+    }
+
+    /// Unmounts all mods mounted by `mount`.
+    pub fn unmount(&mut self, ctx: &mut Context) {
+        for root in self.mounted.drain(..) {
+            filesystem::unmount_vfs(ctx, &root);
+        }
+    }
+
+    /// Verifies and installs packages placed in `mods/import`, replacing installed packages with the same name.
+    ///
+    /// Returns the file name of each package found along with the result of installing it. If any package was found,
+    /// mods are unmounted and have to be mounted again afterwards.
+    pub fn import_packages(&mut self, ctx: &mut Context) -> Vec<(String, GameResult)> {
+        let packages: Vec<String> = match filesystem::user_read_dir(ctx, IMPORT_DIR) {
+            Ok(entries) => entries
+                .filter_map(|path| path.file_name().and_then(|name| name.to_str()).map(|name| name.to_owned()))
+                .filter(|file_name| is_package(file_name))
+                .collect(),
+            Err(_) => return Vec::new(),
+        };
+
+        if packages.is_empty() {
+            return Vec::new();
+        }
+
+        // packages being replaced can't be open while they're overwritten
+        self.unmount(ctx);
+
+        let mut results = Vec::new();
+        for file_name in packages {
+            let result = self.import_package(ctx, &file_name);
+            match &result {
+                Ok(()) => log::info!("Imported mod package {}", file_name),
+                Err(e) => log::warn!("Failed to import mod package {}: {}", file_name, e),
+            }
+
+            results.push((file_name, result));
+        }
+
+        self.validate();
+        results
+    }
+
+    fn import_package(&mut self, ctx: &mut Context, file_name: &str) -> GameResult {
+        let id = file_name[..file_name.len() - 4].to_owned();
+        let src_path = format!("{}/{}", IMPORT_DIR, file_name);
+
+        let manifest = {
+            let fs = open_package_at(ctx, &src_path)?;
+            let manifest = read_manifest(&fs)?;
+            verify_package(&fs, &manifest)?;
+            manifest
+        };
+
+        let existing = self.mods.iter().position(|m| m.id == id);
+        let file_name = match existing.map(|idx| &self.mods[idx].package) {
+            Some(Some(package)) => package.clone(),
+            Some(None) => {
+                return Err(GameError::InvalidValue(format!("Mod {} is already installed as a directory.", id)));
+            }
+            None => format!("{}.zip", id),
+        };
+
+        let mut src = filesystem::user_open(ctx, &src_path)?;
+        let mut dst = filesystem::user_create(ctx, format!("/mods/{}", file_name))?;
+        io::copy(&mut src, &mut dst)?;
+        drop(src);
+        filesystem::user_delete(ctx, &src_path)?;
+
+        let installed = InstalledMod { id, package: Some(file_name), manifest, enabled: true, problems: Vec::new() };
+        match existing {
+            Some(idx) => self.mods[idx] = installed,
+            None => self.mods.push(installed),
+        }
+
+        Ok(())
+    }
+
+    /// Uninstalls a mod, deleting its package or directory. Mods have to be mounted again afterwards.
+    pub fn remove_mod(&mut self, ctx: &mut Context, idx: usize) -> GameResult {
+        if idx >= self.mods.len() {
+            return Ok(());
+        }
+
+        // the package can't be deleted while it's open
+        self.unmount(ctx);
+
+        let installed = &self.mods[idx];
+        match &installed.package {
+            Some(package) => filesystem::user_delete(ctx, format!("/mods/{}", package))?,
+            None => filesystem::user_delete_dir(ctx, format!("/mods/{}", installed.id))?,
+        }
+
+        log::info!("Removed mod {}", installed.id);
+        self.mods.remove(idx);
+        self.validate();
+
+        Ok(())
+    }
+
+    /// Loads the icon declared in the manifest of a mod, `None` if it doesn't have one.
+    pub fn load_icon(&self, ctx: &mut Context, idx: usize) -> GameResult<Option<Box<dyn BackendTexture>>> {
+        let installed = match self.mods.get(idx) {
+            Some(installed) => installed,
+            None => return Ok(None),
+        };
+        let icon = match &installed.manifest.icon {
+            Some(icon) => icon,
+            None => return Ok(None),
+        };
+
+        let mut data = Vec::new();
+        match &installed.package {
+            Some(package) => open_package(ctx, package)?.open(&Path::new("/").join(icon))?.read_to_end(&mut data)?,
+            None => filesystem::user_open(ctx, format!("/mods/{}/{}", installed.id, icon))?.read_to_end(&mut data)?,
+        };
+
+        let image = image::load_from_memory(&data)?.to_rgba8();
+        let (width, height) = image.dimensions();
+
+        create_texture(ctx, width as u16, height as u16, &image).map(Some)
+    }
+
+    /// Moves a mod by given offset in the load order, returns its new position.
+    pub fn move_mod(&mut self, idx: usize, offset: isize) -> usize {
+        let new_idx = (idx as isize + offset).clamp(0, self.mods.len() as isize - 1) as usize;
+        if idx != new_idx {
+            let installed = self.mods.remove(idx);
+            self.mods.insert(new_idx, installed);
+            self.validate();
+        }
+
+        new_idx
+    }
+
+    pub fn set_enabled(&mut self, idx: usize, enabled: bool) {
+        if let Some(installed) = self.mods.get_mut(idx) {
+            installed.enabled = enabled;
+            self.validate();
+        }
+    }
+
+    /// Stores the load order and enabled state of mods in settings.
+    pub fn store_settings(&self, settings: &mut Settings) {
+        settings.mod_order = self.mods.iter().map(|m| m.id.clone()).collect();
+        settings.disabled_mods = self.mods.iter().filter(|m| !m.enabled).map(|m| m.id.clone()).collect();
+    }
+}
+
+fn is_package(file_name: &str) -> bool {
+    file_name.len() > 4 && file_name.get(file_name.len() - 4..).map_or(false, |ext| ext.eq_ignore_ascii_case(".zip"))
+}
+
+fn open_package(ctx: &Context, package: &str) -> GameResult<ZipFS> {
+    open_package_at(ctx, &format!("/mods/{}", package))
+}
+
+fn open_package_at(ctx: &Context, path: &str) -> GameResult<ZipFS> {
+    let filesystem::File::VfsFile(file) = filesystem::user_open(ctx, path)?;
+    ZipFS::new(Path::new(path), file)
+}
+
+fn read_manifest(fs: &dyn VFS) -> GameResult<ModManifest> {
+    let file = fs.open(Path::new("/mod.json"))?;
+    serde_json::from_reader(file).map_err(|e| GameError::ParseError(format!("{:?}: mod.json: {}", fs, e)))
+}
+
+fn read_dir_manifest(ctx: &Context, id: &str) -> GameResult<ModManifest> {
+    let path = format!("/mods/{}/mod.json", id);
+    let file = filesystem::user_open(ctx, &path)?;
+    serde_json::from_reader(file).map_err(|e| GameError::ParseError(format!("{}: {}", path, e)))
+}
+
+/// Checks the CRC32 of every file in a package and the SHA-256 hashes listed in its manifest.
+fn verify_package(fs: &ZipFS, manifest: &ModManifest) -> GameResult {
+    fs.verify()?;
+
+    for (path, expected) in manifest.checksums.iter() {
+        let mut file = fs.open(&Path::new("/").join(path))?;
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher)?;
+
+        let actual = format!("{:x}", hasher.finalize());
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(GameError::InvalidValue(format!("Checksum mismatch for {}.", path)));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_version(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .map(|part| part.chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().unwrap_or(0))
+        .collect()
+}
+
+#[test]
+fn test_mod_validation() {
+    fn installed(id: &str, dependencies: &[&str], conflicts: &[&str]) -> InstalledMod {
+        InstalledMod {
+            id: id.to_owned(),
+            package: None,
+            manifest: ModManifest {
+                name: id.to_owned(),
+                author: String::new(),
+                version: String::new(),
+                description: String::new(),
+                icon: None,
+                engine_version: None,
+                dependencies: dependencies.iter().map(|s| s.to_string()).collect(),
+                conflicts: conflicts.iter().map(|s| s.to_string()).collect(),
+                checksums: HashMap::new(),
+            },
+            enabled: true,
+            problems: Vec::new(),
+        }
+    }
+
+    let mut manager = ModManager::new();
+    manager.mods.push(installed("base", &[], &[]));
+    manager.mods.push(installed("addon", &["base"], &[]));
+    manager.mods.push(installed("other", &["missing"], &["base"]));
+    manager.validate();
+
+    assert!(manager.mods[0].problems.is_empty());
+    assert!(manager.mods[1].problems.is_empty());
+    assert_eq!(manager.mods[2].problems.len(), 2);
+
+    assert_eq!(manager.move_mod(1, -1), 0);
+    assert_eq!(manager.mods[0].problems.len(), 1);
+
+    assert!(parse_version("0.101.0") < parse_version("0.102"));
+    assert!(parse_version("1.0.0-beta") >= parse_version("1.0"));
+}
@@ -39,6 +39,7 @@ fn catch(error_list: Rc<RefCell<ErrorList>>, func: impl FnOnce() -> GameResult<(
 
 pub struct EditorScene {
     stage_list: StageListWindow,
+    resize_window: ResizeWindow,
     error_list: Rc<RefCell<ErrorList>>,
     instances: Vec<EditorInstance>,
     subscene: Option<Box<GameScene>>,
@@ -51,6 +52,7 @@ impl EditorScene {
     pub fn new() -> Self {
         EditorScene {
             stage_list: StageListWindow::new(),
+            resize_window: ResizeWindow::new(),
             error_list: Rc::new(RefCell::new(ErrorList::new())),
             instances: Vec::new(),
             subscene: None,
@@ -76,8 +78,12 @@ impl EditorScene {
 
             if let Some(stage) = state.stages.get(stage_id) {
                 let stage = Stage::load(&state.constants.base_paths, stage, ctx)?;
+                let entities = stage.load_npcs(&state.constants.base_paths, ctx).unwrap_or_else(|e| {
+                    log::warn!("Failed to load entities of stage {}: {}", stage.data.map, e);
+                    Vec::new()
+                });
 
-                let new_instance = EditorInstance::new(stage_id, stage);
+                let new_instance = EditorInstance::new(stage_id, stage, entities);
                 self.instances.push(new_instance);
                 self.selected_instance = self.instances.len() - 1;
                 self.switch_tab = true;
@@ -87,6 +93,28 @@ impl EditorScene {
         });
     }
 
+    fn save_stage(&mut self, state: &mut SharedGameState, ctx: &mut Context) {
+        catch(self.error_list.clone(), || {
+            if let Some(instance) = self.instances.get(self.selected_instance) {
+                instance.save(state, ctx)?;
+            }
+
+            Ok(())
+        });
+    }
+
+    fn undo(&mut self) {
+        if let Some(instance) = self.instances.get_mut(self.selected_instance) {
+            instance.undo();
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(instance) = self.instances.get_mut(self.selected_instance) {
+            instance.redo();
+        }
+    }
+
     fn test_stage(&mut self, state: &mut SharedGameState, ctx: &mut Context) {
         catch(self.error_list.clone(), || {
             if let Some(instance) = self.instances.get(self.selected_instance) {
@@ -203,6 +231,28 @@ impl Scene for EditorScene {
         Ok(())
     }
 
+    fn process_debug_keys(&mut self, state: &mut SharedGameState, ctx: &mut Context, key_code: ScanCode) -> GameResult {
+        if let Some(scene) = &mut self.subscene {
+            return scene.process_debug_keys(state, ctx, key_code);
+        }
+
+        let mods = keyboard::active_mods(ctx);
+        if !mods.ctrl() {
+            return Ok(());
+        }
+
+        match key_code {
+            ScanCode::Z if mods.shift() => self.redo(),
+            ScanCode::Z => self.undo(),
+            ScanCode::Y => self.redo(),
+            ScanCode::S => self.save_stage(state, ctx),
+            ScanCode::O => self.stage_list.show(),
+            _ => (),
+        }
+
+        Ok(())
+    }
+
     fn imgui_draw(
         &mut self,
         _game_ui: &mut Components,
@@ -226,6 +276,10 @@ impl Scene for EditorScene {
                     self.stage_list.show();
                 }
 
+                if MenuItem::new("Save stage").shortcut("Ctrl+S").enabled(!self.instances.is_empty()).build(ui) {
+                    self.save_stage(state, ctx);
+                }
+
                 ui.separator();
 
                 if MenuItem::new("Exit editor").build(ui) {
@@ -234,6 +288,31 @@ impl Scene for EditorScene {
 
                 menu.end();
             }
+
+            if let Some(menu) = ui.begin_menu("Edit") {
+                let (can_undo, can_redo) = match self.instances.get(self.selected_instance) {
+                    Some(instance) => (instance.history.can_undo(), instance.history.can_redo()),
+                    None => (false, false),
+                };
+
+                if MenuItem::new("Undo").shortcut("Ctrl+Z").enabled(can_undo).build(ui) {
+                    self.undo();
+                }
+
+                if MenuItem::new("Redo").shortcut("Ctrl+Y").enabled(can_redo).build(ui) {
+                    self.redo();
+                }
+
+                ui.separator();
+
+                if MenuItem::new("Resize map").enabled(!self.instances.is_empty()).build(ui) {
+                    if let Some(instance) = self.instances.get(self.selected_instance) {
+                        self.resize_window.show(instance);
+                    }
+                }
+
+                menu.end();
+            }
             menu_bar.end();
         }
 
@@ -258,6 +337,10 @@ impl Scene for EditorScene {
                 if ui.tool_button("Rectangle", self.current_tool == CurrentTool::Rectangle) {
                     self.current_tool = CurrentTool::Rectangle;
                 }
+                ui.same_line();
+                if ui.tool_button("Entity", self.current_tool == CurrentTool::Entity) {
+                    self.current_tool = CurrentTool::Entity;
+                }
 
                 ui.same_line();
                 ui.text("|");
@@ -290,6 +373,7 @@ impl Scene for EditorScene {
         self.stage_list.action(state, ctx, ui);
 
         if let Some(instance) = self.instances.get_mut(self.selected_instance) {
+            self.resize_window.action(instance, ui);
             instance.process(state, ctx, ui, self.current_tool);
         }
 
@@ -351,3 +435,50 @@ impl StageListWindow {
             });
     }
 }
+
+struct ResizeWindow {
+    visible: bool,
+    size: [i32; 2],
+    offset: [i32; 2],
+}
+
+impl ResizeWindow {
+    fn new() -> Self {
+        ResizeWindow { visible: false, size: [0, 0], offset: [0, 0] }
+    }
+
+    fn show(&mut self, instance: &EditorInstance) {
+        self.visible = true;
+        self.size = [instance.stage.map.width as i32, instance.stage.map.height as i32];
+        self.offset = [0, 0];
+    }
+
+    fn action(&mut self, instance: &mut EditorInstance, ui: &mut imgui::Ui) {
+        if !self.visible {
+            return;
+        }
+
+        Window::new("Resize map")
+            .resizable(false)
+            .collapsible(false)
+            .size([300.0, 140.0], Condition::FirstUseEver)
+            .build(ui, || {
+                ui.text(format!("Current size: {}x{}", instance.stage.map.width, instance.stage.map.height));
+                ui.input_int2("Size", &mut self.size).build();
+                ui.input_int2("Shift", &mut self.offset).build();
+
+                self.size[0] = self.size[0].clamp(1, u16::MAX as i32);
+                self.size[1] = self.size[1].clamp(1, u16::MAX as i32);
+
+                if ui.button("Apply") {
+                    instance.resize(self.size[0] as u16, self.size[1] as u16, self.offset[0], self.offset[1]);
+                    self.visible = false;
+                }
+
+                ui.same_line();
+                if ui.button("Cancel") {
+                    self.visible = false;
+                }
+            });
+    }
+}
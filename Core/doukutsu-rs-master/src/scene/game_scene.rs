@@ -23,7 +23,7 @@ use crate::components::tilemap::{TileLayer, Tilemap};
 use crate::components::water_renderer::{WaterLayer, WaterRenderer};
 use crate::components::whimsical_star::WhimsicalStar;
 use crate::entity::GameEntity;
-use crate::framework::backend::SpriteBatchCommand;
+use crate::framework::backend::{BackendShader, SpriteBatchCommand, VertexData};
 use crate::framework::context::Context;
 use crate::framework::error::GameResult;
 use crate::framework::graphics::{draw_rect, BlendMode, FilterMode};
@@ -39,6 +39,8 @@ use crate::game::npc::list::NPCList;
 use crate::game::npc::{NPCLayer, NPC};
 use crate::game::physics::{PhysicalEntity, OFFSETS};
 use crate::game::player::{ControlMode, Player, TargetPlayer};
+#[cfg(feature = "scripting-lua")]
+use crate::game::scripting::lua::LuaScriptingState;
 use crate::game::scripting::tsc::credit_script::CreditScriptVM;
 use crate::game::scripting::tsc::text_script::{ScriptMode, TextScriptExecutionState, TextScriptVM};
 use crate::game::settings::ControllerType;
@@ -52,8 +54,18 @@ use crate::input::touch_controls::TouchControlType;
 use crate::menu::pause_menu::PauseMenu;
 use crate::scene::title_scene::TitleScene;
 use crate::scene::Scene;
+use crate::sound::backend::SoundListener;
+use crate::sound::effects::AudioEffects;
 use crate::util::rng::RNG;
 
+/// Where light sources of the light map are drawn to.
+enum LightBatch<'a> {
+    /// Spot sprites, used when there's no normal map to shade.
+    Spot(&'a mut Box<dyn SpriteBatch>),
+    /// Quads shaded with the normal map, with the scale of the screen.
+    Normal(Vec<VertexData>, f32),
+}
+
 pub struct GameScene {
     pub tick: u32,
     pub stage: Stage,
@@ -247,7 +259,24 @@ impl GameScene {
     }
 
     fn draw_bullets(&self, state: &mut SharedGameState, ctx: &mut Context) -> GameResult {
+        self.draw_bullet_layer(state, ctx, false).map(|_| ())
+    }
+
+    /// Draws the normal map layer of the bullet spritesheet, returns false if it doesn't have one.
+    fn draw_bullets_normal_map(&self, state: &mut SharedGameState, ctx: &mut Context) -> GameResult<bool> {
+        self.draw_bullet_layer(state, ctx, true)
+    }
+
+    fn draw_bullet_layer(&self, state: &mut SharedGameState, ctx: &mut Context, normal_map: bool) -> GameResult<bool> {
         let batch = state.texture_set.get_or_load_batch(ctx, &state.constants, "Bullet")?;
+        let batch = if normal_map {
+            match batch.normal() {
+                Some(batch) => batch,
+                None => return Ok(false),
+            }
+        } else {
+            batch.as_mut()
+        };
         let mut x: i32;
         let mut y: i32;
         let mut prev_x: i32;
@@ -290,7 +319,7 @@ impl GameScene {
         }
 
         batch.draw(ctx)?;
-        Ok(())
+        Ok(true)
     }
 
     fn draw_carets(&self, state: &mut SharedGameState, ctx: &mut Context) -> GameResult {
@@ -369,15 +398,31 @@ impl GameScene {
         Ok(())
     }
 
-    fn draw_light(&self, x: f32, y: f32, size: f32, color: (u8, u8, u8), batch: &mut Box<dyn SpriteBatch>) {
-        batch.add_rect_scaled_tinted(
-            x - size * 32.0,
-            y - size * 32.0,
-            (color.0, color.1, color.2, 255),
-            size,
-            size,
-            &Rect::new(0, 0, 64, 64),
-        )
+    fn draw_light(&self, x: f32, y: f32, size: f32, color: (u8, u8, u8), batch: &mut LightBatch) {
+        match batch {
+            LightBatch::Spot(batch) => batch.add_rect_scaled_tinted(
+                x - size * 32.0,
+                y - size * 32.0,
+                (color.0, color.1, color.2, 255),
+                size,
+                size,
+                &Rect::new(0, 0, 64, 64),
+            ),
+            LightBatch::Normal(vertices, scale) => {
+                let color = (color.0, color.1, color.2, 255);
+                let left = (x - size * 32.0) * *scale;
+                let top = (y - size * 32.0) * *scale;
+                let right = (x + size * 32.0) * *scale;
+                let bottom = (y + size * 32.0) * *scale;
+
+                vertices.push(VertexData { position: (left, bottom), uv: (0.0, 1.0), color });
+                vertices.push(VertexData { position: (left, top), uv: (0.0, 0.0), color });
+                vertices.push(VertexData { position: (right, top), uv: (1.0, 0.0), color });
+                vertices.push(VertexData { position: (left, bottom), uv: (0.0, 1.0), color });
+                vertices.push(VertexData { position: (right, top), uv: (1.0, 0.0), color });
+                vertices.push(VertexData { position: (right, bottom), uv: (1.0, 1.0), color });
+            }
+        }
     }
 
     fn draw_light_raycast(
@@ -388,7 +433,7 @@ impl GameScene {
         (br, bg, bb): (u8, u8, u8),
         att: f32,
         angle: Range<i32>,
-        batch: &mut Box<dyn SpriteBatch>,
+        batch: &mut LightBatch,
     ) {
         let px = world_point_x as f32 / 512.0;
         let py = world_point_y as f32 / 512.0;
@@ -498,7 +543,52 @@ impl GameScene {
         }
     }
 
+    /// Draws normal map layers of the stage and characters, returns false if none of them have one.
+    /// Draws the normal maps of everything lit by the light map. Returns whether the tileset of the stage has normal
+    /// maps, only then lights are shaded by them.
+    fn draw_normal_map(&self, state: &mut SharedGameState, ctx: &mut Context) -> GameResult<bool> {
+        if !state.settings.normal_mapping || state.normal_canvas.is_none() || !state.texture_set.has_normal_layers() {
+            return Ok(false);
+        }
+
+        graphics::set_render_target(ctx, state.normal_canvas.as_ref())?;
+        graphics::set_blend_mode(ctx, BlendMode::Alpha)?;
+        // surfaces without a normal map face the camera
+        graphics::clear(ctx, Color::from_rgb(128, 128, 255));
+
+        let stage_textures_ref = &*self.stage_textures.deref().borrow();
+        let mut stage_mapped = false;
+
+        for layer in [TileLayer::Background, TileLayer::Middleground] {
+            stage_mapped |=
+                self.tilemap.draw_normal_map(state, ctx, &self.frame, layer, stage_textures_ref, &self.stage)?;
+        }
+
+        for npc in self.npc_list.iter_alive() {
+            npc.draw_normal_map(state, ctx, &self.frame)?;
+        }
+
+        self.boss.draw_normal_map(state, ctx, &self.frame)?;
+        self.draw_bullets_normal_map(state, ctx)?;
+        self.player2.draw_normal_map(state, ctx, &self.frame)?;
+        self.player1.draw_normal_map(state, ctx, &self.frame)?;
+        stage_mapped |= self.tilemap.draw_normal_map(
+            state,
+            ctx,
+            &self.frame,
+            TileLayer::Foreground,
+            stage_textures_ref,
+            &self.stage,
+        )?;
+
+        graphics::set_render_target(ctx, None)?;
+
+        Ok(stage_mapped)
+    }
+
     fn draw_light_map(&self, state: &mut SharedGameState, ctx: &mut Context) -> GameResult {
+        let normal_mapped = self.draw_normal_map(state, ctx)?;
+
         {
             let maybe_canvas = state.lightmap_canvas.as_ref();
 
@@ -532,7 +622,9 @@ impl GameScene {
         }
 
         {
-            let batch = state.texture_set.get_or_load_batch(ctx, &state.constants, "builtin/lightmap/spot")?;
+            let spot = state.texture_set.get_or_load_batch(ctx, &state.constants, "builtin/lightmap/spot")?;
+            let batch =
+                &mut if normal_mapped { LightBatch::Normal(Vec::new(), state.scale) } else { LightBatch::Spot(spot) };
 
             'cc: for (player, inv) in
                 [(&self.player1, &self.inventory_player1), (&self.player2, &self.inventory_player2)].iter()
@@ -1070,7 +1162,14 @@ impl GameScene {
                 }
             }
 
-            batch.draw_filtered(FilterMode::Linear, ctx)?;
+            match batch {
+                LightBatch::Spot(batch) => batch.draw_filtered(FilterMode::Linear, ctx)?,
+                LightBatch::Normal(vertices, _) => {
+                    // the canvas size is truncated to whole pixels
+                    let shader = BackendShader::NormalLight(state.screen_size.0.trunc(), state.screen_size.1.trunc());
+                    graphics::draw_triangle_list(ctx, vertices, state.normal_canvas.as_ref(), shader)?;
+                }
+            }
         }
 
         graphics::set_blend_mode(ctx, BlendMode::Multiply)?;
@@ -1136,7 +1235,7 @@ impl GameScene {
                         let _ = self.npc_list.spawn(0x100, droplet.clone());
                     }
 
-                    state.sound_manager.play_sfx(56);
+                    state.sound_manager.play_sfx_at(56, npc.x, npc.y);
                 }
 
                 npc.splash = true;
@@ -1166,6 +1265,9 @@ impl GameScene {
                 if npc.npc_flags.shootable() {
                     npc.life = (npc.life as i32).saturating_sub(bullet.damage as i32).clamp(0, u16::MAX as i32) as u16;
 
+                    #[cfg(feature = "scripting-lua")]
+                    state.lua.call_event("bulletHit", &[npc.id as i32, bullet.btype as i32, bullet.damage as i32]);
+
                     if npc.life == 0 {
                         if npc.npc_flags.show_damage() {
                             npc.popup.add_value(-bullet.damage);
@@ -1181,7 +1283,7 @@ impl GameScene {
                     } else {
                         if npc.shock < 14 {
                             if let Some(table_entry) = state.npc_table.get_entry(npc.npc_type) {
-                                state.sound_manager.play_sfx(table_entry.hurt_sound);
+                                state.sound_manager.play_sfx_at(table_entry.hurt_sound, npc.x, npc.y);
                             }
 
                             npc.shock = 16;
@@ -1214,7 +1316,7 @@ impl GameScene {
                         CaretType::ProjectileDissipation,
                         Direction::Right,
                     );
-                    state.sound_manager.play_sfx(31);
+                    state.sound_manager.play_sfx_at(31, bullet.x, bullet.y);
                     bullet.life = 0;
                     continue;
                 }
@@ -1272,12 +1374,19 @@ impl GameScene {
                     if npc.life == 0 {
                         npc.life = npc.id;
 
+                        #[cfg(feature = "scripting-lua")]
+                        {
+                            if idx == 0 {
+                                state.lua.call_event("bossDefeat", &[self.boss.boss_type as i32]);
+                            }
+                        }
+
                         if self.player1.cond.alive() && npc.npc_flags.event_when_killed() {
                             state.control_flags.set_tick_world(true);
                             state.control_flags.set_interactions_disabled(true);
                             state.textscript_vm.start_script(npc.event_num);
                         } else {
-                            state.sound_manager.play_sfx(self.boss.death_sound[idx]);
+                            state.sound_manager.play_sfx_at(self.boss.death_sound[idx], npc.x, npc.y);
 
                             let destroy_count = 4usize * (2usize).pow((npc.size as u32).saturating_sub(1));
 
@@ -1296,7 +1405,7 @@ impl GameScene {
                             for _ in 0..3 {
                                 state.create_caret(bullet.x, bullet.y, CaretType::HurtParticles, Direction::Left);
                             }
-                            state.sound_manager.play_sfx(self.boss.hurt_sound[idx]);
+                            state.sound_manager.play_sfx_at(self.boss.hurt_sound[idx], npc.x, npc.y);
                         }
 
                         npc.shock = 8;
@@ -1316,7 +1425,7 @@ impl GameScene {
                     bullet.life = bullet.life.saturating_sub(1);
                 } else if !bullet.weapon_flags.no_proj_dissipation() {
                     state.create_caret(bullet.x, bullet.y, CaretType::ProjectileDissipation, Direction::Right);
-                    state.sound_manager.play_sfx(31);
+                    state.sound_manager.play_sfx_at(31, bullet.x, bullet.y);
                     bullet.life = 0;
                     continue;
                 }
@@ -1334,14 +1443,14 @@ impl GameScene {
 
         self.player1.current_weapon = {
             if let Some(weapon) = self.inventory_player1.get_current_weapon_mut() {
-                weapon.wtype as u8
+                weapon.wtype.id()
             } else {
                 0
             }
         };
         self.player2.current_weapon = {
             if let Some(weapon) = self.inventory_player2.get_current_weapon_mut() {
-                weapon.wtype as u8
+                weapon.wtype.id()
             } else {
                 0
             }
@@ -1658,8 +1767,11 @@ impl Scene for GameScene {
 
         self.npc_list.set_rng_seed(state.game_rng.next());
         self.boss.init_rng(state.game_rng.next());
+        if state.global_scripts_outdated {
+            state.reload_global_scripts(ctx)?;
+        }
         state.textscript_vm.set_scene_script(self.stage.load_text_script(
-            &state.constants.base_paths,
+            &state.constants.text_script_paths,
             &state.constants,
             ctx,
         )?);
@@ -1739,6 +1851,9 @@ impl Scene for GameScene {
         self.pause_menu.init(state, ctx)?;
         self.whimsical_star.init(&self.player1);
 
+        #[cfg(feature = "scripting-lua")]
+        state.lua.call_event("stageLoad", &[self.stage_id as i32]);
+
         #[cfg(feature = "discord-rpc")]
         {
             if self.stage.data.map == state.stages[state.constants.game.intro_stage as usize].map {
@@ -1890,8 +2005,27 @@ impl Scene for GameScene {
         self.flash.tick(state, ())?;
         self.text_boxes.tick(state, ())?;
 
+        let audio_effects = if state.settings.environmental_audio {
+            state.audio_effects.get(&self.stage.data.map, self.player1.flags.in_water())
+        } else {
+            AudioEffects::NONE
+        };
+        state.sound_manager.set_audio_effects(audio_effects);
+
+        let listener = if state.settings.positional_sfx {
+            let half_width = state.canvas_size.0 as i32 * 0x200 / 2;
+            let half_height = state.canvas_size.1 as i32 * 0x200 / 2;
+            Some(SoundListener { x: self.frame.x + half_width, y: self.frame.y + half_height, half_width, half_height })
+        } else {
+            None
+        };
+        state.sound_manager.set_listener(listener);
+
         #[cfg(feature = "scripting-lua")]
-        state.lua.scene_tick();
+        {
+            state.lua.scene_tick();
+            state.lua.hud_tick();
+        }
 
         if state.control_flags.tick_world() {
             self.tick = self.tick.wrapping_add(1);
@@ -2051,6 +2185,9 @@ impl Scene for GameScene {
                 self.hud_player2.draw(state, ctx, &self.frame)?;
                 self.boss_life_bar.draw(state, ctx, &self.frame)?;
 
+                #[cfg(feature = "scripting-lua")]
+                LuaScriptingState::draw_hud(state, ctx)?;
+
                 if self.player2.cond.alive() && !self.player2.cond.hidden() {
                     if self.player2.teleport_counter < state.settings.timing_mode.get_tps() as u16 * 3
                         || self.player2.teleport_counter % 5 != 0
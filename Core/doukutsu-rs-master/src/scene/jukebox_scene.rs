@@ -6,6 +6,8 @@ use crate::components::background::Background;
 use crate::framework::context::Context;
 use crate::framework::error::GameResult;
 use crate::framework::filesystem;
+use crate::framework::keyboard::ScanCode;
+use crate::framework::ui::Components;
 use crate::game::frame::Frame;
 use crate::game::map::Map;
 use crate::game::settings::ControllerType;
@@ -13,6 +15,7 @@ use crate::game::shared_game_state::{SharedGameState, TileSize};
 use crate::game::stage::{BackgroundType, NpcType, Stage, StageData, StageTexturePaths, Tileset};
 use crate::graphics::font::Font;
 use crate::input::combined_menu_controller::CombinedMenuController;
+use crate::live_debugger::org_editor::OrgEditor;
 use crate::scene::title_scene::TitleScene;
 use crate::scene::Scene;
 
@@ -27,6 +30,7 @@ pub struct JukeboxScene {
     stage: Stage,
     textures: StageTexturePaths,
     previous_pause_on_focus_loss_setting: bool,
+    org_editor: OrgEditor,
 }
 
 impl JukeboxScene {
@@ -62,6 +66,7 @@ impl JukeboxScene {
             stage: fake_stage,
             textures,
             previous_pause_on_focus_loss_setting: true,
+            org_editor: OrgEditor::new(),
         }
     }
 }
@@ -116,6 +121,11 @@ impl Scene for JukeboxScene {
 
         self.background.tick()?;
 
+        // the keyboard belongs to the song editor while it's open
+        if self.org_editor.visible {
+            return Ok(());
+        }
+
         let mut song = self.selected_song as i16
             + if self.controller.trigger_right() {
                 1
@@ -161,6 +171,7 @@ impl Scene for JukeboxScene {
         }
 
         if self.controller.trigger_back() {
+            self.org_editor.close(state);
             state.settings.pause_on_focus_loss = self.previous_pause_on_focus_loss_setting;
             state.next_scene = Some(Box::new(TitleScene::new()));
         }
@@ -338,4 +349,32 @@ impl Scene for JukeboxScene {
 
         Ok(())
     }
+
+    fn imgui_draw(
+        &mut self,
+        _game_ui: &mut Components,
+        state: &mut SharedGameState,
+        ctx: &mut Context,
+        ui: &mut imgui::Ui,
+    ) -> GameResult {
+        self.org_editor.draw(state, ctx, ui);
+        Ok(())
+    }
+
+    fn process_debug_keys(
+        &mut self,
+        state: &mut SharedGameState,
+        _ctx: &mut Context,
+        key_code: ScanCode,
+    ) -> GameResult {
+        if key_code == ScanCode::F2 {
+            if self.org_editor.visible {
+                self.org_editor.close(state);
+            } else {
+                self.org_editor.visible = true;
+            }
+        }
+
+        Ok(())
+    }
 }
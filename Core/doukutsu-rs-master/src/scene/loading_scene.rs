@@ -1,7 +1,7 @@
 use crate::framework::context::Context;
 use crate::framework::error::GameResult;
 use crate::framework::graphics;
-use crate::game::shared_game_state::SharedGameState;
+use crate::game::shared_game_state::{ReplayState, SharedGameState};
 use crate::scene::no_data_scene::NoDataScene;
 use crate::scene::Scene;
 
@@ -17,7 +17,10 @@ impl LoadingScene {
     fn load_stuff(&mut self, state: &mut SharedGameState, ctx: &mut Context) -> GameResult {
         state.reload_resources(ctx)?;
 
-        if ctx.headless {
+        if let ReplayState::Playback(_) = state.replay_state {
+            log::info!("Replay playback requested, skipping intro.");
+            state.start_new_game(ctx)?;
+        } else if ctx.headless {
             log::info!("Headless mode detected, skipping intro and loading last saved game.");
             state.load_or_start_game(ctx)?;
         } else {
@@ -15,11 +15,13 @@ use crate::graphics::font::Font;
 use crate::input::combined_menu_controller::CombinedMenuController;
 use crate::input::touch_controls::TouchControlType;
 use crate::menu::coop_menu::PlayerCountMenu;
+use crate::menu::mods_menu::ModsMenu;
 use crate::menu::save_select_menu::SaveSelectMenu;
 use crate::menu::settings_menu::SettingsMenu;
 use crate::menu::{Menu, MenuEntry, MenuSelectionResult};
 use crate::scene::jukebox_scene::JukeboxScene;
 use crate::scene::Scene;
+use crate::sound::effects::AudioEffects;
 
 #[derive(PartialEq, Eq, Copy, Clone)]
 #[repr(u8)]
@@ -31,12 +33,14 @@ enum CurrentMenu {
     ChallengesMenu,
     ChallengeConfirmMenu,
     PlayerCountMenu,
+    ModsMenu,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum MainMenuEntry {
     Start,
     Challenges,
+    Mods,
     Options,
     Editor,
     Jukebox,
@@ -85,6 +89,7 @@ pub struct TitleScene {
     challenges_menu: Menu<ChallengesMenuEntry>,
     confirm_menu: Menu<ConfirmMenuEntry>,
     coop_menu: PlayerCountMenu,
+    mods_menu: ModsMenu,
     settings_menu: SettingsMenu,
     background: Background,
     frame: Frame,
@@ -127,6 +132,7 @@ impl TitleScene {
             challenges_menu: Menu::new(0, 0, 150, 0),
             confirm_menu: Menu::new(0, 0, 150, 0),
             coop_menu: PlayerCountMenu::new(),
+            mods_menu: ModsMenu::new(),
             settings_menu,
             background: Background::new(),
             frame: Frame::new(),
@@ -209,6 +215,9 @@ impl Scene for TitleScene {
         self.controller.add(state.settings.create_player1_controller());
         self.controller.add(state.settings.create_player2_controller());
 
+        state.sound_manager.set_audio_effects(AudioEffects::NONE);
+        state.sound_manager.set_listener(None);
+
         self.main_menu
             .push_entry(MainMenuEntry::Start, MenuEntry::Active(state.loc.t("menus.main_menu.start").to_owned()));
 
@@ -219,6 +228,9 @@ impl Scene for TitleScene {
             );
         }
 
+        self.main_menu
+            .push_entry(MainMenuEntry::Mods, MenuEntry::Active(state.loc.t("menus.main_menu.mods").to_owned()));
+
         self.main_menu
             .push_entry(MainMenuEntry::Options, MenuEntry::Active(state.loc.t("menus.main_menu.options").to_owned()));
 
@@ -339,6 +351,10 @@ impl Scene for TitleScene {
                 MenuSelectionResult::Selected(MainMenuEntry::Challenges, _) => {
                     self.current_menu = CurrentMenu::ChallengesMenu;
                 }
+                MenuSelectionResult::Selected(MainMenuEntry::Mods, _) => {
+                    self.mods_menu.init(state)?;
+                    self.current_menu = CurrentMenu::ModsMenu;
+                }
                 MenuSelectionResult::Selected(MainMenuEntry::Options, _) => {
                     self.current_menu = CurrentMenu::OptionMenu;
                 }
@@ -471,6 +487,17 @@ impl Scene for TitleScene {
                     ctx,
                 )?;
             }
+            CurrentMenu::ModsMenu => {
+                let cm = &mut self.current_menu;
+                self.mods_menu.tick(
+                    &mut || {
+                        *cm = CurrentMenu::MainMenu;
+                    },
+                    &mut self.controller,
+                    state,
+                    ctx,
+                )?;
+            }
         }
 
         self.confirm_menu.update_width(state);
@@ -509,6 +536,7 @@ impl Scene for TitleScene {
                 CurrentMenu::OptionMenu => state.loc.t("menus.main_menu.options"),
                 CurrentMenu::MainMenu => unreachable!(),
                 CurrentMenu::PlayerCountMenu => state.loc.t("menus.main_menu.start"),
+                CurrentMenu::ModsMenu => state.loc.t("menus.main_menu.mods"),
             };
             state
                 .font
@@ -540,6 +568,7 @@ impl Scene for TitleScene {
             CurrentMenu::OptionMenu => self.settings_menu.draw(state, ctx)?,
             CurrentMenu::SaveSelectMenu => self.save_select_menu.draw(state, ctx)?,
             CurrentMenu::PlayerCountMenu => self.coop_menu.draw(state, ctx)?,
+            CurrentMenu::ModsMenu => self.mods_menu.draw(state, ctx)?,
         }
 
         Ok(())
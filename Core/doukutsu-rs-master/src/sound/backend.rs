@@ -7,6 +7,8 @@ use crate::framework::context::Context;
 use crate::framework::error::GameResult;
 use crate::game::settings::Settings;
 use crate::game::LaunchOptions;
+use crate::sound::effects::AudioEffects;
+use crate::sound::organya::Song;
 use crate::sound::pixtone::PixToneParameters;
 
 
@@ -16,6 +18,52 @@ pub enum SongFormat {
     OggSinglePart,
     #[cfg(feature = "ogg-playback")]
     OggMultiPart,
+    #[cfg(feature = "flac-playback")]
+    FlacSinglePart,
+    #[cfg(feature = "flac-playback")]
+    FlacMultiPart,
+    /// MOD, S3M, XM or IT module.
+    Tracker,
+}
+
+/// Extensions of tracker modules, in order of preference.
+pub const TRACKER_EXTENSIONS: [&str; 4] = ["xm", "it", "s3m", "mod"];
+
+/// Files a song can be loaded from, in order of preference. The selected soundtrack is looked up first, then the
+/// Organya paths of the game.
+pub fn song_paths(constants: &EngineConstants, soundtrack: &str, song_name: &str) -> Vec<(SongFormat, Vec<String>)> {
+    let mut prefixes = constants.organya_paths.clone();
+    prefixes.insert(0, "/Soundtracks/".to_owned() + soundtrack + "/");
+
+    if let Some(soundtrack) = constants.soundtracks.iter().find(|s| s.available && s.name == soundtrack) {
+        prefixes.insert(0, soundtrack.path.clone());
+    }
+
+    let mut paths = Vec::new();
+    for prefix in prefixes.iter() {
+        #[cfg(feature = "ogg-playback")]
+        {
+            paths.push((
+                SongFormat::OggMultiPart,
+                vec![format!("{}{}_intro.ogg", prefix, song_name), format!("{}{}_loop.ogg", prefix, song_name)],
+            ));
+            paths.push((SongFormat::OggSinglePart, vec![format!("{}{}.ogg", prefix, song_name)]));
+        }
+        #[cfg(feature = "flac-playback")]
+        {
+            paths.push((
+                SongFormat::FlacMultiPart,
+                vec![format!("{}{}_intro.flac", prefix, song_name), format!("{}{}_loop.flac", prefix, song_name)],
+            ));
+            paths.push((SongFormat::FlacSinglePart, vec![format!("{}{}.flac", prefix, song_name)]));
+        }
+        for extension in TRACKER_EXTENSIONS.iter() {
+            paths.push((SongFormat::Tracker, vec![format!("{}{}.{}", prefix, song_name, extension)]));
+        }
+        paths.push((SongFormat::Organya, vec![format!("{}{}.org", prefix, song_name)]));
+    }
+
+    paths
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -27,6 +75,49 @@ pub enum InterpolationMode {
     Polyphase,
 }
 
+impl InterpolationMode {
+    pub fn from_name(name: &str) -> Option<InterpolationMode> {
+        match name.to_ascii_lowercase().as_str() {
+            "nearest" => Some(InterpolationMode::Nearest),
+            "linear" => Some(InterpolationMode::Linear),
+            "cosine" => Some(InterpolationMode::Cosine),
+            "cubic" => Some(InterpolationMode::Cubic),
+            "polyphase" => Some(InterpolationMode::Polyphase),
+            _ => None,
+        }
+    }
+}
+
+/// Point positional sound effects are heard from, in the same fixed point units as entity positions.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SoundListener {
+    pub x: i32,
+    pub y: i32,
+    /// Half of the size of the visible area, sounds at its edges are panned the furthest.
+    pub half_width: i32,
+    pub half_height: i32,
+}
+
+impl SoundListener {
+    /// How far sounds are panned at the edge of the screen, sounds are never played on one side only.
+    const MAX_PAN: f32 = 0.6;
+    /// Volume of sounds far off screen, they still matter to the player so they're never silenced.
+    const MIN_VOLUME: f32 = 0.3;
+
+    /// Returns the volume of the left and right channel for a sound emitted at given position.
+    pub fn gains(&self, x: i32, y: i32) -> [f32; 2] {
+        let dx = (x - self.x) as f32 / self.half_width.max(1) as f32;
+        let dy = (y - self.y) as f32 / self.half_height.max(1) as f32;
+
+        // sounds on screen play at full volume, then fade out over the distance of another screen
+        let distance = (dx.abs() - 1.0).max(dy.abs() - 1.0).max(0.0);
+        let volume = (1.0 - distance * 0.5).max(Self::MIN_VOLUME);
+        let pan = dx.clamp(-1.0, 1.0) * Self::MAX_PAN;
+
+        [volume * (1.0 - pan).min(1.0), volume * (1.0 + pan).min(1.0)]
+    }
+}
+
 pub trait SoundManager {
     fn reload(&mut self) -> GameResult<()>;
 
@@ -36,6 +127,13 @@ pub trait SoundManager {
 
     fn play_sfx(&mut self, id: u8);
 
+    /// Plays a sound effect emitted at given position, panned and attenuated relative to the listener. It's played
+    /// centered like [SoundManager::play_sfx] if there's no listener.
+    fn play_sfx_at(&mut self, id: u8, x: i32, y: i32);
+
+    /// Sets the point positional sound effects are heard from, `None` plays them all centered.
+    fn set_listener(&mut self, listener: Option<SoundListener>);
+
     fn loop_sfx(&self, id: u8);
 
     fn loop_sfx_freq(&mut self, id: u8, freq: f32);
@@ -50,6 +148,9 @@ pub trait SoundManager {
 
     fn set_sfx_samples(&mut self, id: u8, data: Vec<i16>);
 
+    /// Sets the effects applied to the whole mix, does nothing if they're the same as the current ones.
+    fn set_audio_effects(&mut self, effects: AudioEffects);
+
     fn reload_songs(&mut self, constants: &EngineConstants, settings: &Settings, ctx: &mut Context) -> GameResult;
 
     fn play_song(
@@ -69,10 +170,22 @@ pub trait SoundManager {
 
     fn current_song(&self) -> usize;
 
+    /// Returns the position of the playing Organya song in ticks, `None` if the current song isn't an Organya one.
+    fn org_position(&self) -> Option<i32>;
+
+    /// Swaps the playing Organya song for an edited version of it without restarting playback.
+    fn replace_org_song(&mut self, song: Song);
+
+    /// Silences Organya tracks set in given bit mask.
+    fn set_org_muted_tracks(&mut self, mask: u16);
+
     fn set_sample_params_from_file(&mut self, id: u8, data: Box<dyn io::Read>) -> GameResult;
 
     fn set_sample_params(&mut self, id: u8, params: PixToneParameters) -> GameResult;
 
+    /// Returns the parameters sound effect with given id is currently synthesized from.
+    fn get_sample_params(&self, id: u8) -> PixToneParameters;
+
     fn load_custom_sound_effects(&mut self, ctx: &mut Context, roots: &Vec<String>) -> GameResult;
 
     fn as_any(&self) -> &dyn Any;
@@ -81,6 +194,17 @@ pub trait SoundManager {
 #[allow(unreachable_code)]
 pub fn init_sound_backend(ctx: &mut Context, launch_options: &mut LaunchOptions) -> GameResult<Box<dyn SoundManager>> {
 
+    #[cfg(not(feature = "backend-libretro"))]
+    if let Some(capture) = &mut launch_options.audio_capture {
+        log::info!("Capturing audio, it won't be played.");
+        let mut config = crate::sound::backend_libretro::OutputBufConfig {
+            sample_rate: capture.sample_rate as f64,
+            channel_count: 2,
+            runner_out: &mut capture.runner,
+        };
+        return crate::sound::backend_libretro::SoundManagerLibretro::new(ctx, &mut config);
+    }
+
     if ctx.headless {
         return crate::sound::backend_null::SoundManagerNull::new(ctx);
     }
@@ -101,5 +225,19 @@ pub fn init_sound_backend(ctx: &mut Context, launch_options: &mut LaunchOptions)
 
 }
 
-
-
+#[test]
+fn test_sound_listener_gains() {
+    let listener = SoundListener { x: 0x1000, y: 0x1000, half_width: 0x800, half_height: 0x800 };
+    let assert_gains = |x, y, expected: [f32; 2]| {
+        let gains = listener.gains(x, y);
+        assert!((gains[0] - expected[0]).abs() < 1e-5 && (gains[1] - expected[1]).abs() < 1e-5, "{:?}", gains);
+    };
+
+    assert_gains(0x1000, 0x1000, [1.0, 1.0]);
+    // the edges of the screen are panned, but still audible on the other side
+    assert_gains(0x1800, 0x1000, [0.4, 1.0]);
+    assert_gains(0x800, 0x1000, [1.0, 0.4]);
+    // half a screen past the edge the volume is down to 3/4, far away sounds stay at the floor
+    assert_gains(0x1000, 0x1c00, [0.75, 0.75]);
+    assert_gains(0x1000, 0x100000, [0.3, 0.3]);
+}
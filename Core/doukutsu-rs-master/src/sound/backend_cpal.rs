@@ -1,9 +1,11 @@
 use std::any::Any;
+use std::collections::HashMap;
 use std::io;
-use std::io::{BufRead, BufReader, Lines};
-use std::str::FromStr;
+use std::io::BufReader;
+use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 #[cfg(feature = "ogg-playback")]
@@ -20,10 +22,12 @@ use crate::game::settings::Settings;
 #[cfg(feature = "ogg-playback")]
 use crate::sound::ogg_playback::{OggPlaybackEngine, SavedOggPlaybackState};
 use crate::sound::org_playback::{OrgPlaybackEngine, SavedOrganyaPlaybackState};
+use crate::sound::effects::{AudioEffects, EffectsBus};
 use crate::sound::organya;
 use crate::sound::organya::Song;
 use crate::sound::pixtone::{PixToneParameters, PixTonePlayback};
-use crate::sound::{wave_bank, wave_bank::SoundBank, wav};
+use crate::sound::stream_playback::{open_song, SavedStreamPlaybackState, SongStream, StreamPlaybackEngine};
+use crate::sound::{custom_sfx, wave_bank, wave_bank::SoundBank};
 use crate::sound::backend::*;
 
 pub struct SoundManagerCpal {
@@ -33,6 +37,12 @@ pub struct SoundManagerCpal {
     current_song_id: usize,
     no_audio: bool,
     load_failed: bool,
+    audio_effects: AudioEffects,
+    listener: Option<SoundListener>,
+    /// Sound effect parameters replacing the built-in ones.
+    sample_params: HashMap<u8, PixToneParameters>,
+    /// Position of the playing Organya song in ticks, -1 if an Organya song isn't playing.
+    org_position: Arc<AtomicI32>,
     stream: Option<cpal::Stream>,
 }
 
@@ -72,6 +82,10 @@ impl SoundManagerCpal {
             current_song_id: 0,
             no_audio: false,
             load_failed: false,
+            audio_effects: AudioEffects::NONE,
+            listener: None,
+            sample_params: HashMap::new(),
+            org_position: Arc::new(AtomicI32::new(-1)),
             stream: None,
         };
 
@@ -97,18 +111,19 @@ impl SoundManagerCpal {
         }
 
         let config = config_result.unwrap();
+        let org_position = sound_manager.org_position.clone();
 
         let res = match config.sample_format() {
-            cpal::SampleFormat::I8 => run::<i8>(rx, soundbank.to_owned(), device, config.into()),
-            cpal::SampleFormat::I16 => run::<i16>(rx, soundbank.to_owned(), device, config.into()),
-            cpal::SampleFormat::I32 => run::<i32>(rx, soundbank.to_owned(), device, config.into()),
-            cpal::SampleFormat::I64 => run::<i64>(rx, soundbank.to_owned(), device, config.into()),
-            cpal::SampleFormat::U8 => run::<u8>(rx, soundbank.to_owned(), device, config.into()),
-            cpal::SampleFormat::U16 => run::<u16>(rx, soundbank.to_owned(), device, config.into()),
-            cpal::SampleFormat::U32 => run::<u32>(rx, soundbank.to_owned(), device, config.into()),
-            cpal::SampleFormat::U64 => run::<u64>(rx, soundbank.to_owned(), device, config.into()),
-            cpal::SampleFormat::F32 => run::<f32>(rx, soundbank.to_owned(), device, config.into()),
-            cpal::SampleFormat::F64 => run::<f64>(rx, soundbank.to_owned(), device, config.into()),
+            cpal::SampleFormat::I8 => run::<i8>(rx, soundbank.to_owned(), org_position, device, config.into()),
+            cpal::SampleFormat::I16 => run::<i16>(rx, soundbank.to_owned(), org_position, device, config.into()),
+            cpal::SampleFormat::I32 => run::<i32>(rx, soundbank.to_owned(), org_position, device, config.into()),
+            cpal::SampleFormat::I64 => run::<i64>(rx, soundbank.to_owned(), org_position, device, config.into()),
+            cpal::SampleFormat::U8 => run::<u8>(rx, soundbank.to_owned(), org_position, device, config.into()),
+            cpal::SampleFormat::U16 => run::<u16>(rx, soundbank.to_owned(), org_position, device, config.into()),
+            cpal::SampleFormat::U32 => run::<u32>(rx, soundbank.to_owned(), org_position, device, config.into()),
+            cpal::SampleFormat::U64 => run::<u64>(rx, soundbank.to_owned(), org_position, device, config.into()),
+            cpal::SampleFormat::F32 => run::<f32>(rx, soundbank.to_owned(), org_position, device, config.into()),
+            cpal::SampleFormat::F64 => run::<f64>(rx, soundbank.to_owned(), org_position, device, config.into()),
             _ => Err(AudioError("Unsupported sample format.".to_owned())),
         };
 
@@ -173,6 +188,21 @@ impl SoundManager for SoundManagerCpal {
         self.send(PlaybackMessage::PlaySample(id)).unwrap();
     }
 
+    fn play_sfx_at(&mut self, id: u8, x: i32, y: i32) {
+        if self.no_audio {
+            return;
+        }
+
+        match self.listener {
+            Some(listener) => self.send(PlaybackMessage::PlaySamplePanned(id, listener.gains(x, y))).unwrap(),
+            None => self.send(PlaybackMessage::PlaySample(id)).unwrap(),
+        }
+    }
+
+    fn set_listener(&mut self, listener: Option<SoundListener>) {
+        self.listener = listener;
+    }
+
     fn loop_sfx(&self, id: u8) {
         if self.no_audio {
             return;
@@ -223,6 +253,15 @@ impl SoundManager for SoundManagerCpal {
         self.send(PlaybackMessage::SetSampleData(id, data)).unwrap();
     }
 
+    fn set_audio_effects(&mut self, effects: AudioEffects) {
+        if self.no_audio || self.audio_effects == effects {
+            return;
+        }
+
+        self.audio_effects = effects;
+        self.send(PlaybackMessage::SetAudioEffects(effects)).unwrap();
+    }
+
     fn reload_songs(&mut self, constants: &EngineConstants, settings: &Settings, ctx: &mut Context) -> GameResult {
         let prev_song = self.prev_song_id;
         let current_song = self.current_song_id;
@@ -262,119 +301,110 @@ impl SoundManager for SoundManagerCpal {
                 self.send(PlaybackMessage::Stop).unwrap();
             }
         } else if let Some(song_name) = constants.music_table.get(song_id) {
-            let mut paths = constants.organya_paths.clone();
-
-            paths.insert(0, "/Soundtracks/".to_owned() + &settings.soundtrack + "/");
-
-            if let Some(soundtrack) =
-            constants.soundtracks.iter().find(|s| s.available && s.name == settings.soundtrack)
+            for (format, paths) in song_paths(constants, &settings.soundtrack, song_name)
+                .iter()
+                .filter(|(_, paths)| paths.iter().all(|path| filesystem::exists(ctx, path)))
             {
-                paths.insert(0, soundtrack.path.clone());
-            }
-
-            let songs_paths = paths.iter().map(|prefix| {
-                [
-                        #[cfg(feature = "ogg-playback")]
-                    (
-                        SongFormat::OggMultiPart,
-                        vec![format!("{}{}_intro.ogg", prefix, song_name), format!("{}{}_loop.ogg", prefix, song_name)],
-                    ),
-                        #[cfg(feature = "ogg-playback")]
-                    (SongFormat::OggSinglePart, vec![format!("{}{}.ogg", prefix, song_name)]),
-                    (SongFormat::Organya, vec![format!("{}{}.org", prefix, song_name)]),
-                ]
-            });
-
-            for songs in songs_paths {
-                for (format, paths) in
-                songs.iter().filter(|(_, paths)| paths.iter().all(|path| filesystem::exists(ctx, path)))
-                {
-                    match format {
-                        SongFormat::Organya => {
-                            // we're sure that there's one element
-                            let path = unsafe { paths.get_unchecked(0) };
-
-                            match filesystem::open(ctx, path).map(organya::Song::load_from) {
-                                Ok(Ok(org)) => {
-                                    log::info!("Playing Organya BGM: {} {}", song_id, path);
-
-                                    self.prev_song_id = self.current_song_id;
-                                    self.current_song_id = song_id;
-                                    let _ = self
-                                        .send(PlaybackMessage::SetOrgInterpolation(settings.organya_interpolation))
-                                        .unwrap();
-                                    self.send(PlaybackMessage::SaveState).unwrap();
-                                    self.send(PlaybackMessage::PlayOrganyaSong(Box::new(org))).unwrap();
-
-                                    return Ok(());
-                                }
-                                Ok(Err(err)) | Err(err) => {
-                                    log::warn!("Failed to load Organya BGM {}: {}", song_id, err);
-                                }
+                match format {
+                    SongFormat::Organya => {
+                        // we're sure that there's one element
+                        let path = unsafe { paths.get_unchecked(0) };
+
+                        match filesystem::open(ctx, path).map(organya::Song::load_from) {
+                            Ok(Ok(org)) => {
+                                log::info!("Playing Organya BGM: {} {}", song_id, path);
+
+                                self.prev_song_id = self.current_song_id;
+                                self.current_song_id = song_id;
+                                let _ = self
+                                    .send(PlaybackMessage::SetOrgInterpolation(settings.organya_interpolation))
+                                    .unwrap();
+                                self.send(PlaybackMessage::SaveState).unwrap();
+                                self.send(PlaybackMessage::PlayOrganyaSong(Box::new(org))).unwrap();
+
+                                return Ok(());
+                            }
+                            Ok(Err(err)) | Err(err) => {
+                                log::warn!("Failed to load Organya BGM {}: {}", song_id, err);
                             }
                         }
-                        #[cfg(feature = "ogg-playback")]
-                        SongFormat::OggSinglePart => {
-                            // we're sure that there's one element
-                            let path = unsafe { paths.get_unchecked(0) };
+                    }
+                    #[cfg(feature = "ogg-playback")]
+                    SongFormat::OggSinglePart => {
+                        // we're sure that there's one element
+                        let path = unsafe { paths.get_unchecked(0) };
+
+                        match filesystem::open(ctx, path).map(|f| {
+                            OggStreamReader::new(f).map_err(|e| GameError::ResourceLoadError(e.to_string()))
+                        }) {
+                            Ok(Ok(song)) => {
+                                log::info!("Playing single part Ogg BGM: {} {}", song_id, path);
+
+                                self.prev_song_id = self.current_song_id;
+                                self.current_song_id = song_id;
+                                self.send(PlaybackMessage::SaveState).unwrap();
+                                self.send(PlaybackMessage::PlayOggSongSinglePart(Box::new(song))).unwrap();
+
+                                return Ok(());
+                            }
+                            Ok(Err(err)) | Err(err) => {
+                                log::warn!("Failed to load single part Ogg BGM {}: {}", song_id, err);
+                            }
+                        }
+                    }
+                    #[cfg(feature = "ogg-playback")]
+                    SongFormat::OggMultiPart => {
+                        // we're sure that there are two elements
+                        let path_intro = unsafe { paths.get_unchecked(0) };
+                        let path_loop = unsafe { paths.get_unchecked(1) };
 
-                            match filesystem::open(ctx, path).map(|f| {
+                        match (
+                            filesystem::open(ctx, path_intro).map(|f| {
                                 OggStreamReader::new(f).map_err(|e| GameError::ResourceLoadError(e.to_string()))
-                            }) {
-                                Ok(Ok(song)) => {
-                                    log::info!("Playing single part Ogg BGM: {} {}", song_id, path);
-
-                                    self.prev_song_id = self.current_song_id;
-                                    self.current_song_id = song_id;
-                                    self.send(PlaybackMessage::SaveState).unwrap();
-                                    self.send(PlaybackMessage::PlayOggSongSinglePart(Box::new(song))).unwrap();
-
-                                    return Ok(());
-                                }
-                                Ok(Err(err)) | Err(err) => {
-                                    log::warn!("Failed to load single part Ogg BGM {}: {}", song_id, err);
-                                }
+                            }),
+                            filesystem::open(ctx, path_loop).map(|f| {
+                                OggStreamReader::new(f).map_err(|e| GameError::ResourceLoadError(e.to_string()))
+                            }),
+                        ) {
+                            (Ok(Ok(song_intro)), Ok(Ok(song_loop))) => {
+                                log::info!(
+                                    "Playing multi part Ogg BGM: {} {} + {}",
+                                    song_id,
+                                    path_intro,
+                                    path_loop
+                                );
+
+                                self.prev_song_id = self.current_song_id;
+                                self.current_song_id = song_id;
+                                self.send(PlaybackMessage::SaveState).unwrap();
+                                self.send(PlaybackMessage::PlayOggSongMultiPart(
+                                    Box::new(song_intro),
+                                    Box::new(song_loop),
+                                ))
+                                    .unwrap();
+
+                                return Ok(());
                             }
-                        }
-                        #[cfg(feature = "ogg-playback")]
-                        SongFormat::OggMultiPart => {
-                            // we're sure that there are two elements
-                            let path_intro = unsafe { paths.get_unchecked(0) };
-                            let path_loop = unsafe { paths.get_unchecked(1) };
-
-                            match (
-                                filesystem::open(ctx, path_intro).map(|f| {
-                                    OggStreamReader::new(f).map_err(|e| GameError::ResourceLoadError(e.to_string()))
-                                }),
-                                filesystem::open(ctx, path_loop).map(|f| {
-                                    OggStreamReader::new(f).map_err(|e| GameError::ResourceLoadError(e.to_string()))
-                                }),
-                            ) {
-                                (Ok(Ok(song_intro)), Ok(Ok(song_loop))) => {
-                                    log::info!(
-                                        "Playing multi part Ogg BGM: {} {} + {}",
-                                        song_id,
-                                        path_intro,
-                                        path_loop
-                                    );
-
-                                    self.prev_song_id = self.current_song_id;
-                                    self.current_song_id = song_id;
-                                    self.send(PlaybackMessage::SaveState).unwrap();
-                                    self.send(PlaybackMessage::PlayOggSongMultiPart(
-                                        Box::new(song_intro),
-                                        Box::new(song_loop),
-                                    ))
-                                        .unwrap();
-
-                                    return Ok(());
-                                }
-                                (Ok(Err(err)), _) | (Err(err), _) | (_, Ok(Err(err))) | (_, Err(err)) => {
-                                    log::warn!("Failed to load multi part Ogg BGM {}: {}", song_id, err);
-                                }
+                            (Ok(Err(err)), _) | (Err(err), _) | (_, Ok(Err(err))) | (_, Err(err)) => {
+                                log::warn!("Failed to load multi part Ogg BGM {}: {}", song_id, err);
                             }
                         }
                     }
+                    _ => match open_song(ctx, format, paths) {
+                        Ok((intro, song)) => {
+                            log::info!("Playing streamed BGM: {} {}", song_id, paths.join(" + "));
+
+                            self.prev_song_id = self.current_song_id;
+                            self.current_song_id = song_id;
+                            self.send(PlaybackMessage::SaveState).unwrap();
+                            self.send(PlaybackMessage::PlayStreamSong(intro, song)).unwrap();
+
+                            return Ok(());
+                        }
+                        Err(err) => {
+                            log::warn!("Failed to load BGM {}: {}", song_id, err);
+                        }
+                    },
                 }
             }
         }
@@ -428,103 +458,54 @@ impl SoundManager for SoundManagerCpal {
             return Ok(());
         }
 
-        let mut reader = BufReader::new(data).lines();
-        let mut params = PixToneParameters::empty();
+        let params = PixToneParameters::read_pxt(BufReader::new(data))?;
+        self.set_sample_params(id, params)
+    }
 
-        fn next_string<T: FromStr>(reader: &mut Lines<BufReader<Box::<dyn io::Read>>>) -> GameResult<T> {
-            while let Some(Ok(str)) = reader.next() {
-                let str = str.trim();
-                if str.is_empty() || str.starts_with('#') {
-                    continue;
-                }
+    fn set_sample_params(&mut self, id: u8, params: PixToneParameters) -> GameResult {
+        self.sample_params.insert(id, params);
 
-                let mut splits = str.split(':');
+        if self.no_audio {
+            return Ok(());
+        }
 
-                let _ = splits.next();
-                if let Some(str) = splits.next() {
-                    return str.trim().parse::<T>().map_err(|_| {
-                        GameError::ParseError("failed to parse the value as specified type.".to_string())
-                    });
-                } else {
-                    break;
-                }
-            }
+        self.send(PlaybackMessage::SetSampleParams(id, params)).unwrap();
 
-            Err(GameError::ParseError("unexpected end.".to_string()))
+        Ok(())
+    }
+
+    fn org_position(&self) -> Option<i32> {
+        match self.org_position.load(Ordering::Relaxed) {
+            -1 => None,
+            position => Some(position),
         }
+    }
 
-        for channel in &mut params.channels {
-            channel.enabled = next_string::<u8>(&mut reader)? != 0;
-            channel.length = next_string::<u32>(&mut reader)?;
-
-            channel.carrier.waveform_type = next_string::<u8>(&mut reader)?;
-            channel.carrier.pitch = next_string::<f32>(&mut reader)?;
-            channel.carrier.level = next_string::<i32>(&mut reader)?;
-            channel.carrier.offset = next_string::<i32>(&mut reader)?;
-
-            channel.frequency.waveform_type = next_string::<u8>(&mut reader)?;
-            channel.frequency.pitch = next_string::<f32>(&mut reader)?;
-            channel.frequency.level = next_string::<i32>(&mut reader)?;
-            channel.frequency.offset = next_string::<i32>(&mut reader)?;
-
-            channel.amplitude.waveform_type = next_string::<u8>(&mut reader)?;
-            channel.amplitude.pitch = next_string::<f32>(&mut reader)?;
-            channel.amplitude.level = next_string::<i32>(&mut reader)?;
-            channel.amplitude.offset = next_string::<i32>(&mut reader)?;
-
-            channel.envelope.initial = next_string::<i32>(&mut reader)?;
-            channel.envelope.time_a = next_string::<i32>(&mut reader)?;
-            channel.envelope.value_a = next_string::<i32>(&mut reader)?;
-            channel.envelope.time_b = next_string::<i32>(&mut reader)?;
-            channel.envelope.value_b = next_string::<i32>(&mut reader)?;
-            channel.envelope.time_c = next_string::<i32>(&mut reader)?;
-            channel.envelope.value_c = next_string::<i32>(&mut reader)?;
+    fn replace_org_song(&mut self, song: Song) {
+        if self.no_audio {
+            return;
         }
 
-        self.set_sample_params(id, params)
+        self.send(PlaybackMessage::ReplaceOrganyaSong(Box::new(song))).unwrap();
     }
 
-    fn set_sample_params(&mut self, id: u8, params: PixToneParameters) -> GameResult {
+    fn set_org_muted_tracks(&mut self, mask: u16) {
         if self.no_audio {
-            return Ok(());
+            return;
         }
 
-        self.send(PlaybackMessage::SetSampleParams(id, params)).unwrap();
+        self.send(PlaybackMessage::SetOrgMutedTracks(mask)).unwrap();
+    }
 
-        Ok(())
+    fn get_sample_params(&self, id: u8) -> PixToneParameters {
+        self.sample_params.get(&id).copied().unwrap_or_else(|| PixToneParameters::default_for(id))
     }
 
     fn load_custom_sound_effects(&mut self, ctx: &mut Context, roots: &Vec<String>) -> GameResult {
-        for path in roots.iter().rev() {
-            let wavs = filesystem::read_dir(ctx, [path, "sfx/"].join(""))?
-                .filter(|f| f.to_string_lossy().to_lowercase().ends_with(".wav"));
-
-            for filename in wavs {
-                if let Ok(mut file) = filesystem::open(ctx, &filename) {
-                    let wav = wav::WavSample::read_from(&mut file)?;
-                    let id = filename
-                        .file_stem()
-                        .unwrap_or_default()
-                        .to_str()
-                        .unwrap_or_default()
-                        .parse::<u8>()
-                        .unwrap_or(0);
-                    if id == 0 {
-                        continue;
-                    }
-                    let step = (wav.format.channels * 2) as usize;
-                    let data = wav
-                        .data
-                        .chunks_exact(2)
-                        .into_iter()
-                        .step_by(step)
-                        .map(|a| i16::from_ne_bytes([a[0], a[1]]))
-                        .collect();
-
-                    self.set_sfx_samples(id, data);
-                }
-            }
+        for (id, data) in custom_sfx::load_custom_sound_effects(ctx, roots) {
+            self.set_sfx_samples(id, data);
         }
+
         Ok(())
     }
 
@@ -541,7 +522,9 @@ pub(in crate::sound) enum PlaybackMessage {
     PlayOggSongSinglePart(Box<OggStreamReader<File>>),
     #[cfg(feature = "ogg-playback")]
     PlayOggSongMultiPart(Box<OggStreamReader<File>>, Box<OggStreamReader<File>>),
+    PlayStreamSong(Option<Box<dyn SongStream>>, Box<dyn SongStream>),
     PlaySample(u8),
+    PlaySamplePanned(u8, [f32; 2]),
     LoopSample(u8),
     LoopSampleFreq(u8, f32),
     StopSample(u8),
@@ -554,6 +537,9 @@ pub(in crate::sound) enum PlaybackMessage {
     SetSampleParams(u8, PixToneParameters),
     SetOrgInterpolation(InterpolationMode),
     SetSampleData(u8, Vec<i16>),
+    SetAudioEffects(AudioEffects),
+    ReplaceOrganyaSong(Box<Song>),
+    SetOrgMutedTracks(u16),
 }
 
 #[derive(PartialEq, Eq)]
@@ -562,6 +548,7 @@ enum PlaybackState {
     PlayingOrg,
     #[cfg(feature = "ogg-playback")]
     PlayingOgg,
+    PlayingStream,
 }
 
 enum PlaybackStateType {
@@ -569,6 +556,7 @@ enum PlaybackStateType {
     Organya(SavedOrganyaPlaybackState),
     #[cfg(feature = "ogg-playback")]
     Ogg(SavedOggPlaybackState),
+    Stream(SavedStreamPlaybackState),
 }
 
 impl Default for PlaybackStateType {
@@ -580,6 +568,7 @@ impl Default for PlaybackStateType {
 fn run<T>(
     rx: Receiver<PlaybackMessage>,
     bank: SoundBank,
+    org_position: Arc<AtomicI32>,
     device: cpal::Device,
     config: cpal::StreamConfig,
 ) -> GameResult<cpal::Stream>
@@ -594,11 +583,14 @@ fn run<T>(
     let mut org_engine = Box::new(OrgPlaybackEngine::new());
     #[cfg(feature = "ogg-playback")]
     let mut ogg_engine = Box::new(OggPlaybackEngine::new());
+    let mut stream_engine = Box::new(StreamPlaybackEngine::new());
     let mut pixtone = Box::new(PixTonePlayback::new());
+    let mut effects_bus = Box::new(EffectsBus::new(sample_rate));
     pixtone.create_samples();
 
     log::info!("Audio format: {} {}", sample_rate, channels);
     org_engine.set_sample_rate(sample_rate as usize);
+    stream_engine.set_sample_rate(sample_rate as usize);
     #[cfg(feature = "ogg-playback")]
     {
         org_engine.loops = usize::MAX;
@@ -607,7 +599,7 @@ fn run<T>(
 
     let buf_size = sample_rate as usize * 10 / 1000;
     let mut bgm_buf = vec![0x8080; buf_size * 2];
-    let mut pxt_buf = vec![0x8000; buf_size];
+    let mut pxt_buf = vec![0x8000; buf_size * 2];
     let mut bgm_index = 0;
     let mut pxt_index = 0;
     let mut samples = 0;
@@ -694,9 +686,32 @@ fn run<T>(
 
                         state = PlaybackState::PlayingOgg;
                     }
+                    Ok(PlaybackMessage::PlayStreamSong(intro, song)) => {
+                        if state == PlaybackState::Stopped {
+                            saved_state = PlaybackStateType::None;
+                        }
+
+                        if bgm_fadeout {
+                            bgm_fadeout = false;
+                            bgm_vol = bgm_vol_saved;
+                        }
+
+                        stream_engine.start(intro, song);
+
+                        for i in &mut bgm_buf[0..samples] {
+                            *i = 0x8000
+                        }
+                        samples = stream_engine.render_to(&mut bgm_buf);
+                        bgm_index = 0;
+
+                        state = PlaybackState::PlayingStream;
+                    }
                     Ok(PlaybackMessage::PlaySample(id)) => {
                         pixtone.play_sfx(id);
                     }
+                    Ok(PlaybackMessage::PlaySamplePanned(id, gain)) => {
+                        pixtone.play_sfx_panned(id, gain);
+                    }
 
                     Ok(PlaybackMessage::LoopSample(id)) => {
                         pixtone.loop_sfx(id);
@@ -720,6 +735,7 @@ fn run<T>(
                         #[cfg(feature = "ogg-playback")]
                             ogg_engine.set_sample_rate((sample_rate / new_speed) as usize);
                         org_engine.set_sample_rate((sample_rate / new_speed) as usize);
+                        stream_engine.set_sample_rate((sample_rate / new_speed) as usize);
                     }
                     Ok(PlaybackMessage::SetSongVolume(new_volume)) => {
                         assert!(bgm_vol >= 0.0);
@@ -743,6 +759,7 @@ fn run<T>(
                             PlaybackState::PlayingOrg => PlaybackStateType::Organya(org_engine.get_state()),
                             #[cfg(feature = "ogg-playback")]
                             PlaybackState::PlayingOgg => PlaybackStateType::Ogg(ogg_engine.get_state()),
+                            PlaybackState::PlayingStream => PlaybackStateType::Stream(stream_engine.get_state()),
                         };
                     }
                     Ok(PlaybackMessage::RestoreState) => {
@@ -793,6 +810,26 @@ fn run<T>(
 
                                 state = PlaybackState::PlayingOgg;
                             }
+                            PlaybackStateType::Stream(playback_state) => {
+                                stream_engine.set_state(playback_state);
+
+                                if state == PlaybackState::Stopped {
+                                    stream_engine.rewind();
+                                }
+
+                                for i in &mut bgm_buf[0..samples] {
+                                    *i = 0x8000
+                                }
+                                samples = stream_engine.render_to(&mut bgm_buf);
+                                bgm_index = 0;
+
+                                if bgm_fadeout {
+                                    bgm_fadeout = false;
+                                    bgm_vol = bgm_vol_saved;
+                                }
+
+                                state = PlaybackState::PlayingStream;
+                            }
                         }
                     }
                     Ok(PlaybackMessage::SetSampleParams(id, params)) => {
@@ -804,6 +841,17 @@ fn run<T>(
                     Ok(PlaybackMessage::SetSampleData(id, data)) => {
                         pixtone.set_sample_data(id, data);
                     }
+                    Ok(PlaybackMessage::SetAudioEffects(effects)) => {
+                        effects_bus.set_effects(effects);
+                    }
+                    Ok(PlaybackMessage::ReplaceOrganyaSong(song)) => {
+                        if state == PlaybackState::PlayingOrg {
+                            org_engine.replace_song(*song, &bank);
+                        }
+                    }
+                    Ok(PlaybackMessage::SetOrgMutedTracks(mask)) => {
+                        org_engine.muted_tracks = mask;
+                    }
                     Err(_) => {
                         break;
                     }
@@ -831,6 +879,9 @@ fn run<T>(
                             PlaybackState::PlayingOgg => {
                                 samples = ogg_engine.render_to(&mut bgm_buf);
                             }
+                            PlaybackState::PlayingStream => {
+                                samples = stream_engine.render_to(&mut bgm_buf);
+                            }
                             _ => unreachable!(),
                         }
                         bgm_index = 2;
@@ -838,47 +889,38 @@ fn run<T>(
                     }
                 };
 
-                let pxt_sample: u16 = pxt_buf[pxt_index];
+                let (pxt_sample_l, pxt_sample_r): (u16, u16) = (pxt_buf[pxt_index], pxt_buf[pxt_index + 1]);
 
-                if pxt_index < (pxt_buf.len() - 1) {
-                    pxt_index += 1;
+                if pxt_index < (pxt_buf.len() - 2) {
+                    pxt_index += 2;
                 } else {
                     pxt_index = 0;
                     pxt_buf.fill(0x8000);
-                    pixtone.mix(&mut pxt_buf, sample_rate / speed);
+                    pixtone.mix(&mut pxt_buf, sample_rate / (speed * effects_bus.pitch()));
                 }
 
+                let [mix_l, mix_r] = effects_bus.process([
+                    ((bgm_sample_l ^ 0x8000) as i16) as f32 * bgm_vol
+                        + ((pxt_sample_l ^ 0x8000) as i16) as f32 * sfx_vol,
+                    ((bgm_sample_r ^ 0x8000) as i16) as f32 * bgm_vol
+                        + ((pxt_sample_r ^ 0x8000) as i16) as f32 * sfx_vol,
+                ]);
+
                 if frame.len() >= 2 {
-                    let sample_l = clamp(
-                        (((bgm_sample_l ^ 0x8000) as i16) as f32 * bgm_vol) as isize
-                            + (((pxt_sample ^ 0x8000) as i16) as f32 * sfx_vol) as isize,
-                        -0x7fff,
-                        0x7fff,
-                    ) as u16
-                        ^ 0x8000;
-                    let sample_r = clamp(
-                        (((bgm_sample_r ^ 0x8000) as i16) as f32 * bgm_vol) as isize
-                            + (((pxt_sample ^ 0x8000) as i16) as f32 * sfx_vol) as isize,
-                        -0x7fff,
-                        0x7fff,
-                    ) as u16
-                        ^ 0x8000;
+                    let sample_l = clamp(mix_l as isize, -0x7fff, 0x7fff) as u16 ^ 0x8000;
+                    let sample_r = clamp(mix_r as isize, -0x7fff, 0x7fff) as u16 ^ 0x8000;
 
                     frame[0] = T::from_sample(sample_l);
                     frame[1] = T::from_sample(sample_r);
                 } else {
-                    let sample = clamp(
-                        ((((bgm_sample_l ^ 0x8000) as i16) + ((bgm_sample_r ^ 0x8000) as i16)) as f32 * bgm_vol / 2.0)
-                            as isize
-                            + (((pxt_sample ^ 0x8000) as i16) as f32 * sfx_vol) as isize,
-                        -0x7fff,
-                        0x7fff,
-                    ) as u16
-                        ^ 0x8000;
+                    let sample = clamp(((mix_l + mix_r) / 2.0) as isize, -0x7fff, 0x7fff) as u16 ^ 0x8000;
 
                     frame[0] = T::from_sample(sample);
                 }
             }
+
+            let position = if state == PlaybackState::PlayingOrg { org_engine.get_position() } else { -1 };
+            org_position.store(position, Ordering::Relaxed);
         },
         err_fn,
         None
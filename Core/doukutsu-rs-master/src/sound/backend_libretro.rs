@@ -1,31 +1,34 @@
 //this backend will output audio to a raw buffer
 
 use std::any::Any;
+use std::collections::HashMap;
 use std::io;
-use std::io::{BufRead, BufReader, Lines};
-use std::str::FromStr;
+use std::io::BufReader;
+use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
 
 #[cfg(feature = "ogg-playback")]
 use lewton::inside_ogg::OggStreamReader;
 use num_traits::clamp;
-use serde::de::value::I8Deserializer;
 
 use crate::engine_constants::EngineConstants;
 use crate::framework::context::Context;
 use crate::framework::error::{GameError, GameResult};
-use crate::framework::error::GameError::{AudioError, InvalidValue};
+use crate::framework::error::GameError::InvalidValue;
 use crate::framework::filesystem;
 use crate::framework::filesystem::File;
 use crate::game::settings::Settings;
 #[cfg(feature = "ogg-playback")]
 use crate::sound::ogg_playback::{OggPlaybackEngine, SavedOggPlaybackState};
 use crate::sound::org_playback::{OrgPlaybackEngine, SavedOrganyaPlaybackState};
+use crate::sound::effects::{AudioEffects, EffectsBus};
 use crate::sound::organya;
 use crate::sound::organya::Song;
 use crate::sound::pixtone::{PixToneParameters, PixTonePlayback};
-use crate::sound::{wave_bank, wave_bank::SoundBank, wav};
+use crate::sound::stream_playback::{open_song, SavedStreamPlaybackState, SongStream, StreamPlaybackEngine};
+use crate::sound::{custom_sfx, wave_bank, wave_bank::SoundBank};
 use crate::sound::backend::*;
 
 
@@ -102,6 +105,12 @@ pub struct SoundManagerLibretro {
     current_song_id: usize,
     no_audio: bool,
     load_failed: bool,
+    audio_effects: AudioEffects,
+    listener: Option<SoundListener>,
+    /// Sound effect parameters replacing the built-in ones.
+    sample_params: HashMap<u8, PixToneParameters>,
+    /// Position of the playing Organya song in ticks, -1 if an Organya song isn't playing.
+    org_position: Arc<AtomicI32>,
     
     //stream: Option<Stream>,
     //
@@ -132,6 +141,10 @@ impl SoundManagerLibretro {
             current_song_id: 0,
             no_audio: false,
             load_failed: false,
+            audio_effects: AudioEffects::NONE,
+            listener: None,
+            sample_params: HashMap::new(),
+            org_position: Arc::new(AtomicI32::new(-1)),
         };
 
         let runner_config = RunnerConfig {
@@ -139,7 +152,7 @@ impl SoundManagerLibretro {
             channel_count: config.channel_count,
         };
 
-        let runner = Runner::init(rx, soundbank.to_owned(), runner_config);
+        let runner = Runner::init(rx, soundbank.to_owned(), sound_manager.org_position.clone(), runner_config);
         if let Err(runner) = &runner {
             log::error!("Error initializing audio: {}", runner);
         }
@@ -206,6 +219,21 @@ impl SoundManager for SoundManagerLibretro {
         self.send(PlaybackMessage::PlaySample(id)).unwrap();
     }
 
+    fn play_sfx_at(&mut self, id: u8, x: i32, y: i32) {
+        if self.no_audio {
+            return;
+        }
+
+        match self.listener {
+            Some(listener) => self.send(PlaybackMessage::PlaySamplePanned(id, listener.gains(x, y))).unwrap(),
+            None => self.send(PlaybackMessage::PlaySample(id)).unwrap(),
+        }
+    }
+
+    fn set_listener(&mut self, listener: Option<SoundListener>) {
+        self.listener = listener;
+    }
+
     fn loop_sfx(&self, id: u8) {
         if self.no_audio {
             return;
@@ -256,6 +284,15 @@ impl SoundManager for SoundManagerLibretro {
         self.send(PlaybackMessage::SetSampleData(id, data)).unwrap();
     }
 
+    fn set_audio_effects(&mut self, effects: AudioEffects) {
+        if self.no_audio || self.audio_effects == effects {
+            return;
+        }
+
+        self.audio_effects = effects;
+        self.send(PlaybackMessage::SetAudioEffects(effects)).unwrap();
+    }
+
     fn reload_songs(&mut self, constants: &EngineConstants, settings: &Settings, ctx: &mut Context) -> GameResult {
         let prev_song = self.prev_song_id;
         let current_song = self.current_song_id;
@@ -295,119 +332,110 @@ impl SoundManager for SoundManagerLibretro {
                 self.send(PlaybackMessage::Stop).unwrap();
             }
         } else if let Some(song_name) = constants.music_table.get(song_id) {
-            let mut paths = constants.organya_paths.clone();
-
-            paths.insert(0, "/Soundtracks/".to_owned() + &settings.soundtrack + "/");
-
-            if let Some(soundtrack) =
-            constants.soundtracks.iter().find(|s| s.available && s.name == settings.soundtrack)
+            for (format, paths) in song_paths(constants, &settings.soundtrack, song_name)
+                .iter()
+                .filter(|(_, paths)| paths.iter().all(|path| filesystem::exists(ctx, path)))
             {
-                paths.insert(0, soundtrack.path.clone());
-            }
-
-            let songs_paths = paths.iter().map(|prefix| {
-                [
-                        #[cfg(feature = "ogg-playback")]
-                    (
-                        SongFormat::OggMultiPart,
-                        vec![format!("{}{}_intro.ogg", prefix, song_name), format!("{}{}_loop.ogg", prefix, song_name)],
-                    ),
-                        #[cfg(feature = "ogg-playback")]
-                    (SongFormat::OggSinglePart, vec![format!("{}{}.ogg", prefix, song_name)]),
-                    (SongFormat::Organya, vec![format!("{}{}.org", prefix, song_name)]),
-                ]
-            });
-
-            for songs in songs_paths {
-                for (format, paths) in
-                songs.iter().filter(|(_, paths)| paths.iter().all(|path| filesystem::exists(ctx, path)))
-                {
-                    match format {
-                        SongFormat::Organya => {
-                            // we're sure that there's one element
-                            let path = unsafe { paths.get_unchecked(0) };
-
-                            match filesystem::open(ctx, path).map(organya::Song::load_from) {
-                                Ok(Ok(org)) => {
-                                    log::info!("Playing Organya BGM: {} {}", song_id, path);
-
-                                    self.prev_song_id = self.current_song_id;
-                                    self.current_song_id = song_id;
-                                    let _ = self
-                                        .send(PlaybackMessage::SetOrgInterpolation(settings.organya_interpolation))
-                                        .unwrap();
-                                    self.send(PlaybackMessage::SaveState).unwrap();
-                                    self.send(PlaybackMessage::PlayOrganyaSong(Box::new(org))).unwrap();
-
-                                    return Ok(());
-                                }
-                                Ok(Err(err)) | Err(err) => {
-                                    log::warn!("Failed to load Organya BGM {}: {}", song_id, err);
-                                }
+                match format {
+                    SongFormat::Organya => {
+                        // we're sure that there's one element
+                        let path = unsafe { paths.get_unchecked(0) };
+
+                        match filesystem::open(ctx, path).map(organya::Song::load_from) {
+                            Ok(Ok(org)) => {
+                                log::info!("Playing Organya BGM: {} {}", song_id, path);
+
+                                self.prev_song_id = self.current_song_id;
+                                self.current_song_id = song_id;
+                                let _ = self
+                                    .send(PlaybackMessage::SetOrgInterpolation(settings.organya_interpolation))
+                                    .unwrap();
+                                self.send(PlaybackMessage::SaveState).unwrap();
+                                self.send(PlaybackMessage::PlayOrganyaSong(Box::new(org))).unwrap();
+
+                                return Ok(());
+                            }
+                            Ok(Err(err)) | Err(err) => {
+                                log::warn!("Failed to load Organya BGM {}: {}", song_id, err);
                             }
                         }
-                        #[cfg(feature = "ogg-playback")]
-                        SongFormat::OggSinglePart => {
-                            // we're sure that there's one element
-                            let path = unsafe { paths.get_unchecked(0) };
+                    }
+                    #[cfg(feature = "ogg-playback")]
+                    SongFormat::OggSinglePart => {
+                        // we're sure that there's one element
+                        let path = unsafe { paths.get_unchecked(0) };
+
+                        match filesystem::open(ctx, path).map(|f| {
+                            OggStreamReader::new(f).map_err(|e| GameError::ResourceLoadError(e.to_string()))
+                        }) {
+                            Ok(Ok(song)) => {
+                                log::info!("Playing single part Ogg BGM: {} {}", song_id, path);
+
+                                self.prev_song_id = self.current_song_id;
+                                self.current_song_id = song_id;
+                                self.send(PlaybackMessage::SaveState).unwrap();
+                                self.send(PlaybackMessage::PlayOggSongSinglePart(Box::new(song))).unwrap();
+
+                                return Ok(());
+                            }
+                            Ok(Err(err)) | Err(err) => {
+                                log::warn!("Failed to load single part Ogg BGM {}: {}", song_id, err);
+                            }
+                        }
+                    }
+                    #[cfg(feature = "ogg-playback")]
+                    SongFormat::OggMultiPart => {
+                        // we're sure that there are two elements
+                        let path_intro = unsafe { paths.get_unchecked(0) };
+                        let path_loop = unsafe { paths.get_unchecked(1) };
 
-                            match filesystem::open(ctx, path).map(|f| {
+                        match (
+                            filesystem::open(ctx, path_intro).map(|f| {
                                 OggStreamReader::new(f).map_err(|e| GameError::ResourceLoadError(e.to_string()))
-                            }) {
-                                Ok(Ok(song)) => {
-                                    log::info!("Playing single part Ogg BGM: {} {}", song_id, path);
-
-                                    self.prev_song_id = self.current_song_id;
-                                    self.current_song_id = song_id;
-                                    self.send(PlaybackMessage::SaveState).unwrap();
-                                    self.send(PlaybackMessage::PlayOggSongSinglePart(Box::new(song))).unwrap();
-
-                                    return Ok(());
-                                }
-                                Ok(Err(err)) | Err(err) => {
-                                    log::warn!("Failed to load single part Ogg BGM {}: {}", song_id, err);
-                                }
+                            }),
+                            filesystem::open(ctx, path_loop).map(|f| {
+                                OggStreamReader::new(f).map_err(|e| GameError::ResourceLoadError(e.to_string()))
+                            }),
+                        ) {
+                            (Ok(Ok(song_intro)), Ok(Ok(song_loop))) => {
+                                log::info!(
+                                    "Playing multi part Ogg BGM: {} {} + {}",
+                                    song_id,
+                                    path_intro,
+                                    path_loop
+                                );
+
+                                self.prev_song_id = self.current_song_id;
+                                self.current_song_id = song_id;
+                                self.send(PlaybackMessage::SaveState).unwrap();
+                                self.send(PlaybackMessage::PlayOggSongMultiPart(
+                                    Box::new(song_intro),
+                                    Box::new(song_loop),
+                                ))
+                                    .unwrap();
+
+                                return Ok(());
                             }
-                        }
-                        #[cfg(feature = "ogg-playback")]
-                        SongFormat::OggMultiPart => {
-                            // we're sure that there are two elements
-                            let path_intro = unsafe { paths.get_unchecked(0) };
-                            let path_loop = unsafe { paths.get_unchecked(1) };
-
-                            match (
-                                filesystem::open(ctx, path_intro).map(|f| {
-                                    OggStreamReader::new(f).map_err(|e| GameError::ResourceLoadError(e.to_string()))
-                                }),
-                                filesystem::open(ctx, path_loop).map(|f| {
-                                    OggStreamReader::new(f).map_err(|e| GameError::ResourceLoadError(e.to_string()))
-                                }),
-                            ) {
-                                (Ok(Ok(song_intro)), Ok(Ok(song_loop))) => {
-                                    log::info!(
-                                        "Playing multi part Ogg BGM: {} {} + {}",
-                                        song_id,
-                                        path_intro,
-                                        path_loop
-                                    );
-
-                                    self.prev_song_id = self.current_song_id;
-                                    self.current_song_id = song_id;
-                                    self.send(PlaybackMessage::SaveState).unwrap();
-                                    self.send(PlaybackMessage::PlayOggSongMultiPart(
-                                        Box::new(song_intro),
-                                        Box::new(song_loop),
-                                    ))
-                                        .unwrap();
-
-                                    return Ok(());
-                                }
-                                (Ok(Err(err)), _) | (Err(err), _) | (_, Ok(Err(err))) | (_, Err(err)) => {
-                                    log::warn!("Failed to load multi part Ogg BGM {}: {}", song_id, err);
-                                }
+                            (Ok(Err(err)), _) | (Err(err), _) | (_, Ok(Err(err))) | (_, Err(err)) => {
+                                log::warn!("Failed to load multi part Ogg BGM {}: {}", song_id, err);
                             }
                         }
                     }
+                    _ => match open_song(ctx, format, paths) {
+                        Ok((intro, song)) => {
+                            log::info!("Playing streamed BGM: {} {}", song_id, paths.join(" + "));
+
+                            self.prev_song_id = self.current_song_id;
+                            self.current_song_id = song_id;
+                            self.send(PlaybackMessage::SaveState).unwrap();
+                            self.send(PlaybackMessage::PlayStreamSong(intro, song)).unwrap();
+
+                            return Ok(());
+                        }
+                        Err(err) => {
+                            log::warn!("Failed to load BGM {}: {}", song_id, err);
+                        }
+                    },
                 }
             }
         }
@@ -461,103 +489,54 @@ impl SoundManager for SoundManagerLibretro {
             return Ok(());
         }
 
-        let mut reader = BufReader::new(data).lines();
-        let mut params = PixToneParameters::empty();
+        let params = PixToneParameters::read_pxt(BufReader::new(data))?;
+        self.set_sample_params(id, params)
+    }
 
-        fn next_string<T: FromStr>(reader: &mut Lines<BufReader<Box::<dyn io::Read>>>) -> GameResult<T> {
-            while let Some(Ok(str)) = reader.next() {
-                let str = str.trim();
-                if str.is_empty() || str.starts_with('#') {
-                    continue;
-                }
+    fn set_sample_params(&mut self, id: u8, params: PixToneParameters) -> GameResult {
+        self.sample_params.insert(id, params);
 
-                let mut splits = str.split(':');
+        if self.no_audio {
+            return Ok(());
+        }
 
-                let _ = splits.next();
-                if let Some(str) = splits.next() {
-                    return str.trim().parse::<T>().map_err(|_| {
-                        GameError::ParseError("failed to parse the value as specified type.".to_string())
-                    });
-                } else {
-                    break;
-                }
-            }
+        self.send(PlaybackMessage::SetSampleParams(id, params)).unwrap();
 
-            Err(GameError::ParseError("unexpected end.".to_string()))
+        Ok(())
+    }
+
+    fn org_position(&self) -> Option<i32> {
+        match self.org_position.load(Ordering::Relaxed) {
+            -1 => None,
+            position => Some(position),
         }
+    }
 
-        for channel in &mut params.channels {
-            channel.enabled = next_string::<u8>(&mut reader)? != 0;
-            channel.length = next_string::<u32>(&mut reader)?;
-
-            channel.carrier.waveform_type = next_string::<u8>(&mut reader)?;
-            channel.carrier.pitch = next_string::<f32>(&mut reader)?;
-            channel.carrier.level = next_string::<i32>(&mut reader)?;
-            channel.carrier.offset = next_string::<i32>(&mut reader)?;
-
-            channel.frequency.waveform_type = next_string::<u8>(&mut reader)?;
-            channel.frequency.pitch = next_string::<f32>(&mut reader)?;
-            channel.frequency.level = next_string::<i32>(&mut reader)?;
-            channel.frequency.offset = next_string::<i32>(&mut reader)?;
-
-            channel.amplitude.waveform_type = next_string::<u8>(&mut reader)?;
-            channel.amplitude.pitch = next_string::<f32>(&mut reader)?;
-            channel.amplitude.level = next_string::<i32>(&mut reader)?;
-            channel.amplitude.offset = next_string::<i32>(&mut reader)?;
-
-            channel.envelope.initial = next_string::<i32>(&mut reader)?;
-            channel.envelope.time_a = next_string::<i32>(&mut reader)?;
-            channel.envelope.value_a = next_string::<i32>(&mut reader)?;
-            channel.envelope.time_b = next_string::<i32>(&mut reader)?;
-            channel.envelope.value_b = next_string::<i32>(&mut reader)?;
-            channel.envelope.time_c = next_string::<i32>(&mut reader)?;
-            channel.envelope.value_c = next_string::<i32>(&mut reader)?;
+    fn replace_org_song(&mut self, song: Song) {
+        if self.no_audio {
+            return;
         }
 
-        self.set_sample_params(id, params)
+        self.send(PlaybackMessage::ReplaceOrganyaSong(Box::new(song))).unwrap();
     }
 
-    fn set_sample_params(&mut self, id: u8, params: PixToneParameters) -> GameResult {
+    fn set_org_muted_tracks(&mut self, mask: u16) {
         if self.no_audio {
-            return Ok(());
+            return;
         }
 
-        self.send(PlaybackMessage::SetSampleParams(id, params)).unwrap();
+        self.send(PlaybackMessage::SetOrgMutedTracks(mask)).unwrap();
+    }
 
-        Ok(())
+    fn get_sample_params(&self, id: u8) -> PixToneParameters {
+        self.sample_params.get(&id).copied().unwrap_or_else(|| PixToneParameters::default_for(id))
     }
 
     fn load_custom_sound_effects(&mut self, ctx: &mut Context, roots: &Vec<String>) -> GameResult {
-        for path in roots.iter().rev() {
-            let wavs = filesystem::read_dir(ctx, [path, "sfx/"].join(""))?
-                .filter(|f| f.to_string_lossy().to_lowercase().ends_with(".wav"));
-
-            for filename in wavs {
-                if let Ok(mut file) = filesystem::open(ctx, &filename) {
-                    let wav = wav::WavSample::read_from(&mut file)?;
-                    let id = filename
-                        .file_stem()
-                        .unwrap_or_default()
-                        .to_str()
-                        .unwrap_or_default()
-                        .parse::<u8>()
-                        .unwrap_or(0);
-                    if id == 0 {
-                        continue;
-                    }
-                    let step = (wav.format.channels * 2) as usize;
-                    let data = wav
-                        .data
-                        .chunks_exact(2)
-                        .into_iter()
-                        .step_by(step)
-                        .map(|a| i16::from_ne_bytes([a[0], a[1]]))
-                        .collect();
-
-                    self.set_sfx_samples(id, data);
-                }
-            }
+        for (id, data) in custom_sfx::load_custom_sound_effects(ctx, roots) {
+            self.set_sfx_samples(id, data);
         }
+
         Ok(())
     }
 
@@ -577,7 +556,9 @@ pub(in crate::sound) enum PlaybackMessage {
     PlayOggSongSinglePart(Box<OggStreamReader<File>>),
     #[cfg(feature = "ogg-playback")]
     PlayOggSongMultiPart(Box<OggStreamReader<File>>, Box<OggStreamReader<File>>),
+    PlayStreamSong(Option<Box<dyn SongStream>>, Box<dyn SongStream>),
     PlaySample(u8),
+    PlaySamplePanned(u8, [f32; 2]),
     LoopSample(u8),
     LoopSampleFreq(u8, f32),
     StopSample(u8),
@@ -590,6 +571,9 @@ pub(in crate::sound) enum PlaybackMessage {
     SetSampleParams(u8, PixToneParameters),
     SetOrgInterpolation(InterpolationMode),
     SetSampleData(u8, Vec<i16>),
+    SetAudioEffects(AudioEffects),
+    ReplaceOrganyaSong(Box<Song>),
+    SetOrgMutedTracks(u16),
 }
 
 #[derive(PartialEq, Eq)]
@@ -598,6 +582,7 @@ enum PlaybackState {
     PlayingOrg,
     #[cfg(feature = "ogg-playback")]
     PlayingOgg,
+    PlayingStream,
 }
 
 enum PlaybackStateType {
@@ -605,6 +590,7 @@ enum PlaybackStateType {
     Organya(SavedOrganyaPlaybackState),
     #[cfg(feature = "ogg-playback")]
     Ogg(SavedOggPlaybackState),
+    Stream(SavedStreamPlaybackState),
 }
 
 impl Default for PlaybackStateType {
@@ -625,7 +611,9 @@ pub struct Runner {
     org_engine: Box<OrgPlaybackEngine>,
     #[cfg(feature = "ogg-playback")]
     ogg_engine: Box<OggPlaybackEngine>,
+    stream_engine: Box<StreamPlaybackEngine>,
     pixtone: Box<PixTonePlayback>,
+    effects_bus: Box<EffectsBus>,
 
     buf_size: usize,
     bgm_buf: Vec<u16>,
@@ -640,6 +628,7 @@ pub struct Runner {
     pub data: Vec<i16>,
 
     bank: SoundBank,
+    org_position: Arc<AtomicI32>,
     rx: Receiver<PlaybackMessage>,
 }
 
@@ -648,6 +637,7 @@ impl Runner {
     pub(in crate::sound) fn init(
         rx: Receiver<PlaybackMessage>,
         bank: SoundBank,
+        org_position: Arc<AtomicI32>,
         config: RunnerConfig,
     ) -> GameResult<Runner> {
 
@@ -659,11 +649,14 @@ impl Runner {
         let mut org_engine = Box::new(OrgPlaybackEngine::new());
         #[cfg(feature = "ogg-playback")]
         let mut ogg_engine = Box::new(OggPlaybackEngine::new());
+        let mut stream_engine = Box::new(StreamPlaybackEngine::new());
         let mut pixtone = Box::new(PixTonePlayback::new());
+        let mut effects_bus = Box::new(EffectsBus::new(sample_rate));
         pixtone.create_samples();
     
         log::info!("Audio format: {} {}", sample_rate, channels);
         org_engine.set_sample_rate(sample_rate as usize);
+        stream_engine.set_sample_rate(sample_rate as usize);
         #[cfg(feature = "ogg-playback")]
         {
             org_engine.loops = usize::MAX;
@@ -672,7 +665,7 @@ impl Runner {
     
         let buf_size = sample_rate as usize * 10 / 1000;
         let mut bgm_buf = vec![0x8080; buf_size * 2];
-        let mut pxt_buf = vec![0x8000; buf_size];
+        let mut pxt_buf = vec![0x8000; buf_size * 2];
         let mut bgm_index = 0;
         let mut pxt_index = 0;
         let mut samples = 0;
@@ -695,7 +688,9 @@ impl Runner {
             org_engine: org_engine,
             #[cfg(feature = "ogg-playback")]
             ogg_engine: ogg_engine,
+            stream_engine: stream_engine,
             pixtone: pixtone,
+            effects_bus: effects_bus,
             buf_size: buf_size,
             bgm_buf: bgm_buf,
             pxt_buf: pxt_buf,
@@ -708,12 +703,21 @@ impl Runner {
             bgm_fadeout: bgm_fadeout,
             data: data,
             bank: bank,
+            org_position: org_position,
             rx: rx,
         })
 
         
     }
 
+    /// Mixes given number of frames into `data`, which is resized to fit them.
+    pub fn render(&mut self, frames: usize) -> &[i16] {
+        self.data.resize(frames * self.channels, 0);
+        self.run();
+
+        &self.data
+    }
+
     pub fn run(&mut self){
         loop {
             if self.bgm_fadeout && self.bgm_vol > 0.0 {
@@ -787,9 +791,32 @@ impl Runner {
 
                     self.state = PlaybackState::PlayingOgg;
                 }
+                Ok(PlaybackMessage::PlayStreamSong(intro, song)) => {
+                    if self.state == PlaybackState::Stopped {
+                        self.saved_state = PlaybackStateType::None;
+                    }
+
+                    if self.bgm_fadeout {
+                        self.bgm_fadeout = false;
+                        self.bgm_vol = self.bgm_vol_saved;
+                    }
+
+                    self.stream_engine.start(intro, song);
+
+                    for i in &mut self.bgm_buf[0..self.samples] {
+                        *i = 0x8000
+                    }
+                    self.samples = self.stream_engine.render_to(&mut self.bgm_buf);
+                    self.bgm_index = 0;
+
+                    self.state = PlaybackState::PlayingStream;
+                }
                 Ok(PlaybackMessage::PlaySample(id)) => {
                     self.pixtone.play_sfx(id);
                 }
+                Ok(PlaybackMessage::PlaySamplePanned(id, gain)) => {
+                    self.pixtone.play_sfx_panned(id, gain);
+                }
 
                 Ok(PlaybackMessage::LoopSample(id)) => {
                     self.pixtone.loop_sfx(id);
@@ -813,6 +840,7 @@ impl Runner {
                     #[cfg(feature = "ogg-playback")]
                     self.ogg_engine.set_sample_rate((self.sample_rate / new_speed) as usize);
                     self.org_engine.set_sample_rate((self.sample_rate / new_speed) as usize);
+                    self.stream_engine.set_sample_rate((self.sample_rate / new_speed) as usize);
                 }
                 Ok(PlaybackMessage::SetSongVolume(new_volume)) => {
                     assert!(self.bgm_vol >= 0.0);
@@ -836,6 +864,7 @@ impl Runner {
                         PlaybackState::PlayingOrg => PlaybackStateType::Organya(self.org_engine.get_state()),
                         #[cfg(feature = "ogg-playback")]
                         PlaybackState::PlayingOgg => PlaybackStateType::Ogg(self.ogg_engine.get_state()),
+                        PlaybackState::PlayingStream => PlaybackStateType::Stream(self.stream_engine.get_state()),
                     };
                 }
                 Ok(PlaybackMessage::RestoreState) => {
@@ -886,6 +915,26 @@ impl Runner {
 
                             self.state = PlaybackState::PlayingOgg;
                         }
+                        PlaybackStateType::Stream(playback_state) => {
+                            self.stream_engine.set_state(playback_state);
+
+                            if self.state == PlaybackState::Stopped {
+                                self.stream_engine.rewind();
+                            }
+
+                            for i in &mut self.bgm_buf[0..self.samples] {
+                                *i = 0x8000
+                            }
+                            self.samples = self.stream_engine.render_to(&mut self.bgm_buf);
+                            self.bgm_index = 0;
+
+                            if self.bgm_fadeout {
+                                self.bgm_fadeout = false;
+                                self.bgm_vol = self.bgm_vol_saved;
+                            }
+
+                            self.state = PlaybackState::PlayingStream;
+                        }
                     }
                 }
                 Ok(PlaybackMessage::SetSampleParams(id, params)) => {
@@ -897,6 +946,17 @@ impl Runner {
                 Ok(PlaybackMessage::SetSampleData(id, data)) => {
                     self.pixtone.set_sample_data(id, data);
                 }
+                Ok(PlaybackMessage::SetAudioEffects(effects)) => {
+                    self.effects_bus.set_effects(effects);
+                }
+                Ok(PlaybackMessage::ReplaceOrganyaSong(song)) => {
+                    if self.state == PlaybackState::PlayingOrg {
+                        self.org_engine.replace_song(*song, &self.bank);
+                    }
+                }
+                Ok(PlaybackMessage::SetOrgMutedTracks(mask)) => {
+                    self.org_engine.muted_tracks = mask;
+                }
                 Err(_) => {
                     break;
                 }
@@ -924,6 +984,9 @@ impl Runner {
                         PlaybackState::PlayingOgg => {
                             self.samples = self.ogg_engine.render_to(&mut self.bgm_buf);
                         }
+                        PlaybackState::PlayingStream => {
+                            self.samples = self.stream_engine.render_to(&mut self.bgm_buf);
+                        }
                         _ => unreachable!(),
                     }
                     self.bgm_index = 2;
@@ -931,47 +994,34 @@ impl Runner {
                 }
             };
 
-            let pxt_sample: u16 = self.pxt_buf[self.pxt_index];
+            let (pxt_sample_l, pxt_sample_r): (u16, u16) =
+                (self.pxt_buf[self.pxt_index], self.pxt_buf[self.pxt_index + 1]);
 
-            if self.pxt_index < (self.pxt_buf.len() - 1) {
-                self.pxt_index += 1;
+            if self.pxt_index < (self.pxt_buf.len() - 2) {
+                self.pxt_index += 2;
             } else {
                 self.pxt_index = 0;
                 self.pxt_buf.fill(0x8000);
-                self.pixtone.mix(&mut self.pxt_buf, self.sample_rate / self.speed);
+                self.pixtone.mix(&mut self.pxt_buf, self.sample_rate / (self.speed * self.effects_bus.pitch()));
             }
 
+            let [mix_l, mix_r] = self.effects_bus.process([
+                ((bgm_sample_l ^ 0x8000) as i16) as f32 * self.bgm_vol
+                    + ((pxt_sample_l ^ 0x8000) as i16) as f32 * self.sfx_vol,
+                ((bgm_sample_r ^ 0x8000) as i16) as f32 * self.bgm_vol
+                    + ((pxt_sample_r ^ 0x8000) as i16) as f32 * self.sfx_vol,
+            ]);
+
             if frame.len() >= 2 {
-                let sample_l = clamp(
-                    (((bgm_sample_l ^ 0x8000) as i16) as f32 * self.bgm_vol) as isize
-                        + (((pxt_sample ^ 0x8000) as i16) as f32 * self.sfx_vol) as isize,
-                    -0x7fff,
-                    0x7fff,
-                ) as i16; //u16
-                //    ^ 0x8000;
-                let sample_r = clamp(
-                    (((bgm_sample_r ^ 0x8000) as i16) as f32 * self.bgm_vol) as isize
-                        + (((pxt_sample ^ 0x8000) as i16) as f32 * self.sfx_vol) as isize,
-                    -0x7fff,
-                    0x7fff,
-                ) as i16; //u16
-                //    ^ 0x8000;
-
-                frame[0] = sample_l; //T::from_sample(sample_l);
-                frame[1] = sample_r; //T::from_sample(sample_r);
+                frame[0] = clamp(mix_l as isize, -0x7fff, 0x7fff) as i16;
+                frame[1] = clamp(mix_r as isize, -0x7fff, 0x7fff) as i16;
             } else {
-                let sample = clamp(
-                    ((((bgm_sample_l ^ 0x8000) as i16) + ((bgm_sample_r ^ 0x8000) as i16)) as f32 * self.bgm_vol / 2.0)
-                        as isize
-                        + (((pxt_sample ^ 0x8000) as i16) as f32 * self.sfx_vol) as isize,
-                    -0x7fff,
-                    0x7fff,
-                ) as i16; //u16
-                //    ^ 0x8000;
-
-                frame[0] = sample; //T::from_sample(sample);
+                frame[0] = clamp(((mix_l + mix_r) / 2.0) as isize, -0x7fff, 0x7fff) as i16;
             }
         }
+
+        let position = if self.state == PlaybackState::PlayingOrg { self.org_engine.get_position() } else { -1 };
+        self.org_position.store(position, Ordering::Relaxed);
     }
 
 }
@@ -19,6 +19,7 @@ use crate::sound::ogg_playback::{OggPlaybackEngine, SavedOggPlaybackState};
 use crate::sound::org_playback::{OrgPlaybackEngine, SavedOrganyaPlaybackState};
 use crate::sound::organya;
 use crate::sound::organya::Song;
+use crate::sound::effects::AudioEffects;
 use crate::sound::pixtone::{PixToneParameters, PixTonePlayback};
 use crate::sound::{wave_bank, wave_bank::SoundBank, wav};
 use crate::sound::backend::*;
@@ -62,6 +63,10 @@ impl SoundManager for SoundManagerNull {
 
     fn play_sfx(&mut self, id: u8) {}
 
+    fn play_sfx_at(&mut self, id: u8, x: i32, y: i32) {}
+
+    fn set_listener(&mut self, listener: Option<SoundListener>) {}
+
     fn loop_sfx(&self, id: u8) {}
 
     fn loop_sfx_freq(&mut self, id: u8, freq: f32) {}
@@ -76,6 +81,8 @@ impl SoundManager for SoundManagerNull {
 
     fn set_sfx_samples(&mut self, id: u8, data: Vec<i16>) {}
 
+    fn set_audio_effects(&mut self, effects: AudioEffects) {}
+
     fn reload_songs(&mut self, constants: &EngineConstants, settings: &Settings, ctx: &mut Context) -> GameResult {
         Ok(())
     }
@@ -116,6 +123,18 @@ impl SoundManager for SoundManagerNull {
         Ok(())
     }
 
+    fn org_position(&self) -> Option<i32> {
+        None
+    }
+
+    fn replace_org_song(&mut self, song: Song) {}
+
+    fn set_org_muted_tracks(&mut self, mask: u16) {}
+
+    fn get_sample_params(&self, id: u8) -> PixToneParameters {
+        PixToneParameters::default_for(id)
+    }
+
     fn load_custom_sound_effects(&mut self, ctx: &mut Context, roots: &Vec<String>) -> GameResult {
         Ok(())
     }
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use byteorder::{ReadBytesExt, LE};
+
+use crate::framework::context::Context;
+use crate::framework::error::{GameError, GameResult};
+use crate::framework::filesystem;
+use crate::sound::pixtone::PIXTONE_SAMPLE_RATE;
+use crate::sound::stuff::resample;
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// Audio decoded from a user provided file, with interleaved samples in `-1.0..1.0` range.
+pub struct DecodedSound {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+impl DecodedSound {
+    /// Reads a WAV file with 8, 16, 24 or 32-bit integer or 32/64-bit float samples and any number of channels.
+    pub fn read_wav<R: io::Read>(mut f: R) -> io::Result<DecodedSound> {
+        let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_owned());
+
+        let mut id = [0; 4];
+        f.read_exact(&mut id)?;
+        let _length = f.read_u32::<LE>()?;
+        if id != *b"RIFF" {
+            return Err(invalid("Expected RIFF signature."));
+        }
+
+        f.read_exact(&mut id)?;
+        if id != *b"WAVE" {
+            return Err(invalid("Expected 'WAVE' RIFF chunk."));
+        }
+
+        // (format, channels, sample rate, bits per sample)
+        let mut format = None;
+
+        loop {
+            f.read_exact(&mut id)?;
+            let length = f.read_u32::<LE>()?;
+            // chunks are padded to an even length
+            let padded = length as u64 + (length & 1) as u64;
+
+            match &id {
+                b"fmt " => {
+                    let mut tag = f.read_u16::<LE>()?;
+                    let channels = f.read_u16::<LE>()?;
+                    let sample_rate = f.read_u32::<LE>()?;
+                    let _byte_rate = f.read_u32::<LE>()?;
+                    let _block_align = f.read_u16::<LE>()?;
+                    let bits = f.read_u16::<LE>()?;
+                    let mut read = 16;
+
+                    if tag == FORMAT_EXTENSIBLE && length >= 40 {
+                        let _extra_size = f.read_u16::<LE>()?;
+                        let _valid_bits = f.read_u16::<LE>()?;
+                        let _channel_mask = f.read_u32::<LE>()?;
+                        // the first two bytes of the sub-format GUID are the actual format tag
+                        tag = f.read_u16::<LE>()?;
+                        read += 10;
+                    }
+
+                    io::copy(&mut (&mut f).take(padded.saturating_sub(read)), &mut io::sink())?;
+
+                    if channels == 0 || sample_rate == 0 {
+                        return Err(invalid("Invalid WAV format."));
+                    }
+
+                    format = Some((tag, channels, sample_rate, bits));
+                }
+                b"data" => {
+                    let (tag, channels, sample_rate, bits) = match format {
+                        Some(format) => format,
+                        None => return Err(invalid("Expected 'fmt ' chunk before 'data'.")),
+                    };
+
+                    let mut data = Vec::new();
+                    (&mut f).take(length as u64).read_to_end(&mut data)?;
+
+                    let samples: Vec<f32> = match (tag, bits) {
+                        (FORMAT_PCM, 8) => data.iter().map(|&s| (s as f32 - 128.0) / 128.0).collect(),
+                        (FORMAT_PCM, 16) => {
+                            data.chunks_exact(2).map(|s| i16::from_le_bytes([s[0], s[1]]) as f32 / 32768.0).collect()
+                        }
+                        (FORMAT_PCM, 24) => data
+                            .chunks_exact(3)
+                            .map(|s| i32::from_le_bytes([0, s[0], s[1], s[2]]) as f32 / 2147483648.0)
+                            .collect(),
+                        (FORMAT_PCM, 32) => data
+                            .chunks_exact(4)
+                            .map(|s| i32::from_le_bytes([s[0], s[1], s[2], s[3]]) as f32 / 2147483648.0)
+                            .collect(),
+                        (FORMAT_FLOAT, 32) => {
+                            data.chunks_exact(4).map(|s| f32::from_le_bytes([s[0], s[1], s[2], s[3]])).collect()
+                        }
+                        (FORMAT_FLOAT, 64) => data
+                            .chunks_exact(8)
+                            .map(|s| f64::from_le_bytes([s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7]]) as f32)
+                            .collect(),
+                        _ => {
+                            return Err(invalid(&format!("Unsupported WAV format {} with {}-bit samples.", tag, bits)));
+                        }
+                    };
+
+                    return Ok(DecodedSound { channels, sample_rate, samples });
+                }
+                _ => {
+                    io::copy(&mut (&mut f).take(padded), &mut io::sink())?;
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "ogg-playback")]
+    pub fn read_ogg<R: io::Read + io::Seek>(f: R) -> GameResult<DecodedSound> {
+        use lewton::inside_ogg::OggStreamReader;
+
+        let mut reader = OggStreamReader::new(f).map_err(|e| GameError::ResourceLoadError(e.to_string()))?;
+        let mut samples = Vec::new();
+
+        loop {
+            match reader.read_dec_packet_itl() {
+                Ok(Some(buf)) => samples.extend(buf.iter().map(|&s| s as f32 / 32768.0)),
+                Ok(None) => break,
+                Err(e) => return Err(GameError::ResourceLoadError(e.to_string())),
+            }
+        }
+
+        Ok(DecodedSound {
+            channels: reader.ident_hdr.audio_channels as u16,
+            sample_rate: reader.ident_hdr.audio_sample_rate,
+            samples,
+        })
+    }
+
+    /// Mixes the sound down to mono and resamples it to given rate.
+    pub fn to_mono(&self, sample_rate: u32) -> Vec<i16> {
+        let channels = self.channels as usize;
+        let mono = self
+            .samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .map(|s| (s.clamp(-1.0, 1.0) * 32767.0) as i16)
+            .collect();
+
+        resample(mono, self.sample_rate, 1, sample_rate)
+    }
+}
+
+fn load_sound(ctx: &mut Context, path: &Path) -> GameResult<Vec<i16>> {
+    let file = filesystem::open(ctx, path)?;
+    let extension = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+
+    let sound = match extension.as_str() {
+        "wav" => DecodedSound::read_wav(file)?,
+        #[cfg(feature = "ogg-playback")]
+        "ogg" => DecodedSound::read_ogg(file)?,
+        _ => return Err(GameError::ResourceLoadError(format!("Unsupported sound format: {}", path.display()))),
+    };
+
+    Ok(sound.to_mono(PIXTONE_SAMPLE_RATE))
+}
+
+/// Loads the sound effects replacing PixTone sounds from `sfx/` directories of given roots, the ones listed first
+/// take precedence.
+///
+/// Files named after the sound ID (eg. `sfx/12.wav`) are picked up automatically, `sfx/sfx.json` can also map IDs to
+/// arbitrary file names: `{ "12": "door.ogg" }`. Files that can't be loaded are skipped.
+pub fn load_custom_sound_effects(ctx: &mut Context, roots: &Vec<String>) -> HashMap<u8, Vec<i16>> {
+    let mut sounds = HashMap::new();
+
+    for root in roots.iter().rev() {
+        let dir = [root, "sfx/"].join("");
+        let mut files: Vec<(u8, String)> = Vec::new();
+
+        if let Ok(entries) = filesystem::read_dir(ctx, &dir) {
+            for path in entries {
+                let extension = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+                if extension != "wav" && extension != "ogg" {
+                    continue;
+                }
+
+                match path.file_stem().unwrap_or_default().to_string_lossy().parse::<u8>() {
+                    Ok(id) if id != 0 => files.push((id, path.to_string_lossy().into_owned())),
+                    _ => {}
+                }
+            }
+        }
+
+        // entries of the manifest are applied after numbered files, so they win within the same directory
+        let manifest_path = [&dir, "sfx.json"].join("");
+        if let Ok(file) = filesystem::open(ctx, &manifest_path) {
+            match serde_json::from_reader::<_, HashMap<String, String>>(file) {
+                Ok(manifest) => {
+                    let mut entries: Vec<(u8, String)> = manifest
+                        .into_iter()
+                        .filter_map(|(id, name)| match id.parse::<u8>() {
+                            Ok(id) if id != 0 => Some((id, [&dir, name.as_str()].join(""))),
+                            _ => {
+                                log::warn!("Invalid sound ID in {}: {}", manifest_path, id);
+                                None
+                            }
+                        })
+                        .collect();
+                    entries.sort();
+                    files.extend(entries);
+                }
+                Err(e) => log::warn!("Failed to parse {}: {}", manifest_path, e),
+            }
+        }
+
+        for (id, path) in files {
+            match load_sound(ctx, Path::new(&path)) {
+                Ok(data) => {
+                    sounds.insert(id, data);
+                }
+                Err(e) => log::warn!("Failed to load custom sound effect {}: {}", path, e),
+            }
+        }
+    }
+
+    sounds
+}
+
+#[test]
+fn test_read_wav() {
+    fn wav(tag: u16, channels: u16, sample_rate: u32, bits: u16, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + 10 + data.len() as u32).to_le_bytes());
+        out.extend_from_slice(b"WAVE");
+        // unknown chunks have to be skipped, including the padding byte
+        out.extend_from_slice(b"LIST");
+        out.extend_from_slice(&1u32.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(b"fmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&tag.to_le_bytes());
+        out.extend_from_slice(&channels.to_le_bytes());
+        out.extend_from_slice(&sample_rate.to_le_bytes());
+        out.extend_from_slice(&(sample_rate * (channels * bits / 8) as u32).to_le_bytes());
+        out.extend_from_slice(&(channels * bits / 8).to_le_bytes());
+        out.extend_from_slice(&bits.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+
+    let sound = DecodedSound::read_wav(&wav(FORMAT_PCM, 2, 11025, 8, &[128, 0, 255, 128])[..]).unwrap();
+    assert_eq!((sound.channels, sound.sample_rate), (2, 11025));
+    assert_eq!(sound.samples, vec![0.0, -1.0, 127.0 / 128.0, 0.0]);
+
+    let sound = DecodedSound::read_wav(&wav(FORMAT_PCM, 1, 22050, 24, &[0, 0, 0x40, 0, 0, 0xc0])[..]).unwrap();
+    assert_eq!(sound.samples, vec![0.5, -0.5]);
+
+    let data: Vec<u8> = [0.25f32, -0.25].iter().flat_map(|s| s.to_le_bytes()).collect();
+    let sound = DecodedSound::read_wav(&wav(FORMAT_FLOAT, 1, 44100, 32, &data)[..]).unwrap();
+    assert_eq!(sound.samples, vec![0.25, -0.25]);
+
+    // 44.1 kHz stereo is mixed down and resampled to half the length
+    let sound = DecodedSound { channels: 2, sample_rate: 44100, samples: vec![0.5, 0.0].repeat(100) };
+    let mono = sound.to_mono(PIXTONE_SAMPLE_RATE);
+    assert_eq!(mono.len(), 50);
+    assert!(mono.iter().all(|&s| s == (0.25 * 32767.0) as i16));
+}
@@ -0,0 +1,239 @@
+//! Effects applied to the final mix of the sound backends, such as the muffled sound while the player is underwater.
+
+use std::collections::HashMap;
+
+use crate::framework::context::Context;
+use crate::framework::error::{GameError, GameResult};
+use crate::framework::filesystem;
+
+/// Longest supported echo delay in milliseconds, the delay line is allocated for it up front so changing the effects
+/// never allocates in the audio thread.
+pub const MAX_ECHO_DELAY: f32 = 1000.0;
+
+/// Time in seconds parameter changes are smoothed over to avoid clicks.
+const RAMP_TIME: f32 = 0.01;
+
+#[derive(Copy, Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct EchoParams {
+    /// Delay between repeats in milliseconds.
+    pub delay: f32,
+    /// Portion of each repeat fed back into the delay line, `0.0..0.95`.
+    pub feedback: f32,
+    /// Volume of the repeats mixed into the output.
+    pub mix: f32,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct AudioEffects {
+    /// Cutoff frequency of the low-pass filter in Hz.
+    #[serde(default)]
+    pub lowpass: Option<f32>,
+    #[serde(default)]
+    pub echo: Option<EchoParams>,
+    /// Playback rate multiplier of sound effects.
+    #[serde(default = "default_pitch")]
+    pub pitch: f32,
+}
+
+fn default_pitch() -> f32 {
+    1.0
+}
+
+impl AudioEffects {
+    pub const NONE: AudioEffects = AudioEffects { lowpass: None, echo: None, pitch: 1.0 };
+
+    /// Layers another set of effects on top of this one, the lower cutoff wins, echo is replaced if the other set has
+    /// one and pitch is multiplied.
+    pub fn combine(self, other: &AudioEffects) -> AudioEffects {
+        AudioEffects {
+            lowpass: match (self.lowpass, other.lowpass) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+            echo: other.echo.or(self.echo),
+            pitch: self.pitch * other.pitch,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct AudioEffectsFile {
+    #[serde(default)]
+    underwater: Option<AudioEffects>,
+    #[serde(default)]
+    stages: HashMap<String, AudioEffects>,
+}
+
+/// Environmental effects of the game, configured through `audio_effects.json` files in data directories.
+///
+/// The base game has no stage effects, mods can add them like this:
+///
+/// ```json
+/// {
+///   "underwater": { "lowpass": 1000.0 },
+///   "stages": {
+///     "Cave": { "echo": { "delay": 180.0, "feedback": 0.3, "mix": 0.2 } },
+///     "Hell1": { "echo": { "delay": 320.0, "feedback": 0.45, "mix": 0.25 }, "pitch": 0.95 }
+///   }
+/// }
+/// ```
+pub struct AudioEffectsTable {
+    /// Effects layered on top of the stage ones while the player is in water.
+    pub underwater: AudioEffects,
+    /// Effects of stages by map name.
+    pub stages: HashMap<String, AudioEffects>,
+}
+
+impl AudioEffectsTable {
+    pub fn new() -> AudioEffectsTable {
+        AudioEffectsTable {
+            underwater: AudioEffects { lowpass: Some(1000.0), echo: None, pitch: 1.0 },
+            stages: HashMap::new(),
+        }
+    }
+
+    /// Loads the effects from data directories, entries in mod directories replace the ones of the base game.
+    pub fn load(ctx: &mut Context, base_paths: &[String]) -> GameResult<AudioEffectsTable> {
+        let mut table = AudioEffectsTable::new();
+
+        for root in base_paths.iter().rev() {
+            let path = [root, "audio_effects.json"].join("");
+            if !filesystem::exists(ctx, &path) {
+                continue;
+            }
+
+            let file: AudioEffectsFile = serde_json::from_reader(filesystem::open(ctx, &path)?)
+                .map_err(|e| GameError::ParseError(format!("{}: {}", path, e)))?;
+
+            if let Some(underwater) = file.underwater {
+                table.underwater = underwater;
+            }
+            table.stages.extend(file.stages);
+        }
+
+        Ok(table)
+    }
+
+    pub fn get(&self, map: &str, underwater: bool) -> AudioEffects {
+        let effects = self.stages.get(map).copied().unwrap_or(AudioEffects::NONE);
+
+        if underwater {
+            effects.combine(&self.underwater)
+        } else {
+            effects
+        }
+    }
+}
+
+/// Processes the mixed output of a sound backend, sample by sample.
+pub(crate) struct EffectsBus {
+    effects: AudioEffects,
+    sample_rate: f32,
+    ramp: f32,
+    lowpass_coef: f32,
+    lowpass_target: f32,
+    lowpass_state: [f32; 2],
+    echo_line: Vec<[f32; 2]>,
+    echo_pos: usize,
+    echo_delay: usize,
+    echo_feedback: f32,
+    echo_mix: f32,
+}
+
+impl EffectsBus {
+    pub fn new(sample_rate: f32) -> EffectsBus {
+        EffectsBus {
+            effects: AudioEffects::NONE,
+            sample_rate,
+            ramp: 1.0 - (-1.0 / (RAMP_TIME * sample_rate)).exp(),
+            lowpass_coef: 1.0,
+            lowpass_target: 1.0,
+            lowpass_state: [0.0; 2],
+            echo_line: vec![[0.0; 2]; (sample_rate * MAX_ECHO_DELAY / 1000.0) as usize + 1],
+            echo_pos: 0,
+            echo_delay: 1,
+            echo_feedback: 0.0,
+            echo_mix: 0.0,
+        }
+    }
+
+    /// Playback rate multiplier for sound effects, limited to two octaves in each direction.
+    pub fn pitch(&self) -> f32 {
+        self.effects.pitch.clamp(0.25, 4.0)
+    }
+
+    pub fn set_effects(&mut self, effects: AudioEffects) {
+        self.lowpass_target = match effects.lowpass {
+            Some(cutoff) => {
+                let cutoff = cutoff.clamp(20.0, self.sample_rate / 2.0);
+                1.0 - (-2.0 * std::f32::consts::PI * cutoff / self.sample_rate).exp()
+            }
+            None => 1.0,
+        };
+
+        // the repeats of a disabled echo are left to decay with the last parameters
+        if let Some(echo) = effects.echo {
+            let delay = echo.delay.clamp(1.0, MAX_ECHO_DELAY) * self.sample_rate / 1000.0;
+            self.echo_delay = (delay as usize).clamp(1, self.echo_line.len() - 1);
+            self.echo_feedback = echo.feedback.clamp(0.0, 0.95);
+            self.echo_mix = echo.mix.clamp(0.0, 1.0);
+        }
+
+        self.effects = effects;
+    }
+
+    pub fn process(&mut self, frame: [f32; 2]) -> [f32; 2] {
+        self.lowpass_coef += (self.lowpass_target - self.lowpass_coef) * self.ramp;
+
+        let len = self.echo_line.len();
+        let delayed = self.echo_line[(self.echo_pos + len - self.echo_delay) % len];
+        let echo_input = if self.effects.echo.is_some() { 1.0 } else { 0.0 };
+        let mut output = [0.0; 2];
+
+        for ch in 0..2 {
+            let state = &mut self.lowpass_state[ch];
+            *state += (frame[ch] - *state) * self.lowpass_coef;
+
+            self.echo_line[self.echo_pos][ch] = *state * echo_input + delayed[ch] * self.echo_feedback;
+            output[ch] = *state + delayed[ch] * self.echo_mix;
+        }
+
+        self.echo_pos = (self.echo_pos + 1) % len;
+
+        output
+    }
+}
+
+#[test]
+fn test_effects_bus() {
+    let mut bus = EffectsBus::new(44100.0);
+    let out = bus.process([0.5, -0.5]);
+    assert!((out[0] - 0.5).abs() < 1e-6 && (out[1] + 0.5).abs() < 1e-6);
+
+    // a 500 Hz low-pass all but removes a tone at the Nyquist frequency once the cutoff has settled
+    bus.set_effects(AudioEffects { lowpass: Some(500.0), ..AudioEffects::NONE });
+    let mut peak = 0.0f32;
+    for i in 0..4410 {
+        let s = if i % 2 == 0 { 1.0 } else { -1.0 };
+        let out = bus.process([s, s]);
+        if i >= 4000 {
+            peak = peak.max(out[0].abs());
+        }
+    }
+    assert!(peak < 0.1);
+
+    let mut bus = EffectsBus::new(1000.0);
+    bus.set_effects(AudioEffects {
+        echo: Some(EchoParams { delay: 10.0, feedback: 0.5, mix: 1.0 }),
+        ..AudioEffects::NONE
+    });
+    let out: Vec<f32> = (0..25).map(|i| bus.process(if i == 0 { [1.0, 1.0] } else { [0.0, 0.0] })[0]).collect();
+    assert!((out[0] - 1.0).abs() < 1e-6);
+    assert!((out[10] - 1.0).abs() < 1e-6 && (out[20] - 0.5).abs() < 1e-6);
+    assert!(out[5].abs() < 1e-6);
+
+    let cave = AudioEffects { echo: Some(EchoParams { delay: 200.0, feedback: 0.3, mix: 0.3 }), ..AudioEffects::NONE };
+    let water = AudioEffects { lowpass: Some(1000.0), echo: None, pitch: 0.8 };
+    let combined = cave.combine(&water);
+    assert_eq!((combined.lowpass, combined.echo, combined.pitch), (Some(1000.0), cave.echo, 0.8));
+}
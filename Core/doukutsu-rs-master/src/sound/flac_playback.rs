@@ -0,0 +1,86 @@
+use std::io::{Seek, SeekFrom};
+
+use claxon::FlacReader;
+
+use crate::framework::error::{GameError, GameResult};
+use crate::framework::filesystem::File;
+use crate::sound::stream_playback::SongStream;
+
+fn flac_error(e: claxon::Error) -> GameError {
+    GameError::ResourceLoadError(format!("FLAC decoding error: {}", e))
+}
+
+/// Streams a FLAC file one block at a time, samples are scaled to 16 bits and only the first two channels are kept.
+pub struct FlacStream {
+    reader: Option<FlacReader<File>>,
+    sample_rate: u32,
+    channels: u8,
+    bits_per_sample: u32,
+    block_buffer: Vec<i32>,
+}
+
+impl FlacStream {
+    pub fn new(file: File) -> GameResult<FlacStream> {
+        let reader = FlacReader::new(file).map_err(flac_error)?;
+        let info = reader.streaminfo();
+
+        Ok(FlacStream {
+            sample_rate: info.sample_rate,
+            channels: info.channels.min(2) as u8,
+            bits_per_sample: info.bits_per_sample,
+            reader: Some(reader),
+            block_buffer: Vec::new(),
+        })
+    }
+}
+
+impl SongStream for FlacStream {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    fn read_packet(&mut self) -> GameResult<Option<Vec<i16>>> {
+        let reader = match &mut self.reader {
+            Some(reader) => reader,
+            None => return Ok(None),
+        };
+
+        let buffer = std::mem::take(&mut self.block_buffer);
+        let block = match reader.blocks().read_next_or_eof(buffer).map_err(flac_error)? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+
+        let shift = self.bits_per_sample as i32 - 16;
+        let mut samples = Vec::with_capacity(block.duration() as usize * self.channels as usize);
+        for i in 0..block.duration() {
+            for ch in 0..self.channels as u32 {
+                let sample = block.sample(ch, i);
+                let sample = if shift > 0 { sample >> shift } else { sample << -shift };
+                samples.push(sample as i16);
+            }
+        }
+
+        self.block_buffer = block.into_buffer();
+
+        Ok(Some(samples))
+    }
+
+    fn rewind(&mut self) -> GameResult {
+        let reader = match self.reader.take() {
+            Some(reader) => reader,
+            None => return Err(GameError::ResourceLoadError("FLAC stream is closed.".to_owned())),
+        };
+
+        // the reader buffers ahead, so the only way back is parsing the headers again
+        let mut file = reader.into_inner();
+        file.seek(SeekFrom::Start(0))?;
+        self.reader = Some(FlacReader::new(file).map_err(flac_error)?);
+
+        Ok(())
+    }
+}
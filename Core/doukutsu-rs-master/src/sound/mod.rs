@@ -2,21 +2,28 @@
 
 pub mod backend;
 
+mod custom_sfx;
+pub mod effects;
 mod fir;
+#[cfg(feature = "flac-playback")]
+mod flac_playback;
 #[cfg(feature = "ogg-playback")]
 mod ogg_playback;
 mod org_playback;
-mod organya;
+pub mod organya;
 pub mod pixtone;
 mod pixtone_sfx;
+pub mod render;
+mod stream_playback;
 mod stuff;
-mod wav;
+mod tracker;
+pub(crate) mod wav;
 mod wave_bank;
 
 #[cfg(feature = "audio-cpal")]
 pub mod backend_cpal;
 
-#[cfg(feature = "backend-libretro")]
+// also used outside of the libretro core to mix audio for video capture
 pub mod backend_libretro;
 
 pub mod backend_null;
@@ -1,10 +1,9 @@
 use std::sync::{Arc, RwLock};
 
 use lewton::inside_ogg::OggStreamReader;
-use num_traits::clamp;
 
 use crate::framework::filesystem::File;
-use crate::sound::stuff::cubic_interp;
+use crate::sound::stuff::resample_pcm;
 use crate::sound::wav::WavFormat;
 
 pub(crate) struct OggPlaybackEngine {
@@ -134,121 +133,8 @@ impl OggPlaybackEngine {
         }
     }
 
-    fn resample_buffer(&self, mut data: Vec<i16>, sample_rate: u32, channels: u8) -> Vec<i16> {
-        if data.is_empty() {
-            return data;
-        }
-
-        if sample_rate != self.output_format.sample_rate {
-            let mut tmp_data = Vec::with_capacity(
-                (data.len() as f64 * self.output_format.sample_rate as f64 / sample_rate as f64) as usize,
-            );
-            let mut pos = 0.0;
-            let phase = sample_rate as f32 / self.output_format.sample_rate as f32;
-            let num_samples = data.len() / channels as usize;
-
-            if channels == 1 {
-                loop {
-                    if pos >= num_samples as f32 {
-                        data = tmp_data;
-                        break;
-                    }
-
-                    let s = unsafe {
-                        let upos = pos as usize;
-                        let s1 = (*data.get_unchecked(upos) as f32) / 32768.0;
-                        let s2 = (*data.get_unchecked(clamp(upos + 1, 0, data.len() - 1)) as f32) / 32768.0;
-                        let s3 = (*data.get_unchecked(clamp(upos + 2, 0, data.len() - 1)) as f32) / 32768.0;
-                        let s4 =
-                            (*data.get_unchecked(clamp(upos.saturating_sub(1), 0, data.len() - 1)) as f32) / 32768.0;
-
-                        (cubic_interp(s1, s2, s4, s3, pos.fract()) * 32768.0) as i16
-                    };
-                    tmp_data.push(s);
-
-                    pos += phase;
-                }
-            } else if channels == 2 {
-                let max_samp = (num_samples - 1) * 2;
-
-                // unrolled for performance reasons
-                loop {
-                    if pos >= num_samples as f32 {
-                        data = tmp_data;
-                        break;
-                    }
-
-                    let sl = unsafe {
-                        let upos = pos as usize;
-                        let max = max_samp as usize;
-                        let s1 = (*data.get_unchecked(upos * 2) as f32) / 32768.0;
-                        let s2 = (*data.get_unchecked(clamp((upos + 1) * 2, 0, max)) as f32) / 32768.0;
-                        let s3 = (*data.get_unchecked(clamp((upos + 2) * 2, 0, max)) as f32) / 32768.0;
-                        let s4 = (*data.get_unchecked(clamp(upos.saturating_sub(1) * 2, 0, max)) as f32) / 32768.0;
-
-                        (cubic_interp(s1, s2, s4, s3, pos.fract()) * 32768.0) as i16
-                    };
-                    tmp_data.push(sl);
-
-                    let sr = unsafe {
-                        let upos = pos as usize;
-                        let max = max_samp as usize + 1;
-                        let s1 = (*data.get_unchecked(upos * 2 + 1) as f32) / 32768.0;
-                        let s2 = (*data.get_unchecked(clamp((upos + 1) * 2 + 1, 1, max)) as f32) / 32768.0;
-                        let s3 = (*data.get_unchecked(clamp((upos + 2) * 2 + 1, 1, max)) as f32) / 32768.0;
-                        let s4 = (*data.get_unchecked(clamp(upos.saturating_sub(1) * 2 + 1, 0, max)) as f32) / 32768.0;
-
-                        (cubic_interp(s1, s2, s4, s3, pos.fract()) * 32768.0) as i16
-                    };
-                    tmp_data.push(sr);
-
-                    pos += phase;
-                }
-            } else {
-                let cc = channels as usize;
-                let max_samp = (num_samples - 1) * cc;
-
-                loop {
-                    if pos >= num_samples as f32 {
-                        data = tmp_data;
-                        break;
-                    }
-
-                    for c in 0..channels {
-                        let s = unsafe {
-                            let upos = pos as usize;
-                            let max = max_samp + c as usize;
-                            let s1 = (*data.get_unchecked(upos * cc + c as usize) as f32) / 32768.0;
-                            let s2 = (*data.get_unchecked(clamp((upos + 1) * cc + c as usize, c as usize, max)) as f32)
-                                / 32768.0;
-                            let s3 = (*data.get_unchecked(clamp((upos + 2) * cc + c as usize, c as usize, max)) as f32) / 32768.0;
-                            let s4 =
-                                (*data.get_unchecked(clamp(upos.saturating_sub(1) * cc + c as usize, c as usize, max))
-                                    as f32)
-                                    / 32768.0;
-
-                            (cubic_interp(s1, s2, s4, s3, pos.fract()) * 32768.0) as i16
-                        };
-                        tmp_data.push(s);
-                    }
-
-                    pos += phase;
-                }
-            }
-        }
-
-        if channels == 1 {
-            let mut tmp_data = Vec::with_capacity(data.len() * 2);
-
-            for s in data.iter().copied() {
-                tmp_data.push(s);
-                tmp_data.push(s);
-            }
-
-            data = tmp_data;
-        }
-
-        data
+    fn resample_buffer(&self, data: Vec<i16>, sample_rate: u32, channels: u8) -> Vec<i16> {
+        resample_pcm(data, sample_rate, channels, self.output_format.sample_rate)
     }
 
     pub fn render_to(&mut self, buf: &mut [u16]) -> usize {
@@ -48,6 +48,8 @@ pub(crate) struct OrgPlaybackEngine {
     frames_per_tick: usize,
     pub loops: usize,
     pub interpolation: InterpolationMode,
+    /// Bit mask of tracks that are played silently.
+    pub muted_tracks: u16,
 }
 
 pub struct SavedOrganyaPlaybackState {
@@ -82,6 +84,7 @@ impl OrgPlaybackEngine {
             frames_per_tick,
             loops: 1,
             interpolation: InterpolationMode::Linear,
+            muted_tracks: 0,
         }
     }
 
@@ -142,6 +145,19 @@ impl OrgPlaybackEngine {
         self.keys.fill(255);
     }
 
+    /// Replaces the song with an edited version of it, playback continues from the same position.
+    pub fn replace_song(&mut self, song: Organya, samples: &SoundBank) {
+        let play_pos = self.play_pos;
+        self.start_song(song, samples);
+
+        let loop_range = self.song.time.loop_range;
+        self.play_pos = if play_pos >= loop_range.end { loop_range.start } else { play_pos };
+    }
+
+    pub fn get_position(&self) -> i32 {
+        self.play_pos
+    }
+
     pub fn set_position(&mut self, position: i32) {
         self.play_pos = position;
     }
@@ -309,7 +325,7 @@ impl OrgPlaybackEngine {
                 self.update_play_state()
             }
 
-            for buf in &mut self.track_buffers {
+            for (idx, buf) in self.track_buffers.iter_mut().enumerate() {
                 if buf.playing {
                     let is_16bit = buf.sample.format.bit_depth == 16;
                     let is_stereo = buf.sample.format.channels == 2;
@@ -344,7 +360,9 @@ impl OrgPlaybackEngine {
                     // index into sound samples
                     let advance = buf.frequency as f64 / freq;
 
-                    let vol = buf.vol_cent;
+                    // melodic buffers are laid out as octave * 8 + track + swap, drums start at 128
+                    let track = if idx < 128 { idx % 8 } else { idx - 120 };
+                    let vol = if self.muted_tracks & (1 << track) != 0 { 0.0 } else { buf.vol_cent };
                     let (pan_l, pan_r) = buf.pan_cent;
 
                     if self.interpolation == InterpolationMode::Polyphase {
@@ -1,6 +1,6 @@
 use std::io;
 
-use byteorder::{LE, ReadBytesExt};
+use byteorder::{LE, ReadBytesExt, WriteBytesExt};
 
 use crate::framework::error::{GameError, GameResult};
 
@@ -78,6 +78,7 @@ pub struct Note {
 pub struct Song {
     pub version: Version,
     pub time: Timing,
+    pub display: Display,
     pub tracks: [Track; 16],
 }
 
@@ -86,6 +87,7 @@ impl Clone for Song {
         Song {
             version: self.version,
             time: self.time,
+            display: self.display,
             tracks: self.tracks.clone(),
         }
     }
@@ -96,6 +98,7 @@ impl Song {
         Song {
             version: Version::Main,
             time: Timing { wait: 8, loop_range: LoopRange { start: 0, end: 1 } },
+            display: Display { beats: 4, steps: 4 },
             tracks: [
                 Track { inst: Instrument { freq: 1000, inst: 0, pipi: 0, notes: 0 }, notes: vec![] },
                 Track { inst: Instrument { freq: 1000, inst: 0, pipi: 0, notes: 0 }, notes: vec![] },
@@ -131,8 +134,8 @@ impl Song {
             };
 
         let wait = f.read_u16::<LE>()?;
-        let _bpm = f.read_u8()?;
-        let _spb = f.read_u8()?;
+        let beats = f.read_u8()?;
+        let steps = f.read_u8()?;
         let start = f.read_i32::<LE>()?;
         let end = f.read_i32::<LE>()?;
 
@@ -220,9 +223,88 @@ impl Song {
                     end,
                 },
             },
+            display: Display {
+                beats,
+                steps,
+            },
             tracks,
         };
 
         Ok(song)
     }
+
+    /// Writes the song in the format read by [Song::load_from], notes are written sorted by position.
+    pub fn write_to<W: io::Write>(&self, mut f: W) -> GameResult {
+        f.write_all(b"Org-0")?;
+        f.write_u8(self.version as u8)?;
+
+        f.write_u16::<LE>(self.time.wait)?;
+        f.write_u8(self.display.beats)?;
+        f.write_u8(self.display.steps)?;
+        f.write_i32::<LE>(self.time.loop_range.start)?;
+        f.write_i32::<LE>(self.time.loop_range.end)?;
+
+        for track in &self.tracks {
+            let notes = u16::try_from(track.notes.len())
+                .map_err(|_| GameError::InvalidValue("Too many notes in a single track".to_string()))?;
+
+            f.write_u16::<LE>(track.inst.freq)?;
+            f.write_u8(track.inst.inst)?;
+            f.write_u8(track.inst.pipi)?;
+            f.write_u16::<LE>(notes)?;
+        }
+
+        for track in &self.tracks {
+            let mut notes = track.notes.clone();
+            notes.sort_by_key(|note| note.pos);
+
+            for note in &notes {
+                f.write_i32::<LE>(note.pos)?;
+            }
+
+            for note in &notes {
+                f.write_u8(note.key)?;
+            }
+
+            for note in &notes {
+                f.write_u8(note.len)?;
+            }
+
+            for note in &notes {
+                f.write_u8(note.vol)?;
+            }
+
+            for note in &notes {
+                f.write_u8(note.pan)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_org_roundtrip() {
+    let mut song = Song::empty();
+    song.time = Timing { wait: 120, loop_range: LoopRange { start: 16, end: 128 } };
+    song.display = Display { beats: 3, steps: 8 };
+    song.tracks[1].inst = Instrument { freq: 1010, inst: 42, pipi: 1, notes: 2 };
+    song.tracks[1].notes = vec![
+        Note { pos: 24, key: 255, len: 1, vol: 80, pan: 255 },
+        Note { pos: 16, key: 40, len: 4, vol: 200, pan: 6 },
+    ];
+
+    let mut data = Vec::new();
+    song.write_to(&mut data).unwrap();
+    let read = Song::load_from(data.as_slice()).unwrap();
+
+    assert_eq!(read.version, Version::Main);
+    assert_eq!((read.time.wait, read.time.loop_range.start, read.time.loop_range.end), (120, 16, 128));
+    assert_eq!((read.display.beats, read.display.steps), (3, 8));
+    assert_eq!((read.tracks[1].inst.inst, read.tracks[1].inst.pipi, read.tracks[1].inst.notes), (42, 1, 2));
+
+    let notes: Vec<(i32, u8, u8, u8, u8)> =
+        read.tracks[1].notes.iter().map(|n| (n.pos, n.key, n.len, n.vol, n.pan)).collect();
+    assert_eq!(notes, vec![(16, 40, 4, 200, 6), (24, 255, 1, 80, 255)]);
+    assert!(read.tracks[0].notes.is_empty());
 }
@@ -1,11 +1,18 @@
 use std::collections::HashMap;
+use std::io;
+use std::io::BufRead;
+use std::str::FromStr;
 
 use lazy_static::lazy_static;
 use vec_mut_scan::VecMutScan;
 
+use crate::framework::error::{GameError, GameResult};
 use crate::sound::pixtone_sfx::DEFAULT_PIXTONE_TABLE;
 use crate::sound::stuff::cubic_interp;
 
+/// Sample rate PixTone sounds are synthesized at.
+pub const PIXTONE_SAMPLE_RATE: u32 = 22050;
+
 lazy_static! {
     static ref WAVEFORMS: [[i8; 0x100]; 6] = {
         let mut sine = [0i8; 0x100];
@@ -129,6 +136,93 @@ impl PixToneParameters {
         }
     }
 
+    /// Parameters of a built-in sound effect, empty if there's none with given id.
+    pub fn default_for(id: u8) -> PixToneParameters {
+        DEFAULT_PIXTONE_TABLE.get(id as usize).copied().unwrap_or_else(PixToneParameters::empty)
+    }
+
+    /// Reads parameters from the `.pxt` text format of PixTone. Only the values matter, they're expected in the order
+    /// [PixToneParameters::write_pxt] writes them in.
+    pub fn read_pxt<R: BufRead>(reader: R) -> GameResult<PixToneParameters> {
+        let mut lines = reader.lines();
+        let mut params = PixToneParameters::empty();
+
+        let mut next = || -> GameResult<String> {
+            while let Some(Ok(line)) = lines.next() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                return match line.split_once(':') {
+                    Some((_, value)) => Ok(value.trim().to_owned()),
+                    None => Err(GameError::ParseError(format!("expected a key and value, found: {}", line))),
+                };
+            }
+
+            Err(GameError::ParseError("unexpected end.".to_string()))
+        };
+
+        fn parse<T: FromStr>(value: GameResult<String>) -> GameResult<T> {
+            value?
+                .parse::<T>()
+                .map_err(|_| GameError::ParseError("failed to parse the value as specified type.".to_string()))
+        }
+
+        for channel in &mut params.channels {
+            channel.enabled = parse::<u8>(next())? != 0;
+            channel.length = parse(next())?;
+
+            for waveform in [&mut channel.carrier, &mut channel.frequency, &mut channel.amplitude] {
+                waveform.waveform_type = parse(next())?;
+                waveform.pitch = parse(next())?;
+                waveform.level = parse(next())?;
+                waveform.offset = parse(next())?;
+            }
+
+            let envelope = &mut channel.envelope;
+            envelope.initial = parse(next())?;
+            envelope.time_a = parse(next())?;
+            envelope.value_a = parse(next())?;
+            envelope.time_b = parse(next())?;
+            envelope.value_b = parse(next())?;
+            envelope.time_c = parse(next())?;
+            envelope.value_c = parse(next())?;
+        }
+
+        Ok(params)
+    }
+
+    /// Writes parameters in the `.pxt` text format of PixTone.
+    pub fn write_pxt<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for channel in &self.channels {
+            writeln!(writer, "use  :{}", channel.enabled as u8)?;
+            writeln!(writer, "size :{}", channel.length)?;
+
+            for (name, waveform) in
+                [("main", &channel.carrier), ("pitch", &channel.frequency), ("volume", &channel.amplitude)]
+            {
+                let key = |field: &str| format!("{}_{}", name, field);
+                writeln!(writer, "{:13}:{}", key("model"), waveform.waveform_type)?;
+                writeln!(writer, "{:13}:{}", key("freq"), waveform.pitch)?;
+                writeln!(writer, "{:13}:{}", key("top"), waveform.level)?;
+                writeln!(writer, "{:13}:{}", key("offset"), waveform.offset)?;
+            }
+
+            let envelope = &channel.envelope;
+            writeln!(writer, "initialY:{}", envelope.initial)?;
+            writeln!(writer, "ax      :{}", envelope.time_a)?;
+            writeln!(writer, "ay      :{}", envelope.value_a)?;
+            writeln!(writer, "bx      :{}", envelope.time_b)?;
+            writeln!(writer, "by      :{}", envelope.value_b)?;
+            writeln!(writer, "cx      :{}", envelope.time_c)?;
+            writeln!(writer, "cy      :{}", envelope.value_c)?;
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+
     pub fn synth(&self) -> Vec<i16> {
         let length = self.channels.iter().map(|c| c.length as usize).max().unwrap_or(0);
         if length == 0 {
@@ -183,6 +277,8 @@ pub struct PlaybackState {
     pos: f32,
     tag: u32,
     freq: f32,
+    /// Volume of the left and right channel.
+    gain: [f32; 2],
 }
 
 pub struct PixTonePlayback {
@@ -219,15 +315,21 @@ impl PixTonePlayback {
     }
 
     pub fn play_sfx(&mut self, id: u8) {
+        self.play_sfx_panned(id, [1.0, 1.0]);
+    }
+
+    /// Plays a sound with given left and right channel volume, restarting it if it's already playing.
+    pub fn play_sfx_panned(&mut self, id: u8, gain: [f32; 2]) {
         for state in &mut self.playback_state {
             if state.id == id && state.tag == 0 {
                 state.pos = 0.0;
                 state.looping = false;
+                state.gain = gain;
                 return;
             }
         }
 
-        self.playback_state.push(PlaybackState { id, pos: 0.0, tag: 0, looping: false, freq: 1.0 });
+        self.playback_state.push(PlaybackState { id, pos: 0.0, tag: 0, looping: false, freq: 1.0, gain });
     }
 
     pub fn loop_sfx(&mut self, id: u8) {
@@ -238,7 +340,7 @@ impl PixTonePlayback {
             }
         }
 
-        self.playback_state.push(PlaybackState { id, pos: 0.0, tag: 0, looping: true, freq: 1.0 });
+        self.playback_state.push(PlaybackState { id, pos: 0.0, tag: 0, looping: true, freq: 1.0, gain: [1.0, 1.0] });
     }
 
     pub fn loop_sfx_freq(&mut self, id: u8, freq: f32) {
@@ -249,7 +351,7 @@ impl PixTonePlayback {
             }
         }
 
-        self.playback_state.push(PlaybackState { id, pos: 0.0, tag: 0, looping: true, freq });
+        self.playback_state.push(PlaybackState { id, pos: 0.0, tag: 0, looping: true, freq, gain: [1.0, 1.0] });
     }
 
     pub fn stop_sfx(&mut self, id: u8) {
@@ -259,12 +361,13 @@ impl PixTonePlayback {
     }
 
     pub fn play_concurrent(&mut self, id: u8, tag: u32) {
-        self.playback_state.push(PlaybackState { id, pos: 0.0, tag, looping: false, freq: 1.0 });
+        self.playback_state.push(PlaybackState { id, pos: 0.0, tag, looping: false, freq: 1.0, gain: [1.0, 1.0] });
     }
 
+    /// Mixes the playing sounds into an interleaved stereo buffer.
     pub fn mix(&mut self, dst: &mut [u16], sample_rate: f32) {
         let mut scan = VecMutScan::new(&mut self.playback_state);
-        let delta = 22050.0 / sample_rate;
+        let delta = PIXTONE_SAMPLE_RATE as f32 / sample_rate;
 
         while let Some(item) = scan.next() {
             let mut state = *item;
@@ -276,7 +379,7 @@ impl PixTonePlayback {
                     continue;
                 };
 
-                for result in dst.iter_mut() {
+                for frame in dst.chunks_exact_mut(2) {
                     if state.pos >= sample.len() as f32 {
                         if state.looping {
                             state.pos = 0.0;
@@ -294,8 +397,10 @@ impl PixTonePlayback {
 
                     let s = cubic_interp(s1, s2, s4, s3, state.pos.fract()) * 32768.0;
                     // let s = sample[pos] as f32;
-                    let sam = (*result ^ 0x8000) as i16;
-                    *result = sam.saturating_add(s as i16) as u16 ^ 0x8000;
+                    for (result, gain) in frame.iter_mut().zip(state.gain.iter()) {
+                        let sam = (*result ^ 0x8000) as i16;
+                        *result = sam.saturating_add((s * gain) as i16) as u16 ^ 0x8000;
+                    }
 
                     state.pos += delta * state.freq;
                 }
@@ -309,3 +414,18 @@ impl PixTonePlayback {
         }
     }
 }
+
+#[test]
+fn test_pxt_roundtrip() {
+    for id in 0..DEFAULT_PIXTONE_TABLE.len() as u8 {
+        let params = PixToneParameters::default_for(id);
+
+        let mut pxt = Vec::new();
+        params.write_pxt(&mut pxt).unwrap();
+        let read = PixToneParameters::read_pxt(pxt.as_slice()).unwrap();
+
+        assert_eq!(read.synth(), params.synth(), "fx{:02x}", id);
+    }
+
+    assert!(PixToneParameters::read_pxt("use  :1\nsize :100\n".as_bytes()).is_err());
+}
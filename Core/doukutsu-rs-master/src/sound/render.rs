@@ -0,0 +1,295 @@
+use std::io;
+
+#[cfg(feature = "ogg-playback")]
+use lewton::inside_ogg::OggStreamReader;
+
+use crate::engine_constants::EngineConstants;
+use crate::framework::context::Context;
+use crate::framework::error::{GameError, GameResult};
+use crate::framework::filesystem;
+#[cfg(feature = "ogg-playback")]
+use crate::framework::filesystem::File;
+use crate::sound::backend::{song_paths, InterpolationMode, SongFormat};
+use crate::sound::org_playback::OrgPlaybackEngine;
+use crate::sound::organya::Song as Organya;
+use crate::sound::stream_playback::{open_song, SongStream};
+use crate::sound::stuff::resample_pcm;
+use crate::sound::wav::{WavFormat, WavSample};
+use crate::sound::wave_bank::SoundBank;
+
+/// Upper bound of the rendered length in seconds, songs with broken loop points would never end otherwise.
+const MAX_RENDER_SECONDS: usize = 30 * 60;
+
+#[derive(Copy, Clone)]
+pub struct RenderOptions {
+    pub sample_rate: u32,
+    pub interpolation: InterpolationMode,
+    /// How many times the looping part is repeated after being played once.
+    pub loops: usize,
+    /// Length of the fade-out following the last loop, in seconds.
+    pub fade_out: f32,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions { sample_rate: 44100, interpolation: InterpolationMode::Linear, loops: 1, fade_out: 0.0 }
+    }
+}
+
+impl RenderOptions {
+    fn output_format(&self) -> WavFormat {
+        WavFormat { channels: 2, sample_rate: self.sample_rate, bit_depth: 16 }
+    }
+
+    fn fade_frames(&self) -> usize {
+        (self.fade_out.max(0.0) * self.sample_rate as f32) as usize
+    }
+
+    fn max_frames(&self) -> usize {
+        self.sample_rate as usize * MAX_RENDER_SECONDS
+    }
+}
+
+pub(crate) fn load_wave_bank(ctx: &mut Context) -> GameResult<SoundBank> {
+    Ok(SoundBank::load_from(filesystem::open(ctx, "/builtin/organya-wavetable-doukutsu.bin")?)?)
+}
+
+/// Renders an Organya song to 16-bit stereo PCM, the output is deterministic for given song and options.
+pub(crate) fn render_organya(song: Organya, bank: &SoundBank, options: &RenderOptions) -> WavSample {
+    let mut engine = OrgPlaybackEngine::new();
+    engine.set_sample_rate(options.sample_rate as usize);
+    engine.interpolation = options.interpolation;
+    engine.loops = options.loops;
+    engine.start_song(song, bank);
+
+    let frames = (engine.get_total_samples() as usize).min(options.max_frames());
+    let fade_frames = options.fade_frames();
+
+    // the length is known upfront, so the engine never has to stop by itself
+    engine.loops = usize::MAX;
+
+    let mut samples = Vec::with_capacity((frames + fade_frames) * 2);
+    let mut buf = vec![0x8000u16; options.sample_rate as usize / 100 * 2];
+    while samples.len() < (frames + fade_frames) * 2 {
+        let len = buf.len().min((frames + fade_frames) * 2 - samples.len());
+        buf[..len].fill(0x8000);
+        engine.render_to(&mut buf[..len]);
+        samples.extend(buf[..len].iter().map(|&s| (s ^ 0x8000) as i16));
+    }
+
+    apply_fade(&mut samples[frames * 2..]);
+
+    to_wav(&samples, options)
+}
+
+/// Loads and renders an Organya song from given stream.
+pub(crate) fn render_organya_from<R: io::Read>(
+    data: R,
+    bank: &SoundBank,
+    options: &RenderOptions,
+) -> GameResult<WavSample> {
+    Ok(render_organya(Organya::load_from(data)?, bank, options))
+}
+
+/// Renders an Ogg song, the intro is played once and is followed by the loop part.
+#[cfg(feature = "ogg-playback")]
+pub(crate) fn render_ogg(
+    intro: Option<OggStreamReader<File>>,
+    loop_part: OggStreamReader<File>,
+    options: &RenderOptions,
+) -> GameResult<WavSample> {
+    let mut samples = match intro {
+        Some(intro) => decode_ogg(intro, options)?,
+        None => Vec::new(),
+    };
+    let loop_samples = decode_ogg(loop_part, options)?;
+
+    for _ in 0..=options.loops {
+        if samples.len() / 2 >= options.max_frames() {
+            break;
+        }
+
+        samples.extend_from_slice(&loop_samples);
+    }
+
+    if !loop_samples.is_empty() {
+        let start = samples.len();
+        samples.extend(loop_samples.iter().cycle().take(options.fade_frames() * 2));
+        apply_fade(&mut samples[start..]);
+    }
+
+    Ok(to_wav(&samples, options))
+}
+
+#[cfg(feature = "ogg-playback")]
+fn decode_ogg(mut reader: OggStreamReader<File>, options: &RenderOptions) -> GameResult<Vec<i16>> {
+    let sample_rate = reader.ident_hdr.audio_sample_rate;
+    let channels = reader.ident_hdr.audio_channels;
+    let mut samples = Vec::new();
+
+    loop {
+        match reader.read_dec_packet_itl() {
+            Ok(Some(buf)) => samples.append(&mut resample_pcm(buf, sample_rate, channels, options.sample_rate)),
+            Ok(None) => break,
+            Err(e) => return Err(GameError::ResourceLoadError(e.to_string())),
+        }
+    }
+
+    Ok(samples)
+}
+
+/// Renders a FLAC song or a tracker module, the intro is played once and is followed by the loop part.
+///
+/// Tracker modules have no separate intro, their first pass includes everything up to the loop point.
+pub(crate) fn render_stream(
+    intro: Option<Box<dyn SongStream>>,
+    mut loop_part: Box<dyn SongStream>,
+    options: &RenderOptions,
+) -> GameResult<WavSample> {
+    let max_frames = options.max_frames();
+    let mut samples = match intro {
+        Some(mut intro) => decode_stream(intro.as_mut(), options, max_frames)?,
+        None => Vec::new(),
+    };
+
+    for i in 0..=options.loops {
+        if samples.len() / 2 >= max_frames {
+            break;
+        }
+
+        if i > 0 {
+            loop_part.rewind()?;
+        }
+
+        let mut part = decode_stream(loop_part.as_mut(), options, max_frames - samples.len() / 2)?;
+        samples.append(&mut part);
+    }
+
+    let start = samples.len();
+    let fade_frames = options.fade_frames();
+    while samples.len() - start < fade_frames * 2 {
+        loop_part.rewind()?;
+
+        let mut part = decode_stream(loop_part.as_mut(), options, fade_frames - (samples.len() - start) / 2)?;
+        if part.is_empty() {
+            break;
+        }
+        samples.append(&mut part);
+    }
+    apply_fade(&mut samples[start..]);
+
+    Ok(to_wav(&samples, options))
+}
+
+/// Decodes a stream until its end, but no more than given number of frames.
+fn decode_stream(stream: &mut dyn SongStream, options: &RenderOptions, max_frames: usize) -> GameResult<Vec<i16>> {
+    let mut samples = Vec::new();
+
+    while samples.len() / 2 < max_frames {
+        match stream.read_packet()? {
+            Some(buf) => {
+                samples.append(&mut resample_pcm(buf, stream.sample_rate(), stream.channels(), options.sample_rate))
+            }
+            None => break,
+        }
+    }
+
+    samples.truncate(max_frames * 2);
+
+    Ok(samples)
+}
+
+/// Renders a song of the music table the same way the game would play it, looking it up in given soundtrack first.
+pub(crate) fn render_song(
+    ctx: &mut Context,
+    constants: &EngineConstants,
+    soundtrack: &str,
+    song_name: &str,
+    bank: &SoundBank,
+    options: &RenderOptions,
+) -> GameResult<WavSample> {
+    for (format, paths) in song_paths(constants, soundtrack, song_name) {
+        if !paths.iter().all(|path| filesystem::exists(ctx, path)) {
+            continue;
+        }
+
+        match format {
+            SongFormat::Organya => {
+                return render_organya_from(filesystem::open(ctx, &paths[0])?, bank, options);
+            }
+            #[cfg(feature = "ogg-playback")]
+            SongFormat::OggSinglePart => {
+                return render_ogg(None, open_ogg(ctx, &paths[0])?, options);
+            }
+            #[cfg(feature = "ogg-playback")]
+            SongFormat::OggMultiPart => {
+                return render_ogg(Some(open_ogg(ctx, &paths[0])?), open_ogg(ctx, &paths[1])?, options);
+            }
+            _ => {
+                let (intro, loop_part) = open_song(ctx, &format, &paths)?;
+                return render_stream(intro, loop_part, options);
+            }
+        }
+    }
+
+    Err(GameError::ResourceLoadError(format!("Song {} not found.", song_name)))
+}
+
+#[cfg(feature = "ogg-playback")]
+fn open_ogg(ctx: &mut Context, path: &str) -> GameResult<OggStreamReader<File>> {
+    OggStreamReader::new(filesystem::open(ctx, path)?).map_err(|e| GameError::ResourceLoadError(e.to_string()))
+}
+
+/// Linearly fades interleaved stereo samples out.
+fn apply_fade(samples: &mut [i16]) {
+    let frames = samples.len() / 2;
+
+    for (i, frame) in samples.chunks_exact_mut(2).enumerate() {
+        let gain = 1.0 - i as f32 / frames as f32;
+        frame[0] = (frame[0] as f32 * gain) as i16;
+        frame[1] = (frame[1] as f32 * gain) as i16;
+    }
+}
+
+fn to_wav(samples: &[i16], options: &RenderOptions) -> WavSample {
+    let data = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+
+    WavSample { format: options.output_format(), data }
+}
+
+pub(crate) fn write_wav<W: io::Write>(sample: &WavSample, f: W) -> GameResult {
+    Ok(sample.write_to(f)?)
+}
+
+#[test]
+fn test_render_organya() {
+    use crate::sound::organya::Note;
+
+    let bank = SoundBank::load_from(&include_bytes!("../data/builtin/organya-wavetable-doukutsu.bin")[..]).unwrap();
+
+    let mut song = Organya::empty();
+    song.time.loop_range.start = 4;
+    song.time.loop_range.end = 16;
+    song.tracks[0].notes =
+        vec![Note { pos: 0, key: 48, len: 4, vol: 200, pan: 6 }, Note { pos: 6, key: 55, len: 4, vol: 200, pan: 6 }];
+    song.tracks[8].notes = vec![Note { pos: 8, key: 30, len: 1, vol: 200, pan: 6 }];
+
+    let options = RenderOptions { sample_rate: 22050, loops: 2, fade_out: 0.1, ..RenderOptions::default() };
+    let linear = render_organya(song.clone(), &bank, &options);
+
+    // 16 ticks, then the 12 looping ticks twice and the fade-out
+    let frames_per_tick = 22050 / 1000 * 8;
+    let frames = frames_per_tick * (16 + 12 * 2) + 2205;
+    assert_eq!(linear.data.len(), frames * 4);
+    assert_eq!(linear.data, render_organya(song.clone(), &bank, &options).data);
+
+    let nearest = render_organya(song, &bank, &RenderOptions { interpolation: InterpolationMode::Nearest, ..options });
+    assert_eq!(nearest.data.len(), linear.data.len());
+    assert_ne!(nearest.data, linear.data);
+
+    let mut wav = Vec::new();
+    write_wav(&linear, &mut wav).unwrap();
+    let read = WavSample::read_from(&wav[..]).unwrap();
+    assert_eq!(read.format, linear.format);
+    assert_eq!(read.data, linear.data);
+}
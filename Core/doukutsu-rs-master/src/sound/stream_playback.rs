@@ -0,0 +1,221 @@
+use std::io::Read;
+use std::sync::{Arc, RwLock};
+
+use crate::framework::context::Context;
+use crate::framework::error::{GameError, GameResult};
+use crate::framework::filesystem;
+use crate::sound::backend::SongFormat;
+#[cfg(feature = "flac-playback")]
+use crate::sound::flac_playback::FlacStream;
+use crate::sound::stuff::resample_pcm;
+use crate::sound::tracker::{Module, TrackerPlayer};
+use crate::sound::wav::WavFormat;
+
+/// Sample rate tracker modules are mixed at before being resampled to the output rate.
+pub const TRACKER_SAMPLE_RATE: u32 = 44100;
+
+/// A source of interleaved 16-bit PCM that's decoded in chunks, used for song formats other than Organya and Ogg.
+pub trait SongStream: Send + Sync {
+    fn sample_rate(&self) -> u32;
+
+    fn channels(&self) -> u8;
+
+    /// Decodes the next chunk of samples, returns `None` once the end of the stream has been reached.
+    fn read_packet(&mut self) -> GameResult<Option<Vec<i16>>>;
+
+    /// Seeks back to the point the stream loops from, which is the beginning for most formats.
+    fn rewind(&mut self) -> GameResult;
+
+    /// Seeks back to the beginning of the stream.
+    fn restart(&mut self) -> GameResult {
+        self.rewind()
+    }
+}
+
+/// Opens a song that's played through [StreamPlaybackEngine], returns the intro part (if the format has a separate
+/// one) and the looping part.
+pub(crate) fn open_song(
+    ctx: &mut Context,
+    format: &SongFormat,
+    paths: &[String],
+) -> GameResult<(Option<Box<dyn SongStream>>, Box<dyn SongStream>)> {
+    match format {
+        #[cfg(feature = "flac-playback")]
+        SongFormat::FlacSinglePart => Ok((None, Box::new(FlacStream::new(filesystem::open(ctx, &paths[0])?)?))),
+        #[cfg(feature = "flac-playback")]
+        SongFormat::FlacMultiPart => Ok((
+            Some(Box::new(FlacStream::new(filesystem::open(ctx, &paths[0])?)?)),
+            Box::new(FlacStream::new(filesystem::open(ctx, &paths[1])?)?),
+        )),
+        SongFormat::Tracker => {
+            let mut data = Vec::new();
+            filesystem::open(ctx, &paths[0])?.read_to_end(&mut data)?;
+
+            Ok((None, Box::new(TrackerPlayer::new(Module::load(&data)?, TRACKER_SAMPLE_RATE))))
+        }
+        _ => Err(GameError::InvalidValue("Song format isn't played as a stream.".to_owned())),
+    }
+}
+
+pub(crate) struct StreamPlaybackEngine {
+    intro_music: Option<Arc<RwLock<Box<dyn SongStream>>>>,
+    loop_music: Option<Arc<RwLock<Box<dyn SongStream>>>>,
+    output_format: WavFormat,
+    playing_intro: bool,
+    /// Whether the loop stream has been rewound without producing samples since, which means it's empty.
+    loop_rewound: bool,
+    buffer: Vec<i16>,
+}
+
+pub struct SavedStreamPlaybackState {
+    intro_music: Option<Arc<RwLock<Box<dyn SongStream>>>>,
+    loop_music: Option<Arc<RwLock<Box<dyn SongStream>>>>,
+    playing_intro: bool,
+}
+
+impl StreamPlaybackEngine {
+    pub fn new() -> StreamPlaybackEngine {
+        StreamPlaybackEngine {
+            intro_music: None,
+            loop_music: None,
+            output_format: WavFormat { channels: 2, sample_rate: 44100, bit_depth: 16 },
+            playing_intro: false,
+            loop_rewound: false,
+            buffer: Vec::with_capacity(4096),
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: usize) {
+        self.output_format.sample_rate = sample_rate as u32;
+    }
+
+    pub fn get_state(&self) -> SavedStreamPlaybackState {
+        SavedStreamPlaybackState {
+            intro_music: self.intro_music.clone(),
+            loop_music: self.loop_music.clone(),
+            playing_intro: self.playing_intro,
+        }
+    }
+
+    pub fn set_state(&mut self, state: SavedStreamPlaybackState) {
+        self.intro_music = state.intro_music;
+        self.loop_music = state.loop_music;
+        self.playing_intro = state.playing_intro;
+        self.loop_rewound = false;
+        self.buffer.clear();
+    }
+
+    pub fn start(&mut self, intro_music: Option<Box<dyn SongStream>>, loop_music: Box<dyn SongStream>) {
+        self.playing_intro = intro_music.is_some();
+        self.intro_music = intro_music.map(|music| Arc::new(RwLock::new(music)));
+        self.loop_music = Some(Arc::new(RwLock::new(loop_music)));
+        self.loop_rewound = false;
+        self.buffer.clear();
+    }
+
+    pub fn rewind(&mut self) {
+        if let Some(music) = &self.intro_music {
+            let _ = music.write().unwrap().restart();
+        }
+
+        if let Some(music) = &self.loop_music {
+            let _ = music.write().unwrap().restart();
+        }
+
+        self.playing_intro = self.intro_music.is_some();
+        self.loop_rewound = false;
+        self.buffer.clear();
+    }
+
+    fn decode(&mut self) {
+        if self.playing_intro {
+            if let Some(music) = &self.intro_music {
+                let mut music = music.write().unwrap();
+
+                let buf = match music.read_packet() {
+                    Ok(Some(buf)) => buf,
+                    Ok(None) => {
+                        self.playing_intro = false;
+                        return;
+                    }
+                    Err(e) => {
+                        log::error!("Error decoding intro: {}", e);
+                        self.playing_intro = false;
+                        return;
+                    }
+                };
+
+                let mut buf = resample_pcm(buf, music.sample_rate(), music.channels(), self.output_format.sample_rate);
+                self.buffer.append(&mut buf);
+            } else {
+                self.playing_intro = false;
+            }
+        } else if let Some(music) = &self.loop_music {
+            let mut music = music.write().unwrap();
+
+            let buf = match music.read_packet() {
+                Ok(Some(buf)) if !buf.is_empty() => {
+                    self.loop_rewound = false;
+                    buf
+                }
+                Ok(Some(_)) => return,
+                Ok(None) => {
+                    // a stream that ends again right after rewinding has nothing to loop, play silence instead
+                    if !self.loop_rewound && music.rewind().is_ok() {
+                        self.loop_rewound = true;
+                        return;
+                    }
+
+                    vec![0; 1000]
+                }
+                Err(_) => vec![0; 1000],
+            };
+
+            let mut buf = resample_pcm(buf, music.sample_rate(), music.channels(), self.output_format.sample_rate);
+            self.buffer.append(&mut buf);
+        } else {
+            let mut buf = vec![0; 1000];
+            self.buffer.append(&mut buf);
+        }
+    }
+
+    pub fn render_to(&mut self, buf: &mut [u16]) -> usize {
+        while self.buffer.len() < buf.len() {
+            self.decode();
+        }
+
+        self.buffer.drain(0..buf.len()).map(|n| n as u16 ^ 0x8000).zip(buf.iter_mut()).for_each(|(n, tgt)| *tgt = n);
+
+        buf.len()
+    }
+}
+
+#[test]
+fn test_empty_loop_stream() {
+    struct EmptyStream;
+
+    impl SongStream for EmptyStream {
+        fn sample_rate(&self) -> u32 {
+            44100
+        }
+
+        fn channels(&self) -> u8 {
+            2
+        }
+
+        fn read_packet(&mut self) -> GameResult<Option<Vec<i16>>> {
+            Ok(None)
+        }
+
+        fn rewind(&mut self) -> GameResult {
+            Ok(())
+        }
+    }
+
+    let mut engine = StreamPlaybackEngine::new();
+    engine.start(None, Box::new(EmptyStream));
+
+    let mut buf = [0u16; 4096];
+    assert_eq!(engine.render_to(&mut buf), buf.len());
+    assert!(buf.iter().all(|&n| n == 0x8000));
+}
@@ -1,3 +1,5 @@
+use num_traits::clamp;
+
 pub const FRQ_TBL: [i16; 12] = [
     262, 277, 294, 311, 330, 349, 370, 392, 415, 440, 466, 494
 ];
@@ -54,3 +56,137 @@ pub fn cubic_interp(s1: f32, s2: f32, sp: f32, sn: f32, mu: f32) -> f32 {
 
     a0 * mu * mu2 + a1 * mu2 + a2 * mu + a3
 }
+
+/// Resamples interleaved PCM data to given rate, mono data is converted to stereo.
+pub fn resample_pcm(data: Vec<i16>, sample_rate: u32, channels: u8, output_rate: u32) -> Vec<i16> {
+    let mut data = resample(data, sample_rate, channels, output_rate);
+
+    if channels == 1 {
+        let mut tmp_data = Vec::with_capacity(data.len() * 2);
+
+        for s in data.iter().copied() {
+            tmp_data.push(s);
+            tmp_data.push(s);
+        }
+
+        data = tmp_data;
+    }
+
+    data
+}
+
+/// Resamples interleaved PCM data to given rate with cubic interpolation, keeping the number of channels.
+pub fn resample(mut data: Vec<i16>, sample_rate: u32, channels: u8, output_rate: u32) -> Vec<i16> {
+    // there's nothing to interpolate without a single whole sample of every channel
+    if channels == 0 || data.len() < channels as usize {
+        return Vec::new();
+    }
+
+    if sample_rate != output_rate {
+        let mut tmp_data = Vec::with_capacity(
+            (data.len() as f64 * output_rate as f64 / sample_rate as f64) as usize,
+        );
+        let mut pos = 0.0;
+        let phase = sample_rate as f32 / output_rate as f32;
+        let num_samples = data.len() / channels as usize;
+
+        if channels == 1 {
+            loop {
+                if pos >= num_samples as f32 {
+                    data = tmp_data;
+                    break;
+                }
+
+                let s = unsafe {
+                    let upos = pos as usize;
+                    let s1 = (*data.get_unchecked(upos) as f32) / 32768.0;
+                    let s2 = (*data.get_unchecked(clamp(upos + 1, 0, data.len() - 1)) as f32) / 32768.0;
+                    let s3 = (*data.get_unchecked(clamp(upos + 2, 0, data.len() - 1)) as f32) / 32768.0;
+                    let s4 =
+                        (*data.get_unchecked(clamp(upos.saturating_sub(1), 0, data.len() - 1)) as f32) / 32768.0;
+
+                    (cubic_interp(s1, s2, s4, s3, pos.fract()) * 32768.0) as i16
+                };
+                tmp_data.push(s);
+
+                pos += phase;
+            }
+        } else if channels == 2 {
+            let max_samp = (num_samples - 1) * 2;
+
+            // unrolled for performance reasons
+            loop {
+                if pos >= num_samples as f32 {
+                    data = tmp_data;
+                    break;
+                }
+
+                let sl = unsafe {
+                    let upos = pos as usize;
+                    let max = max_samp as usize;
+                    let s1 = (*data.get_unchecked(upos * 2) as f32) / 32768.0;
+                    let s2 = (*data.get_unchecked(clamp((upos + 1) * 2, 0, max)) as f32) / 32768.0;
+                    let s3 = (*data.get_unchecked(clamp((upos + 2) * 2, 0, max)) as f32) / 32768.0;
+                    let s4 = (*data.get_unchecked(clamp(upos.saturating_sub(1) * 2, 0, max)) as f32) / 32768.0;
+
+                    (cubic_interp(s1, s2, s4, s3, pos.fract()) * 32768.0) as i16
+                };
+                tmp_data.push(sl);
+
+                let sr = unsafe {
+                    let upos = pos as usize;
+                    let max = max_samp as usize + 1;
+                    let s1 = (*data.get_unchecked(upos * 2 + 1) as f32) / 32768.0;
+                    let s2 = (*data.get_unchecked(clamp((upos + 1) * 2 + 1, 1, max)) as f32) / 32768.0;
+                    let s3 = (*data.get_unchecked(clamp((upos + 2) * 2 + 1, 1, max)) as f32) / 32768.0;
+                    let s4 = (*data.get_unchecked(clamp(upos.saturating_sub(1) * 2 + 1, 0, max)) as f32) / 32768.0;
+
+                    (cubic_interp(s1, s2, s4, s3, pos.fract()) * 32768.0) as i16
+                };
+                tmp_data.push(sr);
+
+                pos += phase;
+            }
+        } else {
+            let cc = channels as usize;
+            let max_samp = (num_samples - 1) * cc;
+
+            loop {
+                if pos >= num_samples as f32 {
+                    data = tmp_data;
+                    break;
+                }
+
+                for c in 0..channels {
+                    let s = unsafe {
+                        let upos = pos as usize;
+                        let max = max_samp + c as usize;
+                        let s1 = (*data.get_unchecked(upos * cc + c as usize) as f32) / 32768.0;
+                        let s2 = (*data.get_unchecked(clamp((upos + 1) * cc + c as usize, c as usize, max)) as f32)
+                            / 32768.0;
+                        let s3 = (*data.get_unchecked(clamp((upos + 2) * cc + c as usize, c as usize, max)) as f32) / 32768.0;
+                        let s4 =
+                            (*data.get_unchecked(clamp(upos.saturating_sub(1) * cc + c as usize, c as usize, max))
+                                as f32)
+                                / 32768.0;
+
+                        (cubic_interp(s1, s2, s4, s3, pos.fract()) * 32768.0) as i16
+                    };
+                    tmp_data.push(s);
+                }
+
+                pos += phase;
+            }
+        }
+    }
+
+    data
+}
+
+#[test]
+fn test_resample_short_input() {
+    assert!(resample(Vec::new(), 22050, 2, 44100).is_empty());
+    assert!(resample(vec![1], 22050, 2, 44100).is_empty());
+    assert!(resample(vec![1, 2], 22050, 3, 44100).is_empty());
+    assert_eq!(resample(vec![1, 2], 22050, 2, 44100).len(), 4);
+}
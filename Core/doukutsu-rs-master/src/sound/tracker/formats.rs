@@ -0,0 +1,1004 @@
+use crate::framework::error::{GameError, GameResult};
+use crate::sound::tracker::{
+    Cell, Effect, Envelope, Instrument, LoopKind, Module, ModuleFormat, Note, Pattern, Sample, VolumeCommand,
+    NOTE_COUNT,
+};
+
+fn truncated() -> GameError {
+    GameError::ResourceLoadError("Module file is truncated.".to_owned())
+}
+
+fn bytes(data: &[u8], pos: usize, len: usize) -> GameResult<&[u8]> {
+    data.get(pos..pos.saturating_add(len)).ok_or_else(truncated)
+}
+
+fn u8_at(data: &[u8], pos: usize) -> GameResult<u8> {
+    data.get(pos).copied().ok_or_else(truncated)
+}
+
+fn u16_at(data: &[u8], pos: usize) -> GameResult<u16> {
+    let b = bytes(data, pos, 2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u16_be_at(data: &[u8], pos: usize) -> GameResult<u16> {
+    let b = bytes(data, pos, 2)?;
+    Ok(u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> GameResult<u32> {
+    let b = bytes(data, pos, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn text(data: &[u8]) -> String {
+    let end = data.iter().position(|&c| c == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).trim_end().to_owned()
+}
+
+/// Clamps the loop to the sample data, loops that end up empty are disabled.
+fn sample_loop(kind: LoopKind, start: usize, end: usize, len: usize) -> (LoopKind, usize, usize) {
+    let end = end.min(len);
+
+    if kind == LoopKind::None || start >= end {
+        (LoopKind::None, 0, 0)
+    } else {
+        (kind, start, end)
+    }
+}
+
+/// Reads 8 or 16-bit PCM, samples that are missing at the end of the file are left out.
+fn read_pcm(data: &[u8], pos: usize, length: usize, is_16bit: bool, signed: bool) -> Vec<f32> {
+    let size = if is_16bit { 2 } else { 1 };
+    let available = data.len().saturating_sub(pos) / size;
+    let data = &data[pos.min(data.len())..];
+
+    if is_16bit {
+        data.chunks_exact(2)
+            .take(length.min(available))
+            .map(|s| {
+                let s = u16::from_le_bytes([s[0], s[1]]);
+                let s = if signed { s as i16 } else { (s ^ 0x8000) as i16 };
+                s as f32 / 32768.0
+            })
+            .collect()
+    } else {
+        data.iter()
+            .take(length.min(available))
+            .map(|&s| {
+                let s = if signed { s as i8 } else { (s ^ 0x80) as i8 };
+                s as f32 / 128.0
+            })
+            .collect()
+    }
+}
+
+/// Maps the positions of a song without the "skip" markers used by S3M and IT, returns the orders and new indices
+/// of the original positions for position jumps.
+fn parse_orders(raw: &[u8]) -> (Vec<usize>, Vec<u8>) {
+    let mut orders = Vec::new();
+    let mut remap = Vec::new();
+
+    for &order in raw {
+        if order == 255 {
+            break;
+        }
+
+        remap.push(orders.len().min(255) as u8);
+        if order != 254 {
+            orders.push(order as usize);
+        }
+    }
+
+    (orders, remap)
+}
+
+fn remap_position_jumps(patterns: &mut [Pattern], remap: &[u8]) {
+    for cell in patterns.iter_mut().flat_map(|p| p.cells.iter_mut()) {
+        if let Effect::PositionJump(pos) = cell.effect {
+            cell.effect = Effect::PositionJump(remap.get(pos as usize).copied().unwrap_or(pos));
+        }
+    }
+}
+
+/// Converts effects of ProTracker and FastTracker 2.
+fn pt_effect(command: u8, param: u8) -> Effect {
+    let (x, y) = (param >> 4, param & 0x0f);
+
+    match command {
+        0x0 if param != 0 => Effect::Arpeggio(param),
+        0x1 => Effect::PortaUp(param),
+        0x2 => Effect::PortaDown(param),
+        0x3 => Effect::TonePorta(param),
+        0x4 => Effect::Vibrato(param),
+        0x5 => Effect::TonePortaVolSlide(param),
+        0x6 => Effect::VibratoVolSlide(param),
+        0x7 => Effect::Tremolo(param),
+        0x8 => Effect::SetPanning(param),
+        0x9 => Effect::SampleOffset(param),
+        0xa => Effect::VolSlide(param),
+        0xb => Effect::PositionJump(param),
+        0xc => Effect::SetVolume(param.min(64)),
+        0xd => Effect::PatternBreak(x * 10 + y),
+        0xe => match x {
+            0x1 => Effect::FinePortaUp(y),
+            0x2 => Effect::FinePortaDown(y),
+            0x6 => Effect::PatternLoop(y),
+            0x8 => Effect::SetPanning(y * 17),
+            0x9 => Effect::Retrigger(y),
+            0xa => Effect::FineVolSlideUp(y),
+            0xb => Effect::FineVolSlideDown(y),
+            0xc => Effect::NoteCut(y),
+            0xd => Effect::NoteDelay(y),
+            0xe => Effect::PatternDelay(y),
+            _ => Effect::None,
+        },
+        0xf if param == 0 => Effect::None,
+        0xf if param < 0x20 => Effect::SetSpeed(param),
+        0xf => Effect::SetTempo(param),
+        // the following ones are FastTracker 2 only
+        0x10 => Effect::SetGlobalVolume(param.min(64)),
+        0x11 => Effect::GlobalVolSlide(param),
+        0x14 => Effect::KeyOff(param),
+        0x1b => Effect::RetriggerVolume(param),
+        0x21 => match x {
+            0x1 => Effect::ExtraFinePortaUp(y),
+            0x2 => Effect::ExtraFinePortaDown(y),
+            _ => Effect::None,
+        },
+        _ => Effect::None,
+    }
+}
+
+/// Converts effects of Scream Tracker 3 and Impulse Tracker, which mostly share their meaning.
+fn st3_effect(command: u8, param: u8, it: bool) -> Effect {
+    let (x, y) = (param >> 4, param & 0x0f);
+
+    match command {
+        1 if param != 0 => Effect::SetSpeed(param),
+        2 => Effect::PositionJump(param),
+        3 if it => Effect::PatternBreak(param),
+        3 => Effect::PatternBreak(x * 10 + y),
+        4 => Effect::VolSlide(param),
+        5 => Effect::PortaDown(param),
+        6 => Effect::PortaUp(param),
+        7 => Effect::TonePorta(param),
+        8 => Effect::Vibrato(param),
+        10 => Effect::Arpeggio(param),
+        11 => Effect::VibratoVolSlide(param),
+        12 => Effect::TonePortaVolSlide(param),
+        15 => Effect::SampleOffset(param),
+        17 => Effect::RetriggerVolume(param),
+        18 => Effect::Tremolo(param),
+        19 => match x {
+            0x8 => Effect::SetPanning(y * 17),
+            0xb => Effect::PatternLoop(y),
+            0xc => Effect::NoteCut(y),
+            0xd => Effect::NoteDelay(y),
+            0xe => Effect::PatternDelay(y),
+            _ => Effect::None,
+        },
+        20 if param >= 0x20 => Effect::SetTempo(param),
+        22 if it => Effect::SetGlobalVolume((param / 2).min(64)),
+        22 => Effect::SetGlobalVolume(param.min(64)),
+        24 if it => Effect::SetPanning(param),
+        24 if param <= 0x80 => Effect::SetPanning((param as u16 * 2).min(255) as u8),
+        _ => Effect::None,
+    }
+}
+
+pub fn load_mod(data: &[u8]) -> GameResult<Module> {
+    let tag = bytes(data, 1080, 4)?;
+    let channels = match tag {
+        b"M.K." | b"M!K!" | b"M&K!" | b"FLT4" | b"4CHN" => 4,
+        b"FLT8" | b"OCTA" | b"CD81" => 8,
+        [c, b'C', b'H', b'N'] if c.is_ascii_digit() => (c - b'0') as usize,
+        [c1, c2, b'C', b'H'] if c1.is_ascii_digit() && c2.is_ascii_digit() => {
+            (c1 - b'0') as usize * 10 + (c2 - b'0') as usize
+        }
+        _ => return Err(GameError::ResourceLoadError("Unsupported module format.".to_owned())),
+    };
+
+    let mut headers = Vec::new();
+    for i in 0..31 {
+        let pos = 20 + i * 30;
+        let length = u16_be_at(data, pos + 22)? as usize * 2;
+        let finetune = (u8_at(data, pos + 24)? & 0x0f) as i32;
+        let finetune = if finetune > 7 { finetune - 16 } else { finetune };
+        let volume = u8_at(data, pos + 25)?.min(64);
+        let loop_start = u16_be_at(data, pos + 26)? as usize * 2;
+        let loop_length = u16_be_at(data, pos + 28)? as usize * 2;
+
+        headers.push((length, finetune, volume, loop_start, loop_length));
+    }
+
+    let song_length = (u8_at(data, 950)? as usize).clamp(1, 128);
+    let restart = u8_at(data, 951)? as usize;
+    let raw_orders = bytes(data, 952, 128)?;
+    let orders: Vec<usize> = raw_orders[..song_length].iter().map(|&o| o as usize).collect();
+    let pattern_count = raw_orders.iter().map(|&o| o as usize + 1).max().unwrap_or(1);
+
+    let mut pos = 1084;
+    let mut patterns = Vec::with_capacity(pattern_count);
+    for _ in 0..pattern_count {
+        let raw = bytes(data, pos, 64 * channels * 4)?;
+        pos += raw.len();
+
+        let cells = raw
+            .chunks_exact(4)
+            .map(|c| {
+                let period = ((c[0] as u16 & 0x0f) << 8) | c[1] as u16;
+                let note = if period == 0 {
+                    Note::None
+                } else {
+                    // period 428 is the pitch of C-4 with no finetune
+                    let note = 48.0 + 12.0 * (428.0 / period as f64).log2();
+                    Note::On(note.round().clamp(0.0, NOTE_COUNT as f64 - 1.0) as u8)
+                };
+
+                Cell {
+                    note,
+                    instrument: (c[0] & 0xf0) | (c[2] >> 4),
+                    volume: VolumeCommand::None,
+                    effect: pt_effect(c[2] & 0x0f, c[3]),
+                }
+            })
+            .collect();
+
+        patterns.push(Pattern { rows: 64, cells });
+    }
+
+    let mut samples = Vec::with_capacity(headers.len());
+    for (length, finetune, volume, loop_start, loop_length) in headers {
+        let data = read_pcm(data, pos, length, false, true);
+        pos += length;
+
+        let kind = if loop_length > 2 { LoopKind::Forward } else { LoopKind::None };
+        let (loop_kind, loop_start, loop_end) = sample_loop(kind, loop_start, loop_start + loop_length, data.len());
+
+        samples.push(Sample {
+            data,
+            loop_start,
+            loop_end,
+            loop_kind,
+            volume,
+            panning: None,
+            tuning: finetune * 16,
+            global_volume: 1.0,
+        });
+    }
+
+    // Amiga channels are hard panned in LRRL order
+    let channel_panning = (0..channels).map(|ch| if ch % 4 == 0 || ch % 4 == 3 { 0x40 } else { 0xc0 }).collect();
+
+    Ok(Module {
+        format: ModuleFormat::Mod,
+        name: text(bytes(data, 0, 20)?),
+        channels,
+        orders,
+        restart: if restart < song_length { restart } else { 0 },
+        patterns,
+        instruments: (0..samples.len()).map(Instrument::from_sample).collect(),
+        samples,
+        initial_speed: 6,
+        initial_tempo: 125,
+        initial_global_volume: 1.0,
+        channel_panning,
+        linear_periods: false,
+    })
+}
+
+fn xm_volume(volume: u8) -> VolumeCommand {
+    let (x, y) = (volume >> 4, volume & 0x0f);
+
+    match x {
+        0x1..=0x4 => VolumeCommand::Set(volume - 0x10),
+        0x5 if volume == 0x50 => VolumeCommand::Set(64),
+        0x6 => VolumeCommand::SlideDown(y),
+        0x7 => VolumeCommand::SlideUp(y),
+        0x8 => VolumeCommand::FineDown(y),
+        0x9 => VolumeCommand::FineUp(y),
+        0xc => VolumeCommand::Panning(y * 17),
+        0xf => VolumeCommand::TonePorta(y << 4),
+        _ => VolumeCommand::None,
+    }
+}
+
+pub fn load_xm(data: &[u8]) -> GameResult<Module> {
+    let header_size = u32_at(data, 60)? as usize;
+    let song_length = (u16_at(data, 64)? as usize).min(256);
+    let restart = u16_at(data, 66)? as usize;
+    let channels = (u16_at(data, 68)? as usize).min(64);
+    let pattern_count = u16_at(data, 70)? as usize;
+    let instrument_count = u16_at(data, 72)? as usize;
+    let flags = u16_at(data, 74)?;
+    let speed = u16_at(data, 76)?;
+    let tempo = u16_at(data, 78)?;
+    let orders = bytes(data, 80, song_length)?.iter().map(|&o| o as usize).collect();
+
+    let mut pos = 60 + header_size;
+    let mut patterns = Vec::with_capacity(pattern_count);
+    for _ in 0..pattern_count {
+        let pattern_header = u32_at(data, pos)? as usize;
+        let rows = (u16_at(data, pos + 5)? as usize).max(1);
+        let packed_size = u16_at(data, pos + 7)? as usize;
+        pos += pattern_header;
+
+        let packed = bytes(data, pos, packed_size)?;
+        pos += packed_size;
+
+        let mut pattern = Pattern::empty(rows, channels);
+        let mut i = 0;
+        let mut next = || {
+            let b = packed.get(i).copied().unwrap_or(0);
+            i += 1;
+            b
+        };
+
+        if packed_size > 0 {
+            for cell in pattern.cells.iter_mut() {
+                let first = next();
+                let (note, instrument, volume, command, param) = if first & 0x80 != 0 {
+                    let mut field = |bit: u8| if first & bit != 0 { next() } else { 0 };
+                    (field(1), field(2), field(4), field(8), field(16))
+                } else {
+                    (first, next(), next(), next(), next())
+                };
+
+                cell.note = match note {
+                    1..=96 => Note::On(note - 1),
+                    97 => Note::Off,
+                    _ => Note::None,
+                };
+                cell.instrument = instrument;
+                cell.volume = xm_volume(volume);
+                cell.effect = pt_effect(command, param);
+            }
+        }
+
+        patterns.push(pattern);
+    }
+
+    let mut instruments = Vec::with_capacity(instrument_count);
+    let mut samples = Vec::new();
+    for _ in 0..instrument_count {
+        let start = pos;
+        let instrument_size = u32_at(data, pos)? as usize;
+        let sample_count = u16_at(data, pos + 27)? as usize;
+
+        if sample_count == 0 {
+            instruments.push(Instrument { note_map: vec![(0, None); NOTE_COUNT], ..Instrument::from_sample(0) });
+            pos = start + instrument_size;
+            continue;
+        }
+
+        let sample_header_size = u32_at(data, pos + 29)? as usize;
+        let sample_map = bytes(data, pos + 33, 96)?;
+        let envelope_points = bytes(data, pos + 129, 48)?;
+        let point_count = (u8_at(data, pos + 225)? as usize).min(12);
+        let sustain = u8_at(data, pos + 227)? as usize;
+        let loop_start = u8_at(data, pos + 228)? as usize;
+        let loop_end = u8_at(data, pos + 229)? as usize;
+        let envelope_flags = u8_at(data, pos + 233)?;
+        let fadeout = u16_at(data, pos + 239)?;
+        pos = start + instrument_size;
+
+        let volume_envelope = if envelope_flags & 1 != 0 && point_count > 0 {
+            Some(Envelope {
+                points: envelope_points
+                    .chunks_exact(4)
+                    .take(point_count)
+                    .map(|p| (u16::from_le_bytes([p[0], p[1]]), u16::from_le_bytes([p[2], p[3]]).min(64) as u8))
+                    .collect(),
+                sustain: if envelope_flags & 2 != 0 { Some((sustain, sustain)) } else { None },
+                loop_range: if envelope_flags & 4 != 0 { Some((loop_start, loop_end)) } else { None },
+            })
+        } else {
+            None
+        };
+
+        let first_sample = samples.len();
+        let mut headers = Vec::with_capacity(sample_count);
+        for _ in 0..sample_count {
+            headers.push(bytes(data, pos, 18)?);
+            pos += sample_header_size;
+        }
+
+        for header in headers {
+            let length = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+            let loop_start = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+            let loop_length = u32::from_le_bytes([header[8], header[9], header[10], header[11]]) as usize;
+            let volume = header[12].min(64);
+            let finetune = header[13] as i8 as i32;
+            let sample_type = header[14];
+            let panning = header[15];
+            let relative_note = header[16] as i8 as i32;
+            let is_16bit = sample_type & 0x10 != 0;
+            let size = if is_16bit { 2 } else { 1 };
+
+            // samples are stored as deltas between consecutive values
+            let mut pcm = read_pcm(data, pos, length / size, is_16bit, true);
+            pos += length;
+
+            let mut acc = 0i32;
+            for s in pcm.iter_mut() {
+                if is_16bit {
+                    acc = (acc + (*s * 32768.0) as i32) as i16 as i32;
+                    *s = acc as f32 / 32768.0;
+                } else {
+                    acc = (acc + (*s * 128.0) as i32) as i8 as i32;
+                    *s = acc as f32 / 128.0;
+                }
+            }
+
+            let kind = match sample_type & 3 {
+                1 => LoopKind::Forward,
+                2 => LoopKind::PingPong,
+                _ => LoopKind::None,
+            };
+            let (loop_kind, loop_start, loop_end) =
+                sample_loop(kind, loop_start / size, (loop_start + loop_length) / size, pcm.len());
+
+            samples.push(Sample {
+                data: pcm,
+                loop_start,
+                loop_end,
+                loop_kind,
+                volume,
+                panning: Some(panning),
+                tuning: relative_note * 128 + finetune,
+                global_volume: 1.0,
+            });
+        }
+
+        let note_map = (0..NOTE_COUNT)
+            .map(|note| {
+                let sample = sample_map[note.min(95)] as usize;
+                (note as u8, if sample < sample_count { Some(first_sample + sample) } else { None })
+            })
+            .collect();
+
+        instruments.push(Instrument {
+            note_map,
+            volume_envelope,
+            fadeout: fadeout as f32 / 32768.0,
+            panning: None,
+            global_volume: 1.0,
+        });
+    }
+
+    Ok(Module {
+        format: ModuleFormat::Xm,
+        name: text(bytes(data, 17, 20)?),
+        channels,
+        orders,
+        restart,
+        patterns,
+        instruments,
+        samples,
+        initial_speed: speed.clamp(1, 255) as u8,
+        initial_tempo: tempo.clamp(32, 255) as u8,
+        initial_global_volume: 1.0,
+        channel_panning: vec![128; channels],
+        linear_periods: flags & 1 != 0,
+    })
+}
+
+pub fn load_s3m(data: &[u8]) -> GameResult<Module> {
+    let order_count = u16_at(data, 0x20)? as usize;
+    let instrument_count = u16_at(data, 0x22)? as usize;
+    let pattern_count = u16_at(data, 0x24)? as usize;
+    let signed_samples = u16_at(data, 0x2a)? == 1;
+    let global_volume = u8_at(data, 0x30)?.min(64);
+    let speed = u8_at(data, 0x31)?;
+    let tempo = u8_at(data, 0x32)?;
+    let stereo = u8_at(data, 0x33)? & 0x80 != 0;
+    let default_panning = u8_at(data, 0x35)? == 252;
+    let channel_settings = bytes(data, 0x40, 32)?;
+
+    // only enabled channels are kept
+    let mut channel_map = [None; 32];
+    let mut channel_panning = Vec::new();
+    for (i, &setting) in channel_settings.iter().enumerate() {
+        if setting < 16 {
+            channel_map[i] = Some(channel_panning.len());
+            channel_panning.push(if !stereo {
+                128
+            } else if setting < 8 {
+                0x33
+            } else {
+                0xcc
+            });
+        }
+    }
+    let channels = channel_panning.len();
+
+    let (orders, remap) = parse_orders(bytes(data, 0x60, order_count)?);
+    let pointers = 0x60 + order_count;
+    let parapointer = |i: usize| -> GameResult<usize> { Ok(u16_at(data, pointers + i * 2)? as usize * 16) };
+
+    if default_panning {
+        let panning = bytes(data, pointers + (instrument_count + pattern_count) * 2, 32)?;
+        for (i, &pan) in panning.iter().enumerate() {
+            if let (Some(ch), true) = (channel_map[i], pan & 0x20 != 0) {
+                channel_panning[ch] = (pan & 0x0f) * 17;
+            }
+        }
+    }
+
+    let mut samples = Vec::with_capacity(instrument_count);
+    for i in 0..instrument_count {
+        let pos = parapointer(i)?;
+        let header = bytes(data, pos, 0x50)?;
+        let mut sample = Sample {
+            data: Vec::new(),
+            loop_start: 0,
+            loop_end: 0,
+            loop_kind: LoopKind::None,
+            volume: header[0x1c].min(64),
+            panning: None,
+            tuning: 0,
+            global_volume: 1.0,
+        };
+
+        if header[0] == 1 {
+            let offset = (((header[0x0d] as usize) << 16) | u16_at(header, 0x0e)? as usize) * 16;
+            let length = u32_at(header, 0x10)? as usize;
+            let flags = header[0x1f];
+            let kind = if flags & 1 != 0 { LoopKind::Forward } else { LoopKind::None };
+
+            // stereo samples store the left channel first, only that one is used
+            sample.data = read_pcm(data, offset, length, flags & 4 != 0, signed_samples);
+            let (loop_kind, loop_start, loop_end) =
+                sample_loop(kind, u32_at(header, 0x14)? as usize, u32_at(header, 0x18)? as usize, sample.data.len());
+            sample.loop_kind = loop_kind;
+            sample.loop_start = loop_start;
+            sample.loop_end = loop_end;
+            sample.tuning = Sample::tuning_from_rate(u32_at(header, 0x20)?);
+        }
+
+        samples.push(sample);
+    }
+
+    let mut patterns = Vec::with_capacity(pattern_count);
+    for i in 0..pattern_count {
+        let mut pattern = Pattern::empty(64, channels);
+        let pos = parapointer(instrument_count + i)?;
+
+        if pos != 0 {
+            let length = u16_at(data, pos)? as usize;
+            let packed = data.get(pos + 2..(pos + 2 + length).min(data.len())).unwrap_or(&[]);
+            let mut i = 0;
+            let mut next = || {
+                let b = packed.get(i).copied();
+                i += 1;
+                b
+            };
+
+            let mut row = 0;
+            while row < 64 {
+                let what = match next() {
+                    Some(what) => what,
+                    None => break,
+                };
+
+                if what == 0 {
+                    row += 1;
+                    continue;
+                }
+
+                let mut cell = Cell::EMPTY;
+                if what & 0x20 != 0 {
+                    let note = next().unwrap_or(255);
+                    cell.note = match note {
+                        255 => Note::None,
+                        254 => Note::Cut,
+                        _ => Note::On(((note >> 4) * 12 + (note & 0x0f)).min(NOTE_COUNT as u8 - 1)),
+                    };
+                    cell.instrument = next().unwrap_or(0);
+                }
+                if what & 0x40 != 0 {
+                    let volume = next().unwrap_or(255);
+                    if volume <= 64 {
+                        cell.volume = VolumeCommand::Set(volume);
+                    }
+                }
+                if what & 0x80 != 0 {
+                    let command = next().unwrap_or(0);
+                    let param = next().unwrap_or(0);
+                    cell.effect = st3_effect(command, param, false);
+                }
+
+                if let Some(ch) = channel_map[(what & 0x1f) as usize] {
+                    pattern.cells[row * channels + ch] = cell;
+                }
+            }
+        }
+
+        patterns.push(pattern);
+    }
+
+    remap_position_jumps(&mut patterns, &remap);
+
+    Ok(Module {
+        format: ModuleFormat::S3m,
+        name: text(bytes(data, 0, 28)?),
+        channels,
+        orders,
+        restart: 0,
+        patterns,
+        instruments: (0..samples.len()).map(Instrument::from_sample).collect(),
+        samples,
+        initial_speed: speed.max(1),
+        initial_tempo: tempo.max(32),
+        initial_global_volume: global_volume as f32 / 64.0,
+        channel_panning,
+        linear_periods: false,
+    })
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, bits: u32) -> u32 {
+        let mut value = 0;
+        for i in 0..bits {
+            let byte = self.data.get(self.pos / 8).copied().unwrap_or(0);
+            value |= ((byte >> (self.pos % 8)) as u32 & 1) << i;
+            self.pos += 1;
+        }
+
+        value
+    }
+
+    fn exhausted(&self) -> bool {
+        self.pos >= self.data.len() * 8
+    }
+}
+
+/// Decompresses IT 2.14/2.15 samples, which are stored in blocks of variable bit width deltas.
+fn decompress_it_sample(data: &[u8], length: usize, is_16bit: bool, it215: bool) -> Vec<f32> {
+    let (block_size, max_width, width_bits, sample_bits) =
+        if is_16bit { (0x4000, 17, 4, 16) } else { (0x8000, 9, 3, 8) };
+    let wrap = |v: i32| if is_16bit { v as i16 as i32 } else { v as i8 as i32 };
+    let scale = if is_16bit { 32768.0 } else { 128.0 };
+
+    let mut out = Vec::with_capacity(length);
+    let mut pos = 0;
+    while out.len() < length {
+        let block_length = match u16_at(data, pos) {
+            Ok(len) => len as usize,
+            Err(_) => break,
+        };
+        let block = &data[(pos + 2).min(data.len())..(pos + 2 + block_length).min(data.len())];
+        pos += 2 + block_length;
+
+        let mut bits = BitReader { data: block, pos: 0 };
+        let count = (length - out.len()).min(block_size);
+        let mut width = max_width;
+        let (mut d1, mut d2) = (0i32, 0i32);
+        let mut decoded = 0;
+
+        while decoded < count && !bits.exhausted() {
+            let value = bits.read(width);
+
+            if width < 7 {
+                if value == 1 << (width - 1) {
+                    let new_width = bits.read(width_bits) + 1;
+                    width = if new_width < width { new_width } else { new_width + 1 };
+                    continue;
+                }
+            } else if width < max_width {
+                let border = (((1u32 << sample_bits) - 1) >> (max_width - width)) - (1 << (width_bits - 1));
+                if value > border && value <= border + (1 << width_bits) {
+                    let new_width = value - border;
+                    width = if new_width < width { new_width } else { new_width + 1 };
+                    continue;
+                }
+            } else if value & (1 << sample_bits) != 0 {
+                width = (value + 1) & 0xff;
+                if width == 0 || width > max_width {
+                    break;
+                }
+                continue;
+            }
+
+            // sign extend the value to the full sample width
+            let shift = 32 - width.min(sample_bits);
+            let delta = ((value << shift) as i32) >> shift;
+            d1 = wrap(d1 + delta);
+            d2 = wrap(d2 + d1);
+            out.push((if it215 { d2 } else { d1 }) as f32 / scale);
+            decoded += 1;
+        }
+
+        if decoded < count {
+            out.resize(out.len() + count - decoded, 0.0);
+        }
+    }
+
+    out
+}
+
+fn it_envelope(data: &[u8]) -> Option<Envelope> {
+    let flags = data[0];
+    let count = (data[1] as usize).min(25);
+    if flags & 1 == 0 || count == 0 {
+        return None;
+    }
+
+    let points =
+        data[6..6 + count * 3].chunks_exact(3).map(|p| (u16::from_le_bytes([p[1], p[2]]), p[0].min(64))).collect();
+
+    Some(Envelope {
+        points,
+        loop_range: if flags & 2 != 0 { Some((data[2] as usize, data[3] as usize)) } else { None },
+        sustain: if flags & 4 != 0 { Some((data[4] as usize, data[5] as usize)) } else { None },
+    })
+}
+
+fn load_it_instrument(data: &[u8], pos: usize, old_format: bool) -> GameResult<Instrument> {
+    let header = bytes(data, pos, if old_format { 0x1ea } else { 0x1b0 })?;
+
+    let note_map = header[0x40..0x130]
+        .chunks_exact(2)
+        .map(|entry| (entry[0].min(NOTE_COUNT as u8 - 1), entry[1].checked_sub(1).map(|s| s as usize)))
+        .collect();
+
+    if old_format {
+        let flags = header[0x11];
+        let points: Vec<(u16, u8)> = header[0x1b8..0x1ea]
+            .chunks_exact(2)
+            .take_while(|p| p[0] != 0xff)
+            .map(|p| (p[0] as u16, p[1].min(64)))
+            .collect();
+
+        return Ok(Instrument {
+            note_map,
+            volume_envelope: if flags & 1 != 0 && !points.is_empty() {
+                Some(Envelope {
+                    points,
+                    loop_range: if flags & 2 != 0 {
+                        Some((header[0x12] as usize, header[0x13] as usize))
+                    } else {
+                        None
+                    },
+                    sustain: if flags & 4 != 0 { Some((header[0x14] as usize, header[0x15] as usize)) } else { None },
+                })
+            } else {
+                None
+            },
+            fadeout: u16_at(header, 0x18)? as f32 / 512.0,
+            panning: None,
+            global_volume: 1.0,
+        });
+    }
+
+    let panning = header[0x19];
+
+    Ok(Instrument {
+        note_map,
+        volume_envelope: it_envelope(&header[0x130..]),
+        fadeout: u16_at(header, 0x14)? as f32 / 1024.0,
+        panning: if panning & 0x80 == 0 { Some((panning as u16 * 4).min(255) as u8) } else { None },
+        global_volume: header[0x18].min(128) as f32 / 128.0,
+    })
+}
+
+fn load_it_sample(data: &[u8], pos: usize) -> GameResult<Sample> {
+    let header = bytes(data, pos, 0x50)?;
+    let global_volume = header[0x11].min(64);
+    let flags = header[0x12];
+    let volume = header[0x13].min(64);
+    let convert = header[0x2e];
+    let panning = header[0x2f];
+    let length = u32_at(header, 0x30)? as usize;
+    let c5speed = u32_at(header, 0x3c)?;
+    let pointer = u32_at(header, 0x48)? as usize;
+    let is_16bit = flags & 2 != 0;
+
+    let pcm = if flags & 1 == 0 {
+        Vec::new()
+    } else if flags & 8 != 0 {
+        decompress_it_sample(data.get(pointer..).unwrap_or(&[]), length, is_16bit, convert & 4 != 0)
+    } else {
+        read_pcm(data, pointer, length, is_16bit, convert & 1 != 0)
+    };
+
+    // the sustain loop is used as a regular one if there's no other
+    let (kind, start, end) = if flags & 0x10 != 0 {
+        (if flags & 0x40 != 0 { LoopKind::PingPong } else { LoopKind::Forward }, 0x34, 0x38)
+    } else if flags & 0x20 != 0 {
+        (if flags & 0x80 != 0 { LoopKind::PingPong } else { LoopKind::Forward }, 0x40, 0x44)
+    } else {
+        (LoopKind::None, 0x34, 0x38)
+    };
+    let (loop_kind, loop_start, loop_end) =
+        sample_loop(kind, u32_at(header, start)? as usize, u32_at(header, end)? as usize, pcm.len());
+
+    Ok(Sample {
+        data: pcm,
+        loop_start,
+        loop_end,
+        loop_kind,
+        volume,
+        panning: if panning & 0x80 != 0 { Some(((panning & 0x7f) as u16 * 4).min(255) as u8) } else { None },
+        // IT notes are an octave higher, C-5 plays at the sample rate
+        tuning: Sample::tuning_from_rate(c5speed) - 12 * 128,
+        global_volume: global_volume as f32 / 64.0,
+    })
+}
+
+fn it_volume(volume: u8) -> VolumeCommand {
+    const PORTA_SPEEDS: [u8; 10] = [0, 1, 4, 8, 16, 32, 64, 96, 128, 255];
+
+    match volume {
+        0..=64 => VolumeCommand::Set(volume),
+        65..=74 => VolumeCommand::FineUp(volume - 65),
+        75..=84 => VolumeCommand::FineDown(volume - 75),
+        85..=94 => VolumeCommand::SlideUp(volume - 85),
+        95..=104 => VolumeCommand::SlideDown(volume - 95),
+        128..=192 => VolumeCommand::Panning(((volume - 128) as u16 * 4).min(255) as u8),
+        193..=202 => VolumeCommand::TonePorta(PORTA_SPEEDS[(volume - 193) as usize]),
+        _ => VolumeCommand::None,
+    }
+}
+
+pub fn load_it(data: &[u8]) -> GameResult<Module> {
+    let order_count = u16_at(data, 0x20)? as usize;
+    let instrument_count = u16_at(data, 0x22)? as usize;
+    let sample_count = u16_at(data, 0x24)? as usize;
+    let pattern_count = u16_at(data, 0x26)? as usize;
+    let compatible_version = u16_at(data, 0x2a)?;
+    let flags = u16_at(data, 0x2c)?;
+    let global_volume = u8_at(data, 0x30)?.min(128);
+    let speed = u8_at(data, 0x32)?;
+    let tempo = u8_at(data, 0x33)?;
+    let channel_settings = bytes(data, 0x40, 64)?;
+
+    let (orders, remap) = parse_orders(bytes(data, 0xc0, order_count)?);
+    let pointers = 0xc0 + order_count;
+    let pointer = |i: usize| -> GameResult<usize> { Ok(u32_at(data, pointers + i * 4)? as usize) };
+
+    let mut samples = Vec::with_capacity(sample_count);
+    for i in 0..sample_count {
+        samples.push(load_it_sample(data, pointer(instrument_count + i)?)?);
+    }
+
+    let instruments = if flags & 4 != 0 {
+        let mut instruments = Vec::with_capacity(instrument_count);
+        for i in 0..instrument_count {
+            instruments.push(load_it_instrument(data, pointer(i)?, compatible_version < 0x200)?);
+        }
+        instruments
+    } else {
+        (0..samples.len()).map(Instrument::from_sample).collect()
+    };
+
+    // patterns are decoded first to find out how many channels are used
+    let mut channels = 1;
+    let mut decoded = Vec::with_capacity(pattern_count);
+    for i in 0..pattern_count {
+        let pos = pointer(instrument_count + sample_count + i)?;
+        let mut cells = Vec::new();
+
+        if pos == 0 {
+            decoded.push((64, cells));
+            continue;
+        }
+
+        let length = u16_at(data, pos)? as usize;
+        let rows = (u16_at(data, pos + 2)? as usize).max(1);
+        let packed = data.get(pos + 8..(pos + 8 + length).min(data.len())).unwrap_or(&[]);
+        let mut i = 0;
+        let mut next = || {
+            let b = packed.get(i).copied();
+            i += 1;
+            b
+        };
+
+        let mut masks = [0u8; 64];
+        let mut last = [Cell::EMPTY; 64];
+        let mut last_volume = [255u8; 64];
+        let mut row = 0;
+        while row < rows {
+            let channel_variable = match next() {
+                Some(v) => v,
+                None => break,
+            };
+
+            if channel_variable == 0 {
+                row += 1;
+                continue;
+            }
+
+            let ch = (channel_variable.wrapping_sub(1) & 63) as usize;
+            if channel_variable & 0x80 != 0 {
+                masks[ch] = next().unwrap_or(0);
+            }
+
+            let mask = masks[ch];
+            let mut cell = Cell::EMPTY;
+            let mut volume = 255;
+
+            if mask & 0x01 != 0 {
+                last[ch].note = match next().unwrap_or(0) {
+                    note @ 0..=119 => Note::On(note),
+                    255 => Note::Off,
+                    254 => Note::Cut,
+                    _ => Note::Fade,
+                };
+            }
+            if mask & 0x02 != 0 {
+                last[ch].instrument = next().unwrap_or(0);
+            }
+            if mask & 0x04 != 0 {
+                last_volume[ch] = next().unwrap_or(255);
+            }
+            if mask & 0x08 != 0 {
+                let command = next().unwrap_or(0);
+                let param = next().unwrap_or(0);
+                last[ch].effect = st3_effect(command, param, true);
+            }
+
+            if mask & 0x11 != 0 {
+                cell.note = last[ch].note;
+            }
+            if mask & 0x22 != 0 {
+                cell.instrument = last[ch].instrument;
+            }
+            if mask & 0x44 != 0 {
+                volume = last_volume[ch];
+            }
+            if mask & 0x88 != 0 {
+                cell.effect = last[ch].effect;
+            }
+            cell.volume = it_volume(volume);
+
+            channels = channels.max(ch + 1);
+            cells.push((row, ch, cell));
+        }
+
+        decoded.push((rows, cells));
+    }
+
+    let mut patterns: Vec<Pattern> = decoded
+        .into_iter()
+        .map(|(rows, cells)| {
+            let mut pattern = Pattern::empty(rows, channels);
+            for (row, ch, cell) in cells {
+                pattern.cells[row * channels + ch] = cell;
+            }
+            pattern
+        })
+        .collect();
+
+    remap_position_jumps(&mut patterns, &remap);
+
+    let channel_panning = channel_settings[..channels]
+        .iter()
+        .map(|&pan| match pan & 0x7f {
+            pan @ 0..=64 => (pan as u16 * 4).min(255) as u8,
+            _ => 128,
+        })
+        .collect();
+
+    Ok(Module {
+        format: ModuleFormat::It,
+        name: text(bytes(data, 4, 26)?),
+        channels,
+        orders,
+        restart: 0,
+        patterns,
+        instruments,
+        samples,
+        initial_speed: speed.max(1),
+        initial_tempo: tempo.max(32),
+        initial_global_volume: global_volume as f32 / 128.0,
+        channel_panning,
+        linear_periods: flags & 8 != 0,
+    })
+}
@@ -0,0 +1,346 @@
+//! Pure Rust player for MOD, S3M, XM and IT tracker modules.
+//!
+//! All formats are converted into a common representation on load: notes are numbered from C-0 (so C-4 is 48),
+//! sample pitch is expressed relative to 8363 Hz at C-4 and the effect columns are mapped to [Effect].
+
+use crate::framework::error::{GameError, GameResult};
+
+mod formats;
+mod player;
+
+pub use player::TrackerPlayer;
+
+pub const NOTE_COUNT: usize = 120;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ModuleFormat {
+    Mod,
+    S3m,
+    Xm,
+    It,
+}
+
+impl ModuleFormat {
+    /// Whether the format uses Scream Tracker style volume slides and portamentos, which encode the fine variants
+    /// in the effect parameter and share their effect memory.
+    pub fn st3_effects(self) -> bool {
+        matches!(self, ModuleFormat::S3m | ModuleFormat::It)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LoopKind {
+    None,
+    Forward,
+    PingPong,
+}
+
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub data: Vec<f32>,
+    pub loop_start: usize,
+    pub loop_end: usize,
+    pub loop_kind: LoopKind,
+    /// Default volume, `0..=64`.
+    pub volume: u8,
+    /// Default panning, `0..=255`. Samples without one keep the panning of the channel.
+    pub panning: Option<u8>,
+    /// Pitch offset in 1/128 of a semitone, a sample with zero tuning plays at 8363 Hz at C-4.
+    pub tuning: i32,
+    pub global_volume: f32,
+}
+
+impl Sample {
+    /// Converts sample rate of C-4 to tuning.
+    pub fn tuning_from_rate(rate: u32) -> i32 {
+        (12.0 * 128.0 * (rate.max(1) as f64 / 8363.0).log2()).round() as i32
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Envelope {
+    /// Points of the envelope as (tick, value in `0..=64`) pairs.
+    pub points: Vec<(u16, u8)>,
+    /// Range of points looped while the note is held.
+    pub sustain: Option<(usize, usize)>,
+    pub loop_range: Option<(usize, usize)>,
+}
+
+impl Envelope {
+    pub fn value_at(&self, tick: u16) -> f32 {
+        let (first, last) = match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return 1.0,
+        };
+
+        if tick <= first.0 {
+            return first.1 as f32 / 64.0;
+        }
+
+        for pair in self.points.windows(2) {
+            let ((t0, v0), (t1, v1)) = (pair[0], pair[1]);
+            if tick < t1 {
+                let mu = (tick - t0) as f32 / (t1 - t0).max(1) as f32;
+                return (v0 as f32 + (v1 as f32 - v0 as f32) * mu) / 64.0;
+            }
+        }
+
+        last.1 as f32 / 64.0
+    }
+
+    /// Advances the position of the envelope by one tick, taking its loops into account.
+    pub fn next_tick(&self, tick: u16, key_on: bool) -> u16 {
+        let range = if key_on { self.sustain.or(self.loop_range) } else { self.loop_range };
+
+        if let Some((start, end)) = range {
+            if let (Some(start), Some(end)) = (self.points.get(start), self.points.get(end)) {
+                if tick >= end.0 {
+                    return start.0;
+                }
+            }
+        }
+
+        tick.saturating_add(1)
+    }
+
+    pub fn finished(&self, tick: u16) -> bool {
+        self.loop_range.is_none() && self.points.last().map_or(true, |last| tick >= last.0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Instrument {
+    /// Sample index and the note it's played at for each note.
+    pub note_map: Vec<(u8, Option<usize>)>,
+    pub volume_envelope: Option<Envelope>,
+    /// How much the volume decreases each tick after a note has been released.
+    pub fadeout: f32,
+    pub panning: Option<u8>,
+    pub global_volume: f32,
+}
+
+impl Instrument {
+    /// An instrument that plays given sample at the notes as written, used by formats that have no instruments.
+    pub fn from_sample(sample: usize) -> Instrument {
+        Instrument {
+            note_map: (0..NOTE_COUNT as u8).map(|note| (note, Some(sample))).collect(),
+            volume_envelope: None,
+            fadeout: 0.0,
+            panning: None,
+            global_volume: 1.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Note {
+    None,
+    On(u8),
+    Off,
+    Cut,
+    Fade,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum VolumeCommand {
+    None,
+    Set(u8),
+    SlideUp(u8),
+    SlideDown(u8),
+    FineUp(u8),
+    FineDown(u8),
+    Panning(u8),
+    TonePorta(u8),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Effect {
+    None,
+    Arpeggio(u8),
+    PortaUp(u8),
+    PortaDown(u8),
+    FinePortaUp(u8),
+    FinePortaDown(u8),
+    ExtraFinePortaUp(u8),
+    ExtraFinePortaDown(u8),
+    TonePorta(u8),
+    Vibrato(u8),
+    TonePortaVolSlide(u8),
+    VibratoVolSlide(u8),
+    Tremolo(u8),
+    SetPanning(u8),
+    SampleOffset(u8),
+    VolSlide(u8),
+    FineVolSlideUp(u8),
+    FineVolSlideDown(u8),
+    PositionJump(u8),
+    SetVolume(u8),
+    PatternBreak(u8),
+    PatternLoop(u8),
+    Retrigger(u8),
+    RetriggerVolume(u8),
+    NoteCut(u8),
+    NoteDelay(u8),
+    PatternDelay(u8),
+    SetSpeed(u8),
+    SetTempo(u8),
+    /// Global volume in `0..=64`.
+    SetGlobalVolume(u8),
+    GlobalVolSlide(u8),
+    KeyOff(u8),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Cell {
+    pub note: Note,
+    /// 1-based instrument number, 0 if the cell has none.
+    pub instrument: u8,
+    pub volume: VolumeCommand,
+    pub effect: Effect,
+}
+
+impl Cell {
+    pub const EMPTY: Cell = Cell { note: Note::None, instrument: 0, volume: VolumeCommand::None, effect: Effect::None };
+}
+
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    pub rows: usize,
+    /// Cells stored row by row.
+    pub cells: Vec<Cell>,
+}
+
+impl Pattern {
+    pub fn empty(rows: usize, channels: usize) -> Pattern {
+        Pattern { rows, cells: vec![Cell::EMPTY; rows * channels] }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Module {
+    pub format: ModuleFormat,
+    pub name: String,
+    pub channels: usize,
+    /// Pattern played at each position of the song.
+    pub orders: Vec<usize>,
+    /// Position the song continues from after the last one.
+    pub restart: usize,
+    pub patterns: Vec<Pattern>,
+    pub instruments: Vec<Instrument>,
+    pub samples: Vec<Sample>,
+    pub initial_speed: u8,
+    pub initial_tempo: u8,
+    pub initial_global_volume: f32,
+    pub channel_panning: Vec<u8>,
+    pub linear_periods: bool,
+}
+
+impl Module {
+    /// Loads a module, the format is detected from its contents.
+    pub fn load(data: &[u8]) -> GameResult<Module> {
+        let mut module = if data.starts_with(b"Extended Module: ") {
+            formats::load_xm(data)?
+        } else if data.starts_with(b"IMPM") {
+            formats::load_it(data)?
+        } else if data.get(0x2c..0x30) == Some(&b"SCRM"[..]) {
+            formats::load_s3m(data)?
+        } else {
+            formats::load_mod(data)?
+        };
+
+        module.validate()?;
+
+        Ok(module)
+    }
+
+    /// Makes sure all orders point to existing patterns so the player doesn't have to check.
+    fn validate(&mut self) -> GameResult {
+        if self.orders.is_empty() || self.channels == 0 {
+            return Err(GameError::ResourceLoadError("Module has no orders or channels.".to_owned()));
+        }
+
+        for order in self.orders.iter_mut() {
+            if *order >= self.patterns.len() {
+                self.patterns.push(Pattern::empty(64, self.channels));
+                *order = self.patterns.len() - 1;
+            }
+        }
+
+        for pattern in self.patterns.iter_mut() {
+            if pattern.rows == 0 {
+                *pattern = Pattern::empty(64, self.channels);
+            }
+        }
+
+        if self.restart >= self.orders.len() {
+            self.restart = 0;
+        }
+
+        self.channel_panning.resize(self.channels, 128);
+
+        Ok(())
+    }
+
+    pub fn cell(&self, order: usize, row: usize, channel: usize) -> &Cell {
+        let pattern = &self.patterns[self.orders[order]];
+        &pattern.cells[row * self.channels + channel]
+    }
+}
+
+#[test]
+fn test_tracker_module() {
+    use crate::sound::stream_playback::SongStream;
+
+    // a 4 channel ProTracker module with a single looped sample
+    let mut data = vec![0u8; 1084];
+    data[..4].copy_from_slice(b"test");
+    data[42..44].copy_from_slice(&16u16.to_be_bytes());
+    data[45] = 64;
+    data[48..50].copy_from_slice(&16u16.to_be_bytes());
+    data[950] = 2;
+    data[953] = 1;
+    data[1080..1084].copy_from_slice(b"M.K.");
+
+    let mut pattern = |cells: &[(usize, usize, [u8; 4])]| {
+        let mut pattern = vec![0u8; 64 * 4 * 4];
+        for (row, ch, cell) in cells {
+            let pos = (row * 4 + ch) * 4;
+            pattern[pos..pos + 4].copy_from_slice(cell);
+        }
+        data.extend(pattern);
+    };
+    // C-4 with the first sample, then a pattern break at row 16
+    pattern(&[(0, 0, [0x01, 0xac, 0x10, 0x00]), (15, 1, [0x00, 0x00, 0x0d, 0x00])]);
+    // speed 3, then a jump back to the second position at row 8
+    pattern(&[(0, 0, [0x00, 0x00, 0x0f, 0x03]), (7, 2, [0x00, 0x00, 0x0b, 0x01])]);
+    data.extend((0..32).map(|i| if i < 16 { 0x40u8 } else { 0xc0 }));
+
+    let module = Module::load(&data).unwrap();
+    assert_eq!((module.format, module.name.as_str(), module.channels), (ModuleFormat::Mod, "test", 4));
+    assert_eq!(module.orders, vec![0, 1]);
+    assert_eq!(module.samples[0].data.len(), 32);
+    assert_eq!((module.samples[0].loop_kind, module.samples[0].loop_end), (LoopKind::Forward, 32));
+    assert_eq!(module.cell(0, 0, 0).note, Note::On(48));
+    assert_eq!(module.cell(0, 0, 0).instrument, 1);
+    assert_eq!(module.cell(1, 7, 2).effect, Effect::PositionJump(1));
+
+    fn play_until_loop(player: &mut TrackerPlayer) -> (usize, bool) {
+        let (mut frames, mut audible) = (0, false);
+        while let Some(buf) = player.read_packet().unwrap() {
+            frames += buf.len() / 2;
+            audible |= buf.iter().any(|&s| s != 0);
+        }
+        (frames, audible)
+    }
+
+    // 882 frames per tick at 125 BPM, 16 rows at speed 6 and 8 rows at speed 3
+    let mut player = TrackerPlayer::new(module, 44100);
+    assert_eq!(play_until_loop(&mut player), (882 * (16 * 6 + 8 * 3), true));
+
+    // the loop starts at the second position and keeps the speed
+    player.rewind().unwrap();
+    assert_eq!(play_until_loop(&mut player).0, 882 * 8 * 3);
+
+    player.restart().unwrap();
+    assert_eq!(play_until_loop(&mut player).0, 882 * (16 * 6 + 8 * 3));
+}
@@ -0,0 +1,748 @@
+use std::f32::consts::PI;
+
+use crate::framework::error::GameResult;
+use crate::sound::stream_playback::SongStream;
+use crate::sound::tracker::{Cell, Effect, LoopKind, Module, ModuleFormat, Note, Sample, VolumeCommand};
+
+/// Converts a note and sample tuning to a period, in 1/64 of a semitone for linear periods and in 1/4 of
+/// ProTracker's units otherwise.
+fn note_period(linear: bool, note: u8, tuning: i32) -> f32 {
+    let pitch = note as f32 * 128.0 + tuning as f32;
+
+    if linear {
+        7680.0 - pitch / 2.0
+    } else {
+        1712.0 * 2f32.powf((48.0 * 128.0 - pitch) / 1536.0)
+    }
+}
+
+fn period_to_frequency(linear: bool, period: f32) -> f32 {
+    if linear {
+        8363.0 * 2f32.powf((4608.0 - period) / 768.0)
+    } else {
+        8363.0 * 1712.0 / period.max(1.0)
+    }
+}
+
+fn waveform(position: u8) -> f32 {
+    (position as f32 * PI * 2.0 / 64.0).sin() * 255.0
+}
+
+#[derive(Clone)]
+struct Channel {
+    instrument: Option<usize>,
+    sample: Option<usize>,
+    playing: bool,
+    position: f64,
+    backwards: bool,
+    period: f32,
+    target_period: f32,
+    volume: i32,
+    panning: u8,
+    key_on: bool,
+    fading: bool,
+    fadeout: f32,
+    envelope_tick: u16,
+    cell: Cell,
+    note_delay: u8,
+
+    arpeggio_offset: u8,
+    vibrato_position: u8,
+    vibrato_offset: f32,
+    tremolo_position: u8,
+    tremolo_offset: i32,
+    retrigger_counter: u8,
+    pattern_loop_row: usize,
+    pattern_loop_count: u8,
+
+    // parameters reused by effects given a zero parameter
+    arpeggio_memory: u8,
+    porta_up_memory: u8,
+    porta_down_memory: u8,
+    tone_porta_memory: u8,
+    vibrato_memory: u8,
+    tremolo_memory: u8,
+    volume_slide_memory: u8,
+    offset_memory: u8,
+    retrigger_memory: u8,
+}
+
+impl Channel {
+    fn new(panning: u8) -> Channel {
+        Channel {
+            instrument: None,
+            sample: None,
+            playing: false,
+            position: 0.0,
+            backwards: false,
+            period: 0.0,
+            target_period: 0.0,
+            volume: 0,
+            panning,
+            key_on: false,
+            fading: false,
+            fadeout: 1.0,
+            envelope_tick: 0,
+            cell: Cell::EMPTY,
+            note_delay: 0,
+            arpeggio_offset: 0,
+            vibrato_position: 0,
+            vibrato_offset: 0.0,
+            tremolo_position: 0,
+            tremolo_offset: 0,
+            retrigger_counter: 0,
+            pattern_loop_row: 0,
+            pattern_loop_count: 0,
+            arpeggio_memory: 0,
+            porta_up_memory: 0,
+            porta_down_memory: 0,
+            tone_porta_memory: 0,
+            vibrato_memory: 0,
+            tremolo_memory: 0,
+            volume_slide_memory: 0,
+            offset_memory: 0,
+            retrigger_memory: 0,
+        }
+    }
+
+    fn slide_volume(&mut self, amount: i32) {
+        self.volume = (self.volume + amount).clamp(0, 64);
+    }
+
+    fn tone_porta(&mut self) {
+        let speed = self.tone_porta_memory as f32 * 4.0;
+
+        if self.period < self.target_period {
+            self.period = (self.period + speed).min(self.target_period);
+        } else {
+            self.period = (self.period - speed).max(self.target_period);
+        }
+    }
+
+    fn vibrato(&mut self) {
+        let (speed, depth) = (self.vibrato_memory >> 4, self.vibrato_memory & 0x0f);
+        self.vibrato_offset = waveform(self.vibrato_position) * depth as f32 / 32.0;
+        self.vibrato_position = (self.vibrato_position + speed) % 64;
+    }
+
+    fn tremolo(&mut self) {
+        let (speed, depth) = (self.tremolo_memory >> 4, self.tremolo_memory & 0x0f);
+        self.tremolo_offset = (waveform(self.tremolo_position) * depth as f32 / 64.0) as i32;
+        self.tremolo_position = (self.tremolo_position + speed) % 64;
+    }
+
+    fn retrigger(&mut self) {
+        self.position = 0.0;
+        self.backwards = false;
+    }
+}
+
+/// Plays a tracker module, the song is rendered one tick at a time.
+///
+/// Playback ends once the song reaches a position it has already played, which is where it loops back to when
+/// rewound, so modules loop the same way they do in their trackers.
+pub struct TrackerPlayer {
+    module: Module,
+    sample_rate: u32,
+    channels: Vec<Channel>,
+    order: usize,
+    row: usize,
+    tick: u32,
+    speed: u32,
+    tempo: u32,
+    pattern_delay: u32,
+    global_volume: f32,
+    global_slide_memory: u8,
+    jump_order: Option<usize>,
+    break_row: Option<usize>,
+    loop_row: Option<usize>,
+    visited: Vec<bool>,
+    looped: bool,
+    mix_buffer: Vec<f32>,
+}
+
+impl TrackerPlayer {
+    pub fn new(module: Module, sample_rate: u32) -> TrackerPlayer {
+        let mut player = TrackerPlayer {
+            channels: Vec::new(),
+            sample_rate,
+            order: 0,
+            row: 0,
+            tick: 0,
+            speed: 6,
+            tempo: 125,
+            pattern_delay: 0,
+            global_volume: 1.0,
+            global_slide_memory: 0,
+            jump_order: None,
+            break_row: None,
+            loop_row: None,
+            visited: vec![false; module.orders.len()],
+            looped: false,
+            mix_buffer: Vec::new(),
+            module,
+        };
+        player.reset();
+
+        player
+    }
+
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    fn reset(&mut self) {
+        self.channels = self.module.channel_panning.iter().map(|&pan| Channel::new(pan)).collect();
+        self.order = 0;
+        self.row = 0;
+        self.tick = 0;
+        self.speed = self.module.initial_speed as u32;
+        self.tempo = self.module.initial_tempo as u32;
+        self.pattern_delay = 0;
+        self.global_volume = self.module.initial_global_volume;
+        self.jump_order = None;
+        self.break_row = None;
+        self.loop_row = None;
+        self.visited.fill(false);
+        self.visited[0] = true;
+        self.looped = false;
+    }
+
+    fn samples_per_tick(&self) -> usize {
+        self.sample_rate as usize * 5 / (self.tempo.max(1) as usize * 2)
+    }
+
+    fn next_row(&mut self) {
+        if let Some(row) = self.loop_row.take() {
+            self.jump_order = None;
+            self.break_row = None;
+            self.row = row;
+            return;
+        }
+
+        let rows = self.module.patterns[self.module.orders[self.order]].rows;
+        let (mut order, row) = if self.jump_order.is_some() || self.break_row.is_some() {
+            (self.jump_order.take().unwrap_or(self.order + 1), self.break_row.take().unwrap_or(0))
+        } else if self.row + 1 < rows {
+            self.row += 1;
+            return;
+        } else {
+            (self.order + 1, 0)
+        };
+
+        if order >= self.module.orders.len() {
+            order = self.module.restart;
+        }
+
+        if self.visited[order] {
+            self.looped = true;
+            self.visited.fill(false);
+        }
+
+        self.visited[order] = true;
+        for channel in self.channels.iter_mut() {
+            channel.pattern_loop_row = 0;
+            channel.pattern_loop_count = 0;
+        }
+
+        self.order = order;
+        self.row = if row < self.module.patterns[self.module.orders[order]].rows { row } else { 0 };
+    }
+
+    fn release(&mut self, ch: usize) {
+        let channel = &mut self.channels[ch];
+        channel.key_on = false;
+
+        let instrument = match channel.instrument.and_then(|i| self.module.instruments.get(i)) {
+            Some(instrument) => instrument,
+            None => return,
+        };
+
+        // without an envelope notes are cut, unless Impulse Tracker fades them out
+        if instrument.volume_envelope.is_none() && (self.module.format != ModuleFormat::It || instrument.fadeout == 0.0)
+        {
+            channel.playing = false;
+        }
+    }
+
+    fn trigger(&mut self, ch: usize) {
+        let module = &self.module;
+        let channel = &mut self.channels[ch];
+        let cell = channel.cell;
+        let porta = matches!(cell.effect, Effect::TonePorta(_) | Effect::TonePortaVolSlide(_))
+            || matches!(cell.volume, VolumeCommand::TonePorta(_));
+
+        if cell.instrument > 0 && (cell.instrument as usize) <= module.instruments.len() {
+            channel.instrument = Some(cell.instrument as usize - 1);
+        }
+
+        let instrument = channel.instrument.and_then(|i| module.instruments.get(i));
+
+        match cell.note {
+            Note::On(note) => {
+                let (note, sample) = match instrument {
+                    Some(instrument) => instrument.note_map[note as usize],
+                    None => (note, None),
+                };
+
+                if let Some(sample) = sample.filter(|&s| module.samples.get(s).map_or(false, |s| !s.data.is_empty())) {
+                    let period = note_period(module.linear_periods, note, module.samples[sample].tuning);
+
+                    if porta && channel.playing {
+                        channel.target_period = period;
+                    } else {
+                        channel.sample = Some(sample);
+                        channel.period = period;
+                        channel.target_period = period;
+                        channel.playing = true;
+                        channel.key_on = true;
+                        channel.fading = false;
+                        channel.fadeout = 1.0;
+                        channel.envelope_tick = 0;
+                        channel.vibrato_position = 0;
+                        channel.tremolo_position = 0;
+                        channel.retrigger();
+
+                        if let Effect::SampleOffset(offset) = cell.effect {
+                            if offset != 0 {
+                                channel.offset_memory = offset;
+                            }
+
+                            channel.position = channel.offset_memory as f64 * 256.0;
+                            if channel.position >= module.samples[sample].data.len() as f64 {
+                                channel.playing = false;
+                            }
+                        }
+                    }
+                }
+            }
+            Note::Cut => channel.playing = false,
+            Note::Fade => channel.fading = true,
+            Note::Off | Note::None => {}
+        }
+
+        // an instrument number resets volume and panning to the defaults
+        if cell.instrument > 0 {
+            if let Some(sample) = channel.sample.and_then(|s| module.samples.get(s)) {
+                channel.volume = sample.volume as i32;
+
+                if let Some(panning) = sample.panning.or_else(|| instrument.and_then(|i| i.panning)) {
+                    channel.panning = panning;
+                }
+            }
+        }
+
+        match cell.volume {
+            VolumeCommand::Set(volume) => channel.volume = volume as i32,
+            VolumeCommand::FineUp(amount) => channel.slide_volume(amount as i32),
+            VolumeCommand::FineDown(amount) => channel.slide_volume(-(amount as i32)),
+            VolumeCommand::Panning(panning) => channel.panning = panning,
+            VolumeCommand::TonePorta(speed) if speed != 0 => channel.tone_porta_memory = speed,
+            _ => {}
+        }
+
+        if cell.note == Note::Off {
+            self.release(ch);
+        }
+    }
+
+    fn process_row(&mut self) {
+        for ch in 0..self.channels.len() {
+            let cell = *self.module.cell(self.order, self.row, ch);
+            let channel = &mut self.channels[ch];
+            channel.cell = cell;
+            channel.arpeggio_offset = 0;
+            channel.note_delay = 0;
+
+            if !matches!(cell.effect, Effect::Vibrato(_) | Effect::VibratoVolSlide(_)) {
+                channel.vibrato_offset = 0.0;
+            }
+
+            if !matches!(cell.effect, Effect::Tremolo(_)) {
+                channel.tremolo_offset = 0;
+            }
+
+            match cell.effect {
+                Effect::NoteDelay(delay) if delay > 0 => channel.note_delay = delay,
+                _ => self.trigger(ch),
+            }
+
+            self.row_effect(ch);
+        }
+    }
+
+    /// Applies effects of the current row that only act on its first tick.
+    fn row_effect(&mut self, ch: usize) {
+        let st3 = self.module.format.st3_effects();
+        let channel = &mut self.channels[ch];
+
+        match channel.cell.effect {
+            Effect::Arpeggio(param) => {
+                if param != 0 {
+                    channel.arpeggio_memory = param;
+                }
+            }
+            Effect::PortaUp(param) | Effect::PortaDown(param) => {
+                let up = matches!(channel.cell.effect, Effect::PortaUp(_));
+
+                if st3 {
+                    // Scream Tracker shares the memory of both directions and encodes fine slides in the parameter
+                    if param != 0 {
+                        channel.porta_up_memory = param;
+                    }
+
+                    let param = channel.porta_up_memory;
+                    let amount = match param {
+                        0xf0..=0xff => (param & 0x0f) as f32 * 4.0,
+                        0xe0..=0xef => (param & 0x0f) as f32,
+                        _ => 0.0,
+                    };
+                    channel.period += if up { -amount } else { amount };
+                } else if param != 0 {
+                    if up {
+                        channel.porta_up_memory = param;
+                    } else {
+                        channel.porta_down_memory = param;
+                    }
+                }
+            }
+            Effect::FinePortaUp(param) => channel.period -= param as f32 * 4.0,
+            Effect::FinePortaDown(param) => channel.period += param as f32 * 4.0,
+            Effect::ExtraFinePortaUp(param) => channel.period -= param as f32,
+            Effect::ExtraFinePortaDown(param) => channel.period += param as f32,
+            Effect::TonePorta(param) => {
+                if param != 0 {
+                    channel.tone_porta_memory = param;
+                }
+            }
+            Effect::Vibrato(param) => {
+                if param >> 4 != 0 {
+                    channel.vibrato_memory = (channel.vibrato_memory & 0x0f) | (param & 0xf0);
+                }
+                if param & 0x0f != 0 {
+                    channel.vibrato_memory = (channel.vibrato_memory & 0xf0) | (param & 0x0f);
+                }
+            }
+            Effect::Tremolo(param) => {
+                if param >> 4 != 0 {
+                    channel.tremolo_memory = (channel.tremolo_memory & 0x0f) | (param & 0xf0);
+                }
+                if param & 0x0f != 0 {
+                    channel.tremolo_memory = (channel.tremolo_memory & 0xf0) | (param & 0x0f);
+                }
+            }
+            Effect::VolSlide(param) | Effect::TonePortaVolSlide(param) | Effect::VibratoVolSlide(param) => {
+                if param != 0 {
+                    channel.volume_slide_memory = param;
+                }
+
+                let (up, down) = (channel.volume_slide_memory >> 4, channel.volume_slide_memory & 0x0f);
+                if st3 && down == 0x0f && up != 0 {
+                    channel.slide_volume(up as i32);
+                } else if st3 && up == 0x0f && down != 0 {
+                    channel.slide_volume(-(down as i32));
+                }
+            }
+            Effect::FineVolSlideUp(param) => channel.slide_volume(param as i32),
+            Effect::FineVolSlideDown(param) => channel.slide_volume(-(param as i32)),
+            Effect::SetPanning(panning) => channel.panning = panning,
+            Effect::SetVolume(volume) => channel.volume = volume as i32,
+            Effect::RetriggerVolume(param) => {
+                if param != 0 {
+                    channel.retrigger_memory = param;
+                }
+            }
+            Effect::NoteCut(0) => channel.volume = 0,
+            Effect::PatternLoop(0) => channel.pattern_loop_row = self.row,
+            Effect::PatternLoop(count) => {
+                if channel.pattern_loop_count == 0 {
+                    channel.pattern_loop_count = count;
+                    self.loop_row = Some(channel.pattern_loop_row);
+                } else {
+                    channel.pattern_loop_count -= 1;
+                    if channel.pattern_loop_count > 0 {
+                        self.loop_row = Some(channel.pattern_loop_row);
+                    }
+                }
+            }
+            Effect::PositionJump(order) => self.jump_order = Some(order as usize),
+            Effect::PatternBreak(row) => self.break_row = Some(row as usize),
+            Effect::PatternDelay(delay) => {
+                if self.pattern_delay == 0 {
+                    self.pattern_delay = delay as u32;
+                }
+            }
+            Effect::SetSpeed(speed) => self.speed = speed.max(1) as u32,
+            Effect::SetTempo(tempo) => self.tempo = tempo.max(32) as u32,
+            Effect::SetGlobalVolume(volume) => self.global_volume = volume.min(64) as f32 / 64.0,
+            Effect::GlobalVolSlide(param) => {
+                if param != 0 {
+                    self.global_slide_memory = param;
+                }
+            }
+            Effect::KeyOff(0) => self.release(ch),
+            _ => {}
+        }
+    }
+
+    /// Applies effects of the current row that act on the following ticks.
+    fn tick_effect(&mut self, ch: usize, tick: u32) {
+        let st3 = self.module.format.st3_effects();
+        let linear = self.module.linear_periods;
+        let channel = &mut self.channels[ch];
+
+        if channel.note_delay != 0 && channel.note_delay as u32 == tick {
+            channel.note_delay = 0;
+            self.trigger(ch);
+            return;
+        }
+
+        match channel.cell.volume {
+            VolumeCommand::SlideUp(amount) => channel.slide_volume(amount as i32),
+            VolumeCommand::SlideDown(amount) => channel.slide_volume(-(amount as i32)),
+            VolumeCommand::TonePorta(_) => channel.tone_porta(),
+            _ => {}
+        }
+
+        match channel.cell.effect {
+            Effect::Arpeggio(_) => {
+                let param = channel.arpeggio_memory;
+                channel.arpeggio_offset = match tick % 3 {
+                    0 => 0,
+                    1 => param >> 4,
+                    _ => param & 0x0f,
+                };
+            }
+            Effect::PortaUp(_) | Effect::PortaDown(_) => {
+                let up = matches!(channel.cell.effect, Effect::PortaUp(_));
+                let param = if st3 || up { channel.porta_up_memory } else { channel.porta_down_memory };
+
+                if !st3 || param < 0xe0 {
+                    let amount = param as f32 * 4.0;
+                    channel.period += if up { -amount } else { amount };
+                }
+
+                if !linear {
+                    channel.period = channel.period.clamp(56.0, 27392.0);
+                }
+            }
+            Effect::TonePorta(_) => channel.tone_porta(),
+            Effect::Vibrato(_) => channel.vibrato(),
+            Effect::Tremolo(_) => channel.tremolo(),
+            Effect::Retrigger(interval) => {
+                if interval != 0 && tick % interval as u32 == 0 {
+                    channel.retrigger();
+                }
+            }
+            Effect::RetriggerVolume(_) => {
+                let (change, interval) = (channel.retrigger_memory >> 4, channel.retrigger_memory & 0x0f);
+                channel.retrigger_counter += 1;
+
+                if interval != 0 && channel.retrigger_counter >= interval {
+                    channel.retrigger_counter = 0;
+                    channel.retrigger();
+                    channel.volume = match change {
+                        0x1..=0x5 => channel.volume - (1 << (change - 1)),
+                        0x6 => channel.volume * 2 / 3,
+                        0x7 => channel.volume / 2,
+                        0x9..=0xd => channel.volume + (1 << (change - 9)),
+                        0xe => channel.volume * 3 / 2,
+                        0xf => channel.volume * 2,
+                        _ => channel.volume,
+                    }
+                    .clamp(0, 64);
+                }
+            }
+            Effect::NoteCut(at) => {
+                if at as u32 == tick {
+                    channel.volume = 0;
+                }
+            }
+            Effect::KeyOff(at) => {
+                if at as u32 == tick {
+                    self.release(ch);
+                }
+            }
+            Effect::GlobalVolSlide(_) => {
+                let (up, down) = (self.global_slide_memory >> 4, self.global_slide_memory & 0x0f);
+                let amount = if up != 0 { up as f32 } else { -(down as f32) };
+                self.global_volume = (self.global_volume + amount / 64.0).clamp(0.0, 1.0);
+            }
+            _ => {}
+        }
+
+        let channel = &mut self.channels[ch];
+        match channel.cell.effect {
+            Effect::TonePortaVolSlide(_) => channel.tone_porta(),
+            Effect::VibratoVolSlide(_) => channel.vibrato(),
+            _ => {}
+        }
+
+        if let Effect::VolSlide(_) | Effect::TonePortaVolSlide(_) | Effect::VibratoVolSlide(_) = channel.cell.effect {
+            let (up, down) = (channel.volume_slide_memory >> 4, channel.volume_slide_memory & 0x0f);
+
+            if !st3 || (up != 0x0f && down != 0x0f) {
+                channel.slide_volume(if up != 0 { up as i32 } else { -(down as i32) });
+            }
+        }
+    }
+
+    /// Advances envelopes and fade-outs of all channels by a tick.
+    fn update_envelopes(&mut self) {
+        for channel in self.channels.iter_mut() {
+            let instrument = match channel.instrument.and_then(|i| self.module.instruments.get(i)) {
+                Some(instrument) => instrument,
+                None => continue,
+            };
+
+            if let Some(envelope) = &instrument.volume_envelope {
+                channel.envelope_tick = envelope.next_tick(channel.envelope_tick, channel.key_on);
+
+                if envelope.finished(channel.envelope_tick) && envelope.value_at(channel.envelope_tick) == 0.0 {
+                    channel.playing = false;
+                }
+            }
+
+            if !channel.key_on || channel.fading {
+                channel.fadeout = (channel.fadeout - instrument.fadeout).max(0.0);
+            }
+        }
+    }
+
+    fn mix_channel(&mut self, ch: usize, frames: usize, gain: f32) {
+        let linear = self.module.linear_periods;
+        let channel = &mut self.channels[ch];
+        let sample: &Sample = match channel.sample.and_then(|s| self.module.samples.get(s)) {
+            Some(sample) if channel.playing => sample,
+            _ => return,
+        };
+        let instrument = channel.instrument.and_then(|i| self.module.instruments.get(i));
+
+        let mut period = channel.period + channel.vibrato_offset;
+        if channel.arpeggio_offset != 0 {
+            let semitones = channel.arpeggio_offset as f32;
+            period = if linear { period - semitones * 64.0 } else { period / 2f32.powf(semitones / 12.0) };
+        }
+        let step = period_to_frequency(linear, period) as f64 / self.sample_rate as f64;
+
+        let envelope = instrument
+            .and_then(|i| i.volume_envelope.as_ref())
+            .map_or(1.0, |envelope| envelope.value_at(channel.envelope_tick));
+        let volume = (channel.volume + channel.tremolo_offset).clamp(0, 64) as f32 / 64.0
+            * envelope
+            * channel.fadeout
+            * sample.global_volume
+            * instrument.map_or(1.0, |i| i.global_volume)
+            * self.global_volume
+            * gain;
+        let pan = channel.panning as f32 / 255.0;
+        let (left, right) = (volume * ((1.0 - pan) * 2.0).min(1.0), volume * (pan * 2.0).min(1.0));
+
+        let data = &sample.data;
+        let len = data.len();
+        for frame in self.mix_buffer.chunks_exact_mut(2).take(frames) {
+            let index = channel.position as usize;
+            let next = if sample.loop_kind == LoopKind::Forward && index + 1 >= sample.loop_end {
+                sample.loop_start
+            } else {
+                (index + 1).min(len - 1)
+            };
+            let mu = channel.position.fract() as f32;
+            let value = data[index.min(len - 1)] * (1.0 - mu) + data[next] * mu;
+
+            frame[0] += value * left;
+            frame[1] += value * right;
+
+            if channel.backwards {
+                channel.position -= step;
+                if channel.position < sample.loop_start as f64 {
+                    channel.position =
+                        (2.0 * sample.loop_start as f64 - channel.position).min(sample.loop_end as f64 - 1.0);
+                    channel.backwards = false;
+                }
+                continue;
+            }
+
+            channel.position += step;
+            match sample.loop_kind {
+                LoopKind::None if channel.position >= len as f64 => {
+                    channel.playing = false;
+                    break;
+                }
+                LoopKind::Forward if channel.position >= sample.loop_end as f64 => {
+                    let length = (sample.loop_end - sample.loop_start) as f64;
+                    channel.position =
+                        sample.loop_start as f64 + (channel.position - sample.loop_start as f64) % length;
+                }
+                LoopKind::PingPong if channel.position >= sample.loop_end as f64 => {
+                    channel.position = (2.0 * sample.loop_end as f64 - channel.position).max(sample.loop_start as f64);
+                    channel.backwards = true;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Plays a single tick of the song, returns interleaved stereo samples.
+    fn render_tick(&mut self) -> Vec<i16> {
+        let tick = self.tick % self.speed;
+        if self.tick == 0 {
+            self.process_row();
+        } else {
+            for ch in 0..self.channels.len() {
+                self.tick_effect(ch, tick);
+            }
+        }
+
+        let frames = self.samples_per_tick();
+        self.mix_buffer.clear();
+        self.mix_buffer.resize(frames * 2, 0.0);
+
+        let gain = 1.0 / (self.channels.len() as f32).sqrt().max(2.0);
+        for ch in 0..self.channels.len() {
+            self.mix_channel(ch, frames, gain);
+        }
+
+        self.update_envelopes();
+
+        self.tick += 1;
+        if self.tick >= self.speed * (1 + self.pattern_delay) {
+            self.tick = 0;
+            self.pattern_delay = 0;
+            self.next_row();
+        }
+
+        self.mix_buffer.iter().map(|&s| (s.clamp(-1.0, 1.0) * 32767.0) as i16).collect()
+    }
+}
+
+impl SongStream for TrackerPlayer {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u8 {
+        2
+    }
+
+    fn read_packet(&mut self) -> GameResult<Option<Vec<i16>>> {
+        if self.looped {
+            return Ok(None);
+        }
+
+        Ok(Some(self.render_tick()))
+    }
+
+    fn rewind(&mut self) -> GameResult {
+        // the song is already at the position it loops from
+        self.looped = false;
+
+        Ok(())
+    }
+
+    fn restart(&mut self) -> GameResult {
+        self.reset();
+
+        Ok(())
+    }
+}
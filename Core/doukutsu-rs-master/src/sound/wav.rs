@@ -1,8 +1,8 @@
 use std::fmt;
 use std::io;
-use std::io::ErrorKind;
+use std::io::{ErrorKind, SeekFrom};
 
-use byteorder::{LE, ReadBytesExt};
+use byteorder::{LE, ReadBytesExt, WriteBytesExt};
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct RiffChunk {
@@ -118,4 +118,60 @@ impl WavSample {
 
         Ok(WavSample { format: WavFormat { channels, sample_rate: samples, bit_depth: bits }, data: buf })
     }
+
+    /// Writes the sample as a PCM RIFF WAVE file.
+    pub fn write_to<W: io::Write>(&self, mut f: W) -> io::Result<()> {
+        let block_align = self.format.channels * (self.format.bit_depth / 8);
+
+        f.write_all(b"RIFF")?;
+        f.write_u32::<LE>(36 + self.data.len() as u32)?;
+        f.write_all(b"WAVE")?;
+
+        f.write_all(b"fmt ")?;
+        f.write_u32::<LE>(16)?;
+        f.write_u16::<LE>(1)?;
+        f.write_u16::<LE>(self.format.channels)?;
+        f.write_u32::<LE>(self.format.sample_rate)?;
+        f.write_u32::<LE>(self.format.sample_rate * block_align as u32)?;
+        f.write_u16::<LE>(block_align)?;
+        f.write_u16::<LE>(self.format.bit_depth)?;
+
+        f.write_all(b"data")?;
+        f.write_u32::<LE>(self.data.len() as u32)?;
+        f.write_all(&self.data)
+    }
+}
+
+/// Writes a PCM RIFF WAVE file as samples come in, the lengths in the header are filled in by `finish`.
+pub struct WavWriter<W: io::Write + io::Seek> {
+    f: W,
+    data_len: u32,
+}
+
+impl<W: io::Write + io::Seek> WavWriter<W> {
+    pub fn new(mut f: W, format: WavFormat) -> io::Result<WavWriter<W>> {
+        WavSample { format, data: Vec::new() }.write_to(&mut f)?;
+
+        Ok(WavWriter { f, data_len: 0 })
+    }
+
+    pub fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        for sample in samples {
+            self.f.write_i16::<LE>(*sample)?;
+        }
+        self.data_len = self.data_len.saturating_add(samples.len() as u32 * 2);
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.f.seek(SeekFrom::Start(4))?;
+        self.f.write_u32::<LE>(self.data_len.saturating_add(36))?;
+        self.f.seek(SeekFrom::Start(40))?;
+        self.f.write_u32::<LE>(self.data_len)?;
+        self.f.seek(SeekFrom::End(0))?;
+        self.f.flush()?;
+
+        Ok(self.f)
+    }
 }
@@ -1,6 +1,8 @@
+use std::collections::HashMap;
 use std::io::Cursor;
 
 use byteorder::ReadBytesExt;
+use lazy_static::lazy_static;
 
 /// Decodes UTF-8 character in a less strict way.
 /// http://simonsapin.github.io/wtf-8/#decoding-wtf-8
@@ -7369,3 +7371,80 @@ pub fn read_cur_shift_jis<T: AsRef<[u8]>>(cursor: &mut Cursor<T>, max_bytes: u32
 
     (consumed, std::char::from_u32(result).unwrap_or('\u{fffd}'))
 }
+
+lazy_static! {
+    static ref SHIFT_JIS_REVERSE: HashMap<char, u16> = {
+        let mut map = HashMap::new();
+
+        for lead in (0x81u8..=0x9f).chain(0xe0..=0xef).chain(0xfa..=0xfc) {
+            for trail in 0x40u8..=0xfc {
+                let bytes = [lead, trail];
+                let (_, chr) = read_cur_shift_jis(&mut Cursor::new(&bytes), 2);
+
+                if chr != '\u{fffd}' {
+                    map.entry(chr).or_insert((lead as u16) << 8 | trail as u16);
+                }
+            }
+        }
+
+        map
+    };
+}
+
+/// Unicode -> Shift-JIS converter, characters that can't be represented are replaced with `?`.
+pub fn encode_shift_jis(text: &str) -> Vec<u8> {
+    let mut result = Vec::with_capacity(text.len());
+
+    for chr in text.chars() {
+        match chr as u32 {
+            0x00..=0x7f => result.push(chr as u8),
+            code @ 0xff60..=0xff9f => result.push((code - 0xfec0) as u8),
+            _ => match SHIFT_JIS_REVERSE.get(&chr) {
+                Some(&sjis) => {
+                    result.push((sjis >> 8) as u8);
+                    result.push(sjis as u8);
+                }
+                None => result.push(b'?'),
+            },
+        }
+    }
+
+    result
+}
+
+/// Shift-JIS -> Unicode converter for whole buffers.
+pub fn decode_shift_jis(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len());
+    let mut cursor = Cursor::new(data);
+
+    while (cursor.position() as usize) < data.len() {
+        let remaining = (data.len() - cursor.position() as usize) as u32;
+        let (_, chr) = read_cur_shift_jis(&mut cursor, remaining);
+        result.push(chr);
+    }
+
+    result
+}
+
+/// Whether given data looks like Shift-JIS text, that is every non-ASCII byte is a half-width katakana or a part of
+/// a double-byte character the decoder knows.
+pub fn is_shift_jis(data: &[u8]) -> bool {
+    let mut idx = 0;
+
+    while idx < data.len() {
+        match data[idx] {
+            0x00..=0x7f | 0xa1..=0xdf => idx += 1,
+            0x81..=0x9f | 0xe0..=0xef | 0xfa..=0xfc => {
+                let (_, chr) = read_cur_shift_jis(&mut Cursor::new(&data[idx..]), 2);
+                if chr == '\u{fffd}' {
+                    return false;
+                }
+
+                idx += 2;
+            }
+            _ => return false,
+        }
+    }
+
+    true
+}